@@ -1,3 +1 @@
-use dungeon_mark;
-
 fn main() {}
@@ -0,0 +1,378 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, mem,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::preprocess::directive::DirectiveConfig;
+use crate::{
+    cmark::read_source,
+    error::Result,
+    model::journal::{Journal, JournalEntry, ParseOptions},
+};
+
+const CACHE_FILE: &str = "entries.json";
+/// Directives whose first argument names a file that the entry's contents depend on.
+const FILE_DIRECTIVES: &[&str] = &[
+    "{{#include",
+    "{{#includecode",
+    TEMPLATE_DIRECTIVE,
+    "{{#table",
+];
+const TEMPLATE_DIRECTIVE: &str = "{{#template";
+/// Directives whose output does not only depend on the files of the entry: rolls draw from the random numbers
+/// shared by the build, and `{{#env}}` reads the environment.
+const UNCACHEABLE_DIRECTIVES: &[&str] = &["{{#roll", ENV_DIRECTIVE];
+const ENV_DIRECTIVE: &str = "{{#env";
+const CLOSE_SEQUENCE: &str = "}}";
+
+/// An on-disk cache of parsed journal entries, keyed by the entry's path and a hash of its contents.
+/// The hash covers the entry's TOC name and level, the raw file contents, the contents of any files
+/// pulled in through `{{#include}}`, `{{#includecode}}`, `{{#template}}` and `{{#table}}` directives, including
+/// those named by the directives of the included files in turn, and the `CacheKey` of the build, so a change to
+/// any of those invalidates the entry.
+///
+/// Entries that hit the cache skip the preprocess and parse stages entirely; they are loaded with an
+/// empty body and swapped for their cached parse once parsing has finished. Entries with `{{#roll}}` directives
+/// are never cached, as skipping them would change the random numbers drawn by the entries after them, nor are
+/// entries with `{{#env}}` directives, whose output changes with the environment.
+///
+/// With a `changed_since` time, cached entries whose file and includes were not modified after that time are hits
+/// without checking their hash, for `JournalBuilder::build_changed_since`.
+pub(crate) struct EntryCache {
+    path: PathBuf,
+    key: String,
+    changed_since: Option<SystemTime>,
+    entries: BTreeMap<PathBuf, CachedEntry>,
+    hits: HashMap<PathBuf, CachedEntry>,
+    misses: HashMap<PathBuf, String>,
+}
+
+/// What the parse of every entry depends on besides its files: the parse options, the configuration of the
+/// `directive` preprocessor, the preprocessors that run and the directives that transformers declare. External
+/// preprocessors are told apart by name only, so changing the command of one keeps its cached entries.
+#[derive(Debug, Serialize)]
+pub(crate) struct CacheKey<'a> {
+    pub(crate) parse: &'a ParseOptions,
+    pub(crate) directive: DirectiveConfig,
+    pub(crate) strict_directives: bool,
+    /// The name and priority of each preprocessor.
+    pub(crate) preprocessors: Vec<(&'a str, i32)>,
+    pub(crate) transformer_directives: Vec<&'a str>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedEntry {
+    hash: String,
+    entry: JournalEntry,
+}
+
+impl EntryCache {
+    /// Open the cache stored in the provided directory. A missing or unreadable cache is treated as empty.
+    pub(crate) fn open(directory: impl AsRef<Path>, key: &CacheKey<'_>) -> Self {
+        let path = directory.as_ref().join(CACHE_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|source| serde_json::from_str(&source).ok())
+            .unwrap_or_default();
+
+        Self {
+            path,
+            key: serde_json::to_string(key).unwrap_or_default(),
+            changed_since: None,
+            entries,
+            hits: HashMap::new(),
+            misses: HashMap::new(),
+        }
+    }
+
+    /// Treat cached entries whose file and includes were not modified after `time` as unchanged.
+    pub(crate) fn with_changed_since(mut self, time: Option<SystemTime>) -> Self {
+        self.changed_since = time;
+
+        self
+    }
+
+    /// Check a freshly loaded entry, read from `file_path`, against the cache. On a hit the entry's body is cleared
+    /// so that it passes through preprocessing and parsing untouched.
+    pub(crate) fn check(&mut self, entry: &mut JournalEntry, file_path: &Path) {
+        let (Some(path), Some(body)) = (&entry.path, &entry.body) else {
+            return;
+        };
+
+        if UNCACHEABLE_DIRECTIVES
+            .iter()
+            .any(|directive| body.contains(directive))
+        {
+            return;
+        }
+
+        let entry_dir = file_path.parent().unwrap_or(Path::new(""));
+        let unchanged = self.changed_since.is_some_and(|time| {
+            !is_modified_after(file_path, time)
+                && included_files(body, entry_dir)
+                    .iter()
+                    .all(|include| !is_modified_after(include, time))
+        });
+
+        if unchanged {
+            if let Some(cached) = self.entries.remove(path) {
+                self.hits.insert(path.clone(), cached);
+                entry.body = None;
+
+                return;
+            }
+        }
+
+        let hash = hash_entry(
+            &self.key,
+            &entry.title,
+            entry.level,
+            entry.heading_offset,
+            body,
+            entry_dir,
+        );
+
+        match self.entries.remove(path) {
+            Some(cached) if cached.hash == hash => {
+                self.hits.insert(path.clone(), cached);
+                entry.body = None;
+            }
+            _ => {
+                self.misses.insert(path.clone(), hash);
+            }
+        }
+    }
+
+    /// Swap cached entries back into the parsed journal and record the parse of every entry that missed.
+    /// Entries are cached without their nested children, which are kept from the freshly loaded journal along with
+    /// the fields set from the table of contents, such as the entry's chapter.
+    pub(crate) fn update(&mut self, mut journal: Journal) -> Journal {
+        let mut entries = BTreeMap::new();
+
+        journal.for_each_entry_mut(|entry| {
+            let Some(path) = entry.path.clone() else {
+                return;
+            };
+
+            if let Some(cached) = self.hits.remove(&path) {
+                // NOTE: What the table of contents sets when the entry is loaded is not covered by the hash, so it
+                // is kept from the freshly loaded entry.
+                let loaded = mem::take(entry);
+                *entry = JournalEntry {
+                    children: loaded.children,
+                    content_hash: loaded.content_hash,
+                    level: loaded.level,
+                    chapter: loaded.chapter,
+                    source_dir: loaded.source_dir,
+                    matter: loaded.matter,
+                    link_title: loaded.link_title,
+                    ..cached.entry.clone()
+                };
+                entries.insert(path, cached);
+            } else if let Some(hash) = self.misses.remove(&path) {
+                let entry = JournalEntry {
+                    children: Vec::new(),
+                    ..entry.clone()
+                };
+                entries.insert(path, CachedEntry { hash, entry });
+            }
+        });
+
+        self.entries = entries;
+
+        journal
+    }
+
+    /// Persist the cache to disk.
+    pub(crate) fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create cache directory: {}", parent.display())
+            })?;
+        }
+
+        let cache = serde_json::to_string(&self.entries)?;
+
+        fs::write(&self.path, cache)
+            .with_context(|| format!("Failed to write cache: {}", self.path.display()))
+    }
+}
+
+fn hash_entry(
+    key: &str,
+    title: &str,
+    level: u8,
+    heading_offset: i8,
+    body: &str,
+    entry_dir: &Path,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(key.as_bytes());
+    hasher.update([0]);
+    hasher.update(title.as_bytes());
+    hasher.update([0, level]);
+    hasher.update(heading_offset.to_le_bytes());
+    hasher.update(body.as_bytes());
+
+    for include in included_files(body, entry_dir) {
+        hasher.update([0]);
+        hasher.update(include.as_os_str().as_encoded_bytes());
+
+        // NOTE: A missing include is hashed as an empty marker so that creating the file invalidates the entry.
+        match fs::read(include) {
+            Ok(contents) => {
+                hasher.update([1]);
+                hasher.update(contents);
+            }
+            Err(_) => hasher.update([0]),
+        }
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Whether the file was modified after `time`. Files whose modification time cannot be read count as modified.
+pub(crate) fn is_modified_after(path: &Path, time: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_or(true, |modified| modified > time)
+}
+
+/// The files that the directives of the body pull in, resolved against `directory`, followed by the files that the
+/// directives of those files pull in in turn, resolved against the directory of the file they are in. Each file is
+/// listed once, so includes that form a cycle end.
+pub(crate) fn included_files(body: &str, directory: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![(body.to_string(), directory.to_path_buf())];
+
+    while let Some((body, directory)) = pending.pop() {
+        for include in includes(&body) {
+            let path = directory.join(include);
+
+            if files.contains(&path) {
+                continue;
+            }
+
+            if let Ok(contents) = read_source(&path) {
+                let directory = path.parent().unwrap_or(Path::new("")).to_path_buf();
+                pending.push((contents, directory));
+            }
+
+            files.push(path);
+        }
+    }
+
+    files
+}
+
+pub(crate) fn includes(body: &str) -> impl Iterator<Item = String> + '_ {
+    FILE_DIRECTIVES
+        .iter()
+        .flat_map(move |directive| {
+            body.match_indices(directive)
+                .map(move |(start, _)| (*directive, &body[start + directive.len()..]))
+        })
+        .filter_map(|(directive, rest)| {
+            // NOTE: `{{#include` is also a prefix of longer directive names, such as `{{#include_url`, which are
+            // matched on their own or do not name a file.
+            if rest.starts_with(|character: char| character.is_alphanumeric() || character == '_') {
+                return None;
+            }
+
+            let args = &rest[..rest.find(CLOSE_SEQUENCE)?];
+
+            // NOTE: Templates take shell-like words after the path, includes an optional `| "default"`.
+            if directive == TEMPLATE_DIRECTIVE {
+                return shlex::Shlex::new(args).next();
+            }
+
+            let path = args.split_once('|').map_or(args, |(path, _)| path);
+
+            Some(path.trim().to_string())
+        })
+        .filter(|include| !include.is_empty())
+}
+
+/// The names of the environment variables read by the `{{#env}}` directives of the body.
+pub(crate) fn env_names(body: &str) -> impl Iterator<Item = &str> + '_ {
+    body.match_indices(ENV_DIRECTIVE)
+        .map(|(start, _)| &body[start + ENV_DIRECTIVE.len()..])
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+        .filter_map(|rest| {
+            let args = &rest[..rest.find(CLOSE_SEQUENCE)?];
+
+            args.split('|').next().map(str::trim)
+        })
+        .filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn finds_include_targets() {
+        let body =
+            "{{#title Test}}\n{{#include a.md}}\ntext\n{{#include  nested/b.md | \"default\"}}\n{{#template npc.md name=\"Borin\"}}\n{{#includecode init.lua}}\n{{#table loot.csv}}\n{{#include_url https://example.com/srd.md}}";
+        let actual: Vec<_> = includes(body).collect();
+
+        assert_eq!(
+            vec!["a.md", "nested/b.md", "init.lua", "npc.md", "loot.csv"],
+            actual
+        );
+    }
+
+    #[test]
+    fn finds_the_includes_of_included_files() {
+        let root = tempfile::tempdir().expect("failed to create temp dir");
+        fs::create_dir_all(root.path().join("parts")).expect("failed to create dir");
+        fs::write(
+            root.path().join("parts/a.md"),
+            "{{#include b.md}} {{#include ../entry.md}}",
+        )
+        .expect("failed to write include");
+        fs::write(root.path().join("parts/b.md"), "{{#include a.md}}")
+            .expect("failed to write include");
+
+        assert_eq!(
+            vec![
+                root.path().join("parts/a.md"),
+                root.path().join("parts/b.md"),
+                root.path().join("parts/../entry.md"),
+            ],
+            included_files("{{#include parts/a.md}}", root.path())
+        );
+    }
+
+    #[test]
+    fn finds_environment_variable_names() {
+        let body = "{{#env BASE_URL}} {{#env  PORT | \"80\"}} {{#environment HOME}} {{#env}}";
+
+        assert_eq!(
+            vec!["BASE_URL", "PORT"],
+            env_names(body).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn hash_changes_with_title_and_body() {
+        let dir = PathBuf::from("test");
+        let original = hash_entry("{}", "Test", 1, 0, "body", &dir);
+
+        assert_eq!(original, hash_entry("{}", "Test", 1, 0, "body", &dir));
+        assert_ne!(original, hash_entry("{}", "Other", 1, 0, "body", &dir));
+        assert_ne!(original, hash_entry("{}", "Test", 2, 0, "body", &dir));
+        assert_ne!(original, hash_entry("{}", "Test", 1, 0, "other body", &dir));
+        assert_ne!(original, hash_entry("[]", "Test", 1, 0, "body", &dir));
+        assert_ne!(original, hash_entry("{}", "Test", 1, 1, "body", &dir));
+    }
+}
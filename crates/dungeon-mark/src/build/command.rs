@@ -0,0 +1,31 @@
+use shlex::Shlex;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::error::Result;
+
+/// Build a command from a shell-like command string. A bare binary name is searched for in PATH,
+/// while anything with a path component is resolved relative to the project root.
+pub(crate) fn build_command(root: &Path, command: &str) -> Result<Command> {
+    let mut parts = Shlex::new(command);
+    let Some(bin) = parts.next() else {
+        anyhow::bail!("Provided command string was empty");
+    };
+
+    // NOTE: Get the path to the binary.
+    let bin = PathBuf::from(bin);
+    let bin = if bin.components().count() == 1 {
+        // NOTE: Search for the binary in PATH.
+        bin
+    } else {
+        // NOTE: Search for the binary relative to the project root.
+        root.join(bin)
+    };
+
+    let mut command = Command::new(bin);
+    command.args(parts);
+
+    Ok(command)
+}
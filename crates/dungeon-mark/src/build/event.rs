@@ -0,0 +1,25 @@
+/// A stage of the build, reported to the callback set with `JournalBuilder::with_progress` as the stage starts
+/// or, for stages that produce a count, as it finishes. For a journal with language editions, the events of each
+/// edition follow its `EditionStarted`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildEvent<'a> {
+    /// An edition of the journal started building. The language is only set for `[language.<code>]` editions.
+    EditionStarted { language: Option<&'a str> },
+    /// The entries of the table of contents were loaded, including nested entries.
+    EntriesLoaded { count: usize },
+    /// A preprocessor started running.
+    PreprocessorStarted { name: &'a str },
+    /// The entries were parsed into sections.
+    EntriesParsed { count: usize },
+    /// A transformer started running, for the given renderer. Transformers are run once per renderer, and without
+    /// a renderer by `JournalBuilder::build_dry_run`.
+    TransformerStarted {
+        name: &'a str,
+        renderer: Option<&'a str>,
+    },
+    /// A renderer started rendering the transformed journal.
+    RendererStarted { name: &'a str },
+    /// A renderer finished, having reported creating the given number of files.
+    RendererFinished { name: &'a str, files: usize },
+}
@@ -0,0 +1,132 @@
+use anyhow::Context;
+use glob::{MatchOptions, Pattern};
+use std::path::{Component, Path};
+
+use crate::error::Result;
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// The `journal.ignore` patterns, for skipping files and directories while walking the file system. See
+/// `JournalConfig::ignore` for the syntax.
+#[derive(Debug, Default)]
+pub(crate) struct IgnorePatterns {
+    patterns: Vec<IgnorePattern>,
+}
+
+#[derive(Debug)]
+struct IgnorePattern {
+    pattern: Pattern,
+    /// Whether the pattern has a `/` before its end, and so is matched against the whole relative path rather
+    /// than against the name of each file and directory.
+    anchored: bool,
+    /// Whether the pattern ends with a `/`, and so only matches directories.
+    directory_only: bool,
+}
+
+impl IgnorePatterns {
+    pub(crate) fn new(patterns: &[String]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| {
+                let directory_only = pattern.ends_with('/');
+                let trimmed = pattern.trim_end_matches('/');
+                let anchored = trimmed.contains('/');
+                let compiled = Pattern::new(trimmed.trim_start_matches('/'))
+                    .with_context(|| format!("Invalid ignore pattern: {pattern}"))?;
+
+                Ok(IgnorePattern {
+                    pattern: compiled,
+                    anchored,
+                    directory_only,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { patterns })
+    }
+
+    /// Whether the file or directory at `path`, relative to the directory being walked, is ignored.
+    pub(crate) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let relative = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let Some(name) = relative.last() else {
+            return false;
+        };
+        let relative = relative.join("/");
+
+        self.patterns
+            .iter()
+            .filter(|pattern| is_dir || !pattern.directory_only)
+            .any(|pattern| {
+                let candidate = if pattern.anchored { &relative } else { *name };
+
+                pattern.pattern.matches_with(candidate, MATCH_OPTIONS)
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ignore(patterns: &[&str]) -> IgnorePatterns {
+        let patterns = patterns
+            .iter()
+            .copied()
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        IgnorePatterns::new(&patterns).expect("should compile patterns")
+    }
+
+    #[test]
+    fn ignores_directories_by_name_at_any_depth() {
+        let patterns = ignore(&[".obsidian", "drafts/"]);
+
+        assert!(patterns.is_ignored(Path::new(".obsidian"), true));
+        assert!(patterns.is_ignored(Path::new("places/.obsidian"), true));
+        assert!(patterns.is_ignored(Path::new("drafts"), true));
+        assert!(patterns.is_ignored(Path::new("places/drafts"), true));
+        assert!(!patterns.is_ignored(Path::new("drafts"), false));
+        assert!(!patterns.is_ignored(Path::new("drafts.md"), false));
+    }
+
+    #[test]
+    fn ignores_files_by_extension() {
+        let patterns = ignore(&["*.psd", "~*"]);
+
+        assert!(patterns.is_ignored(Path::new("map.psd"), false));
+        assert!(patterns.is_ignored(Path::new("images/map.psd"), false));
+        assert!(patterns.is_ignored(Path::new("./~town.md"), false));
+        assert!(!patterns.is_ignored(Path::new("map.png"), false));
+        assert!(!patterns.is_ignored(Path::new("psd"), true));
+    }
+
+    #[test]
+    fn matches_patterns_with_a_slash_against_the_relative_path() {
+        let patterns = ignore(&["places/*.md", "/notes", "archive/**/*.md"]);
+
+        assert!(patterns.is_ignored(Path::new("places/town.md"), false));
+        assert!(!patterns.is_ignored(Path::new("places/inner/town.md"), false));
+        assert!(!patterns.is_ignored(Path::new("other/places/town.md"), false));
+        assert!(patterns.is_ignored(Path::new("notes"), true));
+        assert!(!patterns.is_ignored(Path::new("places/notes"), true));
+        assert!(patterns.is_ignored(Path::new("archive/2023/spring/town.md"), false));
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let err = IgnorePatterns::new(&[String::from("[drafts")]).expect_err("should fail");
+
+        assert!(format!("{err:#}").contains("Invalid ignore pattern: [drafts"));
+    }
+}
@@ -1,215 +1,1818 @@
+mod cache;
+mod command;
+mod event;
+mod ignore;
 pub mod preprocess;
 pub mod render;
 pub mod transform;
+mod watch;
 
+pub use event::*;
+
+use anyhow::Context;
+use sha2::{Digest, Sha256};
 use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    ffi::OsStr,
+    fs, mem,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::Arc,
+    time::SystemTime,
 };
 
 use self::{
-    preprocess::{directive::DirectivePreprocessor, Preprocessor, PreprocessorContext},
-    render::{CommandRenderer, RenderContext, Renderer},
-    transform::{metadata::MetadataTransformer, Transformer, TransformerContext},
+    cache::{env_names, included_files, is_modified_after, CacheKey, EntryCache},
+    ignore::IgnorePatterns,
+    preprocess::{
+        directive::{canonicalize, DirectiveConfig, DirectivePreprocessor},
+        Preprocessor, PreprocessorContext,
+    },
+    render::{
+        build_time, format_timestamp, CommandRenderer, EpubRenderer, HtmlRenderer, PandocRenderer,
+        RenderContext, RenderOutput, Renderer, SearchIndexRenderer, SingleFileRenderer, SourceMap,
+    },
+    transform::{
+        asset_paths::AssetPathsTransformer, conditional::ConditionalTransformer,
+        config_metadata::ConfigMetadataTransformer, embed::EmbedTransformer,
+        empty_sections::EmptySectionsTransformer, glossary::GlossaryTransformer,
+        heading_levels::HeadingLevelsTransformer, include_entry::IncludeEntryTransformer,
+        last_modified::LastModifiedTransformer, metadata::MetadataTransformer,
+        numbering::NumberingTransformer, redaction::RedactionTransformer,
+        section_bodies::SectionBodiesTransformer, statistics::StatisticsTransformer,
+        tags::TagsTransformer, tasks::TasksTransformer, title::TitleTransformer,
+        title_case::TitleCaseTransformer, wikilinks::WikilinksTransformer, Transformer,
+        TransformerContext,
+    },
+    watch::JournalWatcher,
 };
 use crate::{
-    config::Config,
+    cmark::{read_source, Slugger},
+    config::{Config, JournalSource, RendererConfig, CONFIG_FILE, LOCAL_CONFIG_FILE},
     error::Result,
     model::{
-        journal::{ChapterTitle, Journal, JournalEntry, JournalItem},
-        toc::{TOCItem, TableOfContents},
+        journal::{
+            ChapterTitle, Journal, JournalEntry, JournalItem, Matter, MetadataFormats,
+            MetadataParser,
+        },
+        toc::{join_location, Link, SectionTitle, TOCItem, TableOfContents},
     },
 };
 
+/// The preprocessors that are run when the configuration does not list any.
+const DEFAULT_PREPROCESSORS: &[&str] = &["directive"];
+/// The transformers that are run when the configuration does not list any.
+const DEFAULT_TRANSFORMERS: &[&str] = &["conditional", "metadata", "redaction", "title"];
+/// The renderer that is run when neither the configuration nor the builder add any.
+const DEFAULT_RENDERER: &str = "html";
+
+type PreprocessorFactory = Box<dyn Fn() -> Box<dyn Preprocessor>>;
+type TransformerFactory = Box<dyn Fn() -> Box<dyn Transformer>>;
+type ProgressCallback = Box<dyn Fn(BuildEvent<'_>)>;
+
 pub struct JournalBuilder {
     root: PathBuf,
     config: Config,
-    table_of_contents: TableOfContents,
+    editions: Vec<Edition>,
     preprocessors: Vec<Box<dyn Preprocessor>>,
     transformers: Vec<Box<dyn Transformer>>,
     renderers: Vec<Box<dyn Renderer>>,
+    preprocessor_factories: HashMap<String, PreprocessorFactory>,
+    transformer_factories: HashMap<String, TransformerFactory>,
+    cache: bool,
+    /// Entries whose file was last modified no later than this are taken from the cache as they are, as set by
+    /// `build_changed_since`.
+    changed_since: Option<SystemTime>,
+    /// The build time set by `with_build_time`, as seconds since the Unix epoch.
+    build_time: Option<u64>,
+    /// The source location set by `with_source`, which replaces `journal.source` whenever the configuration is
+    /// loaded.
+    source: Option<PathBuf>,
+    /// Whether the editions need to be loaded, as they have not been loaded yet or the configuration changed since.
+    stale_editions: bool,
+    /// The directory renderer output goes to, in place of `build`, as set by `build_to`.
+    output_dir: Option<PathBuf>,
+    metadata_formats: MetadataFormats,
+    progress: Option<ProgressCallback>,
+    /// The entries added with `with_synthetic_entry`, in the order they were added.
+    synthetic_entries: Vec<(JournalEntry, InsertPosition)>,
+}
+
+/// Where `JournalBuilder::with_synthetic_entry` puts an entry among the items of the journal.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsertPosition {
+    /// Before every other item.
+    Front,
+    /// After every other item.
+    Back,
+    /// Right after the first entry with this title, at the same nesting as that entry.
+    AfterEntry(String),
+    /// Right after the first chapter title with this title, so that the entry starts the chapter.
+    AfterChapter(String),
 }
 
 impl JournalBuilder {
+    /// Create a builder for the journal at `root`, reading its `journal.toml`. See `load_with_config`.
     pub fn load(root: impl AsRef<Path>) -> Result<Self> {
         let config = Config::load(&root)?;
 
         Self::load_with_config(root, config)
     }
 
+    /// Create a builder for the journal at `root` with the provided configuration. Nothing is read from disk until
+    /// the journal is built, so the table of contents is loaded when a build starts, after the builder has been
+    /// configured, and problems with it are reported by the build.
     pub fn load_with_config(root: impl AsRef<Path>, config: Config) -> Result<Self> {
-        let source_path = root.as_ref().join(&config.journal.source);
-        let table_of_contents = TableOfContents::load(source_path)?;
-        let builder = Self {
+        let mut builder = Self {
             root: root.as_ref().into(),
             config,
-            table_of_contents,
+            editions: Vec::new(),
             preprocessors: Vec::new(),
             transformers: Vec::new(),
             renderers: Vec::new(),
+            preprocessor_factories: HashMap::new(),
+            transformer_factories: HashMap::new(),
+            cache: false,
+            changed_since: None,
+            build_time: None,
+            source: None,
+            stale_editions: true,
+            output_dir: None,
+            metadata_formats: MetadataFormats::new(),
+            progress: None,
+            synthetic_entries: Vec::new(),
         };
 
+        builder
+            .register_preprocessor("directive", || Box::new(DirectivePreprocessor::new()))
+            .register_transformer("metadata", || Box::new(MetadataTransformer::new()))
+            .register_transformer("asset-paths", || Box::new(AssetPathsTransformer::new()))
+            .register_transformer("conditional", || Box::new(ConditionalTransformer::new()))
+            .register_transformer("config-metadata", || {
+                Box::new(ConfigMetadataTransformer::new())
+            })
+            .register_transformer("embed", || Box::new(EmbedTransformer::new()))
+            .register_transformer("empty-sections", || {
+                Box::new(EmptySectionsTransformer::new())
+            })
+            .register_transformer("glossary", || Box::new(GlossaryTransformer::new()))
+            .register_transformer("heading-levels", || {
+                Box::new(HeadingLevelsTransformer::new())
+            })
+            .register_transformer("include-entry", || Box::new(IncludeEntryTransformer::new()))
+            .register_transformer("last-modified", || Box::new(LastModifiedTransformer::new()))
+            .register_transformer("numbering", || Box::new(NumberingTransformer::new()))
+            .register_transformer("redaction", || Box::new(RedactionTransformer::new()))
+            .register_transformer("statistics", || Box::new(StatisticsTransformer::new()))
+            .register_transformer("tags", || Box::new(TagsTransformer::new()))
+            .register_transformer("tasks", || Box::new(TasksTransformer::new()))
+            .register_transformer("title", || Box::new(TitleTransformer::new()))
+            .register_transformer("title-case", || Box::new(TitleCaseTransformer::new()))
+            .register_transformer("wikilinks", || Box::new(WikilinksTransformer::new()));
+
         Ok(builder)
     }
 
+    /// Register a preprocessor under a name, so that it can be enabled from the `build.preprocessors` list
+    /// of the configuration. Registering a name again replaces the previous registration, including built-ins.
+    pub fn register_preprocessor(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl Fn() -> Box<dyn Preprocessor> + 'static,
+    ) -> &mut Self {
+        self.preprocessor_factories
+            .insert(name.into(), Box::new(factory));
+
+        self
+    }
+
+    /// Register a transformer under a name, so that it can be enabled from the `build.transformers` list
+    /// of the configuration. Registering a name again replaces the previous registration, including built-ins.
+    pub fn register_transformer(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl Fn() -> Box<dyn Transformer> + 'static,
+    ) -> &mut Self {
+        self.transformer_factories
+            .insert(name.into(), Box::new(factory));
+
+        self
+    }
+
+    /// Register a parser for metadata blocks with the given language, which transformers reach through
+    /// `TransformerContext::metadata_formats`. Registering a language again replaces the previous parser,
+    /// including the built-in `toml`, `json` and `yaml` parsers.
+    pub fn register_metadata_format(
+        &mut self,
+        lang: impl Into<String>,
+        parser: MetadataParser,
+    ) -> &mut Self {
+        self.metadata_formats.register(lang, parser);
+
+        self
+    }
+
     pub fn with_preprocessor(&mut self, preprocessor: impl Preprocessor + 'static) -> &mut Self {
-        self.preprocessors.push(Box::new(preprocessor));
+        self.with_preprocessor_boxed(Box::new(preprocessor))
+    }
+
+    /// Add a preprocessor that is already boxed, such as one created at runtime from a plugin registry.
+    pub fn with_preprocessor_boxed(&mut self, preprocessor: Box<dyn Preprocessor>) -> &mut Self {
+        self.preprocessors.push(preprocessor);
 
         self
     }
 
     pub fn with_transformer(&mut self, transformer: impl Transformer + 'static) -> &mut Self {
-        self.transformers.push(Box::new(transformer));
+        self.with_transformer_boxed(Box::new(transformer))
+    }
+
+    /// Add a transformer that is already boxed, such as one created at runtime from a plugin registry.
+    pub fn with_transformer_boxed(&mut self, transformer: Box<dyn Transformer>) -> &mut Self {
+        self.transformers.push(transformer);
 
         self
     }
 
+    /// Add a transformer that replaces the body of every section with what `map` returns for it, such as for a
+    /// search and replace, without implementing `Transformer`. It runs with the default priority, after metadata
+    /// blocks are extracted, and fails the build with the section's title when `map` fails.
+    pub fn map_section_bodies(
+        &mut self,
+        map: impl Fn(&str) -> Result<String> + 'static,
+    ) -> &mut Self {
+        self.with_transformer(SectionBodiesTransformer::new(map))
+    }
+
     pub fn with_renderer(&mut self, renderer: impl Renderer + 'static) -> &mut Self {
-        self.renderers.push(Box::new(renderer));
+        self.with_renderer_boxed(Box::new(renderer))
+    }
+
+    /// Add a renderer that is already boxed, such as one created at runtime from a plugin registry.
+    pub fn with_renderer_boxed(&mut self, renderer: Box<dyn Renderer>) -> &mut Self {
+        self.renderers.push(renderer);
 
         self
     }
 
-    pub fn build(mut self) -> Result<()> {
-        self.load_preprocessors();
-        self.load_transformers();
-        self.load_renderers();
+    /// Add a renderer that runs `command`, like a `[[build.renderers]]` table with a `command` would, without
+    /// adding it to the configuration.
+    pub fn with_command_renderer(&mut self, name: &str, command: &str) -> &mut Self {
+        self.with_renderer(CommandRenderer::new(
+            String::from(name),
+            Some(String::from(command)),
+        ))
+    }
 
-        let journal = self.load_journal()?;
-        let journal = self.preprocess(journal)?;
-        let journal = self.parse_items(journal)?;
-        let journal = self.transform(journal)?;
+    /// Change the loaded configuration before building, for example to point `journal.source` elsewhere without
+    /// writing a `journal.toml`. The journal is loaded from the changed configuration when the build starts.
+    /// When watching, the configuration is reloaded from `journal.toml` on every change, which discards the
+    /// override.
+    pub fn with_config_override(&mut self, override_config: impl FnOnce(&mut Config)) -> &mut Self {
+        override_config(&mut self.config);
+        self.stale_editions = true;
+
+        self
+    }
+
+    /// Load the table of contents and entries from `path` rather than from the `journal.source` of the
+    /// configuration, for scripts that point the builder at a directory of Markdown without a `journal.toml`. A
+    /// relative path is resolved against the journal root, like `journal.source` is. Language editions without a
+    /// `source` of their own are looked for in a directory named after their language code inside `path`. Unlike
+    /// `with_config_override`, the source is kept when watching reloads the configuration.
+    pub fn with_source(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        let path = path.into();
+        self.config.journal.source = JournalSource::Single(path.clone());
+        self.source = Some(path);
+        self.stale_editions = true;
+
+        self
+    }
+
+    /// Enable or disable the incremental build cache. When enabled, parsed entries are cached under the
+    /// build directory and reused on subsequent builds as long as neither the entry nor any of its includes
+    /// have changed.
+    pub fn with_cache(&mut self, enabled: bool) -> &mut Self {
+        self.cache = enabled;
+
+        self
+    }
+
+    /// Stamp renderers with `seconds` since the Unix epoch as the build time, in place of the `SOURCE_DATE_EPOCH`
+    /// environment variable or the current time.
+    pub fn with_build_time(&mut self, seconds: u64) -> &mut Self {
+        self.build_time = Some(seconds);
+
+        self
+    }
+
+    /// Call `progress` with an event as each stage of the build starts, such as running a preprocessor or a
+    /// renderer, for example to drive a progress bar. Replaces any callback set before.
+    pub fn with_progress(&mut self, progress: impl Fn(BuildEvent<'_>) + 'static) -> &mut Self {
+        self.progress = Some(Box::new(progress));
+
+        self
+    }
+
+    /// Add an entry that has no source file, such as a generated index, to every edition of the journal at
+    /// `position`. The entry is added once the table of contents has been loaded, and is then preprocessed, parsed,
+    /// transformed and rendered like the other entries. Its path is cleared, as there is no file behind it. Entries
+    /// are added in the order they were given to the builder, so an entry can be positioned after another
+    /// synthetic entry. A position naming an entry or chapter title that the journal does not have fails the build.
+    pub fn with_synthetic_entry(
+        &mut self,
+        entry: JournalEntry,
+        position: InsertPosition,
+    ) -> &mut Self {
+        let entry = JournalEntry {
+            path: None,
+            ..entry
+        };
+        self.synthetic_entries.push((entry, position));
+
+        self
+    }
+
+    /// Build the journal with every renderer. If the configuration has `[language.<code>]` tables, each
+    /// language edition is loaded from its own source location and built in turn, ordered by language code,
+    /// with each renderer's output going to `build/<renderer>/<code>`. Otherwise the journal is built once,
+    /// into `build/<renderer>`. Returns every file the renderers reported creating.
+    pub fn build(mut self) -> Result<RenderOutput> {
+        self.load_components()?;
+
+        self.run()
+    }
+
+    /// Build the journal like `build`, with each renderer's output going to `<dest>/<renderer>` instead of
+    /// `build/<renderer>`. A relative `dest` is resolved against the journal root, like `build` is. Only renderer
+    /// output is redirected; the incremental build cache stays under `build`.
+    pub fn build_to(mut self, dest: impl AsRef<Path>) -> Result<RenderOutput> {
+        self.output_dir = Some(dest.as_ref().to_path_buf());
+
+        self.build()
+    }
+
+    /// Build the journal like `build`, but only load, preprocess and parse the entries whose file was modified after
+    /// `time`, or that include a file modified after `time`, reusing the entries of the previous build from the
+    /// incremental build cache for the rest, whether or not the cache is enabled. The cache is only written back when
+    /// it is enabled. Renderers still receive the whole journal, as navigation and the like need every entry, and
+    /// every transformer still runs over the whole journal.
+    ///
+    /// Entries that are not in the cache yet are built as usual, and if the table of contents or `journal.toml`
+    /// changed after `time`, entries are only reused when they and their includes are unchanged, as with the cache.
+    pub fn build_changed_since(mut self, time: SystemTime) -> Result<RenderOutput> {
+        self.changed_since = Some(time);
+
+        self.build()
+    }
+
+    /// The names of every renderer the build would run, in order, including those from the configuration and the
+    /// `build.default-renderer` when there are no others.
+    pub fn list_renderers(&self) -> Vec<&str> {
+        let renderers: Vec<_> = self
+            .renderers
+            .iter()
+            .map(|renderer| renderer.name())
+            .chain(
+                self.config
+                    .build
+                    .renderers
+                    .iter()
+                    .map(|renderer| renderer.name.as_str()),
+            )
+            .collect();
+
+        match renderers.is_empty() {
+            true => default_renderer(&self.config).into_iter().collect(),
+            false => renderers,
+        }
+    }
+
+    /// The entries in each source directory of each edition that its table of contents does not link to, such as a
+    /// new entry that was never added to JOURNAL.md, sorted and joined to the journal root. Entries are the files
+    /// with one of `journal.extensions`. Files pulled into a linked entry by `{{#include}}`, `{{#includecode}}`,
+    /// `{{#template}}` or `{{#table}}` directives are not orphans, and neither are the summary file, anything in the
+    /// `build` directory or anything matching `journal.ignore`. Entries in the directories of the `[roots]` table are
+    /// not searched.
+    pub fn orphaned_files(&self) -> Result<Vec<PathBuf>> {
+        let reloaded;
+        let editions = if self.stale_editions {
+            reloaded = Edition::load_all(&self.root, &self.config)?;
+            &reloaded
+        } else {
+            &self.editions
+        };
+
+        let mut orphans = Vec::new();
+        let skipped = [self.root.join("build")];
+
+        for source in editions.iter().flat_map(|edition| &edition.sources) {
+            let source_path = self.root.join(&source.path);
+            let mut referenced = HashSet::from([canonicalize(
+                &source_path.join(&source.config.journal.summary_file),
+            )]);
+            Self::find_referenced_files(
+                &self.root,
+                &source.config,
+                &source.table_of_contents.items,
+                Path::new(""),
+                &mut referenced,
+            )?;
+
+            let ignore = IgnorePatterns::new(&source.config.journal.ignore)?;
+            let mut files = Vec::new();
+            find_entry_files(
+                &source_path,
+                Path::new(""),
+                &source.config.journal.extensions,
+                &skipped,
+                &ignore,
+                &mut files,
+            )?;
+
+            orphans.extend(
+                files
+                    .into_iter()
+                    .filter(|file| !referenced.contains(&canonicalize(file))),
+            );
+        }
+
+        orphans.sort();
+        orphans.dedup();
+
+        Ok(orphans)
+    }
+
+    /// A digest of the files the journal is built from, for skipping a build whose inputs did not change since the
+    /// last one, such as in CI. The digest is a SHA-256, as lowercase hex, of the following files:
+    ///
+    /// - `journal.toml`, and `journal.local.toml` when there is one.
+    /// - The summary file of each source directory of each edition.
+    /// - Every entry the tables of contents link to, and every file pulled into a linked entry by `{{#include}}`,
+    ///   `{{#includecode}}`, `{{#template}}` or `{{#table}}` directives.
+    ///
+    /// Each file is hashed by its path relative to the journal root and its contents, in order of their paths, so
+    /// the digest is the same wherever the journal is checked out and whatever the modification times of its
+    /// files. A missing file is hashed as missing, so that creating it changes the digest. The values of the
+    /// environment variables read by `{{#env}}` directives in those files are hashed too. Nothing else is covered:
+    /// not `build.assets`, the git history read by the `last-modified` transformer, programs run by commands nor
+    /// configuration changed through the builder, such as with `with_config_override`.
+    pub fn input_fingerprint(&self) -> Result<String> {
+        let reloaded;
+        let editions = if self.stale_editions {
+            reloaded = Edition::load_all(&self.root, &self.config)?;
+            &reloaded
+        } else {
+            &self.editions
+        };
+
+        let mut files = HashSet::from([canonicalize(&self.root.join(CONFIG_FILE))]);
+
+        let local_config = self.root.join(LOCAL_CONFIG_FILE);
+        if local_config.exists() {
+            files.insert(canonicalize(&local_config));
+        }
+
+        for source in editions.iter().flat_map(|edition| &edition.sources) {
+            let summary_file = self
+                .root
+                .join(&source.path)
+                .join(&source.config.journal.summary_file);
+            files.insert(canonicalize(&summary_file));
+
+            Self::find_referenced_files(
+                &self.root,
+                &source.config,
+                &source.table_of_contents.items,
+                Path::new(""),
+                &mut files,
+            )?;
+        }
+
+        let root = canonicalize(&self.root);
+        let files: BTreeSet<_> = files
+            .into_iter()
+            .map(|file| (relative_name(&file, &root), file))
+            .collect();
+
+        let mut hasher = Sha256::new();
+        let mut names = BTreeSet::new();
+
+        for (name, file) in files {
+            hasher.update(name.as_bytes());
+            hasher.update([0]);
+
+            match fs::read(&file) {
+                Ok(contents) => {
+                    let source = String::from_utf8_lossy(&contents);
+                    names.extend(env_names(&source).map(String::from));
+
+                    hasher.update([1]);
+                    hasher.update((contents.len() as u64).to_le_bytes());
+                    hasher.update(&contents);
+                }
+                Err(_) => hasher.update([0]),
+            }
+        }
+
+        // NOTE: An unset variable is hashed as missing, so that setting it changes the digest.
+        for name in names {
+            hasher.update(name.as_bytes());
+            hasher.update([0]);
+
+            match std::env::var(&name) {
+                Ok(value) => {
+                    hasher.update([1]);
+                    hasher.update((value.len() as u64).to_le_bytes());
+                    hasher.update(value);
+                }
+                Err(_) => hasher.update([0]),
+            }
+        }
+
+        let fingerprint = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        Ok(fingerprint)
+    }
 
-        self.render(journal)
+    /// Run the load, preprocess, parse and transform stages of the build and return the resulting journal
+    /// without invoking any renderers. Transformers limited to specific renderers are not run.
+    /// For a journal with language editions, only the edition for `journal.language` is built, or the
+    /// first edition if that is not set.
+    pub fn build_dry_run(self) -> Result<Journal> {
+        let (journal, _, _) = self.into_parts()?;
+
+        Ok(journal)
+    }
+
+    /// Run the same stages as `build_dry_run` and take ownership of their results, for embedding the build in a
+    /// larger application: the transformed journal, the configuration with any updates made by transformers, and
+    /// the journal root.
+    pub fn into_parts(mut self) -> Result<(Journal, Config, PathBuf)> {
+        self.load_editions()?;
+        self.load_preprocessors()?;
+        self.load_transformers()?;
+
+        let edition = self
+            .editions
+            .iter()
+            .find(|edition| edition.config.journal.language == self.config.journal.language)
+            .or(self.editions.first())
+            .expect("A journal always has at least one edition");
+        let journal = self.process(edition)?;
+        let (journal, config) = self.transform(edition, journal, None)?;
+
+        Ok((journal, config, self.root))
+    }
+
+    /// Build the journal, then watch the source directory and `journal.toml` for changes, rebuilding
+    /// the journal each time they change. Each rebuild reloads the configuration and table of contents,
+    /// so new entries added to JOURNAL.md are picked up. Changes to files matching `journal.ignore` do not trigger a
+    /// rebuild. Errors during a rebuild are logged and the watcher keeps running; this only returns if the watcher
+    /// itself fails.
+    pub fn watch(mut self) -> Result<()> {
+        self.load_editions()?;
+
+        let user_components = (
+            self.preprocessors.len(),
+            self.transformers.len(),
+            self.renderers.len(),
+        );
+
+        // TODO: Should the `build` directory come from the config?
+        let mut watcher = JournalWatcher::new(self.root.join("build"))?;
+
+        loop {
+            let mut paths: Vec<_> = self
+                .editions
+                .iter()
+                .flat_map(|edition| &edition.sources)
+                .map(|source| self.root.join(&source.path))
+                .collect();
+            paths.push(self.root.join("journal.toml"));
+
+            let ignore = IgnorePatterns::new(&self.config.journal.ignore).unwrap_or_else(|err| {
+                log::error!("Watching without the ignore patterns: {err:?}");
+                IgnorePatterns::default()
+            });
+            watcher.watch(
+                &paths.iter().map(PathBuf::as_path).collect::<Vec<_>>(),
+                ignore,
+            )?;
+
+            if let Err(err) = self.load_components().and_then(|_| self.run()) {
+                log::error!("Failed to build journal: {err:?}");
+            }
+
+            watcher.wait_for_change()?;
+
+            let (preprocessors, transformers, renderers) = user_components;
+            self.preprocessors.truncate(preprocessors);
+            self.transformers.truncate(transformers);
+            self.renderers.truncate(renderers);
+
+            if let Err(err) = self.reload() {
+                log::error!("Failed to reload journal: {err:?}");
+            }
+        }
     }
 }
 
 impl JournalBuilder {
-    fn load_preprocessors(&mut self) {
-        self.with_preprocessor(DirectivePreprocessor::new());
+    fn reload(&mut self) -> Result<()> {
+        let config_path = self.root.join("journal.toml");
+
+        if config_path.exists() {
+            self.config = Config::load(&self.root)?;
+        }
+
+        if let Some(ref source) = self.source {
+            self.config.journal.source = JournalSource::Single(source.clone());
+        }
+
+        self.editions = Edition::load_all(&self.root, &self.config)?;
+
+        Ok(())
+    }
 
-        // TODO: Load additional preprocessors.
+    fn run(&self) -> Result<RenderOutput> {
+        if self.config.build.fail_on_orphans {
+            let orphans = self.orphaned_files()?;
+
+            if !orphans.is_empty() {
+                anyhow::bail!(
+                    "Found entries that the table of contents does not link to: {}",
+                    orphans
+                        .iter()
+                        .map(|orphan| orphan.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+
+        let mut output = RenderOutput::default();
+
+        for edition in &self.editions {
+            let journal = self.process(edition)?;
+            let errors: Vec<_> = journal.errors().into_iter().cloned().collect();
+
+            for error in &errors {
+                log::warn!("Skipped the entry \"{}\": {}", error.title, error.message);
+            }
+
+            output.extend(self.render(edition, journal)?);
+            output.errors.extend(errors);
+        }
+
+        Ok(output)
+    }
+
+    /// Report an event to the progress callback, if one is set. The event is only created when it is reported.
+    fn emit<'a>(&self, event: impl FnOnce() -> BuildEvent<'a>) {
+        if let Some(ref progress) = self.progress {
+            progress(event());
+        }
+    }
+
+    /// Load, preprocess and parse an edition of the journal.
+    fn process(&self, edition: &Edition) -> Result<Journal> {
+        self.emit(|| BuildEvent::EditionStarted {
+            language: edition.language(),
+        });
+
+        let mut cache = self.open_cache(edition)?;
+        let journal = self.load_journal(edition, cache.as_mut())?;
+        self.emit(|| BuildEvent::EntriesLoaded {
+            count: count_entries(&journal),
+        });
+
+        let journal = self.preprocess(edition, journal)?;
+        let journal = self.parse_items(journal)?;
+        self.emit(|| BuildEvent::EntriesParsed {
+            count: count_entries(&journal),
+        });
+
+        self.update_cache(cache, journal)
+    }
+
+    /// Load the editions again if the configuration was overridden since they were loaded.
+    fn load_editions(&mut self) -> Result<()> {
+        if self.stale_editions {
+            self.editions = Edition::load_all(&self.root, &self.config)?;
+            self.stale_editions = false;
+        }
+
+        Ok(())
     }
 
-    fn load_transformers(&mut self) {
-        self.with_transformer(MetadataTransformer::new());
+    fn load_components(&mut self) -> Result<()> {
+        self.load_editions()?;
+        self.load_preprocessors()?;
+        self.load_transformers()?;
+        self.load_renderers();
 
-        // TODO: Load additional transformers.
+        Ok(())
     }
 
-    fn load_renderers(&mut self) {
-        let mut renderers = Vec::with_capacity(self.config.build.renderers.len());
+    fn load_preprocessors(&mut self) -> Result<()> {
+        let names = component_names(&self.config.build.preprocessors, DEFAULT_PREPROCESSORS);
+        let preprocessors = names
+            .iter()
+            .map(|name| match self.preprocessor_factories.get(name) {
+                Some(factory) => Ok(factory()),
+                None => Err(anyhow::anyhow!("Unknown preprocessor: {name}")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.preprocessors.extend(preprocessors);
+
+        Ok(())
+    }
+
+    fn load_transformers(&mut self) -> Result<()> {
+        let mut names = component_names(&self.config.build.transformers, DEFAULT_TRANSFORMERS);
 
-        for renderer in &self.config.build.renderers {
-            let renderer = Box::new(CommandRenderer::new(
-                renderer.name.clone(),
-                renderer.command.clone(),
-            )) as Box<dyn Renderer + 'static>;
-            renderers.push(renderer);
+        if self.config.build.title_case && !names.iter().any(|name| name == "title-case") {
+            names.push(String::from("title-case"));
         }
 
+        // NOTE: Fail rather than silently leaving secret sections in the output.
+        if self.config.build.redact && !names.iter().any(|name| name == "redaction") {
+            anyhow::bail!("`build.redact` is set but the `redaction` transformer is not run");
+        }
+
+        let transformers = names
+            .iter()
+            .map(|name| match self.transformer_factories.get(name) {
+                Some(factory) => Ok(factory()),
+                None => Err(anyhow::anyhow!("Unknown transformer: {name}")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.transformers.extend(transformers);
+
+        Ok(())
+    }
+
+    fn load_renderers(&mut self) {
+        let renderers: Vec<_> = self
+            .config
+            .build
+            .renderers
+            .iter()
+            .map(load_renderer)
+            .collect();
+
         self.renderers.extend(renderers);
+
+        if !self.renderers.is_empty() {
+            return;
+        }
+
+        match default_renderer(&self.config) {
+            Some(name) => self.renderers.push(load_renderer(&RendererConfig {
+                name: String::from(name),
+                ..Default::default()
+            })),
+            None => log::warn!(
+                "No renderers are configured and `build.default-renderer` is empty, so the build will not write \
+                 any output"
+            ),
+        }
+    }
+
+    fn open_cache(&self, edition: &Edition) -> Result<Option<EntryCache>> {
+        if !self.cache && self.changed_since.is_none() {
+            return Ok(None);
+        }
+
+        let directive: DirectiveConfig = edition.config.get("directive")?;
+        let key = CacheKey {
+            parse: &self.config.build.parse,
+            directive,
+            strict_directives: edition.config.build.strict_directives,
+            preprocessors: self
+                .preprocessors
+                .iter()
+                .map(|preprocessor| (preprocessor.name(), preprocessor.priority()))
+                .collect(),
+            transformer_directives: self
+                .transformers
+                .iter()
+                .flat_map(|transformer| transformer.directives())
+                .copied()
+                .collect(),
+        };
+
+        // TODO: Should the `build` directory come from the config?
+        let mut cache_dir = self.root.join("build").join(".cache");
+
+        if let Some(ref language) = edition.language() {
+            cache_dir.push(language);
+        }
+
+        if let Some(time) = self.changed_since {
+            let mut files: Vec<_> = edition
+                .sources
+                .iter()
+                .map(|source| {
+                    self.root
+                        .join(&source.path)
+                        .join(&source.config.journal.summary_file)
+                })
+                .collect();
+            files.push(self.root.join("journal.toml"));
+
+            // NOTE: The cached entries keep the titles and levels of the table of contents they were built with.
+            let changed_since = files
+                .iter()
+                .filter(|file| file.exists())
+                .all(|file| !is_modified_after(file, time))
+                .then_some(time);
+
+            return Ok(Some(
+                EntryCache::open(cache_dir, &key).with_changed_since(changed_since),
+            ));
+        }
+
+        Ok(Some(EntryCache::open(cache_dir, &key)))
+    }
+
+    fn update_cache(&self, cache: Option<EntryCache>, journal: Journal) -> Result<Journal> {
+        let Some(mut cache) = cache else {
+            return Ok(journal);
+        };
+
+        let journal = cache.update(journal);
+
+        // NOTE: `build_changed_since` reads the cache even when it is disabled, but only an enabled cache is written.
+        if self.cache {
+            cache.save()?;
+        }
+
+        Ok(journal)
     }
 
-    fn load_journal(&self) -> Result<Journal> {
-        let items = self.load_items(&self.table_of_contents.items)?;
+    fn load_journal(
+        &self,
+        edition: &Edition,
+        mut cache: Option<&mut EntryCache>,
+    ) -> Result<Journal> {
+        let sources = edition
+            .sources
+            .iter()
+            .map(|source| {
+                Self::load_items(
+                    &self.root,
+                    &source.config,
+                    &source.table_of_contents.items,
+                    Path::new(""),
+                    cache.as_deref_mut(),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        check_source_collisions(&edition.sources, &sources)?;
+
+        let mut items: Vec<_> = sources.into_iter().flatten().collect();
+
+        for (entry, position) in &self.synthetic_entries {
+            if insert_item(&mut items, JournalItem::Entry(entry.clone()), position).is_some() {
+                let missing = match position {
+                    InsertPosition::AfterEntry(title) => format!("entry titled \"{title}\""),
+                    InsertPosition::AfterChapter(title) => format!("chapter titled \"{title}\""),
+                    _ => unreachable!("entries can always be added to the front or back"),
+                };
+
+                anyhow::bail!(
+                    "Failed to add the entry \"{}\", as the journal has no {missing}",
+                    entry.title
+                );
+            }
+        }
+
+        anchor_chapter_titles(
+            &mut items,
+            &mut Slugger::with_strategy(edition.config.build.slug_strategy),
+        );
+        assign_chapters(&mut items, None);
+        assign_matter(&mut items);
+        let title = edition
+            .config
+            .journal
+            .title
+            .clone()
+            .or_else(|| edition.table_of_contents.title.clone());
         let journal = Journal {
             items,
-            title: self.table_of_contents.title.clone(),
+            title,
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
         };
 
         Ok(journal)
     }
 
-    fn load_items(&self, toc_items: &[TOCItem]) -> Result<Vec<JournalItem>, anyhow::Error> {
-        let source_path = self.root.join(&self.config.journal.source);
+    /// Collect the canonical paths of the entries linked from the table of contents, resolved as `load_items` does,
+    /// along with the files their directives pull in.
+    fn find_referenced_files(
+        root: &Path,
+        config: &Config,
+        toc_items: &[TOCItem],
+        base: &Path,
+        referenced: &mut HashSet<PathBuf>,
+    ) -> Result<()> {
+        for item in toc_items {
+            let TOCItem::Link(link) = item else {
+                continue;
+            };
+
+            let mut base = base.to_path_buf();
+
+            if let Some(ref location) = link.location {
+                let location = join_location(&base, location);
+                let (source_path, path, _) = resolve_entry(root, config, link, &location)?;
+                let file_path = source_path.join(path);
+
+                let body = read_source(&file_path).with_context(|| {
+                    format!("Failed to open journal entry: {}", file_path.display())
+                })?;
+                let mut entry_dir = file_path.clone();
+                entry_dir.pop();
+
+                referenced.extend(
+                    included_files(&body, &entry_dir)
+                        .iter()
+                        .map(|include| canonicalize(include)),
+                );
+                referenced.insert(canonicalize(&file_path));
+
+                if link.is_directory() {
+                    base = location;
+                }
+            }
+
+            Self::find_referenced_files(root, config, &link.nested_items, &base, referenced)?;
+        }
+
+        Ok(())
+    }
+
+    /// Load the entries linked from the table of contents. Links are resolved with `Config::resolve_location`, so
+    /// an entry linked as `@name/path` is loaded from the `[roots]` entry `name` while keeping that location as its
+    /// path. A link without an extension is given the first of `journal.extensions` that names an existing file,
+    /// and a link to a directory, such as `chapter/`, loads the `journal.index-file` inside it, with the links
+    /// nested below it relative to that directory, which is passed down as `base`. A link without a location becomes
+    /// a draft entry, without a path or body.
+    fn load_items(
+        root: &Path,
+        config: &Config,
+        toc_items: &[TOCItem],
+        base: &Path,
+        mut cache: Option<&mut EntryCache>,
+    ) -> Result<Vec<JournalItem>, anyhow::Error> {
         let mut items = Vec::new();
 
         for item in toc_items {
             match item {
                 TOCItem::Link(link) => {
                     let Some(ref location) = link.location else {
+                        let entry = JournalEntry {
+                            title: link.name.clone(),
+                            level: link.level,
+                            link_title: link.title.clone(),
+                            draft: true,
+                            children: Self::load_items(
+                                root,
+                                config,
+                                &link.nested_items,
+                                base,
+                                cache.as_deref_mut(),
+                            )?,
+                            ..Default::default()
+                        };
+
+                        items.push(JournalItem::Entry(entry));
                         continue;
                     };
 
-                    let entry =
-                        JournalEntry::load(link.name.clone(), &source_path, location, link.level)?;
+                    let location = join_location(base, location);
+                    let (source_path, path, entry_path) =
+                        resolve_entry(root, config, link, &location)?;
+                    let mut entry = JournalEntry::load_with_options(
+                        link.name.clone(),
+                        &source_path,
+                        &path,
+                        link.level,
+                        &config.build.parse,
+                    )?;
+                    entry.path = Some(entry_path);
+                    entry.source_dir = source_path.join(&path).parent().map(|directory| {
+                        directory
+                            .strip_prefix(root)
+                            .unwrap_or(directory)
+                            .to_path_buf()
+                    });
+                    entry.heading_offset = link.heading_offset;
+                    entry.link_title = link.title.clone();
+
+                    if let Some(ref mut cache) = cache {
+                        cache.check(&mut entry, &source_path.join(&path));
+                    }
+
+                    let base = match link.is_directory() {
+                        true => location.as_path(),
+                        false => base,
+                    };
+                    entry.children = Self::load_items(
+                        root,
+                        config,
+                        &link.nested_items,
+                        base,
+                        cache.as_deref_mut(),
+                    )?;
                     items.push(JournalItem::Entry(entry));
-                    let nested_items = self.load_items(&link.nested_items)?;
-                    items.extend(nested_items);
                 }
                 TOCItem::SectionTitle(section) => {
                     let item = JournalItem::ChapterTitle(ChapterTitle {
                         title: section.title.clone(),
+                        anchor: String::new(),
                     });
 
                     items.push(item)
                 }
-                TOCItem::Separator => items.push(JournalItem::Separator),
+                TOCItem::Separator(separator) => {
+                    items.push(JournalItem::Separator(separator.clone()))
+                }
             }
         }
 
         Ok(items)
     }
 
-    fn preprocess(&self, journal: Journal) -> Result<Journal> {
-        let ctx = PreprocessorContext::new(self.root.clone(), self.config.clone());
-
-        self.preprocessors
+    fn preprocess(&self, edition: &Edition, journal: Journal) -> Result<Journal> {
+        let mut ctx = PreprocessorContext::new(self.root.clone(), edition.config.clone());
+        ctx.table_of_contents = edition.table_of_contents.clone();
+        ctx.transformer_directives = self
+            .transformers
             .iter()
+            .flat_map(|transformer| transformer.directives())
+            .map(|directive| directive.to_string())
+            .collect();
+        let mut preprocessors: Vec<_> = self.preprocessors.iter().collect();
+        preprocessors.sort_by_key(|preprocessor| preprocessor.priority());
+
+        preprocessors
+            .into_iter()
             .try_fold(journal, |journal, preprocessor| {
+                self.emit(|| BuildEvent::PreprocessorStarted {
+                    name: preprocessor.name(),
+                });
+
                 preprocessor.run(&ctx, journal)
             })
     }
 
-    fn parse_items(&self, journal: Journal) -> Result<Journal> {
-        let items = journal
-            .items
-            .into_iter()
-            .map(|item| {
-                let JournalItem::Entry(entry) = item else { return Ok(item); };
-                let entry = entry.parse()?;
+    fn parse_items(&self, mut journal: Journal) -> Result<Journal> {
+        let parse = |entry: &mut JournalEntry| {
+            let name = match entry.path {
+                Some(ref path) => path.display().to_string(),
+                None => entry.title.clone(),
+            };
 
-                Ok(JournalItem::Entry(entry))
-            })
-            .collect::<Result<Vec<_>>>()?;
+            // NOTE: Parse a copy without the children, so that an entry that fails to parse is left as it was.
+            let children = mem::take(&mut entry.children);
+            let parsed = entry.clone().parse_with_options(&self.config.build.parse);
+            entry.children = children;
 
-        let journal = Journal {
-            title: journal.title,
-            items,
+            let parsed =
+                parsed.with_context(|| format!("Failed to parse journal entry: {name}"))?;
+            *entry = JournalEntry {
+                children: mem::take(&mut entry.children),
+                ..parsed
+            };
+
+            Ok(())
         };
 
+        if self.config.build.tolerant {
+            journal.for_each_entry_tolerant(parse);
+        } else {
+            journal.try_for_each_entry_mut(parse)?;
+        }
+
         Ok(journal)
     }
 
-    fn transform(&self, journal: Journal) -> Result<Journal> {
-        let ctx = TransformerContext::new(self.root.clone(), self.config.clone());
+    /// Run every transformer that supports the given renderer over the journal. Without a renderer, only
+    /// transformers that support every renderer are run. Returns the transformed journal along with the
+    /// configuration, including any changes the transformers made to it.
+    fn transform(
+        &self,
+        edition: &Edition,
+        journal: Journal,
+        renderer: Option<&str>,
+    ) -> Result<(Journal, Config)> {
+        let mut ctx = TransformerContext::new(self.root.clone(), edition.config.clone());
+        ctx.metadata_formats = self.metadata_formats.clone();
+        ctx.table_of_contents = edition.table_of_contents.clone();
+        ctx.renderer = renderer.map(String::from);
+        let mut transformers: Vec<_> = self.transformers.iter().collect();
+        transformers.sort_by_key(|transformer| transformer.priority());
 
-        self.transformers
-            .iter()
-            .try_fold(journal, |journal, preprocessor| {
-                preprocessor.run(&ctx, journal)
+        let journal = transformers
+            .into_iter()
+            .filter(|transformer| match transformer.supported_renderers() {
+                Some(renderers) => renderer.is_some_and(|renderer| renderers.contains(&renderer)),
+                None => true,
             })
+            .try_fold(journal, |journal, transformer| {
+                self.emit(|| BuildEvent::TransformerStarted {
+                    name: transformer.name(),
+                    renderer,
+                });
+
+                let journal = transformer.run(&ctx, journal)?;
+                ctx.apply_config_updates()?;
+
+                Ok::<_, anyhow::Error>(journal)
+            })?;
+
+        Ok((journal, ctx.into_config()))
     }
 
-    // TODO: Should the determination of preprocessors and transformers be done as a part of this step?
-    // TODO: Should the journal be fully loaded and transformed for each render pass?
-    fn render(&self, journal: Journal) -> Result<()> {
+    // NOTE: The transform stage is run separately for each renderer, so that transformers can be limited to
+    // the renderers they support. This means the cost of transforming the journal is paid once per renderer.
+    fn render(&self, edition: &Edition, journal: Journal) -> Result<Vec<RenderOutput>> {
+        let mut outputs = Vec::with_capacity(self.renderers.len());
+        let generated_at = format_timestamp(match self.build_time {
+            Some(seconds) => seconds,
+            None => build_time()?,
+        });
+
+        // NOTE: Transformers take the journal they change, so each renderer holds a share of the journal and takes
+        // it out of its share, which copies it while a later renderer still holds a share and moves it for the
+        // last one. The transformed journal is then shared with the renderer rather than copied again.
+        let journal = Arc::new(journal);
+        let shares: Vec<_> = self
+            .renderers
+            .iter()
+            .map(|_| Arc::clone(&journal))
+            .collect();
+        drop(journal);
+
         // TODO: Parallelize renderers and let them all run to completion or error.
-        for renderer in &self.renderers {
+        for (renderer, journal) in self.renderers.iter().zip(shares) {
+            let journal = Arc::unwrap_or_clone(journal);
+            let (mut journal, config) = self.transform(edition, journal, Some(renderer.name()))?;
+
+            if !self.config.build.render_drafts {
+                remove_drafts(&mut journal.items);
+            }
+
+            if self.config.build.strict {
+                journal
+                    .validate_with_strategy(config.build.slug_strategy)
+                    .with_context(|| format!("Invalid journal for renderer {}", renderer.name()))?;
+            }
+
             // TODO: Should the number of renderers influence this?
             // TODO: Should the `build` directory come from the config?
-            let destination = PathBuf::from_str("build")?.join(renderer.name());
+            let mut destination = match self.output_dir {
+                Some(ref output_dir) => output_dir.join(renderer.name()),
+                None => PathBuf::from_str("build")?.join(renderer.name()),
+            };
+
+            if let Some(language) = edition.language() {
+                destination.push(language);
+            }
+
+            if self.config.build.clean {
+                for source in &edition.sources {
+                    clean_destination(
+                        &self.root.join(&destination),
+                        &self.root.join(&source.path),
+                    )?;
+                }
+            }
+
+            let source_map = self
+                .config
+                .build
+                .source_map
+                .then(|| SourceMap::new(&journal));
+
             let ctx = RenderContext::new(
                 self.root.clone(),
-                destination,
-                self.config.clone(),
-                journal.clone(),
-            );
+                destination.clone(),
+                config,
+                journal,
+                edition.table_of_contents.clone(),
+            )
+            .with_generated_at(generated_at.clone());
+
+            self.emit(|| BuildEvent::RendererStarted {
+                name: renderer.name(),
+            });
+
+            let mut output = renderer.render(ctx)?;
+            self.emit(|| BuildEvent::RendererFinished {
+                name: renderer.name(),
+                files: output.files.len(),
+            });
+
+            if let Some(ref assets) = self.config.build.assets {
+                let assets = self.root.join(assets);
+                let ignore = IgnorePatterns::new(&self.config.journal.ignore)?;
+                let copied = copy_assets(
+                    &assets,
+                    Path::new(""),
+                    &self.root.join(&destination),
+                    self.config.build.overwrite_assets,
+                    &ignore,
+                )
+                .with_context(|| format!("Failed to copy assets from {}", assets.display()))?;
 
-            renderer.render(ctx)?;
+                output.files.extend(copied);
+            }
+
+            if let Some(source_map) = source_map {
+                output
+                    .files
+                    .push(source_map.write(&self.root.join(&destination))?);
+            }
+
+            outputs.push(output);
         }
 
-        Ok(())
+        Ok(outputs)
+    }
+}
+
+/// A single language edition of the journal, along with the configuration and table of contents it is built from.
+struct Edition {
+    config: Config,
+    /// The tables of contents of every source, concatenated.
+    table_of_contents: TableOfContents,
+    /// The source locations the edition is merged from, in order. There is just one unless `journal.source` is a
+    /// list.
+    sources: Vec<EditionSource>,
+    /// Whether this edition comes from a `[language.<code>]` table, and so builds into its own directory.
+    localized: bool,
+}
+
+impl Edition {
+    fn load_all(root: &Path, config: &Config) -> Result<Vec<Self>> {
+        let localized = !config.language.is_empty();
+
+        config
+            .editions()
+            .into_iter()
+            .map(|config| {
+                let paths = config.journal.source.paths();
+
+                if paths.is_empty() {
+                    anyhow::bail!("The journal.source list has no source locations");
+                }
+
+                let chapters = config.journal.source_chapters && paths.len() > 1;
+                let sources = paths
+                    .iter()
+                    .map(|path| EditionSource::load(root, &config, path, chapters))
+                    .collect::<Result<Vec<_>>>()?;
+                let table_of_contents = TableOfContents {
+                    title: sources[0].table_of_contents.title.clone(),
+                    items: sources
+                        .iter()
+                        .flat_map(|source| source.table_of_contents.items.iter().cloned())
+                        .collect(),
+                };
+
+                Ok(Self {
+                    config,
+                    table_of_contents,
+                    sources,
+                    localized,
+                })
+            })
+            .collect()
+    }
+
+    /// The language code of the edition, if it is one of several language editions.
+    fn language(&self) -> Option<&str> {
+        self.localized
+            .then_some(self.config.journal.language.as_deref())
+            .flatten()
+    }
+}
+
+/// One source location of an edition, with its own table of contents.
+struct EditionSource {
+    /// The source location, relative to the journal root.
+    path: PathBuf,
+    /// The configuration of the edition with `journal.source` set to this source location alone, so that links
+    /// in its table of contents resolve against it.
+    config: Config,
+    table_of_contents: TableOfContents,
+}
+
+impl EditionSource {
+    /// Load the table of contents of the source at `path`, starting it with a chapter title named after the
+    /// directory if `chapter` is set.
+    fn load(root: &Path, config: &Config, path: &Path, chapter: bool) -> Result<Self> {
+        let mut config = config.clone();
+        config.journal.source = JournalSource::Single(path.to_path_buf());
+
+        let summary_file = &config.journal.summary_file;
+        let mut table_of_contents = if config.journal.keep_separators {
+            TableOfContents::load_exact(root.join(path), summary_file)?
+        } else {
+            TableOfContents::load(root.join(path), summary_file)?
+        };
+
+        if chapter {
+            let title = SectionTitle {
+                title: directory_title(path),
+            };
+            table_of_contents
+                .items
+                .insert(0, TOCItem::SectionTitle(title));
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            config,
+            table_of_contents,
+        })
+    }
+}
+
+/// A chapter title for a source directory, such as `Game master` for `gm/game-master`.
+fn directory_title(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().replace(['-', '_'], " "))
+        .unwrap_or_default();
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
+
+/// Fail if entries loaded from different sources share a path or a title, listing every collision.
+fn check_source_collisions(sources: &[EditionSource], items: &[Vec<JournalItem>]) -> Result<()> {
+    fn collect<'a>(items: &'a [JournalItem], entries: &mut Vec<&'a JournalEntry>) {
+        for item in items {
+            if let JournalItem::Entry(entry) = item {
+                entries.push(entry);
+                collect(&entry.children, entries);
+            }
+        }
+    }
+
+    let mut paths = HashMap::new();
+    let mut titles = HashMap::new();
+    let mut collisions = Vec::new();
+
+    for (index, items) in items.iter().enumerate() {
+        let mut entries = Vec::new();
+        collect(items, &mut entries);
+
+        for entry in entries {
+            if let Some(ref path) = entry.path {
+                match paths.insert(path.clone(), index) {
+                    Some(other) if other != index => collisions.push(format!(
+                        "path {} in {} and {}",
+                        path.display(),
+                        sources[other].path.display(),
+                        sources[index].path.display()
+                    )),
+                    _ => {}
+                }
+            }
+
+            match titles.insert(entry.title.clone(), index) {
+                Some(other) if other != index => collisions.push(format!(
+                    "title \"{}\" in {} and {}",
+                    entry.title,
+                    sources[other].path.display(),
+                    sources[index].path.display()
+                )),
+                _ => {}
+            }
+        }
+    }
+
+    if !collisions.is_empty() {
+        anyhow::bail!(
+            "Entries from different sources collide: {}",
+            collisions.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Give every chapter title an anchor, in document order, sharing one namespace with the titles of the entries.
+fn anchor_chapter_titles(items: &mut [JournalItem], slugger: &mut Slugger) {
+    for item in items {
+        match item {
+            JournalItem::Entry(entry) => {
+                slugger.slug(&entry.title);
+                anchor_chapter_titles(&mut entry.children, slugger);
+            }
+            JournalItem::ChapterTitle(chapter) => chapter.anchor = slugger.slug(&chapter.title),
+            JournalItem::Separator(_) => {}
+            JournalItem::Error(error) => {
+                slugger.slug(&error.title);
+                anchor_chapter_titles(&mut error.children, slugger);
+            }
+        }
+    }
+}
+
+/// Insert the item at `position` among the items or, in document order, the children of their entries, returning
+/// the item if the position is not found.
+fn insert_item(
+    items: &mut Vec<JournalItem>,
+    mut item: JournalItem,
+    position: &InsertPosition,
+) -> Option<JournalItem> {
+    match position {
+        InsertPosition::Front => {
+            items.insert(0, item);
+            return None;
+        }
+        InsertPosition::Back => {
+            items.push(item);
+            return None;
+        }
+        _ => {}
+    }
+
+    let mut index = 0;
+
+    while index < items.len() {
+        let found = match (&items[index], position) {
+            (JournalItem::Entry(entry), InsertPosition::AfterEntry(title)) => entry.title == *title,
+            (JournalItem::ChapterTitle(chapter), InsertPosition::AfterChapter(title)) => {
+                chapter.title == *title
+            }
+            _ => false,
+        };
+
+        if found {
+            items.insert(index + 1, item);
+            return None;
+        }
+
+        if let JournalItem::Entry(ref mut entry) = items[index] {
+            item = insert_item(&mut entry.children, item, position)?;
+        }
+
+        index += 1;
+    }
+
+    Some(item)
+}
+
+/// Set the chapter of every entry to the title of the closest chapter title before it, starting from `chapter`.
+fn assign_chapters(items: &mut [JournalItem], mut chapter: Option<String>) {
+    for item in items {
+        match item {
+            JournalItem::Entry(entry) => {
+                entry.chapter.clone_from(&chapter);
+                assign_chapters(&mut entry.children, chapter.clone());
+            }
+            JournalItem::ChapterTitle(title) => chapter = Some(title.title.clone()),
+            JournalItem::Separator(_) => {}
+            JournalItem::Error(error) => assign_chapters(&mut error.children, chapter.clone()),
+        }
+    }
+}
+
+/// Set the matter of every entry from its place relative to the chapter titles, as described by `Matter`.
+fn assign_matter(items: &mut [JournalItem]) {
+    let is_chapter_title = |item: &JournalItem| matches!(item, JournalItem::ChapterTitle(_));
+    let first_chapter = items.iter().position(is_chapter_title);
+    let back_matter = items.iter().rposition(is_chapter_title).and_then(|last| {
+        items[last..]
+            .iter()
+            .position(|item| matches!(item, JournalItem::Separator(_)))
+            .map(|separator| last + separator)
+    });
+
+    for (index, item) in items.iter_mut().enumerate() {
+        let matter = match (first_chapter, back_matter) {
+            (Some(first), _) if index < first => Matter::Front,
+            (_, Some(back)) if index > back => Matter::Back,
+            _ => Matter::Body,
+        };
+
+        if let JournalItem::Entry(entry) = item {
+            set_matter(entry, matter);
+        }
+    }
+}
+
+fn set_matter(entry: &mut JournalEntry, matter: Matter) {
+    entry.matter = matter;
+
+    for child in &mut entry.children {
+        if let JournalItem::Entry(child) = child {
+            set_matter(child, matter);
+        }
+    }
+}
+
+/// The path of a file relative to `root`, with `/` separators so that it is the same on every platform. Files outside
+/// of `root` keep their full path.
+fn relative_name(file: &Path, root: &Path) -> String {
+    let path = file.strip_prefix(root).unwrap_or(file);
+
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Resolve the location of a table of contents link, already joined to the directory it is nested below, to the
+/// source location it is relative to, the entry's file within that location and the path the entry keeps. A link
+/// to a directory resolves to the `journal.index-file` inside it, and fails if the directory or its index file do
+/// not exist.
+fn resolve_entry(
+    root: &Path,
+    config: &Config,
+    link: &Link,
+    location: &Path,
+) -> Result<(PathBuf, PathBuf, PathBuf)> {
+    let (source, path) = config.resolve_location(location)?;
+    let source_path = root.join(source);
+
+    if !link.is_directory() {
+        let extension = resolve_extension(&source_path, path, &config.journal.extensions)?;
+
+        return Ok((
+            source_path,
+            path.with_extension(extension),
+            location.with_extension(extension),
+        ));
+    }
+
+    let directory = source_path.join(path);
+    let index_file = &config.journal.index_file;
+
+    if !directory.is_dir() {
+        anyhow::bail!(
+            "Failed to find the directory of journal entry {}",
+            directory.display()
+        )
+    }
+
+    if !directory.join(index_file).is_file() {
+        anyhow::bail!(
+            "Failed to find the index file {} of journal entry {}",
+            index_file.display(),
+            directory.display()
+        )
+    }
+
+    Ok((
+        source_path,
+        path.join(index_file),
+        location.join(index_file),
+    ))
+}
+
+/// The extension of the file a link points to: its own extension if it has one, otherwise the first of
+/// `extensions` for which the file exists.
+fn resolve_extension<'a>(
+    source_path: &Path,
+    path: &'a Path,
+    extensions: &'a [String],
+) -> Result<&'a OsStr> {
+    if let Some(extension) = path.extension() {
+        return Ok(extension);
+    }
+
+    let file_path = source_path.join(path);
+
+    match extensions
+        .iter()
+        .find(|extension| file_path.with_extension(extension).is_file())
+    {
+        Some(extension) => Ok(OsStr::new(extension)),
+        None => anyhow::bail!(
+            "Failed to find journal entry {} with any of the extensions: {}",
+            file_path.display(),
+            extensions.join(", ")
+        ),
+    }
+}
+
+/// Remove the draft entries, along with the items nested below them.
+fn remove_drafts(items: &mut Vec<JournalItem>) {
+    items.retain(|item| !matches!(item, JournalItem::Entry(entry) if entry.draft));
+
+    for item in items {
+        if let Some(children) = item.children_mut() {
+            remove_drafts(children);
+        }
+    }
+}
+
+fn count_entries(journal: &Journal) -> usize {
+    journal
+        .iter()
+        .filter(|item| matches!(item, JournalItem::Entry(_)))
+        .count()
+}
+
+/// Collect every file below `directory` with one of `extensions`, skipping the directories in `skipped` and anything
+/// matching the `ignore` patterns. The `relative` path of the directory below the one the walk started from is what the
+/// ignore patterns are matched against.
+fn find_entry_files(
+    directory: &Path,
+    relative: &Path,
+    extensions: &[String],
+    skipped: &[PathBuf],
+    ignore: &IgnorePatterns,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let entries = fs::read_dir(directory)
+        .with_context(|| format!("Failed to read directory: {}", directory.display()))?;
+
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let relative = relative.join(entry.file_name());
+        let is_dir = path.is_dir();
+
+        if ignore.is_ignored(&relative, is_dir) {
+            continue;
+        }
+
+        if is_dir {
+            let is_skipped = skipped
+                .iter()
+                .any(|skipped| canonicalize(skipped) == canonicalize(&path));
+
+            if !is_skipped {
+                find_entry_files(&path, &relative, extensions, skipped, ignore, files)?;
+            }
+
+            continue;
+        }
+
+        let is_entry = path.extension().is_some_and(|extension| {
+            extensions
+                .iter()
+                .any(|allowed| extension == allowed.as_str())
+        });
+
+        if is_entry {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove and recreate a renderer's destination directory. Refuses to remove a directory that is, or contains,
+/// the journal's sources.
+fn clean_destination(destination: &Path, source_path: &Path) -> Result<()> {
+    let source_path = source_path
+        .canonicalize()
+        .unwrap_or_else(|_| source_path.to_path_buf());
+    let resolved = destination
+        .canonicalize()
+        .unwrap_or_else(|_| destination.to_path_buf());
+
+    if source_path.starts_with(&resolved) {
+        anyhow::bail!(
+            "Refusing to clean {} because it contains the journal source {}",
+            destination.display(),
+            source_path.display()
+        );
+    }
+
+    if destination.exists() {
+        fs::remove_dir_all(destination)
+            .with_context(|| format!("Failed to clean destination: {}", destination.display()))?;
+    }
+
+    fs::create_dir_all(destination)
+        .with_context(|| format!("Failed to create destination: {}", destination.display()))
+}
+
+/// Copy every file below `assets` to the same relative path below `destination`, returning the files copied. Files
+/// that already exist in the destination, such as those the renderer wrote, are skipped unless `overwrite` is set,
+/// and files matching the `ignore` patterns are never copied. The `relative` path of `assets` below the assets
+/// directory is what the ignore patterns are matched against.
+fn copy_assets(
+    assets: &Path,
+    relative: &Path,
+    destination: &Path,
+    overwrite: bool,
+    ignore: &IgnorePatterns,
+) -> Result<Vec<PathBuf>> {
+    let mut copied = Vec::new();
+
+    fs::create_dir_all(destination)
+        .with_context(|| format!("Failed to create directory: {}", destination.display()))?;
+
+    for asset in fs::read_dir(assets)? {
+        let asset = asset?;
+        let target = destination.join(asset.file_name());
+        let relative = relative.join(asset.file_name());
+        let is_dir = asset.file_type()?.is_dir();
+
+        if ignore.is_ignored(&relative, is_dir) {
+            continue;
+        }
+
+        if is_dir {
+            copied.extend(copy_assets(
+                &asset.path(),
+                &relative,
+                &target,
+                overwrite,
+                ignore,
+            )?);
+            continue;
+        }
+
+        if target.exists() && !overwrite {
+            log::debug!(
+                "Skipping asset already in the destination: {}",
+                target.display()
+            );
+            continue;
+        }
+
+        fs::copy(asset.path(), &target)
+            .with_context(|| format!("Failed to copy asset: {}", asset.path().display()))?;
+        copied.push(target);
+    }
+
+    Ok(copied)
+}
+
+fn component_names(configured: &Option<Vec<String>>, defaults: &[&str]) -> Vec<String> {
+    match configured {
+        Some(names) => names.clone(),
+        None => defaults.iter().map(|name| name.to_string()).collect(),
+    }
+}
+
+/// The renderer to run when no others are, from `build.default-renderer`, or `None` when it is empty.
+fn default_renderer(config: &Config) -> Option<&str> {
+    match config.build.default_renderer.as_deref() {
+        None => Some(DEFAULT_RENDERER),
+        Some(name) if name.trim().is_empty() => None,
+        Some(name) => Some(name),
+    }
+}
+
+fn load_renderer(renderer: &RendererConfig) -> Box<dyn Renderer> {
+    match renderer {
+        RendererConfig {
+            name,
+            command: None,
+            ..
+        } if name == "html" => Box::new(HtmlRenderer::new()),
+        RendererConfig {
+            name,
+            command: None,
+            ..
+        } if name == "epub" => Box::new(EpubRenderer::new()),
+        RendererConfig {
+            name,
+            command: None,
+            ..
+        } if name == "search-index" => Box::new(SearchIndexRenderer::new()),
+        RendererConfig {
+            name,
+            command: None,
+            ..
+        } if name == "pandoc" => Box::new(PandocRenderer::new()),
+        RendererConfig {
+            name,
+            command: None,
+            ..
+        } if name == "single-file" => Box::new(SingleFileRenderer::new()),
+        RendererConfig { name, command, .. } => Box::new(
+            CommandRenderer::new(name.clone(), command.clone())
+                .with_timeout(renderer.timeout())
+                .with_streaming(renderer.streaming)
+                .with_retries(renderer.retries, renderer.retry_delay()),
+        ),
     }
 }
@@ -0,0 +1,70 @@
+use anyhow::Context;
+use std::{
+    io::{Read, Write},
+    path::Path,
+    process::{Command, Stdio},
+    thread,
+};
+
+use super::{Preprocessor, PreprocessorContext};
+use crate::{build::command::build_command, error::Result, model::journal::Journal};
+
+/// A preprocessor backed by an external command. The command receives `[context, journal]` as JSON on
+/// its stdin and must write the preprocessed journal as JSON to its stdout.
+pub struct CommandPreprocessor {
+    name: String,
+    command: Option<String>,
+}
+
+impl CommandPreprocessor {
+    pub fn new(name: String, command: Option<String>) -> Self {
+        Self { name, command }
+    }
+}
+
+impl CommandPreprocessor {
+    fn build_command(&self, root: &Path) -> Result<Command> {
+        let command = self.command.as_ref().unwrap_or(&self.name);
+
+        build_command(root, command)
+    }
+}
+
+impl Preprocessor for CommandPreprocessor {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(&self, ctx: &PreprocessorContext, journal: Journal) -> Result<Journal> {
+        let mut process = self
+            .build_command(&ctx.root)?
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("Failed to start preprocessor {}", self.name))?;
+
+        let input = serde_json::to_vec(&(ctx, journal))?;
+        let mut stdin = process.stdin.take().expect("Child process has stdin");
+        let mut stdout = process.stdout.take().expect("Child process has stdout");
+
+        // NOTE: Write stdin on its own thread so a child that writes output before it has read all of its
+        // input cannot deadlock against us. Dropping stdin at the end of the thread closes it.
+        let writer = thread::spawn(move || stdin.write_all(&input));
+
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output)?;
+
+        let status = process.wait()?;
+        let written = writer.join().expect("Preprocessor stdin writer panicked");
+
+        if !status.success() {
+            anyhow::bail!("Preprocessor {} failed ({}).", self.name, status);
+        }
+
+        written.with_context(|| format!("Failed to write to preprocessor {}", self.name))?;
+
+        serde_json::from_slice(&output)
+            .with_context(|| format!("Preprocessor {} returned an invalid journal", self.name))
+    }
+}
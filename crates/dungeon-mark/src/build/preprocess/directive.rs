@@ -1,25 +1,126 @@
-use std::fs;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::Context;
+use indexmap::IndexMap;
 use memchr::memmem::Finder;
+use serde::{Deserialize, Serialize};
+use shlex::Shlex;
 
-use super::{Preprocessor, PreprocessorContext};
+use super::{remote::fetch_remote_include, Preprocessor, PreprocessorContext, DIRECTIVE_PRIORITY};
+use crate::build::transform::{
+    conditional::is_conditional,
+    embed::EMBED_DIRECTIVE,
+    include_entry::INCLUDE_ENTRY_DIRECTIVE,
+    statistics::{ENTRY_COUNT_DIRECTIVE, SECTION_COUNT_DIRECTIVE, WORD_COUNT_DIRECTIVE},
+    title::DERIVED_TITLE_DIRECTIVE,
+    DIRECTIVE_ESCAPE,
+};
+use crate::cmark::read_source;
 use crate::error::Result;
-use crate::model::journal::{Journal, JournalEntry, JournalItem};
+use crate::model::journal::{Journal, JournalEntry};
 
 const OPEN_SEQUENCE: &str = "{{#";
 const CLOSE_SEQUENCE: &str = "}}";
+const ESCAPE_CHARACTER: char = '\\';
+const ROLL_DIRECTIVE: &str = "roll";
+const ENV_DIRECTIVE: &str = "env";
+const INCLUDE_URL_DIRECTIVE: &str = "include_url";
+/// The keywords of directives that are left in place for the built-in transformers to replace.
+const TRANSFORMER_DIRECTIVES: [&str; 4] = [
+    WORD_COUNT_DIRECTIVE,
+    ENTRY_COUNT_DIRECTIVE,
+    SECTION_COUNT_DIRECTIVE,
+    EMBED_DIRECTIVE,
+];
+const DEFAULT_REMOTE_INCLUDE_TIMEOUT_SECS: u64 = 30;
+/// The most dice a single roll can have.
+const MAX_DICE: u64 = 1000;
+/// The text of the HTML comment that exempts an entry from directive expansion.
+const NO_DIRECTIVES_MARKER: &str = "dungeon-mark: no-directives";
 
 /// A preprocessor that will look for directives in the form of `{{#...}}` in journal entry bodies and
 /// perform transforms to replace those directives.
-/// - `{{#title ...}}` Replace the title of the document with another title.
+/// - `{{#title ...}}` Replace the title of the document with another title. A `{{#title}}` without a title is
+///   left for the `title` transformer, which titles the entry after its first H1 heading, unless the entry also
+///   has a `{{#title ...}}` with a title, in which case it is removed. An entry with more than one title takes
+///   the last one, with a warning naming the entry, or fails the build when `build.strict` is set.
 /// - `{{#include ...}}` Include an arbitrary file from disk, relative to the location of the journal entry.
+///   A default can be provided with `{{#include path | "default"}}`, which is used if the file does not exist.
+/// - `{{#includecode ...}}` Include a file as a fenced code block, with the fence language detected from the
+///   file's extension and any indentation common to all of its lines removed.
+/// - `{{#template path key="value" ...}}` Include a file relative to the journal entry, replacing each
+///   `{{key}}` placeholder in it with the matching value.
+/// - `{{#table path}}` Include a `.csv` or `.json` file relative to the journal entry as a table. The columns of a
+///   CSV file are named by its first row, and a JSON file must hold an array of objects, whose keys name the
+///   columns in the order they are first seen.
+/// - `{{#roll 2d6+1}}` Roll dice, written as `NdM`, `NdM+K` or `NdM-K` with an optional count, and replace the
+///   directive with the total. Rolls draw from the random numbers shared by the build, see
+///   `PreprocessorContext::random_below`, so they are the same in every build with the same `build.seed`.
+/// - `{{#env NAME}}` Replace the directive with the value of an environment variable. A default can be provided
+///   with `{{#env NAME | "default"}}`, which is used if the variable is unset. An unset variable without a default
+///   is replaced with nothing, with a warning naming the entry, or fails the build when `build.strict` is set.
+/// - `{{#include_url https://...}}` Include the document at an `http` or `https` URL, such as shared rules text
+///   hosted online. Remote includes are disabled by default, and fail the build unless `allow-remote-includes` is
+///   set in the `[directive]` table of `journal.toml`, as they make the build reach out to the network and put
+///   whatever the server sends into the journal, including raw HTML. Only enable them for URLs you trust. Each
+///   URL is fetched with a timeout of `remote-include-timeout-secs` and cached under `build/.cache/remote`, so
+///   later builds use the cached copy until it is older than `remote-include-ttl-secs`, or for good without it;
+///   remove that directory to fetch them all again. A response other than a 2xx fails the build, naming the URL.
+///   Fetching needs the `remote-include` cargo feature, which is enabled by default.
+///
+/// Any other directive is left in place, such as the statistics directives `{{#wordcount}}`, `{{#entrycount}}`
+/// and `{{#sectioncount}}`, which the `statistics` transformer replaces once every entry has been parsed,
+/// `{{#include_entry "Title"}}` and `{{#embed path#Section}}`, which the `include-entry` and `embed` transformers
+/// replace, and the `{{#if ...}}`, `{{#else}}` and `{{#endif}}` directives, which the `conditional` transformer
+/// resolves for each renderer. With `build.strict-directives`, a directive whose keyword is none of these, nor one
+/// that a transformer of the build declares with `Transformer::directives`, fails the build, naming the entry and
+/// the directive, so that a misspelled directive does not end up in the output.
+///
+/// A directive can be escaped with a leading backslash, `\{{#include ...}}`, which is replaced by the literal
+/// directive text without expanding it. Escaped directives that a transformer replaces, such as conditional and
+/// statistics directives, are written with `DIRECTIVE_ESCAPE` instead, for the transformer to remove.
+///
+/// An entry whose file starts with a `<!-- dungeon-mark: no-directives -->` comment is left unchanged, for entries
+/// that document the directive syntax itself. Escapes are not removed in such an entry either, so `\{{#include}}`
+/// keeps its backslash. The `title` and `statistics` transformers leave the entry's directives in place as well.
+///
+/// Files pulled in by directives must be inside the journal root once symbolic links and `..` components have
+/// been resolved, or inside the named root of an entry linked as `@name/path`, unless `allow-outside-root` is set
+/// in the `[directive]` table of `journal.toml`.
 pub struct DirectivePreprocessor {
     open_finder: Finder<'static>,
     close_finder: Finder<'static>,
 }
 
+/// Configuration for the directive preprocessor, read from the `[directive]` table of `journal.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DirectiveConfig {
+    /// Allow directives to pull in files outside of the journal root.
+    pub allow_outside_root: bool,
+    /// Allow `{{#include_url}}` directives to fetch documents over the network. Disabled by default.
+    pub allow_remote_includes: bool,
+    /// How long a remote include may take to fetch, in seconds. Defaults to 30.
+    pub remote_include_timeout_secs: u64,
+    /// How long the cached copy of a remote include is used before it is fetched again, in seconds. Without it the
+    /// cached copy is used until `build/.cache/remote` is removed.
+    pub remote_include_ttl_secs: Option<u64>,
+}
+
+impl Default for DirectiveConfig {
+    fn default() -> Self {
+        Self {
+            allow_outside_root: false,
+            allow_remote_includes: false,
+            remote_include_timeout_secs: DEFAULT_REMOTE_INCLUDE_TIMEOUT_SECS,
+            remote_include_ttl_secs: None,
+        }
+    }
+}
+
 impl DirectivePreprocessor {
     pub(crate) fn new() -> Self {
         Self {
@@ -34,13 +135,17 @@ impl Preprocessor for DirectivePreprocessor {
         "directive"
     }
 
+    fn priority(&self) -> i32 {
+        DIRECTIVE_PRIORITY
+    }
+
     fn run(&self, ctx: &PreprocessorContext, mut journal: Journal) -> Result<Journal> {
-        for item in &mut journal.items {
-            let JournalItem::Entry(ref mut entry) = item else {
-                continue;
-            };
+        let config: DirectiveConfig = ctx.config.get("directive")?;
 
-            self.preprocess_entry(ctx, entry)?;
+        if ctx.config.build.tolerant {
+            journal.for_each_entry_tolerant(|entry| self.preprocess_entry(ctx, &config, entry));
+        } else {
+            journal.try_for_each_entry_mut(|entry| self.preprocess_entry(ctx, &config, entry))?;
         }
 
         Ok(journal)
@@ -48,13 +153,23 @@ impl Preprocessor for DirectivePreprocessor {
 }
 
 impl DirectivePreprocessor {
-    fn preprocess_entry(&self, ctx: &PreprocessorContext, entry: &mut JournalEntry) -> Result<()> {
+    fn preprocess_entry(
+        &self,
+        ctx: &PreprocessorContext,
+        config: &DirectiveConfig,
+        entry: &mut JournalEntry,
+    ) -> Result<()> {
         let Some(ref body) = entry.body else {
             return Ok(());
         };
 
+        if is_exempt(body) {
+            return Ok(());
+        }
+
         let mut input = &body.clone()[..];
         let mut processed_body = Vec::new();
+        let mut explicit_titles = 0;
 
         while let Some(start) = self.open_finder.find(input.as_bytes()) {
             let Some(end) = self.close_finder.find(input.as_bytes()) else {
@@ -68,15 +183,58 @@ impl DirectivePreprocessor {
             }
 
             let directive = &input[start..end];
-            let replacement = preprocess_directive(ctx, entry, directive)?;
+
+            if let Some(preceding) = input[..start].strip_suffix(ESCAPE_CHARACTER) {
+                // NOTE: The directive was escaped, drop the escape and keep the directive as is, unless a
+                // transformer replaces it, which is left to remove the escape instead.
+                processed_body.push(String::from(preceding));
+
+                if is_transformer_directive(ctx, directive) {
+                    processed_body.push(String::from(DIRECTIVE_ESCAPE));
+                }
+
+                processed_body.push(String::from(directive));
+                input = &input[end..];
+                continue;
+            }
+
+            let replacement = preprocess_directive(ctx, config, entry, directive)?;
+            if replacement.is_empty() && is_title_directive(directive) {
+                explicit_titles += 1;
+            }
 
             processed_body.push(String::from(&input[..start]));
             processed_body.push(replacement);
             input = &input[end..];
         }
 
-        // let mut entry = entry.clone();
-        entry.body = Some(processed_body.join(""));
+        processed_body.push(String::from(input));
+
+        let mut body = processed_body.join("");
+
+        if explicit_titles > 1 {
+            let name = match entry.path {
+                Some(ref path) => path.display().to_string(),
+                None => entry.title.clone(),
+            };
+            let message = format!(
+                "Entry {name} has {explicit_titles} {{{{#title ...}}}} directives, the last of which titles it \"{}\"",
+                entry.title
+            );
+
+            if ctx.config.build.strict {
+                anyhow::bail!(message);
+            }
+
+            log::warn!("{message}");
+        }
+
+        // NOTE: An explicit title takes precedence over one derived from the entry's headings.
+        if explicit_titles > 0 {
+            body = remove_derived_titles(&body);
+        }
+
+        entry.body = Some(body);
 
         Ok(())
     }
@@ -84,50 +242,603 @@ impl DirectivePreprocessor {
 
 fn preprocess_directive(
     ctx: &PreprocessorContext,
+    config: &DirectiveConfig,
     entry: &mut JournalEntry,
     directive: &str,
 ) -> Result<String> {
-    let Some(parsed_directive) = directive
-        .strip_prefix(OPEN_SEQUENCE) else {
-            anyhow::bail!("Directive must start with {{#")
-        };
+    let Some(parsed_directive) = directive.strip_prefix(OPEN_SEQUENCE) else {
+        anyhow::bail!("Directive must start with {{#")
+    };
 
-    let Some(parsed_directive) = parsed_directive
-        .strip_suffix(CLOSE_SEQUENCE) else {
-            anyhow::bail!("Directive must end with }}")
-        };
+    let Some(parsed_directive) = parsed_directive.strip_suffix(CLOSE_SEQUENCE) else {
+        anyhow::bail!("Directive must end with }}")
+    };
 
-    // Directive was a title replacement.
+    // Directive was a title replacement, or a title to derive once the entry has been parsed.
     if let Some(title) = parsed_directive.strip_prefix("title") {
+        if title.trim().is_empty() {
+            return Ok(String::from(DERIVED_TITLE_DIRECTIVE));
+        }
+
         entry.title = String::from(title.trim());
         return Ok(String::from(""));
     }
 
+    // Directive was a template replacement.
+    if let Some(args) = strip_directive_name(parsed_directive, "template") {
+        let (path, values) = parse_template_args(args)?;
+        let template_path = resolve_path(ctx, config, entry, "#template", &path)?;
+        let template = read_source(&template_path)
+            .with_context(|| format!("failed to open file: {}", template_path.display()))?;
+
+        return render_template(&template, &values)
+            .with_context(|| format!("failed to render template: {}", template_path.display()));
+    }
+
+    // Directive was a data table replacement.
+    if let Some(args) = strip_directive_name(parsed_directive, "table") {
+        let path = PathBuf::from(args.trim());
+        let table_path = resolve_path(ctx, config, entry, "#table", &path)?;
+        let data = read_source(&table_path)
+            .with_context(|| format!("failed to open file: {}", table_path.display()))?;
+
+        return data_table(&data, &table_path)
+            .with_context(|| format!("failed to read table: {}", table_path.display()));
+    }
+
+    // Directive was a dice roll.
+    if let Some(args) = parsed_directive.strip_prefix(ROLL_DIRECTIVE) {
+        let dice = Dice::parse(args)?;
+
+        return Ok(dice.roll(ctx).to_string());
+    }
+
+    // Directive was an environment variable substitution.
+    if let Some(args) = strip_directive_name(parsed_directive, ENV_DIRECTIVE) {
+        let (name, default) = parse_default_args(args, "#env")?;
+
+        if name.is_empty() {
+            anyhow::bail!("An #env directive requires the name of an environment variable")
+        }
+
+        if let Some(value) = ctx.env_var(name).or(default) {
+            return Ok(value);
+        }
+
+        let entry_name = match entry.path {
+            Some(ref path) => path.display().to_string(),
+            None => entry.title.clone(),
+        };
+        let message =
+            format!("Entry {entry_name} uses the environment variable {name}, which is not set");
+
+        if ctx.config.build.strict {
+            anyhow::bail!(message);
+        }
+
+        log::warn!("{message}");
+        return Ok(String::new());
+    }
+
+    // Directive includes another entry, which is left for the `include-entry` transformer.
+    if parsed_directive.starts_with(INCLUDE_ENTRY_DIRECTIVE) {
+        return Ok(String::from(directive));
+    }
+
+    // Directive was a remote include replacement.
+    if let Some(args) = strip_directive_name(parsed_directive, INCLUDE_URL_DIRECTIVE) {
+        let url = args.trim();
+
+        if !config.allow_remote_includes {
+            let entry_name = match entry.path {
+                Some(ref path) => path.display().to_string(),
+                None => entry.title.clone(),
+            };
+
+            anyhow::bail!(
+                "Entry {entry_name} includes {url}, but remote includes are disabled. Set \
+                 `allow-remote-includes` in the `[directive]` table of `journal.toml` to allow them"
+            );
+        }
+
+        let timeout = Duration::from_secs(config.remote_include_timeout_secs);
+        let ttl = config.remote_include_ttl_secs.map(Duration::from_secs);
+
+        return fetch_remote_include(&ctx.root, url, timeout, ttl);
+    }
+
+    // Directive was a code include replacement.
+    if let Some(args) = strip_directive_name(parsed_directive, "includecode") {
+        let path = PathBuf::from(args.trim());
+        let include_path = resolve_path(ctx, config, entry, "#includecode", &path)?;
+        let code = read_source(&include_path)
+            .with_context(|| format!("failed to open file: {}", include_path.display()))?;
+
+        return Ok(fence_code(&code, &include_path));
+    }
+
     // Directive was an include replacement.
-    if let Some(path) = parsed_directive.strip_prefix("include") {
-        let Some(ref entry_path) = entry.path else {
-            anyhow::bail!("The given journal entry has no file path and cannot have #include directives");
+    if let Some(args) = strip_directive_name(parsed_directive, "include") {
+        let (path, default) = parse_default_args(args, "#include")?;
+        let include_path = resolve_path(ctx, config, entry, "#include", Path::new(path))?;
+
+        return match (read_source(&include_path), default) {
+            (Err(err), Some(default)) if err.kind() == io::ErrorKind::NotFound => Ok(default),
+            (result, _) => {
+                result.with_context(|| format!("failed to open file: {}", include_path.display()))
+            }
         };
+    }
 
-        let path = PathBuf::from(path.trim());
-        let mut include_path = ctx.root.join(&ctx.config.journal.source).join(entry_path);
-        include_path.pop();
-        include_path.push(path);
+    // Directive is replaced by a transformer, leave it be.
+    if is_transformer_directive(ctx, directive) {
+        return Ok(String::from(directive));
+    }
+
+    if ctx.config.build.strict_directives {
+        let entry_name = match entry.path {
+            Some(ref path) => path.display().to_string(),
+            None => entry.title.clone(),
+        };
 
-        return fs::read_to_string(&include_path)
-            .with_context(|| format!("failed to open file: {}", include_path.display()));
+        anyhow::bail!("Entry {entry_name} has an unrecognized directive: {directive}");
     }
 
     // Unmatched directive, leave it be.
     Ok(String::from(directive))
 }
 
+/// The arguments of the directive, with the `{{#` and `}}` around it removed, if it is named `name`. Unlike
+/// `str::strip_prefix`, the name must be followed by whitespace or end the directive, so that `env` does not match
+/// `{{#environment}}`.
+fn strip_directive_name<'a>(directive: &'a str, name: &str) -> Option<&'a str> {
+    directive
+        .strip_prefix(name)
+        .filter(|args| args.is_empty() || args.starts_with(char::is_whitespace))
+}
+
+/// Dice to roll, as in `2d6+1`.
+#[derive(Debug, PartialEq, Eq)]
+struct Dice {
+    count: u64,
+    sides: u32,
+    modifier: i64,
+}
+
+impl Dice {
+    fn parse(args: &str) -> Result<Self> {
+        let args = args.trim();
+        let invalid =
+            || anyhow::anyhow!("Expected dice in the form of NdM, NdM+K or NdM-K, found: {args}");
+
+        let (count, rest) = args.split_once('d').ok_or_else(invalid)?;
+        let (sides, modifier) = match rest.find(['+', '-']) {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "0"),
+        };
+
+        let count = match count {
+            "" => 1,
+            count => count.parse().map_err(|_| invalid())?,
+        };
+        let sides = sides.parse().map_err(|_| invalid())?;
+        let modifier = modifier
+            .strip_prefix('+')
+            .unwrap_or(modifier)
+            .parse()
+            .map_err(|_| invalid())?;
+
+        if count == 0 || count > MAX_DICE || sides == 0 {
+            anyhow::bail!(
+                "Expected between 1 and {MAX_DICE} dice with at least one side, found: {args}"
+            );
+        }
+
+        Ok(Self {
+            count,
+            sides,
+            modifier,
+        })
+    }
+
+    fn roll(&self, ctx: &PreprocessorContext) -> i64 {
+        let total: u64 = (0..self.count)
+            .map(|_| ctx.random_below(self.sides.into()) + 1)
+            .sum();
+
+        // NOTE: At most `MAX_DICE` dice with `u32::MAX` sides always fit.
+        (total as i64).saturating_add(self.modifier)
+    }
+}
+
+/// The keyword of the directive, such as `wordcount` for `{{#wordcount}}`.
+fn keyword(directive: &str) -> &str {
+    let directive = directive.strip_prefix(OPEN_SEQUENCE).unwrap_or(directive);
+
+    directive
+        .strip_suffix(CLOSE_SEQUENCE)
+        .unwrap_or(directive)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+}
+
+/// Whether a transformer replaces the directive, which is then also left to remove the directive's escape.
+fn is_transformer_directive(ctx: &PreprocessorContext, directive: &str) -> bool {
+    let keyword = keyword(directive);
+
+    TRANSFORMER_DIRECTIVES.contains(&keyword)
+        || is_derived_title_directive(directive)
+        || keyword == INCLUDE_ENTRY_DIRECTIVE
+        || ctx
+            .transformer_directives
+            .iter()
+            .any(|name| name == keyword)
+        || is_conditional(directive)
+}
+
+/// Whether the body starts with the comment that exempts its entry from directive expansion.
+pub(crate) fn is_exempt(body: &str) -> bool {
+    body.trim_start()
+        .strip_prefix("<!--")
+        .and_then(|comment| comment.split_once("-->"))
+        .is_some_and(|(comment, _)| comment.trim() == NO_DIRECTIVES_MARKER)
+}
+
+/// Whether the directive is a `{{#title}}` without a title, which the `title` transformer replaces.
+fn is_derived_title_directive(directive: &str) -> bool {
+    directive
+        .strip_prefix(OPEN_SEQUENCE)
+        .and_then(|directive| directive.strip_suffix(CLOSE_SEQUENCE))
+        .is_some_and(|directive| directive.trim_end() == "title")
+}
+
+/// Remove each `{{#title}}` from the body, keeping the escaped ones for the `title` transformer to unescape.
+fn remove_derived_titles(body: &str) -> String {
+    let mut removed = String::with_capacity(body.len());
+    let mut rest = body;
+
+    while let Some(start) = rest.find(DERIVED_TITLE_DIRECTIVE) {
+        let end = start + DERIVED_TITLE_DIRECTIVE.len();
+
+        if rest[..start].ends_with(DIRECTIVE_ESCAPE) {
+            removed.push_str(&rest[..end]);
+        } else {
+            removed.push_str(&rest[..start]);
+        }
+
+        rest = &rest[end..];
+    }
+
+    removed.push_str(rest);
+    removed
+}
+
+fn is_title_directive(directive: &str) -> bool {
+    directive
+        .strip_prefix(OPEN_SEQUENCE)
+        .is_some_and(|directive| directive.starts_with("title"))
+}
+
+/// Wrap the code in a fenced code block, using a fence longer than any run of backticks in the code.
+fn fence_code(code: &str, path: &Path) -> String {
+    let code = dedent(code);
+    let longest_run = code
+        .split(|character| character != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(3.max(longest_run + 1));
+    let language = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(code_language)
+        .unwrap_or_default();
+
+    format!(
+        "{fence}{language}\n{}\n{fence}",
+        code.trim_end_matches('\n')
+    )
+}
+
+/// Write the data of a `.csv` or `.json` file as a Markdown table.
+fn data_table(data: &str, path: &Path) -> Result<String> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default();
+    let (headers, rows) = match extension {
+        "csv" => csv_rows(data)?,
+        "json" => json_rows(data)?,
+        _ => anyhow::bail!("Tables can only be read from .csv or .json files"),
+    };
+
+    if headers.is_empty() {
+        anyhow::bail!("The table has no columns");
+    }
+
+    let mut table = String::new();
+    push_table_row(&mut table, &headers);
+    push_table_row(&mut table, &vec![String::from("---"); headers.len()]);
+
+    for row in &rows {
+        push_table_row(&mut table, row);
+    }
+
+    Ok(table)
+}
+
+fn csv_rows(data: &str) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+    let mut reader = csv::Reader::from_reader(data.as_bytes());
+    let headers = reader.headers()?.iter().map(String::from).collect();
+    let rows = reader
+        .records()
+        .map(|record| Ok(record?.iter().map(String::from).collect()))
+        .collect::<Result<_>>()?;
+
+    Ok((headers, rows))
+}
+
+fn json_rows(data: &str) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+    let objects: Vec<IndexMap<String, serde_json::Value>> =
+        serde_json::from_str(data).context("Expected an array of objects")?;
+    let mut headers: Vec<String> = Vec::new();
+
+    for key in objects.iter().flat_map(IndexMap::keys) {
+        if !headers.contains(key) {
+            headers.push(key.clone());
+        }
+    }
+
+    let rows = objects
+        .iter()
+        .map(|object| {
+            headers
+                .iter()
+                .map(|header| match object.get(header) {
+                    None | Some(serde_json::Value::Null) => String::new(),
+                    Some(serde_json::Value::String(text)) => text.clone(),
+                    Some(value) => value.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok((headers, rows))
+}
+
+/// Write a row of a Markdown table, escaping pipes and joining the lines of each cell, which cannot span lines.
+fn push_table_row(table: &mut String, cells: &[String]) {
+    table.push('|');
+
+    for cell in cells {
+        let cell = cell.replace('|', "\\|");
+        table.push(' ');
+        table.push_str(&cell.split_whitespace().collect::<Vec<_>>().join(" "));
+        table.push_str(" |");
+    }
+
+    table.push('\n');
+}
+
+/// The fence language for a file extension, which is the extension itself unless it is a common alias.
+fn code_language(extension: &str) -> &str {
+    match extension {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" => "javascript",
+        "ts" => "typescript",
+        "rb" => "ruby",
+        "sh" => "bash",
+        "yml" => "yaml",
+        "md" => "markdown",
+        "htm" => "html",
+        "cs" => "csharp",
+        "hpp" | "cc" => "cpp",
+        extension => extension,
+    }
+}
+
+/// Remove the leading whitespace that every non-blank line has in common.
+fn dedent(code: &str) -> String {
+    let indent = code
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or_default();
+
+    code.lines()
+        .map(|line| line.get(indent..).unwrap_or_else(|| line.trim_start()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Resolve a path given to a directive relative to the location of the journal entry, failing if it is outside
+/// of the journal root and that is not allowed.
+fn resolve_path(
+    ctx: &PreprocessorContext,
+    config: &DirectiveConfig,
+    entry: &JournalEntry,
+    directive: &str,
+    path: &Path,
+) -> Result<PathBuf> {
+    let Some(ref entry_path) = entry.path else {
+        anyhow::bail!(
+            "The given journal entry has no file path and cannot have {directive} directives"
+        );
+    };
+
+    let (source, relative_path) = ctx.config.resolve_location(entry_path)?;
+
+    // NOTE: An entry linked from a named root can include files from anywhere inside of that root.
+    let boundary = match relative_path == entry_path {
+        true => ctx.root.clone(),
+        false => ctx.root.join(source),
+    };
+
+    let resolved = ctx.entry_dir(entry)?.join(path);
+
+    if !config.allow_outside_root && !canonicalize(&resolved).starts_with(canonicalize(&boundary)) {
+        anyhow::bail!(
+            "The path {} of a {directive} directive is outside of the journal root. Set `allow-outside-root` in \
+             the `[directive]` table of journal.toml to allow it.",
+            path.display()
+        );
+    }
+
+    Ok(resolved)
+}
+
+/// Resolve the symbolic links and `.` and `..` components of a path. Components that do not exist are resolved
+/// without touching the file system, so that a missing file can still be checked.
+pub(crate) fn canonicalize(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut resolved = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            component => {
+                resolved.push(component);
+
+                if let Ok(canonical) = resolved.canonicalize() {
+                    resolved = canonical;
+                }
+            }
+        }
+    }
+
+    resolved
+}
+
+/// Parse the arguments of a template directive in the form of `path key="value" ...`.
+/// Values are split like shell words, so quoted values may contain spaces.
+fn parse_template_args(args: &str) -> Result<(PathBuf, HashMap<String, String>)> {
+    let mut words = Shlex::new(args);
+    let Some(path) = words.next() else {
+        anyhow::bail!("A #template directive requires a path")
+    };
+
+    let mut values = HashMap::new();
+    for word in &mut words {
+        let Some((key, value)) = word.split_once('=') else {
+            anyhow::bail!("Template arguments must be in the form of key=\"value\": {word}")
+        };
+
+        if values.insert(key.to_string(), value.to_string()).is_some() {
+            anyhow::bail!("Template argument was provided more than once: {key}")
+        }
+    }
+
+    if words.had_error {
+        anyhow::bail!("Unbalanced quotes in #template directive: {}", args.trim())
+    }
+
+    Ok((PathBuf::from(path), values))
+}
+
+/// Replace every `{{key}}` placeholder in the template with its value. Placeholders without a value are
+/// an error, while values that no placeholder references are reported as a warning.
+fn render_template(template: &str, values: &HashMap<String, String>) -> Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut missing = Vec::new();
+    let mut used = Vec::new();
+    let mut input = template;
+
+    while let Some(start) = input.find("{{") {
+        let rest = &input[start + 2..];
+        let placeholder = rest
+            .find(CLOSE_SEQUENCE)
+            .map(|end| (&rest[..end], end))
+            .filter(|(key, _)| is_placeholder(key.trim()));
+
+        let Some((key, end)) = placeholder else {
+            rendered.push_str(&input[..start + 2]);
+            input = rest;
+            continue;
+        };
+
+        let key = key.trim();
+        rendered.push_str(&input[..start]);
+
+        match values.get(key) {
+            Some(value) => {
+                rendered.push_str(value);
+                used.push(key);
+            }
+            None => missing.push(key),
+        }
+
+        input = &rest[end + CLOSE_SEQUENCE.len()..];
+    }
+
+    rendered.push_str(input);
+
+    if !missing.is_empty() {
+        missing.sort_unstable();
+        missing.dedup();
+        anyhow::bail!(
+            "Missing values for template placeholders: {}",
+            missing.join(", ")
+        )
+    }
+
+    let mut unused: Vec<_> = values
+        .keys()
+        .filter(|key| !used.contains(&key.as_str()))
+        .map(String::as_str)
+        .collect();
+
+    if !unused.is_empty() {
+        unused.sort_unstable();
+        log::warn!(
+            "Template arguments are not referenced by the template: {}",
+            unused.join(", ")
+        );
+    }
+
+    Ok(rendered)
+}
+
+fn is_placeholder(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Parse the arguments of a directive in the form of `value` or `value | "default"`, as taken by the include and
+/// env directives.
+fn parse_default_args<'a>(args: &'a str, directive: &str) -> Result<(&'a str, Option<String>)> {
+    let Some((value, default)) = args.split_once('|') else {
+        return Ok((args.trim(), None));
+    };
+
+    let default = default.trim();
+    let Some(default) = default
+        .strip_prefix('"')
+        .and_then(|default| default.strip_suffix('"'))
+    else {
+        anyhow::bail!("The default of an {directive} directive must be a quoted string: {default}")
+    };
+
+    Ok((value.trim(), Some(default.replace("\\\"", "\""))))
+}
+
 #[cfg(test)]
 mod test {
-    use std::path::PathBuf;
+    use std::{collections::BTreeMap, fs, path::PathBuf};
 
     use super::*;
-    use crate::{build::preprocess::PreprocessorContext, config::Config};
+    use crate::{
+        build::preprocess::PreprocessorContext,
+        config::Config,
+        model::journal::{JournalItem, Matter},
+    };
 
     fn new_journal(input: &str) -> Journal {
         Journal {
@@ -137,8 +848,21 @@ mod test {
                 body: Some(String::from(input)),
                 sections: Vec::new(),
                 path: None,
+                source_dir: None,
                 level: 1,
+                children: Vec::new(),
+                tags: Vec::new(),
+                content_hash: None,
+                excerpt: None,
+                number: None,
+                chapter: None,
+                heading_offset: 0,
+                matter: Matter::Body,
+                link_title: None,
+                draft: false,
             })],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
         }
     }
 
@@ -171,6 +895,65 @@ mod test {
         assert_eq!("Test Title", entry.title)
     }
 
+    #[test]
+    fn keeps_the_last_of_several_titles_unless_strict() {
+        let body = "{{#title First}}\n# Heading\n{{#title Second}}";
+        let preprocess = |strict| {
+            let mut config = Config::default();
+            config.build.strict = strict;
+            let ctx = PreprocessorContext::new(PathBuf::from("test"), config);
+
+            DirectivePreprocessor::new().run(&ctx, new_journal_with_path(body))
+        };
+
+        let journal = preprocess(false).expect("should preprocess");
+        let JournalItem::Entry(ref entry) = journal.items[0] else {
+            panic!("first item was not an entry")
+        };
+
+        assert_eq!("Second", entry.title);
+
+        let err = preprocess(true).expect_err("should fail when strict");
+
+        assert_eq!(
+            "Entry entry.md has 2 {{#title ...}} directives, the last of which titles it \"Second\"",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn leaves_title_without_a_title_for_the_title_transformer() {
+        let preprocess = |body| {
+            let preprocessor = DirectivePreprocessor::new();
+            let ctx = PreprocessorContext::new(PathBuf::from("test"), Config::default());
+            let journal = preprocessor
+                .run(&ctx, new_journal(body))
+                .expect("should preprocess");
+
+            let JournalItem::Entry(ref entry) = journal.items[0] else {
+                panic!("first item was not an entry")
+            };
+
+            (entry.title.clone(), entry.body.clone().unwrap_or_default())
+        };
+
+        assert_eq!(
+            (String::from("Test"), String::from("{{#title}}\n# Heading")),
+            preprocess("{{#title  }}\n# Heading")
+        );
+        assert_eq!(
+            (String::from("Explicit"), String::from("\n# Heading")),
+            preprocess("{{#title}}{{#title Explicit}}\n# Heading")
+        );
+        assert_eq!(
+            (
+                String::from("Explicit"),
+                String::from("\\\\{{#title}}\n# Heading")
+            ),
+            preprocess("\\{{#title}}{{#title}}{{#title Explicit}}\n# Heading")
+        );
+    }
+
     #[test]
     #[should_panic]
     fn fails_with_unbalanced_braces() {
@@ -192,4 +975,549 @@ mod test {
 
         preprocessor.run(&ctx, journal).unwrap();
     }
+
+    fn new_journal_with_path(input: &str) -> Journal {
+        let mut journal = new_journal(input);
+        let JournalItem::Entry(ref mut entry) = journal.items[0] else {
+            unreachable!()
+        };
+        entry.path = Some(PathBuf::from("entry.md"));
+
+        journal
+    }
+
+    fn preprocess_body(root: &std::path::Path, journal: Journal) -> Result<String> {
+        let preprocessor = DirectivePreprocessor::new();
+        let ctx = PreprocessorContext::new(root.to_path_buf(), Config::default());
+        let journal = preprocessor.run(&ctx, journal)?;
+
+        let JournalItem::Entry(ref entry) = journal.items[0] else {
+            panic!("first item was not an entry")
+        };
+
+        Ok(entry.body.clone().unwrap_or_default())
+    }
+
+    #[test]
+    fn resolves_paths_relative_to_the_entry_directory() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        fs::create_dir_all(root.path().join("src/town")).expect("should create source dir");
+        fs::write(root.path().join("src/town/sign.txt"), "The Prancing Pony")
+            .expect("should write file");
+
+        let mut journal = new_journal("{{#include sign.txt}}\n\n{{#includecode ./sign.txt}}");
+        let JournalItem::Entry(ref mut entry) = journal.items[0] else {
+            unreachable!()
+        };
+        entry.path = Some(PathBuf::from("./town/inn.md"));
+
+        let ctx = PreprocessorContext::new(root.path().to_path_buf(), Config::default());
+        assert_eq!(
+            root.path().join("./src").join("./town"),
+            ctx.entry_dir(entry).expect("should find the directory")
+        );
+        assert!(ctx.entry_dir(&JournalEntry::new("Draft", "")).is_err());
+
+        let actual = preprocess_body(root.path(), journal).expect("should preprocess");
+
+        assert_eq!(
+            "The Prancing Pony\n\n```txt\nThe Prancing Pony\n```",
+            actual
+        );
+    }
+
+    #[test]
+    fn keeps_text_following_the_last_directive() {
+        let body = "{{#title Test Title}}\n# Heading";
+        let actual =
+            preprocess_body(&PathBuf::from("test"), new_journal(body)).expect("should preprocess");
+
+        assert_eq!("\n# Heading", actual);
+    }
+
+    #[test]
+    fn includes_default_when_file_is_missing() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        let body = r#"Before {{#include missing.md | "Nothing \"here\" yet"}} after"#;
+        let actual =
+            preprocess_body(root.path(), new_journal_with_path(body)).expect("should preprocess");
+
+        assert_eq!(r#"Before Nothing "here" yet after"#, actual);
+    }
+
+    #[test]
+    fn includes_file_instead_of_default_when_present() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        fs::create_dir_all(root.path().join("src")).expect("should create source dir");
+        fs::write(root.path().join("src/optional.md"), "Included").expect("should write include");
+
+        let body = r#"{{#include optional.md | "Nothing here yet"}}"#;
+        let actual =
+            preprocess_body(root.path(), new_journal_with_path(body)).expect("should preprocess");
+
+        assert_eq!("Included", actual);
+    }
+
+    #[test]
+    fn rejects_includes_outside_of_the_root() {
+        let outside = tempfile::tempdir().expect("should create temp dir");
+        let root = outside.path().join("journal");
+        fs::create_dir_all(root.join("src")).expect("should create source dir");
+        fs::write(outside.path().join("secret.md"), "Secret").expect("should write secret");
+
+        for body in [
+            "{{#include ../../secret.md}}",
+            "{{#includecode ../../secret.md}}",
+            "{{#template ../../secret.md}}",
+            r#"{{#include ../../missing.md | "default"}}"#,
+        ] {
+            let err = preprocess_body(&root, new_journal_with_path(body))
+                .expect_err("traversal should fail");
+
+            assert!(
+                err.to_string().contains("outside of the journal root"),
+                "{body}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn includes_files_from_sibling_directories_in_the_root() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        fs::create_dir_all(root.path().join("src")).expect("should create source dir");
+        fs::create_dir_all(root.path().join("shared")).expect("should create shared dir");
+        fs::write(root.path().join("shared/npc.md"), "Borin").expect("should write include");
+
+        let actual = preprocess_body(
+            root.path(),
+            new_journal_with_path("{{#include ../shared/npc.md}}"),
+        )
+        .expect("should preprocess");
+
+        assert_eq!("Borin", actual);
+    }
+
+    #[test]
+    fn includes_files_outside_of_the_root_when_allowed() {
+        let outside = tempfile::tempdir().expect("should create temp dir");
+        let root = outside.path().join("journal");
+        fs::create_dir_all(root.join("src")).expect("should create source dir");
+        fs::write(outside.path().join("shared.md"), "Shared").expect("should write include");
+
+        let config: Config = "[directive]\nallow-outside-root = true"
+            .parse()
+            .expect("should parse");
+        let ctx = PreprocessorContext::new(root.clone(), config);
+        let journal = DirectivePreprocessor::new()
+            .run(&ctx, new_journal_with_path("{{#include ../../shared.md}}"))
+            .expect("should preprocess");
+
+        let JournalItem::Entry(ref entry) = journal.items[0] else {
+            panic!("first item was not an entry")
+        };
+
+        assert_eq!(Some("Shared"), entry.body.as_deref());
+    }
+
+    #[test]
+    fn rejects_remote_includes_unless_allowed() {
+        let err = preprocess_body(
+            Path::new("test"),
+            new_journal_with_path("{{#include_url https://example.com/srd/fireball.md}}"),
+        )
+        .expect_err("should fail");
+
+        assert!(format!("{err:#}").contains(
+            "includes https://example.com/srd/fireball.md, but remote includes are disabled"
+        ));
+
+        // NOTE: A longer directive name starting with `include_url` is neither a remote include nor an include.
+        let body = "{{#include_urls https://example.com/srd/fireball.md}}";
+        let actual = preprocess_body(Path::new("test"), new_journal_with_path(body))
+            .expect("should leave the directive");
+
+        assert_eq!(body, actual);
+    }
+
+    #[test]
+    fn leaves_entry_includes_for_the_include_entry_transformer() {
+        let body = "Intro\n\n{{#include_entry \"The Tavern\" sections}}";
+
+        let actual = preprocess_body(Path::new("test"), new_journal_with_path(body))
+            .expect("should preprocess");
+
+        assert_eq!(body, actual);
+
+        let escaped = preprocess_body(
+            Path::new("test"),
+            new_journal_with_path(r#"\{{#include_entry "The Tavern"}}"#),
+        )
+        .expect("should preprocess");
+
+        assert_eq!(r#"\\{{#include_entry "The Tavern"}}"#, escaped);
+    }
+
+    #[test]
+    fn leaves_embeds_for_the_embed_transformer() {
+        let body = "Intro\n\n{{#embed places/tavern.md#The Cellar}}";
+
+        let actual = preprocess_body(Path::new("test"), new_journal_with_path(body))
+            .expect("should preprocess");
+
+        assert_eq!(body, actual);
+    }
+
+    #[test]
+    fn leaves_exempt_entries_unchanged() {
+        let body = "<!--dungeon-mark: no-directives -->\n# Syntax\n\nUse {{#include file.md}} or \\{{#title Title}}.";
+
+        let actual = preprocess_body(Path::new("test"), new_journal_with_path(body))
+            .expect("should preprocess");
+
+        assert_eq!(body, actual);
+        assert!(!is_exempt("# Syntax\n<!-- dungeon-mark: no-directives -->"));
+        assert!(!is_exempt("<!-- dungeon-mark: directives -->"));
+    }
+
+    #[test]
+    fn fails_on_missing_include_without_default() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        let body = "{{#include missing.md}}";
+
+        assert!(preprocess_body(root.path(), new_journal_with_path(body)).is_err());
+    }
+
+    #[test]
+    fn fails_on_unquoted_include_default() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        let body = "{{#include missing.md | Nothing here yet}}";
+
+        assert!(preprocess_body(root.path(), new_journal_with_path(body)).is_err());
+    }
+
+    #[test]
+    fn keeps_escaped_directives_literally() {
+        let body = r"Before \{{#title Escaped}}{{#title Real}} after \{{#include a.md}} \{{#if renderer=html}} \{{#wordcount}}";
+        let journal = new_journal(body);
+        let preprocessor = DirectivePreprocessor::new();
+        let ctx = PreprocessorContext::new(PathBuf::from("test"), Config::default());
+        let journal = preprocessor.run(&ctx, journal).expect("should preprocess");
+
+        let JournalItem::Entry(ref entry) = journal.items[0] else {
+            panic!("first item was not an entry")
+        };
+
+        assert_eq!("Real", entry.title);
+        assert_eq!(
+            Some(
+                r"Before {{#title Escaped}} after {{#include a.md}} \\{{#if renderer=html}} \\{{#wordcount}}"
+            ),
+            entry.body.as_deref()
+        );
+    }
+
+    #[test]
+    fn parses_dice() {
+        assert_eq!(
+            Dice {
+                count: 2,
+                sides: 6,
+                modifier: 1
+            },
+            Dice::parse(" 2d6+1").unwrap()
+        );
+        assert_eq!(
+            Dice {
+                count: 1,
+                sides: 20,
+                modifier: -2
+            },
+            Dice::parse(" d20-2").unwrap()
+        );
+
+        for args in ["", " 2", " 0d6", " 2d0", " 2d", " 2d6+", " 2x6", " 1001d6"] {
+            assert!(Dice::parse(args).is_err(), "{args} should be rejected");
+        }
+    }
+
+    #[test]
+    fn rolls_the_same_for_the_same_seed() {
+        let body = "{{#roll 2d6}} {{#roll d20+5}} {{#roll 4d100-3}} \\{{#roll d4}}";
+        let roll = |seed| {
+            let mut config = Config::default();
+            config.build.seed = Some(seed);
+            let ctx = PreprocessorContext::new(PathBuf::from("test"), config);
+            let journal = DirectivePreprocessor::new()
+                .run(&ctx, new_journal(body))
+                .expect("should preprocess");
+
+            let JournalItem::Entry(ref entry) = journal.items[0] else {
+                panic!("first item was not an entry")
+            };
+
+            entry.body.clone().expect("should have a body")
+        };
+
+        let rolls = roll(1234);
+        let totals: Vec<i64> = rolls
+            .split_whitespace()
+            .take(3)
+            .map(|total| total.parse().expect("should be a number"))
+            .collect();
+
+        assert_eq!(rolls, roll(1234));
+        assert_ne!(rolls, roll(4321));
+        assert!((2..=12).contains(&totals[0]), "{rolls}");
+        assert!((6..=25).contains(&totals[1]), "{rolls}");
+        assert!((1..=397).contains(&totals[2]), "{rolls}");
+        assert!(rolls.ends_with(" {{#roll d4}}"), "{rolls}");
+    }
+
+    #[test]
+    fn substitutes_environment_variables() {
+        let substitute = |body, strict| {
+            let mut config = Config::default();
+            config.build.strict = strict;
+            let mut ctx = PreprocessorContext::new(PathBuf::from("test"), config);
+            ctx.environment = Some(HashMap::from([(
+                String::from("BASE_URL"),
+                String::from("https://example.com"),
+            )]));
+
+            let journal = DirectivePreprocessor::new().run(&ctx, new_journal_with_path(body))?;
+            let JournalItem::Entry(ref entry) = journal.items[0] else {
+                panic!("first item was not an entry")
+            };
+
+            Ok::<_, anyhow::Error>(entry.body.clone().unwrap_or_default())
+        };
+
+        assert_eq!(
+            "[Home](https://example.com/) https://example.com",
+            substitute(
+                r#"[Home]({{#env BASE_URL}}/) {{#env BASE_URL | "http://localhost"}}"#,
+                true
+            )
+            .expect("should substitute")
+        );
+        assert_eq!(
+            "http://localhost \"quoted\" {{#env MISSING}}",
+            substitute(
+                r#"{{#env MISSING | "http://localhost"}} {{#env QUOTED | "\"quoted\""}} \{{#env MISSING}}"#,
+                true
+            )
+            .expect("should substitute defaults")
+        );
+        assert_eq!(
+            "Visit  today",
+            substitute("Visit {{#env MISSING}} today", false).expect("should substitute nothing")
+        );
+        assert_eq!(
+            "Entry entry.md uses the environment variable MISSING, which is not set",
+            substitute("{{#env MISSING}}", true)
+                .expect_err("should fail when strict")
+                .to_string()
+        );
+        assert_eq!(
+            "{{#environment BASE_URL}}",
+            substitute("{{#environment BASE_URL}}", true).expect("should leave other directives")
+        );
+        assert!(substitute("{{#env}}", false).is_err());
+        assert!(substitute("{{#env BASE_URL | unquoted}}", false).is_err());
+    }
+
+    #[test]
+    fn renders_template_with_quoted_values() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        fs::create_dir_all(root.path().join("src")).expect("should create source dir");
+        fs::write(
+            root.path().join("src/npc.md"),
+            "## {{name}}\n*{{ race }}*, {{name}}",
+        )
+        .expect("should write template");
+
+        let body = r#"{{#template npc.md name="Borin Stonefist" race=Dwarf}}"#;
+        let actual =
+            preprocess_body(root.path(), new_journal_with_path(body)).expect("should preprocess");
+
+        assert_eq!("## Borin Stonefist\n*Dwarf*, Borin Stonefist", actual);
+
+        let body = "{{#templates}}";
+        let actual =
+            preprocess_body(root.path(), new_journal_with_path(body)).expect("should preprocess");
+
+        assert_eq!(body, actual);
+    }
+
+    #[test]
+    fn fails_on_template_placeholder_without_value() {
+        let values = HashMap::from([(String::from("name"), String::from("Borin"))]);
+        let err = render_template("{{name}} the {{race}}", &values).expect_err("should fail");
+
+        assert!(err.to_string().contains("race"));
+    }
+
+    #[test]
+    fn ignores_unused_template_values_and_non_placeholders() {
+        let values = HashMap::from([
+            (String::from("name"), String::from("Borin")),
+            (String::from("race"), String::from("Dwarf")),
+        ]);
+        let actual = render_template("{{name}} {{not a placeholder}}", &values)
+            .expect("should render template");
+
+        assert_eq!("Borin {{not a placeholder}}", actual);
+    }
+
+    #[test]
+    fn fails_on_malformed_template_arguments() {
+        assert!(parse_template_args("").is_err());
+        assert!(parse_template_args("npc.md name").is_err());
+        assert!(parse_template_args(r#"npc.md name="Borin"#).is_err());
+        assert!(parse_template_args("npc.md name=a name=b").is_err());
+    }
+
+    #[test]
+    fn includes_code_fenced_and_dedented() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        fs::create_dir_all(root.path().join("src/scripts")).expect("should create source dir");
+        fs::write(
+            root.path().join("src/scripts/init.lua"),
+            "    local x = 1\r\n\r\n    if x then\r\n      print(\"```\")\r\n    end\r\n",
+        )
+        .expect("should write script");
+
+        let body = "{{#includecode scripts/init.lua}}";
+        let actual =
+            preprocess_body(root.path(), new_journal_with_path(body)).expect("should preprocess");
+
+        assert_eq!(
+            "````lua\nlocal x = 1\n\nif x then\n  print(\"```\")\nend\n````",
+            actual
+        );
+
+        let body = "{{#includecodes}}";
+        let actual =
+            preprocess_body(root.path(), new_journal_with_path(body)).expect("should preprocess");
+
+        assert_eq!(body, actual);
+    }
+
+    #[test]
+    fn detects_code_languages_from_extensions() {
+        assert_eq!("rust", code_language("rs"));
+        assert_eq!("toml", code_language("toml"));
+        assert_eq!("```\ntext\n```", fence_code("text", Path::new("notes")));
+    }
+
+    #[test]
+    fn includes_csv_and_json_data_as_tables() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        fs::create_dir_all(root.path().join("src/data")).expect("should create source dir");
+        fs::write(
+            root.path().join("src/data/loot.csv"),
+            "Roll,Item\n1,\"Rope, 50 ft\"\n2,\"A \"\"lucky\"\" coin | copper\"\n",
+        )
+        .expect("should write csv");
+        fs::write(
+            root.path().join("src/data/foes.json"),
+            r#"[{"name": "Goblin", "hp": 7}, {"name": "Ogre", "notes": "Hungry\nand loud", "hp": null}]"#,
+        )
+        .expect("should write json");
+
+        let body = "{{#table data/loot.csv}}\n{{#table data/foes.json}}";
+        let actual =
+            preprocess_body(root.path(), new_journal_with_path(body)).expect("should preprocess");
+
+        assert_eq!(
+            "| Roll | Item |\n| --- | --- |\n| 1 | Rope, 50 ft |\n| 2 | A \"lucky\" coin \\| copper |\n\n\
+             | name | hp | notes |\n| --- | --- | --- |\n| Goblin | 7 |  |\n| Ogre |  | Hungry and loud |\n",
+            actual
+        );
+
+        let body = "{{#tableofcontents}}";
+        let actual =
+            preprocess_body(root.path(), new_journal_with_path(body)).expect("should preprocess");
+
+        assert_eq!(body, actual);
+    }
+
+    #[test]
+    fn fails_on_malformed_table_data() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        fs::create_dir_all(root.path().join("src")).expect("should create source dir");
+        fs::write(root.path().join("src/rows.csv"), "a,b\n1,2,3\n").expect("should write csv");
+        fs::write(root.path().join("src/rows.json"), r#"{"a": 1}"#).expect("should write json");
+        fs::write(root.path().join("src/rows.txt"), "a").expect("should write text");
+
+        for (file, problem) in [
+            ("rows.csv", "found record with 3 fields"),
+            ("rows.json", "Expected an array of objects"),
+            ("rows.txt", "only be read from .csv or .json"),
+        ] {
+            let body = format!("{{{{#table {file}}}}}");
+            let err = preprocess_body(root.path(), new_journal_with_path(&body))
+                .expect_err("should fail");
+            let message = format!("{err:#}");
+
+            assert!(message.contains("failed to read table:"), "{message}");
+            assert!(message.contains(file), "{message}");
+            assert!(message.contains(problem), "{message}");
+        }
+    }
+
+    #[test]
+    fn fails_on_unrecognized_directives_when_strict() {
+        let preprocess = |body, strict| {
+            let mut config = Config::default();
+            config.build.strict_directives = strict;
+            let ctx = PreprocessorContext::new(PathBuf::from("test"), config);
+
+            let journal = DirectivePreprocessor::new().run(&ctx, new_journal_with_path(body))?;
+            let JournalItem::Entry(ref entry) = journal.items[0] else {
+                panic!("first item was not an entry")
+            };
+
+            Ok::<_, anyhow::Error>((entry.title.clone(), entry.body.clone().unwrap_or_default()))
+        };
+
+        let recognized = "{{#title Inn}}{{#wordcount}} {{#embed inn.md#Cellar}} {{#if renderer=html}}x{{#endif}}";
+        assert_eq!(
+            (
+                String::from("Inn"),
+                String::from(
+                    "{{#wordcount}} {{#embed inn.md#Cellar}} {{#if renderer=html}}x{{#endif}}"
+                )
+            ),
+            preprocess(recognized, true).expect("should preprocess")
+        );
+
+        let err = preprocess("Ale {{#titel Inn}}", true).expect_err("should fail when strict");
+        assert_eq!(
+            "Entry entry.md has an unrecognized directive: {{#titel Inn}}",
+            err.to_string()
+        );
+
+        assert_eq!(
+            (String::from("Test"), String::from("Ale {{#titel Inn}}")),
+            preprocess("Ale {{#titel Inn}}", false).expect("should preprocess")
+        );
+    }
+
+    #[test]
+    fn accepts_the_directives_of_transformers_when_strict() {
+        let mut config = Config::default();
+        config.build.strict_directives = true;
+        let mut ctx = PreprocessorContext::new(PathBuf::from("test"), config);
+        ctx.transformer_directives = vec![String::from("npc")];
+
+        let journal = DirectivePreprocessor::new()
+            .run(&ctx, new_journal_with_path("Ale {{#npc Borin}}"))
+            .expect("should preprocess");
+        let JournalItem::Entry(ref entry) = journal.items[0] else {
+            panic!("first item was not an entry")
+        };
+
+        assert_eq!(Some("Ale {{#npc Borin}}"), entry.body.as_deref());
+    }
 }
@@ -1,9 +1,26 @@
+mod command;
 pub(crate) mod directive;
+mod random;
+mod remote;
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{cell::RefCell, collections::HashMap, path::PathBuf};
 
-use crate::{config::Config, error::Result, model::journal::Journal};
+use crate::{
+    config::Config,
+    error::Result,
+    model::{
+        journal::{Journal, JournalEntry},
+        toc::TableOfContents,
+    },
+};
+
+pub use command::*;
+use random::Rng;
+
+/// The priority of the built-in `directive` preprocessor. It runs ahead of preprocessors with the default
+/// priority of 0, so that they see entries with their directives expanded.
+pub const DIRECTIVE_PRIORITY: i32 = -100;
 
 /// A preprocessor will take a journal with unparsed entries (all contents are in the body, no sections)
 /// and transforms that journal prior to running it through the parsing stage.
@@ -11,6 +28,13 @@ pub trait Preprocessor {
     fn name(&self) -> &str;
 
     fn run(&self, ctx: &PreprocessorContext, journal: Journal) -> Result<Journal>;
+
+    /// The order this preprocessor runs in relative to other preprocessors, from lowest to highest. Preprocessors
+    /// with the same priority run in the order they were added. See `DIRECTIVE_PRIORITY` for the position of
+    /// the built-in `directive` preprocessor.
+    fn priority(&self) -> i32 {
+        0
+    }
 }
 
 #[non_exhaustive]
@@ -21,10 +45,66 @@ pub struct PreprocessorContext {
 
     /// Configuration for the journal from the journal.toml file.
     pub config: Config,
+
+    /// The table of contents the journal was loaded from, for resolving references between entries.
+    #[serde(default)]
+    pub table_of_contents: TableOfContents,
+
+    /// The random number generator shared by every preprocessor of the build, seeded from `build.seed`.
+    #[serde(skip)]
+    rng: RefCell<Rng>,
+
+    /// Environment variables read in place of the process environment, see `PreprocessorContext::env_var`.
+    #[serde(skip)]
+    pub(crate) environment: Option<HashMap<String, String>>,
+
+    /// The keywords of the directives the transformers of the build declare, see `Transformer::directives`.
+    #[serde(skip)]
+    pub(crate) transformer_directives: Vec<String>,
 }
 
 impl PreprocessorContext {
     pub(crate) fn new(root: PathBuf, config: Config) -> Self {
-        Self { root, config }
+        let rng = match config.build.seed {
+            Some(seed) => Rng::new(seed),
+            None => Rng::from_time(),
+        };
+
+        Self {
+            root,
+            config,
+            table_of_contents: TableOfContents::default(),
+            rng: RefCell::new(rng),
+            environment: None,
+            transformer_directives: Vec::new(),
+        }
+    }
+
+    /// A random number from 0 up to, but not including, `upper`, drawn from a single stream shared by every
+    /// preprocessor of the build. With `build.seed` set, the stream is the same for every build, so the numbers
+    /// drawn only change when the seed changes or something draws from the stream in a different order. Each
+    /// language edition starts the stream over. Returns 0 when `upper` is 0.
+    pub fn random_below(&self, upper: u64) -> u64 {
+        if upper == 0 {
+            return 0;
+        }
+
+        self.rng.borrow_mut().below(upper)
+    }
+
+    /// The directory of the entry's file, joined to the journal root, which paths written in the entry, such as
+    /// those of `{{#include}}` directives, are relative to. See `Config::resolve_entry_dir`, which this fails like,
+    /// such as for entries without a path, like drafts, and for paths in a named root that is not configured.
+    pub fn entry_dir(&self, entry: &JournalEntry) -> Result<PathBuf> {
+        Ok(self.root.join(self.config.resolve_entry_dir(entry)?))
+    }
+
+    /// The value of an environment variable for the build, or `None` when it is unset or not valid unicode.
+    /// Read from the process environment, unless the context was given its own environment variables.
+    pub fn env_var(&self, name: &str) -> Option<String> {
+        match self.environment {
+            Some(ref environment) => environment.get(name).cloned(),
+            None => std::env::var(name).ok(),
+        }
     }
 }
@@ -0,0 +1,55 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A small SplitMix64 generator, which is fast and stable across platforms and releases, so that a seed always
+/// produces the same numbers. It is not suitable for anything that needs to be unpredictable.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A generator seeded from the current time, for builds without a configured seed.
+    pub(crate) fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos());
+
+        Self::new(nanos as u64)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        value ^ (value >> 31)
+    }
+
+    /// A number from 0 up to, but not including, `upper`, which must not be 0.
+    pub(crate) fn below(&mut self, upper: u64) -> u64 {
+        ((u128::from(self.next_u64()) * u128::from(upper)) >> 64) as u64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn repeats_the_stream_of_a_seed() {
+        let rolls = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..8).map(|_| rng.below(20)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(rolls(1234), rolls(1234));
+        assert_ne!(rolls(1234), rolls(1));
+        assert!(rolls(1234).iter().all(|value| *value < 20));
+    }
+}
@@ -0,0 +1,227 @@
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[cfg(feature = "remote-include")]
+use crate::cmark::normalize_source;
+use crate::error::Result;
+
+/// The directory, relative to the journal root, that the bodies of remote includes are cached in.
+const REMOTE_CACHE_DIR: &str = "build/.cache/remote";
+
+/// The body of the document at `url`, read from the cache under `root` when it was fetched before, or else fetched
+/// with `timeout` and cached, so that a build only goes to the network for URLs it has not seen yet. A cached copy
+/// older than `ttl` is fetched again; without a `ttl` it is kept until `build/.cache/remote` is removed.
+pub(crate) fn fetch_remote_include(
+    root: &Path,
+    url: &str,
+    timeout: Duration,
+    ttl: Option<Duration>,
+) -> Result<String> {
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        anyhow::bail!("Remote includes must be http or https URLs, found: {url}");
+    }
+
+    let cached = cache_path(root, url);
+
+    if is_fresh(&cached, ttl) {
+        if let Ok(body) = fs::read_to_string(&cached) {
+            return Ok(body);
+        }
+    }
+
+    let body = fetch(url, timeout).with_context(|| format!("Failed to fetch {url}"))?;
+
+    if let Some(parent) = cached.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    fs::write(&cached, &body)
+        .with_context(|| format!("Failed to cache {url} at {}", cached.display()))?;
+
+    Ok(body)
+}
+
+/// The file the body of `url` is cached in, named after a hash of the URL.
+fn cache_path(root: &Path, url: &str) -> PathBuf {
+    let hash: String = Sha256::digest(url.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    root.join(REMOTE_CACHE_DIR).join(hash)
+}
+
+/// Whether the cached copy at `path` exists and was written less than `ttl` ago.
+fn is_fresh(path: &Path, ttl: Option<Duration>) -> bool {
+    let Ok(modified) = fs::metadata(path).and_then(|metadata| metadata.modified()) else {
+        return false;
+    };
+
+    match ttl {
+        Some(ttl) => modified.elapsed().is_ok_and(|age| age < ttl),
+        None => true,
+    }
+}
+
+#[cfg(feature = "remote-include")]
+fn fetch(url: &str, timeout: Duration) -> Result<String> {
+    let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+
+    let response = match agent.get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(status, response)) => {
+            anyhow::bail!(
+                "The server responded with {status} {}",
+                response.status_text()
+            )
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    if !(200..300).contains(&response.status()) {
+        anyhow::bail!(
+            "The server responded with {} {}",
+            response.status(),
+            response.status_text()
+        );
+    }
+
+    Ok(normalize_source(response.into_string()?))
+}
+
+#[cfg(not(feature = "remote-include"))]
+fn fetch(_url: &str, _timeout: Duration) -> Result<String> {
+    anyhow::bail!("dungeon-mark was built without the `remote-include` feature")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "remote-include")]
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    /// Serve a single request on a local port with the given response, returning the URL to request.
+    #[cfg(feature = "remote-include")]
+    fn serve_once(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("should bind");
+        let address = listener.local_addr().expect("should have an address");
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("should accept");
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(response.as_bytes());
+        });
+
+        format!("http://{address}/srd/fireball.md")
+    }
+
+    #[cfg(feature = "remote-include")]
+    #[test]
+    fn fetches_and_caches_remote_includes() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        let url = serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 19\r\nConnection: close\r\n\r\nA ball of fire.\r\n\r\n",
+        );
+
+        let fetched = fetch_remote_include(root.path(), &url, Duration::from_secs(5), None)
+            .expect("should fetch");
+        // NOTE: The server only answers once, so the second include must come from the cache.
+        let cached = fetch_remote_include(root.path(), &url, Duration::from_secs(5), None)
+            .expect("should cache");
+
+        assert_eq!("A ball of fire.\n\n", fetched);
+        assert_eq!(fetched, cached);
+        assert!(cache_path(root.path(), &url).is_file());
+    }
+
+    #[cfg(feature = "remote-include")]
+    #[test]
+    fn fails_naming_the_url_on_error_responses() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        let url =
+            serve_once("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+
+        let err = fetch_remote_include(root.path(), &url, Duration::from_secs(5), None)
+            .expect_err("should fail");
+
+        assert!(format!("{err:#}").contains(&format!("Failed to fetch {url}")));
+        assert!(format!("{err:#}").contains("404 Not Found"));
+        assert!(!cache_path(root.path(), &url).exists());
+    }
+
+    #[cfg(feature = "remote-include")]
+    #[test]
+    fn fails_naming_the_url_on_timeouts() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        let listener = TcpListener::bind("127.0.0.1:0").expect("should bind");
+        let url = format!(
+            "http://{}/slow.md",
+            listener.local_addr().expect("should have an address")
+        );
+
+        let err = fetch_remote_include(root.path(), &url, Duration::from_millis(200), None)
+            .expect_err("should time out");
+
+        assert!(format!("{err:#}").contains(&format!("Failed to fetch {url}")));
+        drop(listener);
+    }
+
+    #[cfg(feature = "remote-include")]
+    #[test]
+    fn fetches_cached_copies_older_than_the_ttl_again() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        let url = serve_once(
+            "HTTP/1.1 200 OK\r\nContent-Length: 16\r\nConnection: close\r\n\r\nA ball of fire.\n",
+        );
+        let cached = cache_path(root.path(), &url);
+        fs::create_dir_all(cached.parent().expect("should have a parent"))
+            .expect("should create cache dir");
+        fs::write(&cached, "A stale ball of fire.\n").expect("should write cached copy");
+
+        let kept = fetch_remote_include(
+            root.path(),
+            &url,
+            Duration::from_secs(5),
+            Some(Duration::from_secs(3600)),
+        )
+        .expect("should read the cache");
+        let fetched = fetch_remote_include(
+            root.path(),
+            &url,
+            Duration::from_secs(5),
+            Some(Duration::ZERO),
+        )
+        .expect("should fetch");
+
+        assert_eq!("A stale ball of fire.\n", kept);
+        assert_eq!("A ball of fire.\n", fetched);
+        assert_eq!(
+            fetched,
+            fs::read_to_string(cached).expect("should read cached copy")
+        );
+    }
+
+    #[test]
+    fn rejects_other_schemes() {
+        let err = fetch_remote_include(
+            Path::new("."),
+            "file:///etc/passwd",
+            Duration::from_secs(1),
+            None,
+        )
+        .expect_err("should fail");
+
+        assert!(err.to_string().contains("must be http or https URLs"));
+    }
+}
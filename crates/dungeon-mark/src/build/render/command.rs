@@ -1,79 +1,364 @@
-use shlex::Shlex;
+use anyhow::Context;
 use std::{
+    fs,
+    io::{self, BufWriter, Write},
     path::{Path, PathBuf},
-    process::{Command, Stdio},
+    process::{Child, ChildStdin, Command, ExitStatus, Stdio},
+    sync::Arc,
+    thread,
+    time::Duration,
 };
 
-use super::Renderer;
-use crate::error::Result;
+use super::{RenderContext, RenderOutput, Renderer};
+use crate::{build::command::build_command, error::Result, model::journal::Journal};
 
+/// The file a command renderer can write to its destination to report the files it created, as a JSON
+/// array of paths relative to the destination.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// A renderer backed by an external command, which receives the `RenderContext` as JSON on its stdin.
+///
+/// To report the files it created, the command can write a JSON array of their paths, relative to the
+/// destination, to [`MANIFEST_FILE`] in the destination. The manifest is removed once it has been read.
+///
+/// With a timeout, the command is killed and rendering fails if it has not exited once the timeout elapses.
+///
+/// With retries, a command that exits with a failure is run again, up to the number of retries, for commands that
+/// depend on flaky services. The delay between attempts doubles after each one. Commands that time out or cannot be
+/// started are not retried.
+///
+/// With streaming, the context is written as newline delimited JSON instead, so that the command can process
+/// the journal one item at a time. The first line is the `RenderContext` with an empty `journal.items`, and each
+/// following line is one of the journal's top level `JournalItem`s, in order. Nested entries stay in the
+/// `children` of the entry they are nested below. The items end when stdin is closed.
 pub struct CommandRenderer {
     name: String,
     command: Option<String>,
+    timeout: Option<Duration>,
+    streaming: bool,
+    retries: u32,
+    retry_delay: Duration,
 }
 
 impl CommandRenderer {
     pub fn new(name: String, command: Option<String>) -> Self {
-        Self { name, command }
+        Self {
+            name,
+            command,
+            timeout: None,
+            streaming: false,
+            retries: 0,
+            retry_delay: Duration::ZERO,
+        }
+    }
+
+    /// Kill the command and fail if it runs for longer than the timeout.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+
+        self
+    }
+
+    /// Write the context to the command as newline delimited JSON, one journal item per line.
+    pub fn with_streaming(mut self, streaming: bool) -> Self {
+        self.streaming = streaming;
+
+        self
+    }
+
+    /// Run the command again when it exits with a failure, up to `retries` more times, waiting `delay` before the
+    /// first retry and twice as long before each one after it.
+    pub fn with_retries(mut self, retries: u32, delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = delay;
+
+        self
     }
 }
 
 impl CommandRenderer {
     fn build_command(&self, root: &Path) -> Result<Command> {
         let command = self.command.as_ref().unwrap_or(&self.name);
-        let mut parts = Shlex::new(command);
-        let Some(bin) = parts.next() else {
-            anyhow::bail!("Provided command string was empty");
-        };
 
-        // NOTE: Get the path to the binary.
-        let bin = PathBuf::from(bin);
-        let bin = if bin.components().count() == 1 {
-            // NOTE: Search for the binary in PATH.
-            bin
+        build_command(root, command)
+    }
+}
+
+impl Renderer for CommandRenderer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn render(&self, ctx: RenderContext) -> anyhow::Result<RenderOutput> {
+        let destination = ctx.root.join(&ctx.destination);
+        let root = ctx.root.clone();
+
+        let status = if self.retries == 0 {
+            // NOTE: Without retries the context is only ever written once, so it is serialized straight into stdin.
+            let streaming = self.streaming;
+            self.run_command(&root, move |stdin| {
+                write_context(ctx, streaming, BufWriter::new(stdin))
+            })?
         } else {
-            // NOTE: Search for the binary relative to the project root.
-            root.join(bin)
+            self.run_with_retries(&root, ctx)?
         };
 
-        let mut command = Command::new(bin);
-        command.args(parts);
+        if !status.success() {
+            anyhow::bail!("Renderer {} failed ({}).", self.name, status);
+        }
 
-        Ok(command)
+        read_manifest(&destination)
     }
 }
 
-impl Renderer for CommandRenderer {
-    fn name(&self) -> &str {
-        &self.name
+impl CommandRenderer {
+    /// Run the command until it succeeds or runs out of retries, returning how the last attempt exited.
+    fn run_with_retries(&self, root: &Path, ctx: RenderContext) -> Result<ExitStatus> {
+        // NOTE: The context is serialized once, so that every attempt is given the same input and a failure to
+        // serialize it is not retried.
+        let mut input = Vec::new();
+        write_context(ctx, self.streaming, &mut input)?;
+
+        let mut delay = self.retry_delay;
+        let mut attempt = 1;
+
+        loop {
+            let status = self.run_command(root, |stdin| {
+                stdin.write_all(&input).map_err(serde_json::Error::io)
+            })?;
+
+            if status.success() || attempt > self.retries {
+                return Ok(status);
+            }
+
+            log::warn!(
+                "Renderer {} failed ({status}) on attempt {attempt} of {}, retrying in {} ms.",
+                self.name,
+                self.retries + 1,
+                delay.as_millis()
+            );
+
+            thread::sleep(delay);
+            delay = delay.saturating_mul(2);
+            attempt += 1;
+        }
     }
 
-    fn render(&self, ctx: super::RenderContext) -> anyhow::Result<()> {
+    /// Run the command once, writing its input to its stdin with `write_input`, and return how it exited.
+    fn run_command<F>(&self, root: &Path, write_input: F) -> Result<ExitStatus>
+    where
+        F: FnOnce(&mut ChildStdin) -> serde_json::Result<()> + Send,
+    {
         let mut process = self
-            .build_command(&ctx.root)?
+            .build_command(root)?
             .stdin(Stdio::piped())
             .stdout(Stdio::inherit())
             .stderr(Stdio::inherit())
             .spawn()?;
 
         let mut stdin = process.stdin.take().expect("Child process has stdin");
-        // TODO: Docs said this should be done on a separate thread to prevent a deadlock?
-        if let Err(err) = serde_json::to_writer(&mut stdin, &ctx) {
-            dbg!(err);
-            // TODO: Emit warnings about errors?
+
+        thread::scope(|scope| {
+            // NOTE: Write stdin on its own thread so that a child that stops reading cannot block the timeout.
+            // Stdin is closed as soon as the input is written, so that the child sees the end of its input.
+            let writer = scope.spawn(move || {
+                let written = write_input(&mut stdin);
+                drop(stdin);
+
+                written
+            });
+
+            let status = match self.timeout {
+                Some(timeout) => wait_with_timeout(&mut process, timeout)?,
+                None => Some(process.wait()?),
+            };
+
+            // NOTE: Once the child has exited, or been killed, the writer either finished or failed on the closed
+            // pipe.
+            let written = writer.join().expect("Renderer stdin writer panicked");
+
+            let Some(status) = status else {
+                anyhow::bail!(
+                    "Renderer {} timed out after {} seconds.",
+                    self.name,
+                    self.timeout.unwrap_or_default().as_secs_f64()
+                );
+            };
+
+            match written {
+                Err(err) if err.is_io() => {
+                    log::warn!("Failed to write to renderer {}: {err}", self.name)
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| {
+                        format!("Failed to serialize the input of renderer {}", self.name)
+                    })
+                }
+                Ok(()) => {}
+            }
+
+            Ok(status)
+        })
+    }
+}
+
+/// Write the context for the renderer's stdin, either as a single JSON document or, when streaming, as newline
+/// delimited JSON with one journal item per line.
+fn write_context(
+    mut ctx: RenderContext,
+    streaming: bool,
+    mut writer: impl Write,
+) -> serde_json::Result<()> {
+    if streaming {
+        // NOTE: The journal is shared, so the items are left out of a copy of it rather than taken out of it.
+        let journal = Arc::clone(&ctx.journal);
+        ctx.journal = Arc::new(Journal {
+            title: journal.title.clone(),
+            items: Vec::new(),
+            tags: journal.tags.clone(),
+            scratch: journal.scratch.clone(),
+        });
+
+        serde_json::to_writer(&mut writer, &ctx)?;
+        writer.write_all(b"\n").map_err(serde_json::Error::io)?;
+
+        for item in &journal.items {
+            serde_json::to_writer(&mut writer, item)?;
+            writer.write_all(b"\n").map_err(serde_json::Error::io)?;
         }
+    } else {
+        serde_json::to_writer(&mut writer, &ctx)?;
+    }
 
-        // NOTE: Explicitly drop stdin to close it.
-        drop(stdin);
+    writer.flush().map_err(serde_json::Error::io)
+}
 
-        let status = process.wait()?;
+/// Wait for the child to exit, killing it if it has not exited once the timeout elapses. Returns `None` if the
+/// child was killed.
+#[cfg(all(unix, not(target_os = "openbsd")))]
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> Result<Option<ExitStatus>> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let pid = rustix::process::Pid::from_child(child);
 
-        if !status.success() {
-            anyhow::bail!("Renderer {} failed ({}).", self.name, status);
+    // NOTE: The waiter is not scoped, so that a failure to kill the child cannot leave the build waiting for it.
+    thread::spawn(move || sender.send(wait_for_exit(pid)));
+
+    let killed = match receiver.recv_timeout(timeout) {
+        Ok(exited) => {
+            exited?;
+            false
+        }
+        Err(_) => {
+            child.kill()?;
+            true
+        }
+    };
+
+    let status = child.wait()?;
+
+    Ok((!killed).then_some(status))
+}
+
+/// Block until the child exits, without reaping it, so that it can still be killed until `Child::wait` reaps it.
+#[cfg(all(unix, not(target_os = "openbsd")))]
+fn wait_for_exit(pid: rustix::process::Pid) -> io::Result<()> {
+    use rustix::{
+        io::Errno,
+        process::{waitid, WaitId, WaitIdOptions},
+    };
+
+    loop {
+        match waitid(
+            WaitId::Pid(pid),
+            WaitIdOptions::EXITED | WaitIdOptions::NOWAIT,
+        ) {
+            Ok(_) => return Ok(()),
+            Err(Errno::INTR) => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Wait for the child to exit, killing it if it has not exited once the timeout elapses. Returns `None` if the
+/// child was killed.
+///
+/// Without a way to wait for the child without reaping it, which killing it relies on, it is polled instead.
+#[cfg(not(all(unix, not(target_os = "openbsd"))))]
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> Result<Option<ExitStatus>> {
+    /// How long to wait between checks on whether the child has exited.
+    const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+    let deadline = std::time::Instant::now() + timeout;
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+
+        let now = std::time::Instant::now();
+
+        if now >= deadline {
+            child.kill()?;
+            child.wait()?;
+
+            return Ok(None);
+        }
+
+        thread::sleep(POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+/// Read and remove the manifest a command renderer may leave in its destination, resolving the files it
+/// lists against the destination.
+fn read_manifest(destination: &Path) -> Result<RenderOutput> {
+    let path = destination.join(MANIFEST_FILE);
+    let manifest = match fs::read_to_string(&path) {
+        Ok(manifest) => manifest,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(RenderOutput::default()),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("Failed to read render manifest: {}", path.display()))
         }
+    };
+
+    let files: Vec<PathBuf> = serde_json::from_str(&manifest)
+        .with_context(|| format!("Invalid render manifest: {}", path.display()))?;
+    fs::remove_file(&path)
+        .with_context(|| format!("Failed to remove render manifest: {}", path.display()))?;
+
+    Ok(RenderOutput::new(
+        files.iter().map(|file| destination.join(file)).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_and_removes_the_manifest() {
+        let destination = tempfile::tempdir().expect("should create temp dir");
+        let manifest = destination.path().join(MANIFEST_FILE);
+        fs::write(&manifest, r#"["index.html", "pages/tavern.html"]"#)
+            .expect("should write manifest");
+
+        let output = read_manifest(destination.path()).expect("should read manifest");
+
+        assert_eq!(
+            vec![
+                destination.path().join("index.html"),
+                destination.path().join("pages/tavern.html")
+            ],
+            output.files
+        );
+        assert!(!manifest.exists());
+    }
 
-        // TODO: Handle errors
+    #[test]
+    fn treats_a_missing_manifest_as_empty() {
+        let destination = tempfile::tempdir().expect("should create temp dir");
+        let output = read_manifest(destination.path()).expect("should read manifest");
 
-        Ok(())
+        assert!(output.files.is_empty());
     }
 }
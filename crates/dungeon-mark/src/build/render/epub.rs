@@ -0,0 +1,515 @@
+use anyhow::Context;
+use pulldown_cmark::{html::push_html, CowStr, Event, Parser, Tag};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use super::{html::page_path, RenderContext, RenderOutput, Renderer};
+use crate::{
+    cmark::{parser_options, push_escaped, SlugStrategy, Slugger},
+    error::Result,
+    model::journal::{JournalEntry, JournalItem, Section},
+};
+
+const EPUB_FILE: &str = "journal.epub";
+const MIMETYPE: &str = "application/epub+zip";
+const CONTENT_DIR: &str = "OEBPS";
+const PAGE_EXTENSION: &str = "xhtml";
+const UNDETERMINED_LANGUAGE: &str = "und";
+
+const CONTAINER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n\
+<rootfiles>\n\
+<rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/>\n\
+</rootfiles>\n\
+</container>\n";
+
+const STYLESHEET: &str = "body{font-family:serif;line-height:1.5}\
+h1,h2,h3,h4,h5,h6{font-family:sans-serif}\
+nav ol{list-style:none}\
+pre{white-space:pre-wrap}";
+
+/// A built-in renderer that packages the journal as an EPUB 3 book named `journal.epub`, for reading it offline on
+/// e-readers. It is used for a `[[build.renderers]]` entry named `epub` that has no command.
+///
+/// The book has one XHTML page per journal entry, placed at the entry's path with an `.xhtml` extension, in the
+/// order of the table of contents, and a navigation document with the nesting of the table of contents. Entries
+/// listed under a chapter title are nested below it in the navigation. The title of the book is the journal's
+/// title, and its creators are the `journal.authors` of the configuration. Section headings get the same anchors
+/// as with the `html` renderer, and links to other entries' Markdown files are pointed at their pages, so that
+/// links to `entry.md#heading` resolve within the book. Metadata blocks are left out.
+///
+/// Raw HTML in entries is copied as it is, so it must also be valid XHTML. Images and other assets are not
+/// packaged.
+pub struct EpubRenderer;
+
+impl EpubRenderer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for EpubRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer for EpubRenderer {
+    fn name(&self) -> &str {
+        "epub"
+    }
+
+    fn render(&self, ctx: RenderContext) -> Result<RenderOutput> {
+        let title = ctx.journal.title.as_deref().unwrap_or("Journal");
+        let language = ctx.language.as_deref().unwrap_or(UNDETERMINED_LANGUAGE);
+        let mut pages = Vec::new();
+
+        ctx.journal.for_each_entry(|entry| {
+            if let Some(page) = epub_page_path(entry) {
+                let prefix = "../".repeat(page.components().count() - 1);
+                let xhtml = render_page(
+                    &entry.title,
+                    language,
+                    &prefix,
+                    &render_entry(entry, ctx.config.build.slug_strategy),
+                );
+                pages.push((page, xhtml));
+            }
+        });
+
+        let package = render_package(&ctx, title, language, &pages);
+        let navigation = render_page(
+            title,
+            language,
+            "",
+            &render_navigation(title, &ctx.journal.items),
+        );
+
+        let destination = ctx.root.join(&ctx.destination);
+        fs::create_dir_all(&destination)
+            .with_context(|| format!("Failed to create directory: {}", destination.display()))?;
+
+        let path = destination.join(EPUB_FILE);
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create EPUB: {}", path.display()))?;
+
+        let mut files = vec![
+            (
+                String::from("META-INF/container.xml"),
+                String::from(CONTAINER),
+            ),
+            (format!("{CONTENT_DIR}/content.opf"), package),
+            (format!("{CONTENT_DIR}/nav.xhtml"), navigation),
+            (format!("{CONTENT_DIR}/style.css"), String::from(STYLESHEET)),
+        ];
+        files.extend(
+            pages
+                .into_iter()
+                .map(|(page, xhtml)| (format!("{CONTENT_DIR}/{}", archive_path(&page)), xhtml)),
+        );
+
+        write_archive(file, &files)
+            .with_context(|| format!("Failed to write EPUB: {}", path.display()))?;
+
+        Ok(RenderOutput::new(vec![path]))
+    }
+}
+
+/// Write the EPUB container, with the uncompressed `mimetype` file first, as EPUB readers require.
+fn write_archive(file: File, files: &[(String, String)]) -> Result<()> {
+    let mut archive = ZipWriter::new(file);
+
+    archive.start_file(
+        "mimetype",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    archive.write_all(MIMETYPE.as_bytes())?;
+
+    for (name, contents) in files {
+        archive.start_file(name, FileOptions::default())?;
+        archive.write_all(contents.as_bytes())?;
+    }
+
+    archive.finish()?;
+
+    Ok(())
+}
+
+/// The location of an entry's page relative to the content directory of the book.
+fn epub_page_path(entry: &JournalEntry) -> Option<PathBuf> {
+    page_path(entry).map(|page| page.with_extension(PAGE_EXTENSION))
+}
+
+/// The path as used within the archive and the package, which always separates components with `/`.
+fn archive_path(path: &Path) -> String {
+    path.iter()
+        .map(|component| component.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A stable identifier for the book, derived from its title and authors, so that e-readers recognize a rebuilt
+/// book as a new version of the same book.
+fn identifier(title: &str, authors: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(title.as_bytes());
+
+    for author in authors {
+        hasher.update([0]);
+        hasher.update(author.as_bytes());
+    }
+
+    let hash = hasher
+        .finalize()
+        .iter()
+        .take(16)
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+
+    format!(
+        "urn:uuid:{}-{}-{}-{}-{}",
+        &hash[..8],
+        &hash[8..12],
+        &hash[12..16],
+        &hash[16..20],
+        &hash[20..]
+    )
+}
+
+fn render_package(
+    ctx: &RenderContext,
+    title: &str,
+    language: &str,
+    pages: &[(PathBuf, String)],
+) -> String {
+    let mut opf = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    opf.push_str(
+        "<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"uid\">\n",
+    );
+    opf.push_str("<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n");
+    push_element(
+        &mut opf,
+        "dc:identifier id=\"uid\"",
+        &identifier(title, &ctx.config.journal.authors),
+    );
+    push_element(&mut opf, "dc:title", title);
+    push_element(&mut opf, "dc:language", language);
+
+    for author in &ctx.config.journal.authors {
+        push_element(&mut opf, "dc:creator", author);
+    }
+
+    if let Some(ref description) = ctx.config.journal.description {
+        push_element(&mut opf, "dc:description", description);
+    }
+
+    push_element(
+        &mut opf,
+        "meta property=\"dcterms:modified\"",
+        &ctx.generated_at,
+    );
+    opf.push_str("</metadata>\n<manifest>\n");
+    opf.push_str(
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n",
+    );
+    opf.push_str("<item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n");
+
+    for (index, (page, _)) in pages.iter().enumerate() {
+        opf.push_str(&format!("<item id=\"page-{index}\" href=\""));
+        push_escaped(&mut opf, &archive_path(page));
+        opf.push_str("\" media-type=\"application/xhtml+xml\"/>\n");
+    }
+
+    opf.push_str("</manifest>\n<spine>\n");
+
+    for index in 0..pages.len() {
+        opf.push_str(&format!("<itemref idref=\"page-{index}\"/>\n"));
+    }
+
+    opf.push_str("</spine>\n</package>\n");
+
+    opf
+}
+
+fn push_element(xml: &mut String, element: &str, text: &str) {
+    let name = element.split(' ').next().unwrap_or(element);
+
+    xml.push('<');
+    xml.push_str(element);
+    xml.push('>');
+    push_escaped(xml, text);
+    xml.push_str(&format!("</{name}>\n"));
+}
+
+/// Render a page of the book, under `prefix` relative to the content directory.
+fn render_page(title: &str, language: &str, prefix: &str, content: &str) -> String {
+    let mut xhtml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n");
+    xhtml.push_str(
+        "<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"",
+    );
+    push_escaped(&mut xhtml, language);
+    xhtml.push_str("\" xml:lang=\"");
+    push_escaped(&mut xhtml, language);
+    xhtml.push_str("\">\n<head>\n<meta charset=\"utf-8\"/>\n<title>");
+    push_escaped(&mut xhtml, title);
+    xhtml.push_str("</title>\n<link rel=\"stylesheet\" type=\"text/css\" href=\"");
+    xhtml.push_str(prefix);
+    xhtml.push_str("style.css\"/>\n</head>\n<body>\n");
+    xhtml.push_str(content);
+    xhtml.push_str("</body>\n</html>\n");
+
+    xhtml
+}
+
+/// Render the navigation document's table of contents, nesting the items that follow a chapter title below it.
+fn render_navigation(title: &str, items: &[JournalItem]) -> String {
+    let mut xhtml = String::from("<nav epub:type=\"toc\" id=\"toc\">\n<h1>");
+    push_escaped(&mut xhtml, title);
+    xhtml.push_str("</h1>\n");
+    push_navigation_items(&mut xhtml, items);
+    xhtml.push_str("</nav>\n");
+
+    xhtml
+}
+
+fn push_navigation_items(xhtml: &mut String, items: &[JournalItem]) {
+    let mut chapters = Vec::new();
+    let mut chapter = (None, Vec::new());
+
+    for item in items {
+        match item {
+            JournalItem::ChapterTitle(title) => {
+                chapters.push(chapter);
+                chapter = (Some(title.title.as_str()), Vec::new());
+            }
+            JournalItem::Entry(entry) => chapter.1.push(entry),
+            // NOTE: Entries that failed to build have no page to link to.
+            JournalItem::Separator(_) | JournalItem::Error(_) => {}
+        }
+    }
+
+    chapters.push(chapter);
+
+    let mut list = String::new();
+
+    for (title, entries) in chapters {
+        match title {
+            Some(title) => {
+                let mut nested = String::new();
+                for entry in entries {
+                    push_navigation_entry(&mut nested, entry);
+                }
+
+                // NOTE: A navigation item without a link must have a nested list, so empty chapters are left out.
+                if !nested.is_empty() {
+                    list.push_str("<li><span>");
+                    push_escaped(&mut list, title);
+                    list.push_str("</span>\n<ol>\n");
+                    list.push_str(&nested);
+                    list.push_str("</ol>\n</li>\n");
+                }
+            }
+            None => {
+                for entry in entries {
+                    push_navigation_entry(&mut list, entry);
+                }
+            }
+        }
+    }
+
+    if !list.is_empty() {
+        xhtml.push_str("<ol>\n");
+        xhtml.push_str(&list);
+        xhtml.push_str("</ol>\n");
+    }
+}
+
+fn push_navigation_entry(xhtml: &mut String, entry: &JournalEntry) {
+    let mut children = String::new();
+    push_navigation_items(&mut children, &entry.children);
+
+    match epub_page_path(entry) {
+        Some(page) => {
+            xhtml.push_str("<li><a href=\"");
+            push_escaped(xhtml, &archive_path(&page));
+            xhtml.push_str("\">");
+            push_escaped(xhtml, &entry.title);
+            xhtml.push_str("</a>");
+        }
+        None if !children.is_empty() => {
+            xhtml.push_str("<li><span>");
+            push_escaped(xhtml, &entry.title);
+            xhtml.push_str("</span>");
+        }
+        None => return,
+    }
+
+    xhtml.push('\n');
+    xhtml.push_str(&children);
+    xhtml.push_str("</li>\n");
+}
+
+fn render_entry(entry: &JournalEntry, strategy: SlugStrategy) -> String {
+    let mut xhtml = String::new();
+    let mut slugger = Slugger::with_strategy(strategy);
+
+    if entry.sections.is_empty() {
+        xhtml.push_str("<h1>");
+        push_escaped(&mut xhtml, &entry.title);
+        xhtml.push_str("</h1>\n");
+    }
+
+    if let Some(ref body) = entry.body {
+        push_markdown(&mut xhtml, body);
+    }
+
+    for section in &entry.sections {
+        push_section(&mut xhtml, section, &mut slugger);
+    }
+
+    xhtml
+}
+
+fn push_section(xhtml: &mut String, section: &Section, slugger: &mut Slugger) {
+    let slug = section.anchor(slugger);
+    let level = section.level.as_u8();
+
+    xhtml.push_str(&format!("<h{level} id=\""));
+    push_escaped(xhtml, &slug);
+    xhtml.push_str("\">");
+    push_escaped(xhtml, &section.title);
+    xhtml.push_str(&format!("</h{level}>\n"));
+    push_markdown(xhtml, &section.body);
+
+    for section in &section.sections {
+        push_section(xhtml, section, slugger);
+    }
+}
+
+/// Render Markdown, pointing links to the Markdown files of entries at their pages.
+fn push_markdown(xhtml: &mut String, markdown: &str) {
+    let events = Parser::new_ext(markdown, parser_options()).map(|event| match event {
+        Event::Start(Tag::Link(kind, destination, title)) => {
+            Event::Start(Tag::Link(kind, page_link(destination), title))
+        }
+        Event::End(Tag::Link(kind, destination, title)) => {
+            Event::End(Tag::Link(kind, page_link(destination), title))
+        }
+        event => event,
+    });
+
+    push_html(xhtml, events);
+}
+
+/// The link to the page of a relative link to a Markdown file, keeping its fragment. Other links are unchanged.
+fn page_link(destination: CowStr<'_>) -> CowStr<'_> {
+    let (path, fragment) = match destination.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment)),
+        None => (destination.as_ref(), None),
+    };
+
+    if path.contains(':') || path.starts_with('/') || !path.ends_with(".md") {
+        return destination;
+    }
+
+    let mut link = format!("{}.{PAGE_EXTENSION}", path.trim_end_matches(".md"));
+
+    if let Some(fragment) = fragment {
+        link.push('#');
+        link.push_str(fragment);
+    }
+
+    CowStr::from(link)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::journal::ChapterTitle;
+
+    fn entry(title: &str, path: Option<&str>, children: Vec<JournalItem>) -> JournalItem {
+        JournalItem::Entry(JournalEntry {
+            title: String::from(title),
+            path: path.map(PathBuf::from),
+            children,
+            ..Default::default()
+        })
+    }
+
+    fn chapter(title: &str) -> JournalItem {
+        JournalItem::ChapterTitle(ChapterTitle {
+            title: String::from(title),
+            anchor: String::new(),
+        })
+    }
+
+    #[test]
+    fn nests_entries_below_their_chapter_titles() {
+        let items = vec![
+            entry("Intro", Some("./intro.md"), Vec::new()),
+            chapter("Empty"),
+            chapter("Places"),
+            entry(
+                "Town",
+                None,
+                vec![entry("Tavern", Some("./town/tavern.md"), Vec::new())],
+            ),
+            entry("Draft", None, Vec::new()),
+        ];
+
+        let mut xhtml = String::new();
+        push_navigation_items(&mut xhtml, &items);
+
+        assert_eq!(
+            "<ol>\n<li><a href=\"intro.xhtml\">Intro</a>\n</li>\n\
+             <li><span>Places</span>\n<ol>\n<li><span>Town</span>\n\
+             <ol>\n<li><a href=\"town/tavern.xhtml\">Tavern</a>\n</li>\n</ol>\n</li>\n</ol>\n</li>\n</ol>\n",
+            xhtml
+        );
+    }
+
+    #[test]
+    fn points_links_to_entries_at_their_pages() {
+        for (link, expected) in [
+            ("tavern.md", "tavern.xhtml"),
+            ("../town/tavern.md#cellar", "../town/tavern.xhtml#cellar"),
+            ("#cellar", "#cellar"),
+            (
+                "https://example.com/notes.md",
+                "https://example.com/notes.md",
+            ),
+            ("map.png", "map.png"),
+        ] {
+            assert_eq!(expected, page_link(CowStr::from(link)).as_ref());
+        }
+    }
+
+    #[test]
+    fn renders_tables_and_strikethrough() {
+        let mut xhtml = String::new();
+        push_markdown(
+            &mut xhtml,
+            "| Name | HP |\n| --- | --- |\n| Snik | 7 |\n\n~~Dead~~ Fled to [the cave](cave.md)",
+        );
+
+        assert!(xhtml.contains("<td>Snik</td>"), "{xhtml}");
+        assert!(xhtml.contains("<del>Dead</del>"), "{xhtml}");
+        assert!(xhtml.contains("href=\"cave.xhtml\""), "{xhtml}");
+    }
+
+    #[test]
+    fn derives_a_stable_identifier() {
+        let authors = vec![String::from("Ada")];
+        let id = identifier("Campaign", &authors);
+
+        assert_eq!(id, identifier("Campaign", &authors));
+        assert_ne!(id, identifier("Campaign", &[]));
+        assert_eq!(45, id.len());
+        assert!(id.starts_with("urn:uuid:"));
+    }
+}
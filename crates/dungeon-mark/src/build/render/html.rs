@@ -0,0 +1,257 @@
+use anyhow::Context;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{relative_output_path, RenderContext, RenderOutput, Renderer};
+use crate::{
+    cmark::{push_escaped, SlugStrategy},
+    error::Result,
+    model::journal::{Journal, JournalEntry, JournalItem},
+};
+
+const STYLESHEET: &str = "body{display:flex;margin:0;font-family:sans-serif;line-height:1.5}\
+nav{min-width:16rem;padding:1rem;background:#f4f4f4}\
+nav ul{list-style:none;padding-left:1rem}\
+main{max-width:48rem;padding:1rem 2rem}";
+
+/// A built-in renderer that writes the journal as a static HTML site, with one page per journal entry and an
+/// `index.html` holding the navigation. Each entry's page is placed at the entry's path with an `.html`
+/// extension. Section headings get an `id` from their `slug` metadata if they have one, otherwise from a
+/// slug of their title, so that links to `page.html#heading` resolve.
+///
+/// This renderer is used for a `[[build.renderers]]` entry named `html` that has no command.
+pub struct HtmlRenderer;
+
+impl HtmlRenderer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for HtmlRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer for HtmlRenderer {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn render(&self, ctx: RenderContext) -> Result<RenderOutput> {
+        let destination = ctx.root.join(&ctx.destination);
+        let title = ctx.journal.title.as_deref().unwrap_or("Journal");
+        let mut files = Vec::new();
+
+        ctx.journal.try_for_each_entry(|entry| {
+            let Some(page) = page_path(entry) else {
+                return Ok(());
+            };
+
+            let prefix = "../".repeat(page.components().count() - 1);
+            let html = render_page(
+                &entry.title,
+                &render_navigation(&ctx.journal, &prefix),
+                &entry.to_html_with_strategy(ctx.config.build.slug_strategy),
+            );
+
+            let page = ctx.output_path_for(entry, "html");
+            write_page(&page, &html)?;
+            files.push(page);
+
+            Ok(())
+        })?;
+
+        let mut heading = String::from("<h1>");
+        push_escaped(&mut heading, title);
+        heading.push_str("</h1>\n");
+
+        let index = render_page(title, &render_navigation(&ctx.journal, ""), &heading);
+
+        let index_page = destination.join("index.html");
+        write_page(&index_page, &index)?;
+        files.push(index_page);
+
+        Ok(RenderOutput::new(files))
+    }
+}
+
+/// The location of an entry's page relative to the output directory.
+pub(crate) fn page_path(entry: &JournalEntry) -> Option<PathBuf> {
+    entry.path.as_ref()?;
+
+    // NOTE: Only drafts are named after a slug, and they have no page.
+    Some(relative_output_path(entry, "html", SlugStrategy::default()))
+}
+
+fn write_page(path: &Path, html: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    fs::write(path, html).with_context(|| format!("Failed to write page: {}", path.display()))
+}
+
+fn render_page(title: &str, navigation: &str, content: &str) -> String {
+    let mut html =
+        String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>");
+    push_escaped(&mut html, title);
+    html.push_str("</title>\n<style>");
+    html.push_str(STYLESHEET);
+    html.push_str("</style>\n</head>\n<body>\n<nav>\n");
+    html.push_str(navigation);
+    html.push_str("</nav>\n<main>\n");
+    html.push_str(content);
+    html.push_str("</main>\n</body>\n</html>\n");
+
+    html
+}
+
+/// Render the navigation of the journal as nested lists, with links relative to a page under `prefix`.
+fn render_navigation(journal: &Journal, prefix: &str) -> String {
+    let mut html = String::new();
+    html.push_str("<a href=\"");
+    html.push_str(prefix);
+    html.push_str("index.html\">");
+    push_escaped(&mut html, journal.title.as_deref().unwrap_or("Journal"));
+    html.push_str("</a>\n");
+    push_navigation_items(&mut html, &journal.items, prefix);
+
+    html
+}
+
+fn push_navigation_items(html: &mut String, items: &[JournalItem], prefix: &str) {
+    if items.is_empty() {
+        return;
+    }
+
+    html.push_str("<ul>\n");
+
+    for item in items {
+        match item {
+            JournalItem::Entry(entry) => {
+                html.push_str("<li>");
+
+                match page_path(entry) {
+                    Some(page) => {
+                        html.push_str("<a href=\"");
+                        push_escaped(html, &format!("{prefix}{}", page.display()));
+
+                        if let Some(ref title) = entry.link_title {
+                            html.push_str("\" title=\"");
+                            push_escaped(html, title);
+                        }
+
+                        html.push_str("\">");
+                        push_escaped(html, &entry.title);
+                        html.push_str("</a>");
+                    }
+                    None => push_escaped(html, &entry.title),
+                }
+
+                html.push('\n');
+                push_navigation_items(html, &entry.children, prefix);
+                html.push_str("</li>\n");
+            }
+            JournalItem::ChapterTitle(chapter) => {
+                if chapter.anchor.is_empty() {
+                    html.push_str("<li><strong>");
+                } else {
+                    html.push_str("<li id=\"");
+                    push_escaped(html, &chapter.anchor);
+                    html.push_str("\"><strong>");
+                }
+
+                push_escaped(html, &chapter.title);
+                html.push_str("</strong></li>\n");
+            }
+            JournalItem::Separator(separator) => match separator.label {
+                Some(ref label) => {
+                    html.push_str("<li><hr class=\"");
+                    push_escaped(html, label);
+                    html.push_str("\"></li>\n");
+                }
+                None => html.push_str("<li><hr></li>\n"),
+            },
+            JournalItem::Error(error) => {
+                html.push_str("<li class=\"error\">");
+                push_escaped(html, &error.title);
+                html.push('\n');
+                push_navigation_items(html, &error.children, prefix);
+                html.push_str("</li>\n");
+            }
+        }
+    }
+
+    html.push_str("</ul>\n");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::{journal::ChapterTitle, toc::Separator};
+
+    #[test]
+    fn places_pages_next_to_their_sources() {
+        let entry = JournalEntry {
+            path: Some(PathBuf::from("./town/tavern.md")),
+            ..Default::default()
+        };
+
+        assert_eq!(Some(PathBuf::from("town/tavern.html")), page_path(&entry));
+    }
+
+    #[test]
+    fn renders_link_titles_as_tooltips() {
+        let items = vec![JournalItem::Entry(JournalEntry {
+            title: String::from("Keep"),
+            path: Some(PathBuf::from("./keep.md")),
+            link_title: Some(String::from("The \"old\" keep")),
+            ..Default::default()
+        })];
+
+        let mut html = String::new();
+        push_navigation_items(&mut html, &items, "");
+
+        assert_eq!(
+            "<ul>\n<li><a href=\"keep.html\" title=\"The &quot;old&quot; keep\">Keep</a>\n</li>\n</ul>\n",
+            html
+        );
+    }
+
+    #[test]
+    fn renders_labeled_separators_with_a_class() {
+        let items = vec![
+            JournalItem::Separator(Separator::default()),
+            JournalItem::Separator(Separator::new(Some(String::from("page-break")))),
+        ];
+
+        let mut html = String::new();
+        push_navigation_items(&mut html, &items, "");
+
+        assert_eq!(
+            "<ul>\n<li><hr></li>\n<li><hr class=\"page-break\"></li>\n</ul>\n",
+            html
+        );
+    }
+
+    #[test]
+    fn renders_chapter_titles_with_their_anchor() {
+        let items = vec![JournalItem::ChapterTitle(ChapterTitle {
+            title: String::from("Part Two"),
+            anchor: String::from("part-two"),
+        })];
+
+        let mut html = String::new();
+        push_navigation_items(&mut html, &items, "");
+
+        assert_eq!(
+            "<ul>\n<li id=\"part-two\"><strong>Part Two</strong></li>\n</ul>\n",
+            html
+        );
+    }
+}
@@ -1,16 +1,74 @@
 mod command;
+mod epub;
+mod html;
+mod pandoc;
+mod search;
+mod single_file;
+mod source_map;
+mod timestamp;
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{
+    path::{Component, PathBuf},
+    sync::Arc,
+};
 
-use crate::{config::Config, error::Result, model::journal::Journal};
+use crate::{
+    cmark::SlugStrategy,
+    config::Config,
+    error::Result,
+    model::{
+        journal::{EntryError, Journal, JournalEntry},
+        toc::TableOfContents,
+    },
+};
+
+/// The file stem of the output of entries without a path whose title has nothing to slugify.
+const UNTITLED_STEM: &str = "untitled";
 
 pub use command::*;
+pub use epub::*;
+pub use html::*;
+pub use pandoc::*;
+pub use search::*;
+pub use single_file::*;
+pub use source_map::*;
+
+pub(crate) use timestamp::{build_time, format_timestamp};
 
 pub trait Renderer {
     fn name(&self) -> &str;
 
-    fn render(&self, ctx: RenderContext) -> Result<()>;
+    /// Render the journal, returning the files that were created.
+    fn render(&self, ctx: RenderContext) -> Result<RenderOutput>;
+}
+
+/// The output of a renderer run.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderOutput {
+    /// Every file the renderer created.
+    pub files: Vec<PathBuf>,
+    /// The entries that failed to build and were left out of the output, with `build.tolerant` set.
+    #[serde(default)]
+    pub errors: Vec<EntryError>,
+}
+
+impl RenderOutput {
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        Self {
+            files,
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl Extend<RenderOutput> for RenderOutput {
+    fn extend<T: IntoIterator<Item = RenderOutput>>(&mut self, iter: T) {
+        for output in iter {
+            self.files.extend(output.files);
+            self.errors.extend(output.errors);
+        }
+    }
 }
 
 #[non_exhaustive]
@@ -19,21 +77,207 @@ pub struct RenderContext {
     /// The root directory of the journal.toml file.
     pub root: PathBuf,
     /// The directory where the renderer **must** put its output.
-    /// This directory is not guaranteed to be empty nor to exist.
+    /// This directory is not guaranteed to be empty nor to exist, unless `build.clean` is set, in which case
+    /// it is created empty before the renderer runs.
     pub destination: PathBuf,
     /// The configuration of the book.
     pub config: Config,
-    /// The journal itself.
-    pub journal: Journal,
+    /// The language code of the edition being rendered, if the journal has one.
+    pub language: Option<String>,
+    /// The journal itself, shared rather than owned, so that handing it to a renderer, or sharing it between the
+    /// threads of one, does not copy it. Command renderers receive it serialized as the journal itself.
+    pub journal: Arc<Journal>,
+    /// The table of contents the journal was loaded from, preserving the nesting of its links, which
+    /// is flattened away in the journal's items.
+    pub table_of_contents: TableOfContents,
+    /// When the journal was rendered, as an RFC 3339 timestamp in UTC such as `2024-03-01T12:30:00Z`, for
+    /// footers like "generated at". Every renderer of a build gets the same time, which is taken from
+    /// `JournalBuilder::with_build_time` or the `SOURCE_DATE_EPOCH` environment variable when either is set, for
+    /// reproducible builds.
+    #[serde(default)]
+    pub generated_at: String,
+    /// The version of dungeon-mark that rendered the journal.
+    #[serde(default)]
+    pub tool_version: String,
 }
 
 impl RenderContext {
-    pub fn new(root: PathBuf, destination: PathBuf, config: Config, journal: Journal) -> Self {
+    /// A context for rendering the journal to `destination`, with the language of `config`, the current time and
+    /// this version of dungeon-mark. The `with_*` methods change the fields with defaults, and fields added later
+    /// get such a method rather than a parameter, so that this constructor stays the same.
+    pub fn new(
+        root: PathBuf,
+        destination: PathBuf,
+        config: Config,
+        journal: impl Into<Arc<Journal>>,
+        table_of_contents: TableOfContents,
+    ) -> Self {
         Self {
             root,
             destination,
+            language: config.journal.language.clone(),
             config,
-            journal,
+            journal: journal.into(),
+            table_of_contents,
+            generated_at: format_timestamp(timestamp::now()),
+            tool_version: String::from(env!("CARGO_PKG_VERSION")),
         }
     }
+
+    /// Set the language code of the edition being rendered, rather than `journal.language` of the configuration.
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Set the table of contents the journal was loaded from.
+    pub fn with_table_of_contents(mut self, table_of_contents: TableOfContents) -> Self {
+        self.table_of_contents = table_of_contents;
+        self
+    }
+
+    /// Set when the journal was rendered, rather than the time the context was created, such as to render every
+    /// renderer of a build with the same time.
+    pub fn with_generated_at(mut self, generated_at: impl Into<String>) -> Self {
+        self.generated_at = generated_at.into();
+        self
+    }
+
+    /// Set the version of the tool that rendered the journal, such as for a harness pinning the version in the
+    /// output it compares.
+    pub fn with_tool_version(mut self, tool_version: impl Into<String>) -> Self {
+        self.tool_version = tool_version.into();
+        self
+    }
+
+    /// The file a renderer writing one file per entry writes the entry to: the entry's path relative to the
+    /// source, under the destination, with its extension replaced by `extension`, such as `town/tavern.html` for
+    /// `./town/tavern.md`. Drafts, which have no path, are named after a slug of their title instead, made with
+    /// `build.slug-strategy`. Renderers linking between entries can rely on the same mapping for every entry.
+    pub fn output_path_for(&self, entry: &JournalEntry, extension: &str) -> PathBuf {
+        self.root.join(&self.destination).join(relative_output_path(
+            entry,
+            extension,
+            self.config.build.slug_strategy,
+        ))
+    }
+}
+
+/// The location of an entry's output file relative to the destination, as described by
+/// `RenderContext::output_path_for`.
+pub(crate) fn relative_output_path(
+    entry: &JournalEntry,
+    extension: &str,
+    strategy: SlugStrategy,
+) -> PathBuf {
+    let path = match entry.path {
+        Some(ref path) => path
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .collect(),
+        None => match strategy.slugify(&entry.title) {
+            slug if slug.is_empty() => PathBuf::from(UNTITLED_STEM),
+            slug => PathBuf::from(slug),
+        },
+    };
+
+    path.with_extension(extension)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn context() -> RenderContext {
+        RenderContext::new(
+            PathBuf::from("root"),
+            PathBuf::from("build/html"),
+            Config::default(),
+            Journal {
+                title: None,
+                items: Vec::new(),
+                tags: BTreeMap::new(),
+                scratch: BTreeMap::new(),
+            },
+            TableOfContents::default(),
+        )
+    }
+
+    #[test]
+    fn shares_the_journal_and_serializes_it_as_the_journal_itself() {
+        let journal = Arc::new(Journal::from_entries(
+            Some(String::from("Campaign")),
+            vec![JournalEntry::new("Tavern", "Ale")],
+        ));
+        let ctx = RenderContext::new(
+            PathBuf::from("root"),
+            PathBuf::from("build/html"),
+            Config::default(),
+            Arc::clone(&journal),
+            TableOfContents::default(),
+        );
+
+        assert!(Arc::ptr_eq(&journal, &ctx.journal));
+
+        let json = serde_json::to_value(&ctx).expect("should serialize");
+        let context: RenderContext =
+            serde_json::from_value(json.clone()).expect("should deserialize");
+
+        assert_eq!(
+            serde_json::to_value(&*journal).expect("should serialize"),
+            json["journal"]
+        );
+        assert_eq!(*journal, *context.journal);
+    }
+
+    #[test]
+    fn overrides_defaults_with_setters() {
+        let ctx = context()
+            .with_language(Some(String::from("de")))
+            .with_generated_at("2024-03-01T12:30:00Z")
+            .with_tool_version("0.0.0");
+
+        assert_eq!(Some("de"), ctx.language.as_deref());
+        assert_eq!("2024-03-01T12:30:00Z", ctx.generated_at);
+        assert_eq!("0.0.0", ctx.tool_version);
+    }
+
+    #[test]
+    fn places_output_next_to_the_source_under_the_destination() {
+        let entry = JournalEntry {
+            path: Some(PathBuf::from("./town/tavern.md")),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            PathBuf::from("root/build/html/town/tavern.html"),
+            context().output_path_for(&entry, "html")
+        );
+    }
+
+    #[test]
+    fn names_drafts_after_their_title() {
+        let draft = |title: &str| JournalEntry {
+            title: String::from(title),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            PathBuf::from("root/build/html/the-old-mill.xhtml"),
+            context().output_path_for(&draft("The Old Mill!"), "xhtml")
+        );
+        assert_eq!(
+            PathBuf::from("root/build/html/untitled.html"),
+            context().output_path_for(&draft("?"), "html")
+        );
+
+        let mut context = context();
+        context.config.build.slug_strategy = SlugStrategy::Underscore;
+
+        assert_eq!(
+            PathBuf::from("root/build/html/the_old_mill.html"),
+            context.output_path_for(&draft("The Old Mill!"), "html")
+        );
+    }
 }
@@ -0,0 +1,222 @@
+use anyhow::Context;
+use std::fs;
+
+use super::{RenderContext, RenderOutput, Renderer};
+use crate::{
+    error::Result,
+    model::journal::{JournalEntry, JournalItem, Section},
+};
+
+const PANDOC_FILE: &str = "pandoc.md";
+const MAX_HEADING_LEVEL: u8 = 6;
+
+/// A built-in renderer that writes the whole journal to a single `pandoc.md`, for turning it into a PDF or
+/// other documents with Pandoc. It is used for a `[[build.renderers]]` entry named `pandoc` that has no command.
+///
+/// The file starts with a Pandoc title block made from the journal's title and the `journal.authors` of the
+/// configuration, followed by every entry in order. The headings of each entry are shifted so that its highest
+/// headings are at the entry's level in the table of contents, so top level entries start at H1 and nested
+/// entries one level lower for each level of nesting, keeping the nesting of the entry's own sections. Headings
+/// are never shifted past H6. An entry without any headings is given one with its title. Chapter titles of the
+/// table of contents become H1 headings and separators become rules. Metadata blocks are left out.
+pub struct PandocRenderer;
+
+impl PandocRenderer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for PandocRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer for PandocRenderer {
+    fn name(&self) -> &str {
+        "pandoc"
+    }
+
+    fn render(&self, ctx: RenderContext) -> Result<RenderOutput> {
+        let mut blocks = Vec::new();
+
+        if let Some(block) = title_block(ctx.journal.title.as_deref(), &ctx.config.journal.authors)
+        {
+            blocks.push(block);
+        }
+
+        for item in ctx.journal.iter() {
+            match item {
+                JournalItem::Entry(entry) => render_entry(entry, &mut blocks),
+                JournalItem::ChapterTitle(chapter) => blocks.push(format!("# {}", chapter.title)),
+                JournalItem::Separator(_) => blocks.push(String::from("---")),
+                JournalItem::Error(_) => {}
+            }
+        }
+
+        let mut markdown = blocks.join("\n\n");
+        markdown.push('\n');
+
+        let destination = ctx.root.join(&ctx.destination);
+        fs::create_dir_all(&destination)
+            .with_context(|| format!("Failed to create directory: {}", destination.display()))?;
+
+        let path = destination.join(PANDOC_FILE);
+        fs::write(&path, markdown)
+            .with_context(|| format!("Failed to write Pandoc document: {}", path.display()))?;
+
+        Ok(RenderOutput::new(vec![path]))
+    }
+}
+
+/// The Pandoc title block, with the authors separated by `;`. Returns `None` without a title or authors.
+fn title_block(title: Option<&str>, authors: &[String]) -> Option<String> {
+    if title.is_none() && authors.is_empty() {
+        return None;
+    }
+
+    let mut block = format!("% {}", title.unwrap_or_default());
+    block.truncate(block.trim_end().len());
+
+    if !authors.is_empty() {
+        block.push_str(&format!("\n% {}", authors.join("; ")));
+    }
+
+    Some(block)
+}
+
+fn render_entry(entry: &JournalEntry, blocks: &mut Vec<String>) {
+    let start = entry.level.clamp(1, MAX_HEADING_LEVEL);
+
+    if let Some(body) = entry.body.as_deref().map(str::trim) {
+        if entry.sections.is_empty() {
+            blocks.push(format!("{} {}", "#".repeat(start.into()), entry.title));
+        }
+
+        if !body.is_empty() {
+            blocks.push(String::from(body));
+        }
+    }
+
+    let Some(highest) = entry
+        .iter_sections()
+        .map(|section| section.level.as_u8())
+        .min()
+    else {
+        return;
+    };
+
+    for section in &entry.sections {
+        render_section(section, highest, start, blocks);
+    }
+}
+
+/// Render a section with its heading moved from `highest` to `start`, along with its nested sections.
+fn render_section(section: &Section, highest: u8, start: u8, blocks: &mut Vec<String>) {
+    let level = (section.level.as_u8() - highest + start).min(MAX_HEADING_LEVEL);
+    blocks.push(format!("{} {}", "#".repeat(level.into()), section.title));
+
+    let body = section.body.trim();
+    if !body.is_empty() {
+        blocks.push(String::from(body));
+    }
+
+    for section in &section.sections {
+        render_section(section, highest, start, blocks);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        model::{
+            journal::{ChapterTitle, Journal},
+            toc::TableOfContents,
+        },
+    };
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    fn entry(title: &str, level: u8, body: &str, children: Vec<JournalItem>) -> JournalItem {
+        let entry = JournalEntry {
+            title: String::from(title),
+            level,
+            body: Some(String::from(body)),
+            children,
+            ..Default::default()
+        };
+
+        JournalItem::Entry(entry.parse().expect("should parse"))
+    }
+
+    #[test]
+    fn writes_the_title_block() {
+        let authors = vec![String::from("Ada"), String::from("Borin")];
+
+        assert_eq!(
+            Some(String::from("% Campaign\n% Ada; Borin")),
+            title_block(Some("Campaign"), &authors)
+        );
+        assert_eq!(
+            Some(String::from("%\n% Ada; Borin")),
+            title_block(None, &authors)
+        );
+        assert_eq!(
+            Some(String::from("% Campaign")),
+            title_block(Some("Campaign"), &[])
+        );
+        assert_eq!(None, title_block(None, &[]));
+    }
+
+    #[test]
+    fn shifts_headings_to_the_level_of_each_entry() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        let mut config = Config::default();
+        config.journal.authors = vec![String::from("Ada")];
+
+        let journal = Journal {
+            title: Some(String::from("Campaign")),
+            items: vec![
+                JournalItem::ChapterTitle(ChapterTitle {
+                    title: String::from("Part One"),
+                    anchor: String::new(),
+                }),
+                entry(
+                    "Town",
+                    1,
+                    "Intro\n\n## Town\n\nStreets\n\n### Tavern\n\nAle",
+                    vec![entry("Dock", 2, "#### Dock\n\n##### Pier", vec![])],
+                ),
+                entry("Notes", 1, "Loose notes", vec![]),
+            ],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+        let toc = TableOfContents {
+            title: None,
+            items: Vec::new(),
+        };
+        let ctx = RenderContext::new(
+            root.path().to_path_buf(),
+            PathBuf::from("build/pandoc"),
+            config,
+            journal,
+            toc,
+        );
+
+        let output = PandocRenderer.render(ctx).expect("should render");
+        let markdown = fs::read_to_string(&output.files[0]).expect("should read output");
+
+        assert_eq!(
+            vec![root.path().join("build/pandoc/pandoc.md")],
+            output.files
+        );
+        assert_eq!(
+            "% Campaign\n% Ada\n\n# Part One\n\nIntro\n\n# Town\n\nStreets\n\n## Tavern\n\nAle\n\n## Dock\n\n\
+             ### Pier\n\n# Notes\n\nLoose notes\n",
+            markdown
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs};
+
+use super::{html::page_path, RenderContext, RenderOutput, Renderer};
+use crate::{
+    cmark::{plain_text, SlugStrategy, Slugger},
+    error::Result,
+    model::journal::{JournalEntry, Section},
+};
+
+const SEARCH_INDEX_FILE: &str = "searchindex.json";
+const SNIPPET_LENGTH: usize = 120;
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "has", "have", "he",
+    "her", "his", "i", "in", "is", "it", "its", "of", "on", "or", "she", "that", "the", "their",
+    "they", "this", "to", "was", "were", "will", "with", "you",
+];
+
+/// Configuration for the search index renderer, from the `[search-index]` table.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SearchIndexConfig {
+    /// Leave common English words such as "the" and "of" out of the index.
+    pub stop_words: bool,
+}
+
+/// A built-in renderer that writes a `searchindex.json` file for client-side search. It is used for a
+/// `[[build.renderers]]` entry named `search-index` that has no command.
+///
+/// The index has the following shape:
+///
+/// ```json
+/// {
+///   "documents": [
+///     {
+///       "page": "town/tavern.html",
+///       "entry": "The Tavern",
+///       "section": "Patrons",
+///       "anchor": "patrons",
+///       "snippet": "A handful of regulars..."
+///     }
+///   ],
+///   "index": {
+///     "regulars": [0]
+///   }
+/// }
+/// ```
+///
+/// Each document is either the body of an entry preceding its first heading, which has no `section` or
+/// `anchor`, or a single section. `page` and `anchor` match the pages and heading ids written by the
+/// `html` renderer. `index` maps every token to the positions of the documents containing it, in
+/// ascending order. Tokens are the lowercased runs of alphanumeric characters of a document's text
+/// with its markdown stripped.
+pub struct SearchIndexRenderer;
+
+impl SearchIndexRenderer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for SearchIndexRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer for SearchIndexRenderer {
+    fn name(&self) -> &str {
+        "search-index"
+    }
+
+    fn render(&self, ctx: RenderContext) -> Result<RenderOutput> {
+        let config: SearchIndexConfig = ctx.config.get("search-index")?;
+        let mut index = SearchIndex::default();
+
+        ctx.journal.for_each_entry(|entry| {
+            index.add_entry(entry, &config, ctx.config.build.slug_strategy)
+        });
+
+        let destination = ctx.root.join(&ctx.destination);
+        fs::create_dir_all(&destination)
+            .with_context(|| format!("Failed to create directory: {}", destination.display()))?;
+
+        let path = destination.join(SEARCH_INDEX_FILE);
+        let json = serde_json::to_string(&index)?;
+
+        fs::write(&path, json)
+            .with_context(|| format!("Failed to write search index: {}", path.display()))?;
+
+        Ok(RenderOutput::new(vec![path]))
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+struct SearchIndex {
+    documents: Vec<SearchDocument>,
+    index: BTreeMap<String, Vec<usize>>,
+}
+
+#[derive(Debug, Serialize)]
+struct SearchDocument {
+    page: Option<String>,
+    entry: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    section: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    anchor: Option<String>,
+    snippet: String,
+}
+
+impl SearchIndex {
+    fn add_entry(
+        &mut self,
+        entry: &JournalEntry,
+        config: &SearchIndexConfig,
+        strategy: SlugStrategy,
+    ) {
+        let page = page_path(entry).map(|page| page.display().to_string());
+        let mut slugger = Slugger::with_strategy(strategy);
+
+        if let Some(ref body) = entry.body {
+            let body = plain_text(body);
+            let document = SearchDocument {
+                page: page.clone(),
+                entry: entry.title.clone(),
+                section: None,
+                anchor: None,
+                snippet: snippet(&body),
+            };
+
+            self.add_document(document, &body, config);
+        }
+
+        self.add_sections(entry, &page, &entry.sections, &mut slugger, config);
+    }
+
+    fn add_sections(
+        &mut self,
+        entry: &JournalEntry,
+        page: &Option<String>,
+        sections: &[Section],
+        slugger: &mut Slugger,
+        config: &SearchIndexConfig,
+    ) {
+        for section in sections {
+            let body = plain_text(&section.body);
+            let document = SearchDocument {
+                page: page.clone(),
+                entry: entry.title.clone(),
+                section: Some(section.title.clone()),
+                anchor: Some(section.anchor(slugger)),
+                snippet: snippet(&body),
+            };
+
+            self.add_document(document, &format!("{}\n{body}", section.title), config);
+            self.add_sections(entry, page, &section.sections, slugger, config);
+        }
+    }
+
+    /// Add a document to the index, indexing every token of the provided text.
+    fn add_document(&mut self, document: SearchDocument, text: &str, config: &SearchIndexConfig) {
+        let position = self.documents.len();
+
+        for token in tokenize(text) {
+            if config.stop_words && STOP_WORDS.contains(&token.as_str()) {
+                continue;
+            }
+
+            let positions = self.index.entry(token).or_default();
+
+            if positions.last() != Some(&position) {
+                positions.push(position);
+            }
+        }
+
+        self.documents.push(document);
+    }
+}
+
+/// Split text into lowercase tokens on every character that is not alphanumeric.
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|character: char| !character.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// The start of the text with its whitespace collapsed, cut at a word boundary.
+fn snippet(text: &str) -> String {
+    let mut snippet = String::new();
+
+    for word in text.split_whitespace() {
+        if snippet.len() + word.len() > SNIPPET_LENGTH {
+            snippet.push_str("...");
+            break;
+        }
+
+        if !snippet.is_empty() {
+            snippet.push(' ');
+        }
+
+        snippet.push_str(word);
+    }
+
+    snippet
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::journal::{Metadata, SectionLevel};
+    use std::path::PathBuf;
+
+    fn entry() -> JournalEntry {
+        JournalEntry {
+            title: String::from("The Tavern"),
+            path: Some(PathBuf::from("./tavern.md")),
+            sections: vec![Section {
+                title: String::from("Patrons"),
+                level: SectionLevel::H1,
+                body: String::from("The *regulars* of the `Tavern`."),
+                metadata: Metadata::new(),
+                sections: Vec::new(),
+                span: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn indexes_sections_by_token() {
+        let mut index = SearchIndex::default();
+        index.add_entry(
+            &entry(),
+            &SearchIndexConfig::default(),
+            SlugStrategy::default(),
+        );
+
+        let document = &index.documents[0];
+
+        assert_eq!(Some("tavern.html"), document.page.as_deref());
+        assert_eq!(Some("patrons"), document.anchor.as_deref());
+        assert_eq!("The regulars of the Tavern.", document.snippet);
+        assert_eq!(Some(&vec![0]), index.index.get("regulars"));
+        assert_eq!(Some(&vec![0]), index.index.get("tavern"));
+        assert_eq!(Some(&vec![0]), index.index.get("the"));
+    }
+
+    #[test]
+    fn indexes_the_text_of_tables_and_strikethrough() {
+        let mut entry = entry();
+        entry.sections[0].body =
+            String::from("| Name | Drink |\n| --- | --- |\n| Bree | ~~Ale~~ |");
+
+        let mut index = SearchIndex::default();
+        index.add_entry(
+            &entry,
+            &SearchIndexConfig::default(),
+            SlugStrategy::default(),
+        );
+
+        assert_eq!("Name Drink Bree Ale", index.documents[0].snippet);
+        assert_eq!(Some(&vec![0]), index.index.get("ale"));
+    }
+
+    #[test]
+    fn filters_stop_words_when_enabled() {
+        let mut index = SearchIndex::default();
+        index.add_entry(
+            &entry(),
+            &SearchIndexConfig { stop_words: true },
+            SlugStrategy::default(),
+        );
+
+        assert_eq!(None, index.index.get("the"));
+        assert_eq!(None, index.index.get("of"));
+        assert_eq!(Some(&vec![0]), index.index.get("patrons"));
+    }
+
+    #[test]
+    fn cuts_snippets_at_word_boundaries() {
+        let text = "word ".repeat(50);
+        let snippet = snippet(&text);
+
+        assert!(snippet.len() <= SNIPPET_LENGTH + 3);
+        assert!(snippet.ends_with("word..."));
+    }
+}
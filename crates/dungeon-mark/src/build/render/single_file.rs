@@ -0,0 +1,517 @@
+use anyhow::Context;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+use super::{RenderContext, RenderOutput, Renderer};
+use crate::{
+    build::transform::wikilinks::relative_path,
+    cmark::{links, push_escaped, LinkKind, SlugStrategy, Slugger},
+    error::Result,
+    model::journal::{JournalEntry, JournalItem},
+};
+
+const BUNDLE_FILE: &str = "index.html";
+
+const STYLESHEET: &str = "body{display:flex;margin:0;font-family:sans-serif;line-height:1.5}\
+nav{position:sticky;top:0;align-self:flex-start;max-height:100vh;overflow-y:auto;min-width:16rem;padding:1rem;\
+background:#f4f4f4}\
+nav ul{list-style:none;padding-left:1rem}\
+main{max-width:48rem;padding:1rem 2rem}\
+article+article{border-top:1px solid #ddd}\
+img{max-width:100%}";
+
+/// A built-in renderer that writes the whole journal as a single self-contained `index.html`, for sharing it as
+/// one file, such as by email. Every entry is inlined into the page in document order, within an `<article>`
+/// whose id is a slug of the entry's title, and the navigation links to these ids. The stylesheet is embedded in
+/// the page, so that it makes no external requests.
+///
+/// Section headings get the same anchors as with the `html` renderer, made unique across the whole page by
+/// suffixing repeats. Inline links to the Markdown files of other entries, with or without a fragment, are
+/// pointed at the matching ids of the page. With `inline-images` set in the `[single-file]` table of
+/// `journal.toml`, local images up to `max-image-size` bytes are embedded as `data:` URIs. Any other local image
+/// is pointed at its file relative to the page, so that it still shows while the page is next to the journal.
+///
+/// This renderer is used for a `[[build.renderers]]` entry named `single-file` that has no command.
+pub struct SingleFileRenderer;
+
+/// Configuration for the single file renderer, read from the `[single-file]` table of `journal.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SingleFileConfig {
+    /// Embed local images in the page as `data:` URIs.
+    pub inline_images: bool,
+    /// The size in bytes of the largest image that is embedded, to keep the page small enough to send around.
+    /// Defaults to 100 KiB.
+    pub max_image_size: u64,
+}
+
+impl Default for SingleFileConfig {
+    fn default() -> Self {
+        Self {
+            inline_images: false,
+            max_image_size: 100 * 1024,
+        }
+    }
+}
+
+impl SingleFileRenderer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for SingleFileRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer for SingleFileRenderer {
+    fn name(&self) -> &str {
+        "single-file"
+    }
+
+    fn render(&self, ctx: RenderContext) -> Result<RenderOutput> {
+        let config: SingleFileConfig = ctx.config.get("single-file")?;
+        let strategy = ctx.config.build.slug_strategy;
+        let title = ctx.journal.title.as_deref().unwrap_or("Journal");
+
+        let destination = ctx.root.join(&ctx.destination);
+        let mut bundle = Bundle::new(strategy, destination.clone());
+        ctx.journal.for_each_entry(|entry| {
+            let directory = ctx.config.resolve_entry_dir(entry).ok();
+            bundle.add_anchors(entry, directory.map(|directory| ctx.root.join(directory)))
+        });
+
+        if config.inline_images {
+            bundle.images = Some(config);
+        }
+
+        let mut navigation = String::new();
+        let mut content = String::from("<h1>");
+        push_escaped(&mut navigation, title);
+        push_escaped(&mut content, title);
+        navigation.push('\n');
+        content.push_str("</h1>\n");
+
+        bundle.push_items(&mut navigation, &mut content, &ctx.journal.items);
+
+        fs::create_dir_all(&destination)
+            .with_context(|| format!("Failed to create directory: {}", destination.display()))?;
+
+        let path = destination.join(BUNDLE_FILE);
+        fs::write(&path, render_page(title, &navigation, &content))
+            .with_context(|| format!("Failed to write page: {}", path.display()))?;
+
+        Ok(RenderOutput::new(vec![path]))
+    }
+}
+
+/// The ids of an entry within the page.
+struct Anchors {
+    /// The id of the entry's article.
+    entry: String,
+    /// The ids of the entry's sections, by the anchor the section has on a page of its own.
+    sections: HashMap<String, String>,
+    /// The directory of the entry's file, which its images are relative to, for entries with a file.
+    directory: Option<PathBuf>,
+}
+
+/// The state of rendering the entries of a journal into one page.
+struct Bundle {
+    strategy: SlugStrategy,
+    /// The directory the page is written to, which images that are not inlined are linked relative to.
+    output: PathBuf,
+    /// The configuration to inline images with, when they are inlined.
+    images: Option<SingleFileConfig>,
+    /// The ids of every entry, in document order.
+    anchors: Vec<Anchors>,
+    /// The index in `anchors` of every entry with a path, by its normalized path.
+    entries: HashMap<PathBuf, usize>,
+    /// Makes the ids of `anchors`.
+    ids: Slugger,
+    /// Makes the ids of the page as it is written, which are the same as those of `anchors`, since it is given the
+    /// same text to slug in the same order.
+    slugger: Slugger,
+    /// The index in `anchors` of the next entry to write.
+    next: usize,
+}
+
+impl Bundle {
+    fn new(strategy: SlugStrategy, output: PathBuf) -> Self {
+        Self {
+            strategy,
+            output,
+            images: None,
+            anchors: Vec::new(),
+            entries: HashMap::new(),
+            ids: Slugger::with_strategy(strategy),
+            slugger: Slugger::with_strategy(strategy),
+            next: 0,
+        }
+    }
+
+    /// Work out the ids of the next entry in document order, before any entry is written, so that links can be
+    /// pointed at entries further down the page. `directory` is the directory of the entry's file.
+    fn add_anchors(&mut self, entry: &JournalEntry, directory: Option<PathBuf>) {
+        let mut own = Slugger::with_strategy(self.strategy);
+
+        let anchors = Anchors {
+            entry: entry_anchor(entry, &mut self.ids),
+            sections: entry
+                .iter_sections()
+                .map(|section| (section.anchor(&mut own), section.anchor(&mut self.ids)))
+                .collect(),
+            directory,
+        };
+
+        if let Some(path) = entry.path.as_deref() {
+            self.entries.insert(normalize(path), self.anchors.len());
+        }
+
+        self.anchors.push(anchors);
+    }
+
+    fn push_items(&mut self, navigation: &mut String, content: &mut String, items: &[JournalItem]) {
+        if items.is_empty() {
+            return;
+        }
+
+        navigation.push_str("<ul>\n");
+
+        for item in items {
+            match item {
+                JournalItem::Entry(entry) => {
+                    let index = self.next;
+                    self.next += 1;
+
+                    navigation.push_str("<li><a href=\"#");
+                    push_escaped(navigation, &self.anchors[index].entry);
+                    navigation.push_str("\">");
+                    push_escaped(navigation, &entry.title);
+                    navigation.push_str("</a>\n");
+
+                    self.push_entry(content, entry, index);
+                    self.push_items(navigation, content, &entry.children);
+                    navigation.push_str("</li>\n");
+                }
+                JournalItem::ChapterTitle(chapter) => {
+                    navigation.push_str("<li><strong>");
+                    push_escaped(navigation, &chapter.title);
+                    navigation.push_str("</strong></li>\n");
+                }
+                JournalItem::Separator(_) => navigation.push_str("<li><hr></li>\n"),
+                JournalItem::Error(error) => {
+                    navigation.push_str("<li class=\"error\">");
+                    push_escaped(navigation, &error.title);
+                    navigation.push('\n');
+                    self.push_items(navigation, content, &error.children);
+                    navigation.push_str("</li>\n");
+                }
+            }
+        }
+
+        navigation.push_str("</ul>\n");
+    }
+
+    fn push_entry(&mut self, content: &mut String, entry: &JournalEntry, index: usize) {
+        let directory = entry
+            .path
+            .as_deref()
+            .and_then(Path::parent)
+            .map(normalize)
+            .unwrap_or_default();
+
+        let mut entry = entry.clone();
+
+        if let Some(ref mut body) = entry.body {
+            *body = self.rewrite(body, &directory, index);
+        }
+
+        entry.for_each_mut(|section| section.body = self.rewrite(&section.body, &directory, index));
+
+        content.push_str("<article id=\"");
+        push_escaped(content, &entry_anchor(&entry, &mut self.slugger));
+        content.push_str("\">\n");
+
+        if entry.sections.is_empty() {
+            content.push_str("<h1>");
+            push_escaped(content, &entry.title);
+            content.push_str("</h1>\n");
+        }
+
+        content.push_str(&entry.to_html_with_slugger(&mut self.slugger));
+        content.push_str("</article>\n");
+    }
+
+    /// Point the links of the Markdown of the entry at `index`, which is in `directory`, at the ids of the page,
+    /// and its images at their files, inlining them when they are inlined.
+    fn rewrite(&self, markdown: &str, directory: &Path, index: usize) -> String {
+        let mut replacements: Vec<(Range<usize>, String)> = Vec::new();
+
+        for link in links(markdown) {
+            let (LinkKind::Inline, Some(span)) = (link.kind, link.span.clone()) else {
+                continue;
+            };
+
+            if link.has_scheme() || link.destination.starts_with('/') {
+                continue;
+            }
+
+            let replacement = match link.image {
+                true => self.image_source(&link.destination, index),
+                false => self.link_target(&link.destination, directory, index),
+            };
+
+            if let Some(replacement) = replacement {
+                replacements.push((span, replacement));
+            }
+        }
+
+        // NOTE: A link can contain an image, whose destination precedes the link's.
+        replacements.sort_by_key(|(range, _)| range.start);
+
+        let mut rewritten = String::with_capacity(markdown.len());
+        let mut position = 0;
+
+        for (range, replacement) in replacements {
+            rewritten.push_str(&markdown[position..range.start]);
+            rewritten.push_str(&replacement);
+            position = range.end;
+        }
+
+        rewritten.push_str(&markdown[position..]);
+        rewritten
+    }
+
+    /// The id of the page a link to another entry's Markdown file, or to a section of the same entry, points to.
+    fn link_target(&self, destination: &str, directory: &Path, index: usize) -> Option<String> {
+        let (path, fragment) = match destination.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment)),
+            None => (destination, None),
+        };
+
+        let anchors = match path {
+            "" => &self.anchors[index],
+            path if path.ends_with(".md") => {
+                &self.anchors[*self.entries.get(&normalize(&directory.join(path)))?]
+            }
+            _ => return None,
+        };
+
+        let id = match fragment {
+            Some(fragment) => anchors.sections.get(fragment)?,
+            None => &anchors.entry,
+        };
+
+        Some(format!("#{id}"))
+    }
+
+    /// The source of a local image of the entry at `index`: a `data:` URI when it is inlined, or else its path
+    /// relative to the page.
+    fn image_source(&self, destination: &str, index: usize) -> Option<String> {
+        let path = self.anchors[index].directory.as_ref()?.join(destination);
+
+        match self.inline_image(&path) {
+            Some(uri) => Some(uri),
+            // NOTE: Both paths lose their root alike, but `..` components must be resolved first.
+            None => Some(relative_path(
+                &self.output.join(BUNDLE_FILE),
+                &normalize(&path),
+            )),
+        }
+    }
+
+    /// The `data:` URI of the local image at `path`, when images are inlined and it is small enough.
+    fn inline_image(&self, path: &Path) -> Option<String> {
+        let config = self.images.as_ref()?;
+        let mime = image_mime_type(path)?;
+
+        let size = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(err) => {
+                log::warn!("Failed to read image {}: {err}", path.display());
+                return None;
+            }
+        };
+
+        if size > config.max_image_size {
+            log::warn!(
+                "The image {} is larger than max-image-size of [single-file] and was not inlined",
+                path.display()
+            );
+            return None;
+        }
+
+        match fs::read(path) {
+            Ok(bytes) => Some(format!("data:{mime};base64,{}", STANDARD.encode(bytes))),
+            Err(err) => {
+                log::warn!("Failed to read image {}: {err}", path.display());
+                None
+            }
+        }
+    }
+}
+
+/// The id of an entry's article, which is a slug of its title, or of `entry` for entries without a title.
+fn entry_anchor(entry: &JournalEntry, slugger: &mut Slugger) -> String {
+    match entry.title.trim() {
+        "" => slugger.slug("entry"),
+        title => slugger.slug(title),
+    }
+}
+
+/// The MIME type of an image, by its extension. Returns `None` for files that are not known to be images.
+fn image_mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+    let mime = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "avif" => "image/avif",
+        _ => return None,
+    };
+
+    Some(mime)
+}
+
+/// The normal components of the path, without any `.` components. `..` components are resolved where they can be.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => {}
+        }
+    }
+
+    normalized
+}
+
+fn render_page(title: &str, navigation: &str, content: &str) -> String {
+    let mut html =
+        String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>");
+    push_escaped(&mut html, title);
+    html.push_str("</title>\n<style>");
+    html.push_str(STYLESHEET);
+    html.push_str("</style>\n</head>\n<body>\n<nav>\n");
+    html.push_str(navigation);
+    html.push_str("</nav>\n<main>\n");
+    html.push_str(content);
+    html.push_str("</main>\n</body>\n</html>\n");
+
+    html
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::journal::Journal;
+
+    fn entry(title: &str, path: &str, body: &str) -> JournalEntry {
+        JournalEntry {
+            title: String::from(title),
+            path: Some(PathBuf::from(path)),
+            body: Some(String::from(body)),
+            ..Default::default()
+        }
+        .parse()
+        .expect("should parse")
+    }
+
+    /// Render the journal, with the entries' files in `source`.
+    fn render(journal: &Journal, bundle: &mut Bundle, source: &Path) -> String {
+        journal.for_each_entry(|entry| {
+            let directory = entry.path.as_deref().and_then(Path::parent);
+            bundle.add_anchors(entry, directory.map(|directory| source.join(directory)))
+        });
+
+        let mut navigation = String::new();
+        let mut content = String::new();
+        bundle.push_items(&mut navigation, &mut content, &journal.items);
+
+        navigation + &content
+    }
+
+    #[test]
+    fn points_links_at_the_ids_of_the_page() {
+        let journal = Journal::from_entries(
+            None,
+            vec![
+                entry(
+                    "Town",
+                    "./town.md",
+                    "# Overview\n\nSee [the cellar](town/tavern.md#cellar), [the inn](town/tavern.md) \
+                     and [here](#overview).",
+                ),
+                entry(
+                    "Tavern",
+                    "./town/tavern.md",
+                    "# Overview\n\nBack to [town](../town.md#overview).\n\n## Cellar\n\nBarrels",
+                ),
+            ],
+        );
+
+        let html = render(
+            &journal,
+            &mut Bundle::new(SlugStrategy::default(), PathBuf::new()),
+            Path::new(""),
+        );
+
+        assert!(html.contains("<li><a href=\"#town\">Town</a>"));
+        assert!(html.contains("<article id=\"town\">\n<h1 id=\"overview\">Overview</h1>"));
+        assert!(html.contains("<article id=\"tavern\">\n<h1 id=\"overview-1\">Overview</h1>"));
+        assert!(html.contains("<a href=\"#cellar\">the cellar</a>"));
+        assert!(html.contains("<a href=\"#tavern\">the inn</a>"));
+        assert!(html.contains("<a href=\"#overview\">here</a>"));
+        assert!(html.contains("<a href=\"#overview\">town</a>"));
+    }
+
+    #[test]
+    fn inlines_images_up_to_the_maximum_size() {
+        let root = tempfile::tempdir().expect("should create temp dir");
+        let source = root.path().join("src");
+        fs::create_dir_all(source.join("town")).expect("should create directory");
+        fs::write(source.join("town/small.png"), "Man").expect("should write image");
+        fs::write(source.join("town/large.png"), "Many bytes").expect("should write image");
+
+        let journal = Journal::from_entries(
+            None,
+            vec![entry(
+                "Town",
+                "./town/market.md",
+                "![Small](small.png) ![Large](large.png) ![Map](../maps/map.png) \
+                 ![Remote](https://example.com/map.png)",
+            )],
+        );
+
+        let mut bundle = Bundle::new(
+            SlugStrategy::default(),
+            root.path().join("build/single-file"),
+        );
+        bundle.images = Some(SingleFileConfig {
+            inline_images: true,
+            max_image_size: 4,
+        });
+
+        let html = render(&journal, &mut bundle, &source);
+
+        assert!(html.contains("src=\"data:image/png;base64,TWFu\""));
+        assert!(html.contains("src=\"../../src/town/large.png\""));
+        assert!(html.contains("src=\"../../src/maps/map.png\""));
+        assert!(html.contains("src=\"https://example.com/map.png\""));
+    }
+}
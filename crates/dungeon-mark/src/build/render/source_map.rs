@@ -0,0 +1,181 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    error::Result,
+    model::journal::{Journal, JournalEntry, Section},
+};
+
+/// The name of the file a source map is written to, in the destination of each renderer.
+pub const SOURCE_MAP_FILE: &str = "sourcemap.json";
+/// The version of the source map schema. It changes whenever a field is removed or changes meaning.
+pub const SOURCE_MAP_VERSION: u32 = 1;
+
+/// A map from the entries of a rendered journal back to the files they were built from, for tracing a rendered
+/// page back to its source. With `build.source-map` set, the builder writes one as `sourcemap.json` into the
+/// destination of each renderer once it has run.
+///
+/// The map has the following shape:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "entries": [
+///     {
+///       "title": "The Tavern",
+///       "source": "town/tavern.md",
+///       "sections": [
+///         {
+///           "title": "Patrons",
+///           "level": 1,
+///           "span": { "start": 12, "end": 96 },
+///           "sections": []
+///         }
+///       ]
+///     }
+///   ]
+/// }
+/// ```
+///
+/// Entries are listed in document order, with nested entries following the entry they are nested below, and
+/// entries that failed to build left out. `source` is the path of the entry's file relative to the source
+/// directory, as linked from the table of contents without any `./` components, and `null` for drafts. Sections
+/// are nested as they are in the entry, and `span` is the byte range of the section in the entry's text, as
+/// described by `Section::span`, or `null` for sections that were not parsed, such as those added by a transformer.
+/// As entries are parsed after preprocessing, spans are offsets into the preprocessed text rather than the file,
+/// and only line up with the file when no preprocessor changed the entry.
+///
+/// Command renderers receive the same spans on the sections of the render context, and can write a map of their
+/// own that also names their output files.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMap {
+    pub version: u32,
+    pub entries: Vec<SourceMapEntry>,
+}
+
+/// An entry of a `SourceMap`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMapEntry {
+    pub title: String,
+    pub source: Option<PathBuf>,
+    pub sections: Vec<SourceMapSection>,
+}
+
+/// A section of an entry of a `SourceMap`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMapSection {
+    pub title: String,
+    pub level: u8,
+    pub span: Option<Range<usize>>,
+    pub sections: Vec<SourceMapSection>,
+}
+
+impl SourceMap {
+    /// Map every entry of the journal to its source.
+    pub fn new(journal: &Journal) -> Self {
+        let mut entries = Vec::new();
+        journal.for_each_entry(|entry| entries.push(SourceMapEntry::new(entry)));
+
+        Self {
+            version: SOURCE_MAP_VERSION,
+            entries,
+        }
+    }
+
+    /// Write the map to `sourcemap.json` in `destination`, returning the path of the file.
+    pub fn write(&self, destination: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(destination)
+            .with_context(|| format!("Failed to create directory: {}", destination.display()))?;
+
+        let path = destination.join(SOURCE_MAP_FILE);
+        let json = serde_json::to_string_pretty(self)?;
+
+        fs::write(&path, json)
+            .with_context(|| format!("Failed to write source map: {}", path.display()))?;
+
+        Ok(path)
+    }
+}
+
+impl SourceMapEntry {
+    fn new(entry: &JournalEntry) -> Self {
+        Self {
+            title: entry.title.clone(),
+            source: entry.path.as_deref().map(normalize),
+            sections: entry.sections.iter().map(SourceMapSection::new).collect(),
+        }
+    }
+}
+
+impl SourceMapSection {
+    fn new(section: &Section) -> Self {
+        Self {
+            title: section.title.clone(),
+            level: section.level.as_u8(),
+            span: section.span.clone(),
+            sections: section.sections.iter().map(Self::new).collect(),
+        }
+    }
+}
+
+/// The path without any `.` components, as the table of contents may link to `./town/tavern.md`.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::journal::JournalItem;
+
+    #[test]
+    fn maps_entries_and_sections_to_their_sources() {
+        let source = "Ale\n\n# Cellar\n\nBarrels\n\n## Vault\n\nGold\n";
+        let tavern = JournalEntry {
+            path: Some(PathBuf::from("./town/tavern.md")),
+            children: vec![JournalItem::Entry(JournalEntry::new("Draft", ""))],
+            ..JournalEntry::new("Tavern", source)
+        }
+        .parse()
+        .expect("should parse");
+        let journal = Journal::from_entries(None, vec![tavern]);
+
+        let source_map = SourceMap::new(&journal);
+
+        assert_eq!(
+            SourceMap {
+                version: SOURCE_MAP_VERSION,
+                entries: vec![
+                    SourceMapEntry {
+                        title: String::from("Tavern"),
+                        source: Some(PathBuf::from("town/tavern.md")),
+                        sections: vec![SourceMapSection {
+                            title: String::from("Cellar"),
+                            level: 1,
+                            span: Some(5..source.len()),
+                            sections: vec![SourceMapSection {
+                                title: String::from("Vault"),
+                                level: 2,
+                                span: Some(24..source.len()),
+                                sections: Vec::new(),
+                            }],
+                        }],
+                    },
+                    SourceMapEntry {
+                        title: String::from("Draft"),
+                        source: None,
+                        sections: Vec::new(),
+                    },
+                ],
+            },
+            source_map
+        );
+    }
+}
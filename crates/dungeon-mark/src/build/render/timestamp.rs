@@ -0,0 +1,78 @@
+use anyhow::Context;
+use std::{
+    env,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::error::Result;
+
+/// The environment variable of the reproducible builds specification, holding the seconds since the Unix epoch
+/// to use as the build time instead of the current time.
+const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// The time of the build, as seconds since the Unix epoch: `SOURCE_DATE_EPOCH` when it is set, so that builds
+/// can be reproduced, or else the current time.
+pub(crate) fn build_time() -> Result<u64> {
+    match env::var(SOURCE_DATE_EPOCH) {
+        Ok(epoch) => epoch.trim().parse().with_context(|| {
+            format!("Expected {SOURCE_DATE_EPOCH} to be a number of seconds, found: {epoch}")
+        }),
+        Err(_) => Ok(now()),
+    }
+}
+
+/// The current time as seconds since the Unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Format seconds since the Unix epoch as an RFC 3339 timestamp in UTC, such as `2024-03-01T12:30:00Z`.
+pub(crate) fn format_timestamp(seconds: u64) -> String {
+    let (year, month, day) = civil_date(seconds / SECONDS_PER_DAY);
+    let time = seconds % SECONDS_PER_DAY;
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// The year, month and day of the given number of days since the Unix epoch, in the proleptic Gregorian
+/// calendar, using Howard Hinnant's `civil_from_days` algorithm.
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    // NOTE: Shift the epoch to 0000-03-01, so that leap days fall at the end of each year.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formats_timestamps_in_utc() {
+        assert_eq!("1970-01-01T00:00:00Z", format_timestamp(0));
+        assert_eq!("2000-02-29T23:59:59Z", format_timestamp(951_868_799));
+        assert_eq!("2024-03-01T12:30:05Z", format_timestamp(1_709_296_205));
+        assert_eq!("2100-03-01T00:00:00Z", format_timestamp(4_107_542_400));
+    }
+}
@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    path::{Component, Path, PathBuf},
+};
+use toml::Value;
+
+use super::{Transformer, TransformerContext};
+use crate::{
+    cmark::{links, Link, LinkKind},
+    error::Result,
+    model::journal::Journal,
+};
+
+/// Rewrites the destinations of images and links in entries from being relative to the entry's file to being
+/// relative to the journal's source, so that renderers writing every page to a single directory keep them
+/// working. Absolute paths, URLs such as `https://` links and `data:` URIs, fragment-only links and
+/// destinations leading outside the journal's source are left untouched. Only inline images and links are
+/// rewritten; reference definitions and autolinks are kept as they are.
+///
+/// When `manifest` is set in the `[asset-paths]` table of `journal.toml`, the rewritten paths of every image,
+/// and of every link to something other than a markdown file, are set as the `assets` array of the
+/// configuration, so that renderers can copy them into their output.
+pub struct AssetPathsTransformer;
+
+/// Configuration for the asset paths transformer, read from the `[asset-paths]` table of `journal.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct AssetPathsConfig {
+    /// Rewrite the destinations of links as well as images.
+    pub links: bool,
+
+    /// Collect the referenced assets into the `assets` configuration key.
+    pub manifest: bool,
+}
+
+impl Default for AssetPathsConfig {
+    fn default() -> Self {
+        Self {
+            links: true,
+            manifest: false,
+        }
+    }
+}
+
+impl AssetPathsTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for AssetPathsTransformer {
+    fn name(&self) -> &str {
+        "asset-paths"
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let config: AssetPathsConfig = ctx.config.get("asset-paths")?;
+        let mut assets = BTreeSet::new();
+
+        journal.for_each_entry_mut(|entry| {
+            let Some(directory) = entry.path.as_deref().and_then(Path::parent) else {
+                return;
+            };
+            let directory = directory.to_path_buf();
+
+            if let Some(ref mut body) = entry.body {
+                *body = rewrite_paths(body, &directory, &config, &mut assets);
+            }
+
+            entry.for_each_mut(|section| {
+                section.body = rewrite_paths(&section.body, &directory, &config, &mut assets);
+            });
+        });
+
+        if config.manifest {
+            let assets = assets
+                .into_iter()
+                .map(|asset| Value::String(asset.display().to_string()))
+                .collect();
+
+            ctx.set_config("assets", Value::Array(assets));
+        }
+
+        Ok(journal)
+    }
+}
+
+/// Rewrite the image and link destinations of the markdown from being relative to `directory`, adding the
+/// referenced assets to `assets`.
+fn rewrite_paths(
+    markdown: &str,
+    directory: &Path,
+    config: &AssetPathsConfig,
+    assets: &mut BTreeSet<PathBuf>,
+) -> String {
+    let mut replacements = Vec::new();
+
+    for link in links(markdown) {
+        if link.kind != LinkKind::Inline || !(link.image || config.links) {
+            continue;
+        }
+
+        let Some(span) = link.span.clone() else {
+            continue;
+        };
+
+        let Some((rewritten, asset)) = rebase(&link, directory) else {
+            continue;
+        };
+
+        if link.image || asset.extension().is_none_or(|extension| extension != "md") {
+            assets.insert(asset);
+        }
+
+        replacements.push((span, rewritten));
+    }
+
+    // NOTE: A link can contain an image, whose destination precedes the link's.
+    replacements.sort_by_key(|(range, _)| range.start);
+
+    let mut rewritten = String::with_capacity(markdown.len());
+    let mut position = 0;
+
+    for (range, replacement) in replacements {
+        rewritten.push_str(&markdown[position..range.start]);
+        rewritten.push_str(&replacement);
+        position = range.end;
+    }
+
+    rewritten.push_str(&markdown[position..]);
+    rewritten
+}
+
+/// Rebase the destination of a link relative to `directory` onto the journal's source, returning the new
+/// destination and the path it refers to. Returns `None` for destinations that are not relative paths within the
+/// source.
+fn rebase(link: &Link, directory: &Path) -> Option<(String, PathBuf)> {
+    let destination = link.destination.as_str();
+
+    if destination.is_empty() || destination.starts_with(['#', '/', '\\']) || link.has_scheme() {
+        return None;
+    }
+
+    let (path, suffix) = match destination.find(['?', '#']) {
+        Some(index) => destination.split_at(index),
+        None => (destination, ""),
+    };
+
+    let path = directory.join(path);
+    let mut rebased = Vec::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => rebased.push(name.to_str()?),
+            Component::ParentDir => {
+                rebased.pop()?;
+            }
+            Component::CurDir => {}
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    let rebased = rebased.join("/");
+
+    Some((format!("{rebased}{suffix}"), PathBuf::from(rebased)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn rewrite(markdown: &str) -> (String, Vec<PathBuf>) {
+        let mut assets = BTreeSet::new();
+        let rewritten = rewrite_paths(
+            markdown,
+            Path::new("./town"),
+            &AssetPathsConfig::default(),
+            &mut assets,
+        );
+
+        (rewritten, assets.into_iter().collect())
+    }
+
+    #[test]
+    fn rewrites_images_and_links_relative_to_the_source() {
+        let (rewritten, assets) = rewrite(
+            "![map](images/map.png) and [the inn](../inn.md#rooms) and [![icon](icon.png)](<deed 1.pdf>)",
+        );
+
+        assert_eq!(
+            "![map](town/images/map.png) and [the inn](inn.md#rooms) and [![icon](town/icon.png)](<town/deed 1.pdf>)",
+            rewritten
+        );
+        assert_eq!(
+            vec![
+                PathBuf::from("town/deed 1.pdf"),
+                PathBuf::from("town/icon.png"),
+                PathBuf::from("town/images/map.png"),
+            ],
+            assets
+        );
+    }
+
+    #[test]
+    fn leaves_urls_and_absolute_paths_untouched() {
+        let markdown = "![a](https://example.com/a.png) ![b](data:image/png;base64,AAAA) \
+            ![c](/c.png) [d](#d) [e](mailto:gm@example.com) ![f](../../f.png)";
+        let (rewritten, assets) = rewrite(markdown);
+
+        assert_eq!(markdown, rewritten);
+        assert!(assets.is_empty());
+    }
+
+    #[test]
+    fn collects_assets_into_the_config_when_enabled() {
+        use crate::{config::Config, model::journal::JournalEntry, model::journal::JournalItem};
+
+        let config: Config = "[asset-paths]\nmanifest = true"
+            .parse()
+            .expect("should parse");
+        let mut ctx = TransformerContext::new(PathBuf::from("test"), config);
+        let journal = Journal {
+            title: None,
+            items: vec![JournalItem::Entry(JournalEntry {
+                path: Some(PathBuf::from("./town/tavern.md")),
+                body: Some(String::from("![map](map.png)")),
+                ..Default::default()
+            })],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        AssetPathsTransformer
+            .run(&ctx, journal)
+            .expect("should transform");
+        ctx.apply_config_updates().expect("should update config");
+
+        let assets: Vec<PathBuf> = ctx.into_config().get("assets").expect("should get assets");
+
+        assert_eq!(vec![PathBuf::from("town/map.png")], assets);
+    }
+}
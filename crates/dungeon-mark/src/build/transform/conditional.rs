@@ -0,0 +1,375 @@
+use anyhow::Context;
+use toml::Value;
+
+use super::{Transformer, TransformerContext, CONDITIONAL_PRIORITY, DIRECTIVE_ESCAPE};
+use crate::{build::preprocess::directive::is_exempt, error::Result, model::journal::Journal};
+
+const OPEN_SEQUENCE: &str = "{{#";
+const CLOSE_SEQUENCE: &str = "}}";
+const RENDERER_KEY: &str = "renderer";
+const PROFILE_KEY: &str = "profile";
+
+/// Keeps or removes blocks of an entry depending on the renderer the journal is transformed for and the
+/// configuration, for content that only belongs in some outputs.
+///
+/// ```markdown
+/// {{#if renderer=html}}
+/// ## Interactive map
+/// {{#else}}
+/// See the printed map.
+/// {{#endif}}
+/// ```
+///
+/// A condition has the form `key=value` or `key!=value`, where the value may be quoted, and the key is one of:
+/// - `renderer` The name of the renderer the journal is being transformed for. It is unset in dry runs.
+/// - `profile` The `profile` of the `[build]` table of `journal.toml`.
+/// - Any other dotted key into the free-form tables of `journal.toml`, such as `campaign.players`. Values that
+///   are not strings are compared as they are written in TOML, so `campaign.online=true` matches a boolean.
+///
+/// A condition on an unset key never matches with `=` and always matches with `!=`. Blocks can be nested and can
+/// span headings, as the directives are resolved over the whole entry, which is then parsed again. A directive
+/// alone on its line is removed along with the line. Conditional directives can be escaped with a leading
+/// backslash, `\{{#if ...}}`, which the `directive` preprocessor keeps as `DIRECTIVE_ESCAPE` for this transformer
+/// to remove.
+///
+/// The transformer runs ahead of the `metadata` transformer, so that metadata blocks can be conditional too.
+/// Entries exempt from directive expansion, as described by the `directive` preprocessor, are left unchanged.
+pub struct ConditionalTransformer;
+
+impl ConditionalTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for ConditionalTransformer {
+    fn name(&self) -> &str {
+        "conditional"
+    }
+
+    fn priority(&self) -> i32 {
+        CONDITIONAL_PRIORITY
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        journal.try_for_each_entry_mut(|entry| {
+            if entry.body.as_deref().is_some_and(is_exempt) {
+                return Ok(());
+            }
+
+            let markdown = entry.to_markdown();
+            let resolved = resolve(&markdown, ctx)
+                .with_context(|| format!("Failed to resolve conditions in \"{}\"", entry.title))?;
+
+            if resolved == markdown {
+                return Ok(());
+            }
+
+            entry.set_body_with_options(resolved, &ctx.config.build.parse)
+        })?;
+
+        Ok(journal)
+    }
+}
+
+/// A conditional directive, without its `{{#` and `}}`.
+#[derive(Debug, PartialEq, Eq)]
+enum Directive<'a> {
+    If(&'a str),
+    Else,
+    EndIf,
+}
+
+impl<'a> Directive<'a> {
+    fn parse(directive: &'a str) -> Option<Self> {
+        match directive.trim() {
+            "else" => Some(Self::Else),
+            "endif" => Some(Self::EndIf),
+            directive => directive
+                .strip_prefix("if")
+                .filter(|condition| {
+                    condition.is_empty() || condition.starts_with(char::is_whitespace)
+                })
+                .map(|condition| Self::If(condition.trim())),
+        }
+    }
+}
+
+/// Whether the directive, such as `{{#if renderer=html}}`, is an `if`, `else` or `endif` directive.
+pub(crate) fn is_conditional(directive: &str) -> bool {
+    directive
+        .strip_prefix(OPEN_SEQUENCE)
+        .and_then(|directive| directive.strip_suffix(CLOSE_SEQUENCE))
+        .and_then(Directive::parse)
+        .is_some()
+}
+
+/// A condition of an `{{#if}}` directive.
+#[derive(Debug, PartialEq, Eq)]
+struct Condition<'a> {
+    key: &'a str,
+    value: &'a str,
+    negated: bool,
+}
+
+impl<'a> Condition<'a> {
+    fn parse(condition: &'a str) -> Result<Self> {
+        let (key, value, negated) = match condition.split_once("!=") {
+            Some((key, value)) => (key, value, true),
+            None => match condition.split_once('=') {
+                Some((key, value)) => (key, value, false),
+                None => anyhow::bail!(
+                    "Expected a condition in the form of key=value or key!=value, found: {condition}"
+                ),
+            },
+        };
+
+        let key = key.trim();
+        if key.is_empty() {
+            anyhow::bail!("Expected a key before the operator of the condition: {condition}");
+        }
+
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+
+        Ok(Self {
+            key,
+            value,
+            negated,
+        })
+    }
+
+    fn evaluate(&self, ctx: &TransformerContext) -> Result<bool> {
+        let actual = match self.key {
+            RENDERER_KEY => ctx.renderer.clone(),
+            PROFILE_KEY => ctx.config.build.profile.clone(),
+            key => ctx.config.try_get::<Value>(key)?.map(|value| match value {
+                Value::String(value) => value,
+                value => value.to_string(),
+            }),
+        };
+
+        Ok((actual.as_deref() == Some(self.value)) != self.negated)
+    }
+}
+
+/// An `{{#if}}` block that has been opened but not yet closed.
+struct Block {
+    matched: bool,
+    in_else: bool,
+}
+
+impl Block {
+    fn is_active(&self) -> bool {
+        self.matched != self.in_else
+    }
+}
+
+/// Resolve every conditional directive in the text, keeping only the text of the blocks that match.
+fn resolve(text: &str, ctx: &TransformerContext) -> Result<String> {
+    let mut resolved = String::new();
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut position = 0;
+
+    while let Some(start) = text[position..]
+        .find(OPEN_SEQUENCE)
+        .map(|start| position + start)
+    {
+        let Some(length) = text[start..].find(CLOSE_SEQUENCE) else {
+            break;
+        };
+
+        let mut end = start + length + CLOSE_SEQUENCE.len();
+        let active = blocks.iter().all(Block::is_active);
+        let Some(directive) = Directive::parse(&text[start + OPEN_SEQUENCE.len()..start + length])
+        else {
+            // NOTE: Other directives are kept, along with any text up to and including them.
+            if active {
+                resolved.push_str(&text[position..end]);
+            }
+
+            position = end;
+            continue;
+        };
+
+        if let Some(preceding) = text[position..start].strip_suffix(DIRECTIVE_ESCAPE) {
+            if active {
+                resolved.push_str(preceding);
+                resolved.push_str(&text[start..end]);
+            }
+
+            position = end;
+            continue;
+        }
+
+        if active {
+            resolved.push_str(&text[position..start]);
+        }
+
+        match directive {
+            Directive::If(condition) => {
+                let matched = Condition::parse(condition)?.evaluate(ctx)?;
+                blocks.push(Block {
+                    matched,
+                    in_else: false,
+                });
+            }
+            Directive::Else => match blocks.last_mut() {
+                Some(block) if !block.in_else => block.in_else = true,
+                Some(_) => anyhow::bail!("Found a second {{{{#else}}}} in the same {{{{#if}}}}"),
+                None => anyhow::bail!("Found {{{{#else}}}} without a matching {{{{#if}}}}"),
+            },
+            Directive::EndIf => {
+                if blocks.pop().is_none() {
+                    anyhow::bail!("Found {{{{#endif}}}} without a matching {{{{#if}}}}");
+                }
+            }
+        }
+
+        // NOTE: Remove the line of a directive that is alone on its line, rather than leaving a blank line.
+        let line_start = text[..start].rfind('\n').map_or(0, |index| index + 1);
+        if text[line_start..start].trim().is_empty() && text[end..].starts_with('\n') {
+            if active {
+                resolved.truncate(resolved.trim_end_matches([' ', '\t']).len());
+            }
+
+            end += 1;
+        }
+
+        position = end;
+    }
+
+    if !blocks.is_empty() {
+        anyhow::bail!("Expected {{{{#endif}}}} to close every {{{{#if}}}}");
+    }
+
+    resolved.push_str(&text[position..]);
+
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        model::journal::{JournalEntry, JournalItem},
+    };
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    fn context(renderer: Option<&str>, config: &str) -> TransformerContext {
+        let config: Config = config.parse().expect("should parse config");
+        let mut ctx = TransformerContext::new(PathBuf::from("test"), config);
+        ctx.renderer = renderer.map(String::from);
+
+        ctx
+    }
+
+    #[test]
+    fn keeps_the_matching_branches() {
+        let ctx = context(
+            Some("html"),
+            "[build]\nprofile = \"print\"\n\n[campaign]\nonline = true",
+        );
+        let text = "Intro\n{{#if renderer=html}}\nHTML\n{{#else}}\nOther\n{{#endif}}\n\
+                    {{#if renderer!=\"html\"}}Not HTML{{#endif}}\n\
+                    {{#if profile=print}}\nPrint {{#if campaign.online=true}}online{{#endif}}\n{{#endif}}\n\
+                    {{#if campaign.missing=yes}}Missing{{#else}}Unset{{#endif}}\n";
+
+        assert_eq!(
+            "Intro\nHTML\n\nPrint online\nUnset\n",
+            resolve(text, &ctx).expect("should resolve")
+        );
+    }
+
+    #[test]
+    fn resolves_conditions_without_a_renderer() {
+        let ctx = context(None, "");
+
+        assert_eq!(
+            "Everywhere but HTML",
+            resolve(
+                "{{#if renderer=html}}HTML{{#endif}}{{#if renderer!=html}}Everywhere but HTML{{#endif}}",
+                &ctx
+            )
+            .expect("should resolve")
+        );
+    }
+
+    #[test]
+    fn keeps_escaped_and_other_directives() {
+        let ctx = context(Some("html"), "");
+
+        assert_eq!(
+            "{{#if renderer=html}} {{#wordcount}}",
+            resolve("\\\\{{#if renderer=html}} {{#wordcount}}", &ctx).expect("should resolve")
+        );
+    }
+
+    #[test]
+    fn rejects_unbalanced_blocks_and_invalid_conditions() {
+        let ctx = context(Some("html"), "");
+
+        for (text, error) in [
+            (
+                "{{#if renderer=html}}",
+                "Expected {{#endif}} to close every {{#if}}",
+            ),
+            ("{{#endif}}", "Found {{#endif}} without a matching {{#if}}"),
+            ("{{#else}}", "Found {{#else}} without a matching {{#if}}"),
+            (
+                "{{#if renderer=html}}{{#else}}{{#else}}{{#endif}}",
+                "Found a second {{#else}} in the same {{#if}}",
+            ),
+            (
+                "{{#if renderer}}{{#endif}}",
+                "Expected a condition in the form of key=value",
+            ),
+            (
+                "{{#if =html}}{{#endif}}",
+                "Expected a key before the operator",
+            ),
+        ] {
+            let err = resolve(text, &ctx).expect_err("should fail");
+            assert!(format!("{err:#}").contains(error), "{text}: {err:#}");
+        }
+    }
+
+    #[test]
+    fn reparses_entries_with_conditional_sections() {
+        let entry = JournalEntry {
+            title: String::from("Town"),
+            body: Some(String::from(
+                "Intro\n\n{{#if renderer=html}}\n# Map\n\nInteractive\n{{#endif}}\n\n# Shops\n\nBread",
+            )),
+            ..Default::default()
+        };
+        let journal = Journal {
+            title: None,
+            items: vec![JournalItem::Entry(entry.parse().expect("should parse"))],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        let titles = |renderer| {
+            let journal = ConditionalTransformer
+                .run(&context(Some(renderer), ""), journal.clone())
+                .expect("should transform");
+            let JournalItem::Entry(ref entry) = journal.items[0] else {
+                panic!("first item was not an entry")
+            };
+
+            entry
+                .sections
+                .iter()
+                .map(|section| section.title.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec!["Map", "Shops"], titles("html"));
+        assert_eq!(vec!["Shops"], titles("pandoc"));
+    }
+}
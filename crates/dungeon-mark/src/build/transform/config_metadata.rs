@@ -0,0 +1,232 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use toml::Value;
+
+use super::{Transformer, TransformerContext, CONFIG_METADATA_PRIORITY};
+use crate::{
+    error::Result,
+    model::journal::{Journal, JournalEntry, JournalItem, MetadataFormats, SectionMetadata},
+};
+
+/// Merges a metadata block of a journal entry into the free-form configuration, so that transformers running
+/// after it and renderers can read the block's keys through `Config::get`.
+///
+/// The block is the first metadata block stored under `key` in the sections of `entry`, or only in the sections
+/// titled `section` when one is given. Its data is parsed in the format of its language, as registered in
+/// `TransformerContext::metadata_formats`, and must be a table. Each of its keys is merged into the configuration as
+/// with `Config::merge`. This transformer is not run by default and is configured through the `[config-metadata]`
+/// table of `journal.toml`.
+pub struct ConfigMetadataTransformer;
+
+/// Configuration for the config metadata transformer, read from the `[config-metadata]` table of `journal.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ConfigMetadataConfig {
+    /// The path of the entry holding the metadata, relative to the journal's source. Defaults to the first
+    /// entry of the journal.
+    pub entry: Option<PathBuf>,
+
+    /// The title of the section holding the metadata. Defaults to any section of the entry.
+    pub section: Option<String>,
+
+    /// The key of the metadata block.
+    pub key: String,
+}
+
+impl Default for ConfigMetadataConfig {
+    fn default() -> Self {
+        Self {
+            entry: None,
+            section: None,
+            key: String::from("config"),
+        }
+    }
+}
+
+impl ConfigMetadataTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for ConfigMetadataTransformer {
+    fn name(&self) -> &str {
+        "config-metadata"
+    }
+
+    fn priority(&self) -> i32 {
+        CONFIG_METADATA_PRIORITY
+    }
+
+    fn run(&self, ctx: &TransformerContext, journal: Journal) -> Result<Journal> {
+        let config: ConfigMetadataConfig = ctx.config.get("config-metadata")?;
+
+        let Some(entry) = find_entry(&journal, config.entry.as_deref()) else {
+            match config.entry {
+                Some(path) => anyhow::bail!(
+                    "The journal has no entry at {} to read configuration from",
+                    path.display()
+                ),
+                None => return Ok(journal),
+            }
+        };
+
+        let Some(metadata) = find_metadata(entry, &config) else {
+            log::warn!(
+                "Entry \"{}\" has no `{}` metadata to read configuration from",
+                entry.title,
+                config.key
+            );
+
+            return Ok(journal);
+        };
+
+        let table = parse_table(&ctx.metadata_formats, metadata).with_context(|| {
+            format!(
+                "Failed to read the `{}` metadata of entry \"{}\" as configuration",
+                config.key, entry.title
+            )
+        })?;
+
+        for (key, value) in table {
+            ctx.set_config(key, value);
+        }
+
+        Ok(journal)
+    }
+}
+
+/// Find the entry at `path`, or the first entry of the journal when no path is given.
+fn find_entry<'a>(journal: &'a Journal, path: Option<&Path>) -> Option<&'a JournalEntry> {
+    journal.iter().find_map(|item| match item {
+        JournalItem::Entry(entry) => match (path, &entry.path) {
+            (None, _) => Some(entry),
+            (Some(path), Some(entry_path)) if normalize(path) == normalize(entry_path) => {
+                Some(entry)
+            }
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+fn find_metadata<'a>(
+    entry: &'a JournalEntry,
+    config: &ConfigMetadataConfig,
+) -> Option<&'a SectionMetadata> {
+    entry
+        .iter_sections()
+        .filter(|section| match config.section {
+            Some(ref title) => section.title == *title,
+            None => true,
+        })
+        .find_map(|section| section.metadata.get(&config.key))
+}
+
+fn parse_table(
+    formats: &MetadataFormats,
+    metadata: &SectionMetadata,
+) -> Result<toml::value::Table> {
+    let value = Value::try_from(formats.parse(metadata)?)?;
+
+    match value {
+        Value::Table(table) => Ok(table),
+        _ => anyhow::bail!("Expected a table of configuration"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        model::journal::{Metadata, Section},
+    };
+    use std::collections::BTreeMap;
+
+    fn journal(lang: &str, data: &str) -> Journal {
+        let mut metadata = Metadata::new();
+        metadata.insert(
+            "config",
+            SectionMetadata {
+                lang: String::from(lang),
+                data: String::from(data),
+                attributes: Vec::new(),
+            },
+        );
+
+        Journal {
+            title: None,
+            items: vec![JournalItem::Entry(JournalEntry {
+                title: String::from("Campaign"),
+                path: Some(PathBuf::from("./campaign.md")),
+                sections: vec![Section {
+                    title: String::from("Settings"),
+                    metadata,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        }
+    }
+
+    fn run(config: &str, journal: Journal) -> Result<Config> {
+        let config: Config = config.parse()?;
+        let mut ctx = TransformerContext::new(PathBuf::from("test"), config);
+
+        ConfigMetadataTransformer.run(&ctx, journal)?;
+        ctx.apply_config_updates()?;
+
+        Ok(ctx.into_config())
+    }
+
+    #[test]
+    fn merges_toml_metadata_into_config() {
+        let config = run(
+            "[campaign]\nsetting = \"Greyhawk\"",
+            journal("toml", "[campaign]\nlevel = 3\n"),
+        )
+        .expect("should transform");
+
+        let campaign: Value = config.try_get("campaign").unwrap().unwrap();
+
+        assert_eq!(Some("Greyhawk"), campaign["setting"].as_str());
+        assert_eq!(Some(3), campaign["level"].as_integer());
+    }
+
+    #[test]
+    fn reads_json_metadata_from_the_configured_section() {
+        let config = run(
+            "[config-metadata]\nentry = \"campaign.md\"\nsection = \"Settings\"",
+            journal("json", r#"{ "party": { "size": 4 } }"#),
+        )
+        .expect("should transform");
+
+        let party: Value = config.try_get("party").unwrap().unwrap();
+
+        assert_eq!(Some(4), party["size"].as_integer());
+    }
+
+    #[test]
+    fn rejects_missing_entries_and_non_table_data() {
+        let missing = run(
+            "[config-metadata]\nentry = \"missing.md\"",
+            journal("toml", "level = 3"),
+        );
+        let not_table = run("", journal("json", "[1, 2]"));
+        let structured = run("", journal("toml", "[build]\nclean = true"));
+
+        assert!(missing.is_err());
+        assert!(not_table.is_err());
+        assert!(structured.is_err());
+    }
+}
@@ -0,0 +1,435 @@
+use anyhow::Context;
+use pulldown_cmark::{Event, Parser, Tag};
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+use super::{replace_directives, Transformer, TransformerContext, EMBED_PRIORITY};
+use crate::{
+    build::preprocess::directive::is_exempt,
+    cmark::{parser_options, splice},
+    error::Result,
+    model::journal::{Journal, JournalEntry, JournalItem, Section},
+};
+
+pub(crate) const EMBED_DIRECTIVE: &str = "embed";
+const OPEN_SEQUENCE: &str = "{{#embed";
+const SECTION_SEPARATOR: char = '#';
+const MAX_HEADING_LEVEL: i32 = 6;
+
+/// Replaces `{{#embed other.md#Section Title}}` directives with the body of the section titled `Section Title` in
+/// the entry at `other.md`, followed by the sections nested below it, for reusing a single section of another
+/// entry. The section's own heading is left out, and the headings of its nested sections are shifted to nest
+/// below the heading the directive is under, or to start at H1 for a directive in the body of an entry. Headings
+/// are never shifted past H6.
+///
+/// The path is relative to the file of the entry with the directive, as with `{{#include}}`, and may leave out the
+/// extension. Directives in embedded sections are replaced in turn, relative to the entry they come from. A path
+/// that no entry has, a section title that the entry does not have and a section that ends up embedding itself
+/// are errors. Entries with embedded sections are parsed again, so that the embedded headings become sections.
+///
+/// The transformer runs ahead of the `metadata` transformer, so that metadata blocks of embedded sections end up in
+/// the sections they are embedded in. An escaped directive, `\{{#embed other.md#Section}}`, is written out as is,
+/// and entries exempt from directive expansion, as described by the `directive` preprocessor, are left unchanged
+/// and are embedded as they are.
+pub struct EmbedTransformer;
+
+impl EmbedTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for EmbedTransformer {
+    fn name(&self) -> &str {
+        "embed"
+    }
+
+    fn priority(&self) -> i32 {
+        EMBED_PRIORITY
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let entries = EmbeddedEntries::new(&journal);
+
+        journal.try_for_each_entry_mut(|entry| {
+            let Some(path) = entry.path.clone() else {
+                return Ok(());
+            };
+
+            if !has_embeds(entry) || entry.body.as_deref().is_some_and(is_exempt) {
+                return Ok(());
+            }
+
+            let mut expanded = entry.clone();
+            entries
+                .expand_entry(&mut expanded, &path, &mut Vec::new())
+                .with_context(|| format!("Failed to embed sections in \"{}\"", entry.title))?;
+
+            let markdown = expanded.to_markdown();
+            expanded.set_body_with_options(markdown, &ctx.config.build.parse)?;
+            *entry = expanded;
+
+            Ok(())
+        })?;
+
+        Ok(journal)
+    }
+}
+
+fn has_embeds(entry: &JournalEntry) -> bool {
+    entry
+        .body
+        .iter()
+        .chain(entry.iter_sections().map(|section| &section.body))
+        .any(|body| body.contains(OPEN_SEQUENCE))
+}
+
+/// The entries of the journal by their normalized path, as they were before any directive was replaced.
+struct EmbeddedEntries {
+    entries: HashMap<PathBuf, JournalEntry>,
+}
+
+impl EmbeddedEntries {
+    fn new(journal: &Journal) -> Self {
+        let entries = journal
+            .iter()
+            .filter_map(|item| match item {
+                JournalItem::Entry(entry) => entry
+                    .path
+                    .as_deref()
+                    .map(|path| (normalize(path), entry.clone())),
+                _ => None,
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    /// Replace every embed directive in the entry's body and sections. `stack` holds the sections being embedded,
+    /// as their entry's path and title.
+    fn expand_entry(
+        &self,
+        entry: &mut JournalEntry,
+        path: &Path,
+        stack: &mut Vec<(PathBuf, String)>,
+    ) -> Result<()> {
+        if let Some(ref mut body) = entry.body {
+            *body = self.expand(body, path, 0, stack)?;
+        }
+
+        for section in &mut entry.sections {
+            self.expand_section(section, path, stack)?;
+        }
+
+        Ok(())
+    }
+
+    fn expand_section(
+        &self,
+        section: &mut Section,
+        path: &Path,
+        stack: &mut Vec<(PathBuf, String)>,
+    ) -> Result<()> {
+        section.body = self.expand(&section.body, path, section.level.as_u8(), stack)?;
+
+        for section in &mut section.sections {
+            self.expand_section(section, path, stack)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace every embed directive in text of the entry at `path` that is under a heading of `level`, or 0 for
+    /// the body of the entry.
+    fn expand(
+        &self,
+        text: &str,
+        path: &Path,
+        level: u8,
+        stack: &mut Vec<(PathBuf, String)>,
+    ) -> Result<String> {
+        replace_directives(text, EMBED_DIRECTIVE, |args| {
+            self.embed(args, path, level, stack).map(Some)
+        })
+    }
+
+    fn embed(
+        &self,
+        args: &str,
+        path: &Path,
+        level: u8,
+        stack: &mut Vec<(PathBuf, String)>,
+    ) -> Result<String> {
+        let Some((target, title)) = args.trim().split_once(SECTION_SEPARATOR) else {
+            anyhow::bail!(
+                "Expected a path and section title in the form of path#Section, found: {args}"
+            )
+        };
+        let title = title.trim();
+
+        let mut target_path = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        target_path.push(target.trim());
+        let target_path = normalize(&target_path);
+
+        let Some((target_path, entry)) = self.find(&target_path) else {
+            anyhow::bail!("No entry is at {}", target.trim())
+        };
+
+        let Some(section) = entry.iter_sections().find(|section| section.title == title) else {
+            anyhow::bail!("\"{}\" has no section titled \"{title}\"", entry.title)
+        };
+
+        let key = (target_path.clone(), String::from(title));
+        if stack.contains(&key) {
+            let chain = stack
+                .iter()
+                .chain([&key])
+                .map(|(path, title)| format!("{}#{title}", path.display()))
+                .collect::<Vec<_>>();
+
+            anyhow::bail!("Sections embed each other: {}", chain.join(" -> "));
+        }
+
+        let mut section = section.clone();
+
+        if !entry.body.as_deref().is_some_and(is_exempt) {
+            stack.push(key);
+            let expanded = self.expand_section(&mut section, target_path, stack);
+            stack.pop();
+            expanded?;
+        }
+
+        let mut markdown = String::from(section.body.trim());
+
+        for nested in &section.sections {
+            markdown.push_str("\n\n");
+            markdown.push_str(&nested.to_markdown());
+        }
+
+        let shift = i32::from(level) - i32::from(section.level.as_u8());
+        Ok(shift_headings(markdown.trim(), shift))
+    }
+
+    /// Find the entry at the normalized path, which may leave out the entry's extension.
+    fn find(&self, path: &Path) -> Option<(&PathBuf, &JournalEntry)> {
+        self.entries.get_key_value(path).or_else(|| {
+            self.entries
+                .iter()
+                .filter(|_| path.extension().is_none())
+                .find(|(entry_path, _)| entry_path.with_extension("") == path)
+        })
+    }
+}
+
+/// Move every heading in the Markdown by `shift` levels, keeping them between H1 and H6. Only the markers of the
+/// headings are rewritten, leaving the rest of the Markdown byte for byte as it is. Setext headings, underlined
+/// with `=` or `-`, are rewritten as ATX headings, as they have no marker for levels past H2.
+fn shift_headings(markdown: &str, shift: i32) -> String {
+    if shift == 0 {
+        return String::from(markdown);
+    }
+
+    let replacements = Parser::new_ext(markdown, parser_options())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::Heading(level, ..)) => Some((level, range)),
+            _ => None,
+        })
+        .map(|(level, range)| {
+            let level = (level as i32 + shift).clamp(1, MAX_HEADING_LEVEL);
+            let marker = "#".repeat(level as usize);
+            let heading = &markdown[range.clone()];
+            let indent = heading.len() - heading.trim_start_matches(' ').len();
+
+            match heading[indent..].starts_with('#') {
+                true => {
+                    let start = range.start + indent;
+                    let hashes =
+                        heading[indent..].len() - heading[indent..].trim_start_matches('#').len();
+
+                    (start..start + hashes, marker)
+                }
+                false => {
+                    let heading = heading.trim_end();
+                    let text = heading
+                        .lines()
+                        .take(heading.lines().count().saturating_sub(1))
+                        .map(str::trim)
+                        .collect::<Vec<_>>()
+                        .join(" ");
+
+                    (
+                        range.start..range.start + heading.len(),
+                        format!("{marker} {text}"),
+                    )
+                }
+            }
+        })
+        .collect();
+
+    splice(markdown, replacements)
+}
+
+/// The normal components of the path, without any `.` components. `..` components are resolved where they can be.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => {}
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::Config;
+    use std::collections::BTreeMap;
+
+    fn entry(title: &str, path: &str, body: &str) -> JournalItem {
+        let entry = JournalEntry {
+            title: String::from(title),
+            path: Some(PathBuf::from(path)),
+            body: Some(String::from(body)),
+            ..Default::default()
+        };
+
+        JournalItem::Entry(entry.parse().expect("should parse"))
+    }
+
+    fn run(items: Vec<JournalItem>) -> Result<Journal> {
+        let journal = Journal {
+            title: None,
+            items,
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+        let ctx = TransformerContext::new(PathBuf::from("test"), Config::default());
+
+        EmbedTransformer.run(&ctx, journal)
+    }
+
+    fn markdown(journal: &Journal, index: usize) -> String {
+        let JournalItem::Entry(ref entry) = journal.items[index] else {
+            panic!("item was not an entry")
+        };
+
+        entry.to_markdown()
+    }
+
+    #[test]
+    fn embeds_sections_with_shifted_headings() {
+        let journal = run(vec![
+            entry(
+                "Overview",
+                "./overview.md",
+                "{{#embed places/tavern.md#Cellar}}\n\n# Dungeon\n\n{{#embed places/tavern#Cellar}}",
+            ),
+            entry(
+                "Tavern",
+                "./places/tavern.md",
+                "# Rooms\n\n## Cellar\n\nDark.\n\n### Barrels\n\nAle.\n\n#### Tap\n\n## Attic",
+            ),
+        ])
+        .expect("should transform");
+
+        assert_eq!(
+            "Dark.\n\n# Barrels\n\nAle.\n\n## Tap\n\n# Dungeon\n\nDark.\n\n## Barrels\n\nAle.\n\n### Tap",
+            markdown(&journal, 0)
+        );
+
+        let JournalItem::Entry(ref overview) = journal.items[0] else {
+            panic!("first item was not an entry")
+        };
+        assert_eq!(
+            vec!["Barrels", "Tap", "Dungeon", "Barrels", "Tap"],
+            overview
+                .iter_sections()
+                .map(|section| section.title.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn shifts_only_the_heading_markers() {
+        let markdown = "## Stock\n\n| Item | Cost |\n|------|------|\n| Ale | 4 |\n\n\\*Cash only\\*\n\nCellar\n------\n";
+
+        assert_eq!(
+            "### Stock\n\n| Item | Cost |\n|------|------|\n| Ale | 4 |\n\n\\*Cash only\\*\n\n### Cellar\n",
+            shift_headings(markdown, 1)
+        );
+    }
+
+    #[test]
+    fn embeds_nested_embeds_relative_to_their_entry() {
+        let journal = run(vec![
+            entry(
+                "Overview",
+                "./overview.md",
+                "# Places\n\n{{#embed places/tavern.md#Cellar}}",
+            ),
+            entry(
+                "Tavern",
+                "./places/tavern.md",
+                "# Cellar\n\n{{#embed ../npcs.md#Borin}}",
+            ),
+            entry(
+                "NPCs",
+                "./npcs.md",
+                "# Borin\n\nBarkeep.\n\n## Stats\n\nStrong.",
+            ),
+        ])
+        .expect("should transform");
+
+        assert_eq!(
+            "# Places\n\nBarkeep.\n\n## Stats\n\nStrong.",
+            markdown(&journal, 0)
+        );
+        assert_eq!(
+            "# Cellar\n\nBarkeep.\n\n## Stats\n\nStrong.",
+            markdown(&journal, 1)
+        );
+    }
+
+    #[test]
+    fn rejects_missing_targets_and_cycles() {
+        for (body, error) in [
+            ("{{#embed missing.md#Cellar}}", "No entry is at missing.md"),
+            (
+                "{{#embed tavern.md#Attic}}",
+                "\"Tavern\" has no section titled \"Attic\"",
+            ),
+            ("{{#embed tavern.md}}", "Expected a path and section title"),
+            (
+                "{{#embed tavern.md#Cellar}}",
+                "Sections embed each other: tavern.md#Cellar -> crypt.md#Tomb -> tavern.md#Cellar",
+            ),
+        ] {
+            let err = run(vec![
+                entry("Overview", "./overview.md", body),
+                entry(
+                    "Tavern",
+                    "./tavern.md",
+                    "# Cellar\n\n{{#embed crypt.md#Tomb}}",
+                ),
+                entry(
+                    "Crypt",
+                    "./crypt.md",
+                    "# Tomb\n\n{{#embed tavern.md#Cellar}}",
+                ),
+            ])
+            .expect_err("should fail");
+
+            assert!(format!("{err:#}").contains(error), "{body}: {err:#}");
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::mem;
+
+use super::{Transformer, TransformerContext, EMPTY_SECTIONS_PRIORITY};
+use crate::{
+    error::Result,
+    model::journal::{Journal, JournalEntry, Section},
+};
+
+/// Cleans up sections whose heading has no title, such as those made by a stray `#` on a line of its own in
+/// imported content. It is not run by default; add `empty-sections` to `build.transformers` to enable it.
+///
+/// What happens to the body of an empty section is set by `EmptySectionsMode`, through the `[empty-sections]`
+/// table of `journal.toml`. Either way, the sections nested below an empty section are put in its place, keeping
+/// their heading levels, so that no titled heading is lost. Empty sections nested below an empty section are
+/// cleaned up first, so their bodies end up wherever the body of the outer section goes.
+pub struct EmptySectionsTransformer;
+
+/// Configuration for the empty sections transformer, read from the `[empty-sections]` table of `journal.toml`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct EmptySectionsConfig {
+    /// What to do with the bodies of empty sections.
+    pub mode: EmptySectionsMode,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmptySectionsMode {
+    /// Append the body of the empty section to the text preceding it: the body of the last section before it in
+    /// document order, or the body of the section or entry it is nested in when it comes first.
+    #[default]
+    Merge,
+    /// Remove the body of the empty section.
+    Drop,
+}
+
+impl EmptySectionsTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for EmptySectionsTransformer {
+    fn name(&self) -> &str {
+        "empty-sections"
+    }
+
+    fn priority(&self) -> i32 {
+        EMPTY_SECTIONS_PRIORITY
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let config: EmptySectionsConfig = ctx.config.get("empty-sections")?;
+
+        journal.for_each_entry_mut(|entry| clean_up_entry(entry, config.mode));
+
+        Ok(journal)
+    }
+}
+
+fn clean_up_entry(entry: &mut JournalEntry, mode: EmptySectionsMode) {
+    let had_body = entry.body.is_some();
+    let mut body = entry.body.take().unwrap_or_default();
+
+    entry.sections = clean_up(mem::take(&mut entry.sections), &mut body, mode);
+    entry.body = (had_body || !body.is_empty()).then_some(body);
+}
+
+/// Remove the empty sections, appending their bodies to `parent_body` when they come first and the mode merges.
+fn clean_up(
+    sections: Vec<Section>,
+    parent_body: &mut String,
+    mode: EmptySectionsMode,
+) -> Vec<Section> {
+    let mut cleaned: Vec<Section> = Vec::with_capacity(sections.len());
+
+    for mut section in sections {
+        section.sections = clean_up(mem::take(&mut section.sections), &mut section.body, mode);
+
+        if !section.title.trim().is_empty() {
+            cleaned.push(section);
+            continue;
+        }
+
+        if mode == EmptySectionsMode::Merge {
+            let preceding = match last_section_mut(&mut cleaned) {
+                Some(preceding) => &mut preceding.body,
+                None => &mut *parent_body,
+            };
+
+            append(preceding, &section.body);
+        }
+
+        cleaned.extend(section.sections);
+    }
+
+    cleaned
+}
+
+/// The last section in document order, which is the deepest last section nested below the last section.
+fn last_section_mut(sections: &mut [Section]) -> Option<&mut Section> {
+    let last = sections.last_mut()?;
+
+    match last.sections.is_empty() {
+        true => Some(last),
+        false => last_section_mut(&mut last.sections),
+    }
+}
+
+/// Append Markdown to a body, as a block of its own.
+fn append(body: &mut String, markdown: &str) {
+    let markdown = markdown.trim();
+
+    if markdown.is_empty() {
+        return;
+    }
+
+    body.truncate(body.trim_end().len());
+
+    if !body.is_empty() {
+        body.push_str("\n\n");
+    }
+
+    body.push_str(markdown);
+    body.push('\n');
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn clean_up_markdown(markdown: &str, mode: EmptySectionsMode) -> JournalEntry {
+        let mut entry = JournalEntry {
+            body: Some(String::from(markdown)),
+            ..Default::default()
+        }
+        .parse()
+        .expect("should parse");
+
+        clean_up_entry(&mut entry, mode);
+
+        entry
+    }
+
+    fn outline(sections: &[Section]) -> Vec<(u8, String, String)> {
+        sections
+            .iter()
+            .flat_map(|section| {
+                std::iter::once((
+                    section.level.as_u8(),
+                    section.title.clone(),
+                    section.body.trim().to_string(),
+                ))
+                .chain(outline(&section.sections))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merges_top_level_empty_sections_into_the_preceding_text() {
+        let entry = clean_up_markdown(
+            "Intro\n\n#\nStray\n\n# Inn\nAle\n\n## Cellar\nBarrels\n\n#\nMore ale\n",
+            EmptySectionsMode::Merge,
+        );
+
+        assert_eq!(Some("Intro\n\nStray\n"), entry.body.as_deref());
+        assert_eq!(
+            vec![
+                (1, String::from("Inn"), String::from("Ale")),
+                (
+                    2,
+                    String::from("Cellar"),
+                    String::from("Barrels\n\nMore ale")
+                ),
+            ],
+            outline(&entry.sections)
+        );
+    }
+
+    #[test]
+    fn promotes_the_sections_nested_below_empty_sections() {
+        let entry = clean_up_markdown(
+            "# Inn\nAle\n\n##\nStray\n\n###\nStrayer\n\n### Cellar\nBarrels\n",
+            EmptySectionsMode::Merge,
+        );
+
+        assert_eq!(None, entry.body);
+        assert_eq!(
+            vec![
+                (
+                    1,
+                    String::from("Inn"),
+                    String::from("Ale\n\nStray\n\nStrayer")
+                ),
+                (3, String::from("Cellar"), String::from("Barrels")),
+            ],
+            outline(&entry.sections)
+        );
+    }
+
+    #[test]
+    fn drops_the_bodies_of_empty_sections() {
+        let entry = clean_up_markdown(
+            "#\nStray\n\n# Inn\nAle\n\n##\nStray\n\n### Cellar\nBarrels\n",
+            EmptySectionsMode::Drop,
+        );
+
+        assert_eq!(None, entry.body);
+        assert_eq!(
+            vec![
+                (1, String::from("Inn"), String::from("Ale")),
+                (3, String::from("Cellar"), String::from("Barrels")),
+            ],
+            outline(&entry.sections)
+        );
+    }
+}
@@ -0,0 +1,411 @@
+use anyhow::Context;
+use pulldown_cmark::{Event, Parser, Tag};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    mem,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+use super::{wikilinks::relative_path, Transformer, TransformerContext, GLOSSARY_PRIORITY};
+use crate::{
+    cmark::{parser_options, push_escaped, splice, SlugStrategy, TextRun},
+    error::Result,
+    model::journal::Journal,
+};
+
+/// Marks up the glossary terms used in the bodies of entries, so that readers can look them up. The terms and
+/// their definitions are read from the `[glossary]` table of `journal.toml`; a glossary kept in the metadata of an
+/// entry can be merged into that table by the `config-metadata` transformer. It is not run by default; add
+/// `glossary` to `build.transformers` to enable it.
+///
+/// Terms are matched as whole words, ignoring case, and the longest term wins where terms overlap. Each match keeps
+/// its own text, and is wrapped in `<abbr title="definition">` or, with `markup = "link"`, linked to the section of
+/// the glossary entry titled like the term. Terms in code spans, code blocks, HTML and the text of existing links
+/// are left as they are, as is the glossary entry itself. With `first-only`, only the first use of each term in a
+/// section is marked up.
+pub struct GlossaryTransformer;
+
+/// Configuration for the glossary transformer, read from the `[glossary]` table of `journal.toml`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct GlossaryConfig {
+    /// The definition of each term.
+    pub terms: BTreeMap<String, String>,
+
+    /// How terms are marked up.
+    pub markup: GlossaryMarkup,
+
+    /// The path of the glossary entry, relative to the journal's source. Required for `markup = "link"`.
+    pub entry: Option<PathBuf>,
+
+    /// Only mark up the first use of each term in a section.
+    pub first_only: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum GlossaryMarkup {
+    /// Wrap terms in an `<abbr>` element titled with their definition.
+    #[default]
+    Abbr,
+    /// Link terms to their section of the glossary entry.
+    Link,
+}
+
+impl GlossaryTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for GlossaryTransformer {
+    fn name(&self) -> &str {
+        "glossary"
+    }
+
+    fn priority(&self) -> i32 {
+        GLOSSARY_PRIORITY
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let config: GlossaryConfig = ctx.config.get("glossary")?;
+
+        if config.terms.is_empty() {
+            return Ok(journal);
+        }
+
+        if config.markup == GlossaryMarkup::Link && config.entry.is_none() {
+            anyhow::bail!(
+                "Linking glossary terms needs the glossary entry to be set in `glossary.entry`"
+            );
+        }
+
+        let glossary = Glossary::new(&config, ctx.config.build.slug_strategy);
+
+        journal.try_for_each_entry_mut(|entry| {
+            let path = entry.path.clone().unwrap_or_default();
+
+            if config
+                .entry
+                .as_deref()
+                .is_some_and(|glossary| normalize(glossary) == normalize(&path))
+            {
+                return Ok(());
+            }
+
+            let title = entry.title.clone();
+            let mark_up = |markdown: &str| {
+                glossary
+                    .mark_up(markdown, &path)
+                    .with_context(|| format!("Failed to mark up the glossary terms of \"{title}\""))
+            };
+
+            if let Some(ref mut body) = entry.body {
+                *body = mark_up(body)?;
+            }
+
+            entry.try_for_each_mut(|section| {
+                section.body = mark_up(&section.body)?;
+
+                Ok(())
+            })
+        })?;
+
+        Ok(journal)
+    }
+}
+
+struct Glossary<'a> {
+    config: &'a GlossaryConfig,
+    /// The lowercase terms, longest first, each with its term as written in the configuration.
+    terms: Vec<(String, &'a str)>,
+    /// How the anchors of terms in the glossary entry are slugified.
+    strategy: SlugStrategy,
+}
+
+impl<'a> Glossary<'a> {
+    fn new(config: &'a GlossaryConfig, strategy: SlugStrategy) -> Self {
+        let mut terms: Vec<_> = config
+            .terms
+            .keys()
+            .filter(|term| !term.trim().is_empty())
+            .map(|term| (term.to_lowercase(), term.as_str()))
+            .collect();
+        terms.sort_by_key(|(term, _)| std::cmp::Reverse(term.chars().count()));
+
+        Self {
+            config,
+            terms,
+            strategy,
+        }
+    }
+
+    /// Mark up the terms in the Markdown of the entry at `path`. Only the terms are rewritten, leaving the rest of
+    /// the Markdown byte for byte as it is.
+    fn mark_up(&self, markdown: &str, path: &Path) -> Result<String> {
+        let mut replacements = Vec::new();
+        let mut text = TextRun::default();
+        let mut skipped = 0;
+        let mut used = HashSet::new();
+
+        // NOTE: Some punctuation is split into text events of its own, so adjacent text is joined before looking
+        // for terms.
+        for (event, range) in Parser::new_ext(markdown, parser_options()).into_offset_iter() {
+            match event {
+                Event::Text(ref fragment) if skipped == 0 => {
+                    if !text.follows(markdown, &range) {
+                        self.find_terms(
+                            markdown,
+                            &mem::take(&mut text),
+                            path,
+                            &mut used,
+                            &mut replacements,
+                        );
+                    }
+
+                    text.push(markdown, fragment, range);
+                    continue;
+                }
+                Event::Start(Tag::CodeBlock(_) | Tag::Link(..) | Tag::Image(..)) => skipped += 1,
+                Event::End(Tag::CodeBlock(_) | Tag::Link(..) | Tag::Image(..)) => skipped -= 1,
+                _ => {}
+            }
+
+            self.find_terms(
+                markdown,
+                &mem::take(&mut text),
+                path,
+                &mut used,
+                &mut replacements,
+            );
+        }
+
+        self.find_terms(markdown, &text, path, &mut used, &mut replacements);
+
+        Ok(splice(markdown, replacements))
+    }
+
+    /// Find the terms in the text of the Markdown, adding the source range of each with the markup replacing it.
+    fn find_terms(
+        &self,
+        markdown: &str,
+        text: &TextRun,
+        path: &Path,
+        used: &mut HashSet<&'a str>,
+        replacements: &mut Vec<(Range<usize>, String)>,
+    ) {
+        let run = text.text();
+        let mut marked = 0;
+        let mut previous = None;
+
+        for (start, character) in run.char_indices() {
+            let starts_word = !previous.is_some_and(is_word_character);
+            previous = Some(character);
+
+            if start < marked || !starts_word {
+                continue;
+            }
+
+            let Some((end, term)) = self.find_term(&run[start..], used) else {
+                continue;
+            };
+
+            if self.config.first_only {
+                used.insert(term);
+            }
+
+            let range = text.source_range(start..start + end);
+            let markup = self.mark_up_term(&markdown[range.clone()], term, path);
+
+            replacements.push((range, markup));
+            marked = start + end;
+        }
+    }
+
+    /// The length of the longest term at the start of the text, followed by the term as configured.
+    fn find_term(&self, text: &str, used: &HashSet<&str>) -> Option<(usize, &'a str)> {
+        self.terms
+            .iter()
+            .filter(|(_, term)| !used.contains(term))
+            .find_map(|(lowercase, term)| {
+                let end = text
+                    .char_indices()
+                    .nth(lowercase.chars().count())
+                    .map_or(text.len(), |(end, _)| end);
+                let followed_by_word = text[end..].chars().next().is_some_and(is_word_character);
+
+                (!followed_by_word && text[..end].to_lowercase() == *lowercase)
+                    .then_some((end, *term))
+            })
+    }
+
+    /// The markup of a term, written as `markdown`.
+    fn mark_up_term(&self, markdown: &str, term: &str, path: &Path) -> String {
+        match (self.config.markup, &self.config.entry) {
+            (GlossaryMarkup::Link, Some(glossary)) => format!(
+                "[{markdown}]({}#{})",
+                relative_path(path, glossary),
+                self.strategy.slugify(term)
+            ),
+            _ => {
+                let mut markup = String::from("<abbr title=\"");
+                push_escaped(&mut markup, &self.config.terms[term]);
+                markup.push_str("\">");
+                markup.push_str(markdown);
+                markup.push_str("</abbr>");
+
+                markup
+            }
+        }
+    }
+}
+
+fn is_word_character(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        model::journal::{JournalEntry, JournalItem},
+    };
+
+    const CONFIG: &str = r#"
+        [glossary.terms]
+        AC = "Armor Class"
+        "Armor Class" = "How hard a creature is to hit"
+        DC = "Difficulty Class"
+    "#;
+
+    fn run(config: &str, entries: &[(&str, &str)]) -> Vec<String> {
+        let items = entries
+            .iter()
+            .map(|(path, body)| {
+                let entry = JournalEntry {
+                    path: Some(PathBuf::from(path)),
+                    body: Some(String::from(*body)),
+                    ..Default::default()
+                };
+
+                JournalItem::Entry(entry.parse().expect("should parse"))
+            })
+            .collect();
+        let journal = Journal {
+            title: None,
+            items,
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        let config: Config = config.parse().expect("should parse config");
+        let ctx = TransformerContext::new(PathBuf::from("test"), config);
+        let journal = GlossaryTransformer
+            .run(&ctx, journal)
+            .expect("should transform");
+
+        journal
+            .items
+            .iter()
+            .map(|item| match item {
+                JournalItem::Entry(entry) => entry.body.clone().unwrap_or_default(),
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn marks_up_whole_words_ignoring_case() {
+        let bodies = run(
+            CONFIG,
+            &[(
+                "./rules.md",
+                "Beat the dc, not the ACE. Your armor class is your AC.",
+            )],
+        );
+
+        assert_eq!(
+            "Beat the <abbr title=\"Difficulty Class\">dc</abbr>, not the ACE. Your \
+             <abbr title=\"How hard a creature is to hit\">armor class</abbr> is your \
+             <abbr title=\"Armor Class\">AC</abbr>.",
+            bodies[0]
+        );
+    }
+
+    #[test]
+    fn skips_code_links_and_images() {
+        let body = "`AC` and [the AC](./ac.md) and ![AC](ac.png)\n\n```\nAC\n```";
+
+        assert_eq!(body, run(CONFIG, &[("./rules.md", body)])[0]);
+    }
+
+    #[test]
+    fn leaves_the_rest_of_the_markdown_as_it_is() {
+        let config: Config = CONFIG.parse().expect("should parse config");
+        let config: GlossaryConfig = config.get("glossary").expect("should read glossary");
+        let glossary = Glossary::new(&config, SlugStrategy::default());
+        let body =
+            "| Stat | Value |\n|------|-------|\n| AC | 15 |\n\n\\*not emphasis\\* and \\*DC\\*";
+
+        assert_eq!(
+            "| Stat | Value |\n|------|-------|\n| <abbr title=\"Armor Class\">AC</abbr> | 15 |\n\n\\*not emphasis\\* \
+             and \\*<abbr title=\"Difficulty Class\">DC</abbr>\\*",
+            glossary
+                .mark_up(body, Path::new("./rules.md"))
+                .expect("should mark up")
+        );
+    }
+
+    #[test]
+    fn marks_up_only_the_first_use_when_asked() {
+        let config = format!("{CONFIG}\n[glossary]\nfirst-only = true");
+        let bodies = run(&config, &[("./rules.md", "AC, AC and DC")]);
+
+        assert_eq!(
+            "<abbr title=\"Armor Class\">AC</abbr>, AC and <abbr title=\"Difficulty Class\">DC</abbr>",
+            bodies[0]
+        );
+    }
+
+    #[test]
+    fn links_to_the_glossary_entry() {
+        let config = format!("{CONFIG}\n[glossary]\nmarkup = \"link\"\nentry = \"glossary.md\"");
+        let bodies = run(
+            &config,
+            &[
+                ("./rules/combat.md", "Roll against the AC"),
+                ("./glossary.md", "AC"),
+            ],
+        );
+
+        assert_eq!(
+            vec!["Roll against the [AC](../glossary.md#ac)", "AC"],
+            bodies
+        );
+    }
+
+    #[test]
+    fn needs_the_glossary_entry_to_link_terms() {
+        let config: Config = format!("{CONFIG}\n[glossary]\nmarkup = \"link\"")
+            .parse()
+            .expect("should parse config");
+        let ctx = TransformerContext::new(PathBuf::from("test"), config);
+        let journal = Journal {
+            title: None,
+            items: Vec::new(),
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        assert!(GlossaryTransformer.run(&ctx, journal).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Transformer, TransformerContext, HEADING_LEVELS_PRIORITY};
+use crate::{
+    error::Result,
+    model::journal::{Journal, JournalEntry, Section, SectionLevel},
+};
+
+/// Finds sections whose heading skips a level below the section they are nested in, such as an H3 directly below
+/// an H1, which leaves gaps in the navigation renderers build from headings. It is not run by default; add
+/// `heading-levels` to `build.transformers` to enable it.
+///
+/// What happens to a skipped level is set by `HeadingLevelsMode`, through the `[heading-levels]` table of
+/// `journal.toml`. When normalizing, every nested section is put one level below the section it is nested in, and
+/// top level sections keep their level, so the nesting of the entry stays the same and only the gaps close. An
+/// entry going H1, H3, H1 becomes H1, H2, H1, and one going H2, H4 becomes H2, H3. Entries whose top level
+/// sections do not start at H1 are left that way; see `normalize-heading-levels` of `[build.parse]` for that.
+pub struct HeadingLevelsTransformer;
+
+/// Configuration for the heading levels transformer, read from the `[heading-levels]` table of `journal.toml`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct HeadingLevelsConfig {
+    /// What to do with sections that skip a level.
+    pub mode: HeadingLevelsMode,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HeadingLevelsMode {
+    /// Log a warning naming the entry and section for every skipped level, leaving the levels as they are.
+    #[default]
+    Warn,
+    /// Close the gaps, as described by `HeadingLevelsTransformer`.
+    Normalize,
+}
+
+impl HeadingLevelsTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for HeadingLevelsTransformer {
+    fn name(&self) -> &str {
+        "heading-levels"
+    }
+
+    fn priority(&self) -> i32 {
+        HEADING_LEVELS_PRIORITY
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let config: HeadingLevelsConfig = ctx.config.get("heading-levels")?;
+
+        journal.for_each_entry_mut(|entry| match config.mode {
+            HeadingLevelsMode::Warn => {
+                for (parent, section) in skipped_levels(entry) {
+                    log::warn!(
+                        "The section \"{}\" of entry \"{}\" skips from H{} to H{}",
+                        section.title,
+                        entry.title,
+                        parent.as_u8(),
+                        section.level.as_u8()
+                    );
+                }
+            }
+            HeadingLevelsMode::Normalize => {
+                for section in &mut entry.sections {
+                    normalize(section);
+                }
+            }
+        });
+
+        Ok(journal)
+    }
+}
+
+/// Every section of the entry that skips a level, with the level of the section it is nested in, in document order.
+fn skipped_levels(entry: &JournalEntry) -> Vec<(SectionLevel, &Section)> {
+    fn visit<'a>(section: &'a Section, skipped: &mut Vec<(SectionLevel, &'a Section)>) {
+        for nested in &section.sections {
+            if section.level.next().is_some_and(|next| nested.level > next) {
+                skipped.push((section.level, nested));
+            }
+
+            visit(nested, skipped);
+        }
+    }
+
+    let mut skipped = Vec::new();
+
+    for section in &entry.sections {
+        visit(section, &mut skipped);
+    }
+
+    skipped
+}
+
+/// Put the sections nested below `section` one level below it, and so on down.
+fn normalize(section: &mut Section) {
+    let Some(level) = section.level.next() else {
+        return;
+    };
+
+    for nested in &mut section.sections {
+        nested.level = level;
+        normalize(nested);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(markdown: &str) -> JournalEntry {
+        JournalEntry {
+            title: String::from("Keep"),
+            body: Some(String::from(markdown)),
+            ..Default::default()
+        }
+        .parse()
+        .expect("should parse")
+    }
+
+    fn levels(entry: &JournalEntry) -> Vec<(u8, &str)> {
+        entry
+            .iter_sections()
+            .map(|section| (section.level.as_u8(), section.title.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn closes_gaps_below_top_level_sections() {
+        let mut entry =
+            parse("# Keep\n\n### Dungeon\n\nCells\n\n###### Oubliette\n\n# Bailey\n\nMud");
+
+        assert_eq!(
+            vec![
+                (SectionLevel::H1, "Dungeon"),
+                (SectionLevel::H3, "Oubliette")
+            ],
+            skipped_levels(&entry)
+                .into_iter()
+                .map(|(parent, section)| (parent, section.title.as_str()))
+                .collect::<Vec<_>>()
+        );
+
+        entry.sections.iter_mut().for_each(normalize);
+
+        assert_eq!(
+            vec![(1, "Keep"), (2, "Dungeon"), (3, "Oubliette"), (1, "Bailey")],
+            levels(&entry)
+        );
+        assert!(skipped_levels(&entry).is_empty());
+    }
+
+    #[test]
+    fn keeps_the_level_of_top_level_sections() {
+        let mut entry = parse("## Keep\n\n#### Dungeon\n\n### Tower");
+
+        entry.sections.iter_mut().for_each(normalize);
+
+        assert_eq!(
+            vec![(2, "Keep"), (3, "Dungeon"), (3, "Tower")],
+            levels(&entry)
+        );
+        assert_eq!("## Keep\n\n### Dungeon\n\n### Tower", entry.to_markdown());
+    }
+}
@@ -0,0 +1,258 @@
+use anyhow::Context;
+use std::collections::HashMap;
+
+use super::{replace_directives, Transformer, TransformerContext, INCLUDE_ENTRY_PRIORITY};
+use crate::{build::preprocess::directive::is_exempt, error::Result, model::journal::Journal};
+
+/// The name of the directive that includes another entry.
+pub(crate) const INCLUDE_ENTRY_DIRECTIVE: &str = "include_entry";
+
+const SECTIONS_ARGUMENT: &str = "sections";
+
+/// Replaces `{{#include_entry "Title"}}` directives with the body of the entry with that title, for overview
+/// entries that assemble excerpts of several others. With `{{#include_entry "Title" sections}}`, the entry's
+/// sections are included after its body, headings and all.
+///
+/// Entries are found by their title once the transformers with the default priority have run, so a title set
+/// by the `title` transformer is used over the name of the entry's link in the table of contents. Directives in
+/// included entries are replaced in turn, and an entry that ends up including itself is an error, as is a title
+/// that no entry or more than one entry has. The `directive` preprocessor leaves these directives in place, as
+/// the other entries are only known once every entry has been parsed. An escaped directive,
+/// `\{{#include_entry "Title"}}`, is written out as is, and entries exempt from directive expansion, as described
+/// by the `directive` preprocessor, are left unchanged and are included as they are.
+pub struct IncludeEntryTransformer;
+
+impl IncludeEntryTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for IncludeEntryTransformer {
+    fn name(&self) -> &str {
+        "include-entry"
+    }
+
+    fn priority(&self) -> i32 {
+        INCLUDE_ENTRY_PRIORITY
+    }
+
+    fn run(&self, _ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let entries = IncludedEntries::new(&journal);
+
+        journal.try_for_each_entry_mut(|entry| {
+            if entry.body.as_deref().is_some_and(is_exempt) {
+                return Ok(());
+            }
+
+            let title = entry.title.clone();
+            let mut stack = vec![title.clone()];
+            let mut expand = |text: &mut String| -> Result<()> {
+                *text = entries
+                    .expand(text, &mut stack)
+                    .with_context(|| format!("Failed to include entries in \"{title}\""))?;
+
+                Ok(())
+            };
+
+            if let Some(ref mut body) = entry.body {
+                expand(body)?;
+            }
+
+            entry.try_for_each_mut(|section| expand(&mut section.body))
+        })?;
+
+        Ok(journal)
+    }
+}
+
+/// The content of an entry that can be included.
+struct IncludedEntry {
+    body: String,
+    /// The body followed by every section, as with `JournalEntry::to_markdown`.
+    markdown: String,
+    exempt: bool,
+}
+
+/// The entries of the journal by title, as they were before any directive was replaced. A title shared by more
+/// than one entry maps to `None`.
+struct IncludedEntries {
+    entries: HashMap<String, Option<IncludedEntry>>,
+}
+
+impl IncludedEntries {
+    fn new(journal: &Journal) -> Self {
+        let mut entries = HashMap::new();
+
+        journal.for_each_entry(|entry| {
+            let body = entry.body.clone().unwrap_or_default();
+            let included = IncludedEntry {
+                exempt: is_exempt(&body),
+                markdown: entry.to_markdown(),
+                body,
+            };
+
+            entries
+                .entry(entry.title.clone())
+                .and_modify(|entry| *entry = None)
+                .or_insert(Some(included));
+        });
+
+        Self { entries }
+    }
+
+    /// Replace every include directive in the text. `stack` holds the titles of the entries being included,
+    /// starting with the entry the text belongs to.
+    fn expand(&self, text: &str, stack: &mut Vec<String>) -> Result<String> {
+        replace_directives(text, INCLUDE_ENTRY_DIRECTIVE, |args| {
+            self.include(args, stack).map(Some)
+        })
+    }
+
+    fn include(&self, args: &str, stack: &mut Vec<String>) -> Result<String> {
+        let (title, sections) = parse_args(args)?;
+
+        let Some(entry) = self.entries.get(title) else {
+            anyhow::bail!("No entry is titled \"{title}\"")
+        };
+
+        let Some(entry) = entry else {
+            anyhow::bail!("More than one entry is titled \"{title}\"")
+        };
+
+        if stack.iter().any(|included| included == title) {
+            anyhow::bail!(
+                "Entries include each other: {} -> {title}",
+                stack.join(" -> ")
+            );
+        }
+
+        let content = match sections {
+            true => &entry.markdown,
+            false => &entry.body,
+        };
+
+        if entry.exempt {
+            return Ok(content.clone());
+        }
+
+        stack.push(String::from(title));
+        let expanded = self.expand(content, stack);
+        stack.pop();
+
+        expanded
+    }
+}
+
+/// Parse the arguments of an include directive in the form of `"Title"` or `"Title" sections`.
+fn parse_args(args: &str) -> Result<(&str, bool)> {
+    let Some((title, rest)) = args
+        .trim()
+        .strip_prefix('"')
+        .and_then(|args| args.split_once('"'))
+    else {
+        anyhow::bail!("Expected the quoted title of an entry to include, found: {args}")
+    };
+
+    match rest.trim() {
+        "" => Ok((title, false)),
+        SECTIONS_ARGUMENT => Ok((title, true)),
+        rest => anyhow::bail!("Unexpected argument to #include_entry: {rest}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        model::journal::{JournalEntry, JournalItem},
+    };
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    fn entry(title: &str, body: &str) -> JournalItem {
+        let entry = JournalEntry {
+            title: String::from(title),
+            body: Some(String::from(body)),
+            ..Default::default()
+        };
+
+        JournalItem::Entry(entry.parse().expect("should parse"))
+    }
+
+    fn run(items: Vec<JournalItem>) -> Result<Journal> {
+        let journal = Journal {
+            title: None,
+            items,
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+        let ctx = TransformerContext::new(PathBuf::from("test"), Config::default());
+
+        IncludeEntryTransformer.run(&ctx, journal)
+    }
+
+    #[test]
+    fn includes_entries_by_title() {
+        let journal = run(vec![
+            entry(
+                "Overview",
+                "{{#include_entry \"The Tavern\"}}\n\n# Dungeon\n\n{{#include_entry \"The Crypt\" sections}}",
+            ),
+            entry(
+                "The Tavern",
+                "Ale and {{#include_entry \"Barkeep\"}}, not \\\\{{#include_entry \"Barkeep\"}}\n\n# Rooms",
+            ),
+            entry("Barkeep", "Borin"),
+            entry("The Crypt", "Dark.\n\n## Tomb\n\nBones."),
+        ])
+        .expect("should transform");
+
+        let JournalItem::Entry(ref overview) = journal.items[0] else {
+            panic!("first item was not an entry")
+        };
+
+        assert_eq!(
+            Some("Ale and Borin, not {{#include_entry \"Barkeep\"}}"),
+            overview.body.as_deref().map(str::trim)
+        );
+        assert_eq!(
+            "Dark.\n\n## Tomb\n\nBones.",
+            overview.sections[0].body.trim()
+        );
+    }
+
+    #[test]
+    fn rejects_cycles_and_unknown_titles() {
+        let cycle = run(vec![
+            entry("A", "{{#include_entry \"B\"}}"),
+            entry("B", "{{#include_entry \"A\"}}"),
+        ])
+        .expect_err("should fail");
+        let unknown = run(vec![entry("A", "{{#include_entry \"C\"}}")]).expect_err("should fail");
+        let ambiguous = run(vec![
+            entry("A", "{{#include_entry \"B\"}}"),
+            entry("B", "One"),
+            entry("B", "Two"),
+        ])
+        .expect_err("should fail");
+
+        assert!(format!("{cycle:#}").contains("Entries include each other: A -> B -> A"));
+        assert!(format!("{unknown:#}").contains("No entry is titled \"C\""));
+        assert!(format!("{ambiguous:#}").contains("More than one entry is titled \"B\""));
+    }
+
+    #[test]
+    fn parses_include_arguments() {
+        assert_eq!(
+            ("The Tavern", false),
+            parse_args(" \"The Tavern\"").unwrap()
+        );
+        assert_eq!(
+            ("The Tavern", true),
+            parse_args(" \"The Tavern\" sections").unwrap()
+        );
+        assert!(parse_args(" The Tavern").is_err());
+        assert!(parse_args(" \"The Tavern\" all").is_err());
+    }
+}
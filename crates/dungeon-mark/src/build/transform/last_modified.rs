@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use super::{Transformer, TransformerContext};
+use crate::{
+    error::Result,
+    model::journal::{Journal, SectionMetadata},
+};
+
+/// The metadata key the last change to each section's file is stored under.
+pub const LAST_MODIFIED_KEY: &str = "__last_modified";
+
+/// Annotates every section with the last commit that changed its entry's file, for "recently updated" views. The
+/// commit is stored as a `toml` metadata block under `__last_modified`, which parses into `LastModified`. Entries
+/// without sections, files that were never committed and entries without a file are left as they are.
+///
+/// The commits are found by running `git log` in the journal root, once for each file over the whole build. The
+/// transformer does nothing if git is not installed, the journal root is not in a git repository or the repository
+/// is a shallow clone, whose history would give the wrong commits. It is not run unless it is listed in
+/// `build.transformers`.
+pub struct LastModifiedTransformer {
+    repository: RefCell<Option<bool>>,
+    commits: RefCell<HashMap<PathBuf, Option<LastModified>>>,
+}
+
+/// The last commit that changed a file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LastModified {
+    /// The date the commit was authored, in strict ISO 8601 format, such as `2024-03-01T19:30:00+01:00`.
+    pub date: String,
+    /// The name of the commit's author.
+    pub author: String,
+}
+
+impl LastModifiedTransformer {
+    pub(crate) fn new() -> Self {
+        Self {
+            repository: RefCell::new(None),
+            commits: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Whether history can be read from the repository holding the journal root, checked on first use.
+    fn has_history(&self, root: &Path) -> bool {
+        *self.repository.borrow_mut().get_or_insert_with(|| {
+            match git(root, &["rev-parse", "--is-shallow-repository"]) {
+                Some(shallow) if shallow.trim() == "false" => true,
+                Some(_) => {
+                    log::warn!(
+                        "Skipping last modified dates, as the repository is a shallow clone"
+                    );
+                    false
+                }
+                None => {
+                    log::debug!(
+                        "Skipping last modified dates, as the journal is not in a git repository"
+                    );
+                    false
+                }
+            }
+        })
+    }
+
+    fn last_modified(&self, root: &Path, file: &Path) -> Option<LastModified> {
+        self.commits
+            .borrow_mut()
+            .entry(file.to_path_buf())
+            .or_insert_with(|| {
+                let file = file.to_str()?;
+                let output = git(root, &["log", "-1", "--format=%aI%n%an", "--", file])?;
+                let (date, author) = output.trim_end().split_once('\n')?;
+
+                Some(LastModified {
+                    date: String::from(date),
+                    author: String::from(author),
+                })
+            })
+            .clone()
+    }
+}
+
+impl Transformer for LastModifiedTransformer {
+    fn name(&self) -> &str {
+        "last-modified"
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        if !self.has_history(&ctx.root) {
+            return Ok(journal);
+        }
+
+        journal.try_for_each_entry_mut(|entry| {
+            let Some(file_name) = entry.path.as_deref().and_then(Path::file_name) else {
+                return Ok(());
+            };
+
+            let file = ctx.config.resolve_entry_dir(entry)?.join(file_name);
+            let Some(last_modified) = self.last_modified(&ctx.root, &file) else {
+                return Ok(());
+            };
+            let data = toml::to_string(&last_modified)?;
+
+            entry.for_each_mut(|section| {
+                section.metadata.remove(LAST_MODIFIED_KEY);
+                section.metadata.insert(
+                    LAST_MODIFIED_KEY,
+                    SectionMetadata {
+                        lang: String::from("toml"),
+                        data: data.clone(),
+                        attributes: Vec::new(),
+                    },
+                );
+            });
+
+            Ok(())
+        })?;
+
+        Ok(journal)
+    }
+}
+
+/// Run git in `root`, returning its output, or `None` if it could not be run or failed.
+fn git(root: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
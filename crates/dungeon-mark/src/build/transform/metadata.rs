@@ -1,17 +1,45 @@
-use std::collections::HashMap;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
 
-use pulldown_cmark::{CodeBlockKind, Event, Tag};
-
-use super::Transformer;
+use super::{Transformer, TransformerContext, METADATA_PRIORITY};
 
 use crate::{
-    cmark::{CMarkParser, EventIteratorExt},
     error::Result,
-    model::journal::{Journal, JournalItem, Section, SectionMetadata},
+    model::journal::{
+        extract_metadata, Journal, MetadataFormats, Section, DEFAULT_METADATA_SENTINEL,
+    },
 };
 
+/// Extracts fenced code blocks tagged as metadata from section bodies into `Section::metadata`.
+///
+/// A metadata block is a fenced code block whose info string is either `lang,metadata,key` or `metadata,key`,
+/// the latter leaving the language empty. Any further comma separated tokens (e.g. `toml,metadata,stats,hidden`)
+/// are kept as the block's attributes. For quick annotations, the key can be left out as well, as in
+/// `toml,metadata` or a bare `metadata`, storing the block under the empty key. Whitespace around the tokens and a
+/// trailing comma are ignored. The `metadata` sentinel can be changed through the `[metadata]` table of
+/// `journal.toml`.
+///
+/// Blocks are stored as they are, and only parsed when they are read. With `build.strict-metadata`, every block is
+/// parsed in the format of its language as it is extracted, as registered in `TransformerContext::metadata_formats`,
+/// and a malformed block fails the build. Blocks in languages without a registered format are not checked.
 pub struct MetadataTransformer;
 
+/// Configuration for the metadata transformer, read from the `[metadata]` table of `journal.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct MetadataConfig {
+    /// The token that marks a fenced code block as metadata.
+    pub sentinel: String,
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        Self {
+            sentinel: String::from(DEFAULT_METADATA_SENTINEL),
+        }
+    }
+}
+
 impl MetadataTransformer {
     pub(crate) fn new() -> Self {
         Self
@@ -23,88 +51,69 @@ impl Transformer for MetadataTransformer {
         "metadata"
     }
 
-    fn run(&self, _ctx: &super::TransformerContext, mut journal: Journal) -> Result<Journal> {
-        for item in &mut journal.items {
-            #[allow(irrefutable_let_patterns)]
-            if let JournalItem::Entry(entry) = item {
-                entry.try_for_each_mut(extract_metadata)?;
-            }
-        }
-
-        Ok(journal)
+    fn priority(&self) -> i32 {
+        METADATA_PRIORITY
     }
-}
 
-fn extract_metadata(section: &mut Section) -> Result<()> {
-    let mut body = Vec::new();
-    let mut metadata = HashMap::new();
-    let mut events = CMarkParser::new(&section.body);
-
-    while let Some(event) = events.peek_event() {
-        match event {
-            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(tag))) if is_metadata_block(tag) => {
-                let (lang, key) = parse_metadata_tag(tag);
-                events.next_event();
-
-                let data = events
-                    .iter_until_and_consume(|event| {
-                        matches! {
-                            event,
-                            Event::End(Tag::CodeBlock(CodeBlockKind::Fenced(_)))
-                        }
-                    })
-                    .stringify()?;
-                let section_meta = SectionMetadata { lang, data };
-
-                metadata.insert(key, section_meta);
-                body.push(String::from("\n\n")); // Replace the missing code block with a hard break.
-            }
-            _ => {
-                let text = events
-                    .iter_until(|event| {
-                        matches! {
-                            event,
-                            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(tag))) if is_metadata_block(tag)
-                        }
-                    })
-                    .stringify()?;
-
-                body.push(text);
-            }
-        }
-    }
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let config: MetadataConfig = ctx.config.get("metadata")?;
 
-    // Consume the end of the event stream.
-    events.next_event();
+        journal.try_for_each_entry_mut(|entry| {
+            let title = entry.title.clone();
 
-    section.body = body.into_iter().collect();
-    section.metadata.extend(metadata);
+            entry.try_for_each_mut(|section| {
+                extract_metadata(section, &config.sentinel)?;
 
-    Ok(())
-}
+                if ctx.config.build.strict_metadata {
+                    check_metadata(&ctx.metadata_formats, section)
+                        .with_context(|| format!("Malformed metadata in \"{title}\""))?;
+                }
+
+                Ok(())
+            })?;
 
-fn is_metadata_block(tag: &str) -> bool {
-    let parts: Vec<_> = tag.split(',').map(|part| part.trim()).collect();
+            entry.update_excerpt(ctx.config.build.parse.excerpt_length);
 
-    matches!(&parts[..], [_, "metadata", _])
+            Ok(())
+        })?;
+
+        Ok(journal)
+    }
 }
 
-fn parse_metadata_tag(tag: &str) -> (String, String) {
-    let parts: Vec<_> = tag.split(',').map(|part| part.trim()).collect();
-    let [lang, "metadata", key] = &parts[..] else {
-        unreachable!("is_metadata_block invariant was violated")
-    };
+/// Parse every metadata block of the section whose language has a registered format, failing on the first block
+/// that does not parse.
+fn check_metadata(formats: &MetadataFormats, section: &Section) -> Result<()> {
+    for (key, metadata) in section.metadata.iter() {
+        if !metadata.lang.is_empty() && formats.get(&metadata.lang).is_none() {
+            continue;
+        }
 
-    (lang.to_string(), key.to_string())
+        formats.parse(metadata).with_context(|| {
+            format!(
+                "The metadata block {key} in section \"{}\" is not valid {}",
+                section.title,
+                if metadata.lang.is_empty() {
+                    "toml"
+                } else {
+                    &metadata.lang
+                }
+            )
+        })?;
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::{path::PathBuf, str::FromStr};
+    use std::{collections::BTreeMap, path::PathBuf, str::FromStr};
 
     use crate::{
-        build::transform::TransformerContext, config::Config, model::journal::JournalEntry,
+        build::transform::TransformerContext,
+        config::Config,
+        model::journal::{JournalEntry, JournalItem, Matter, Metadata, SectionMetadata},
     };
 
     #[test]
@@ -127,24 +136,38 @@ Following text"#;
                 }],
                 level: 1,
                 path: None,
+                source_dir: None,
+                children: Vec::new(),
+                tags: Vec::new(),
+                content_hash: None,
+                excerpt: None,
+                number: None,
+                chapter: None,
+                heading_offset: 0,
+                matter: Matter::Body,
+                link_title: None,
+                draft: false,
             })],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
         };
 
-        let ctx = TransformerContext {
-            root: PathBuf::from_str("test").expect("should parse"),
-            config: Config::default(),
-        };
+        let ctx = TransformerContext::new(
+            PathBuf::from_str("test").expect("should parse"),
+            Config::default(),
+        );
 
         let actual_journal = MetadataTransformer
             .run(&ctx, original_journal)
             .expect("journal should be preprocessed");
 
-        let mut metadata = HashMap::new();
+        let mut metadata = Metadata::new();
         metadata.insert(
             String::from("test"),
             SectionMetadata {
                 lang: String::from("toml"),
                 data: String::from("This is test data\n"),
+                attributes: Vec::new(),
             },
         );
 
@@ -160,8 +183,21 @@ Following text"#;
                     ..Default::default()
                 }],
                 path: None,
+                source_dir: None,
                 level: 1,
+                children: Vec::new(),
+                tags: Vec::new(),
+                content_hash: None,
+                excerpt: Some(String::from("test Test section Following text")),
+                number: None,
+                chapter: None,
+                heading_offset: 0,
+                matter: Matter::Body,
+                link_title: None,
+                draft: false,
             })],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
         };
 
         assert_eq!(expected_journal, actual_journal);
@@ -188,14 +224,27 @@ Following text"#;
                     ..Default::default()
                 }],
                 path: None,
+                source_dir: None,
                 level: 1,
+                children: Vec::new(),
+                tags: Vec::new(),
+                content_hash: None,
+                excerpt: None,
+                number: None,
+                chapter: None,
+                heading_offset: 0,
+                matter: Matter::Body,
+                link_title: None,
+                draft: false,
             })],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
         };
 
-        let ctx = TransformerContext {
-            root: PathBuf::from_str("test").expect("should parse"),
-            config: Config::default(),
-        };
+        let ctx = TransformerContext::new(
+            PathBuf::from_str("test").expect("should parse"),
+            Config::default(),
+        );
 
         let actual_journal = MetadataTransformer
             .run(&ctx, original_journal)
@@ -212,10 +261,178 @@ Following text"#;
                     ..Default::default()
                 }],
                 path: None,
+                source_dir: None,
                 level: 1,
+                children: Vec::new(),
+                tags: Vec::new(),
+                content_hash: None,
+                excerpt: Some(String::from(
+                    "test Test section This is test data Following text",
+                )),
+                number: None,
+                chapter: None,
+                heading_offset: 0,
+                matter: Matter::Body,
+                link_title: None,
+                draft: false,
             })],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
         };
 
         assert_eq!(expected_journal, actual_journal);
     }
+
+    #[test]
+    fn extracts_metadata_with_configured_sentinel() {
+        let section_body = r#"Test section
+```toml,metadata,ignored
+This is not metadata
+```
+```meta,test
+This is test data
+```
+Following text"#;
+
+        let mut section = Section {
+            title: String::from("test"),
+            body: String::from(section_body),
+            ..Default::default()
+        };
+
+        let config: Config = "[metadata]\nsentinel = \"meta\""
+            .parse()
+            .expect("should parse");
+        let config: MetadataConfig = config.get("metadata").expect("should deserialize");
+        extract_metadata(&mut section, &config.sentinel).expect("should extract metadata");
+
+        let mut expected = Metadata::new();
+        expected.insert(
+            String::from("test"),
+            SectionMetadata {
+                lang: String::new(),
+                data: String::from("This is test data\n"),
+                attributes: Vec::new(),
+            },
+        );
+
+        assert_eq!(expected, section.metadata);
+        assert!(section.body.contains("This is not metadata"));
+    }
+
+    #[test]
+    fn keeps_metadata_data_verbatim() {
+        let section_body = "```toml,metadata,npc\n[npc]\nname = \"*Borin*\"\n```";
+
+        let mut section = Section {
+            title: String::from("test"),
+            body: String::from(section_body),
+            ..Default::default()
+        };
+
+        extract_metadata(&mut section, "metadata").expect("should extract metadata");
+
+        assert_eq!(
+            Some("[npc]\nname = \"*Borin*\"\n"),
+            section.metadata.get("npc").map(|m| m.data.as_str())
+        );
+    }
+
+    #[test]
+    fn keeps_every_block_with_the_same_key_in_order() {
+        let section_body = r#"```toml,metadata,npc
+name = "Borin"
+```
+```toml,metadata,npc
+name = "Ilsa"
+```
+```toml,metadata,npc
+name = "Tomas"
+```"#;
+
+        let mut section = Section {
+            title: String::from("test"),
+            body: String::from(section_body),
+            ..Default::default()
+        };
+
+        extract_metadata(&mut section, "metadata").expect("should extract metadata");
+
+        let names: Vec<_> = section
+            .metadata
+            .get_all("npc")
+            .iter()
+            .map(|metadata| metadata.data.as_str())
+            .collect();
+
+        assert_eq!(
+            vec![
+                "name = \"Borin\"\n",
+                "name = \"Ilsa\"\n",
+                "name = \"Tomas\"\n"
+            ],
+            names
+        );
+        assert_eq!(
+            Some("name = \"Borin\"\n"),
+            section.metadata.get("npc").map(|m| m.data.as_str())
+        );
+        assert_eq!(3, section.metadata.len());
+    }
+
+    fn run_strict(body: &str, strict: bool) -> Result<Journal> {
+        let journal = Journal {
+            title: None,
+            items: vec![JournalItem::Entry(JournalEntry {
+                title: String::from("Goblins"),
+                sections: vec![Section {
+                    title: String::from("Boss"),
+                    body: String::from(body),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            })],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+        let config: Config = format!("[build]\nstrict-metadata = {strict}")
+            .parse()
+            .expect("should parse config");
+        let ctx = TransformerContext::new(PathBuf::from("test"), config);
+
+        MetadataTransformer.run(&ctx, journal)
+    }
+
+    #[test]
+    fn accepts_valid_metadata_when_strict() {
+        let body = "```toml,metadata,stats\nhp = 7\n```\n\n```json,metadata,loot\n{ \"gold\": 3 }\n```\n\n```text,metadata,notes\n{ not checked\n```";
+
+        run_strict(body, true).expect("should extract valid metadata");
+    }
+
+    #[test]
+    fn rejects_malformed_metadata_when_strict() {
+        for (body, error) in [
+            (
+                "```toml,metadata,stats\nhp = \n```",
+                "The metadata block stats in section \"Boss\" is not valid toml",
+            ),
+            (
+                "```metadata,stats\nhp = \n```",
+                "The metadata block stats in section \"Boss\" is not valid toml",
+            ),
+            (
+                "```json,metadata,loot\n{ \"gold\": }\n```",
+                "The metadata block loot in section \"Boss\" is not valid json",
+            ),
+        ] {
+            let err = run_strict(body, true).expect_err("should reject malformed metadata");
+            let err = format!("{err:#}");
+
+            assert!(err.contains("Malformed metadata in \"Goblins\""), "{err}");
+            assert!(err.contains(error), "{err}");
+
+            run_strict(body, false).expect("should store malformed metadata when lenient");
+        }
+    }
 }
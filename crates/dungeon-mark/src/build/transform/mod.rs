@@ -1,16 +1,123 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{cell::RefCell, path::PathBuf};
+use toml::{value::Table, Value};
 
-use crate::{config::Config, error::Result, model::journal::Journal};
+use crate::{
+    config::Config,
+    error::Result,
+    model::{
+        journal::{Journal, MetadataFormats},
+        toc::TableOfContents,
+    },
+};
 
+pub(crate) mod asset_paths;
+pub(crate) mod conditional;
+pub(crate) mod config_metadata;
+pub(crate) mod embed;
+pub(crate) mod empty_sections;
+pub(crate) mod glossary;
+pub(crate) mod heading_levels;
+pub(crate) mod include_entry;
+pub(crate) mod last_modified;
 pub(crate) mod metadata;
+pub(crate) mod numbering;
+pub(crate) mod redaction;
+pub(crate) mod section_bodies;
+pub(crate) mod statistics;
+pub(crate) mod tags;
+pub(crate) mod tasks;
+pub(crate) mod title;
+pub(crate) mod title_case;
+pub(crate) mod wikilinks;
 
+/// The priority of the built-in `conditional` transformer. It runs ahead of every other built-in transformer, so
+/// that they only see the content meant for the renderer, including metadata blocks.
+pub const CONDITIONAL_PRIORITY: i32 = -110;
+
+/// What the `directive` preprocessor writes ahead of an escaped directive that a transformer replaces, such as
+/// `\{{#wordcount}}`: an escaped backslash, so that the escape outlasts the parsing of the entry's Markdown.
+pub const DIRECTIVE_ESCAPE: &str = "\\\\";
+
+/// The priority of the built-in `embed` transformer. It runs after the `conditional` transformer, so that only the
+/// content kept for the renderer is embedded, and ahead of the `metadata` transformer, so that metadata blocks of
+/// embedded sections are extracted along with the rest of the entry they are embedded in.
+pub const EMBED_PRIORITY: i32 = -105;
+
+/// The priority of the built-in `empty-sections` transformer. It runs after the `embed` transformer, so that
+/// embedded sections are cleaned up too, and ahead of the `metadata` transformer, so that metadata blocks in the
+/// body of an empty section are extracted from wherever that body ends up.
+pub const EMPTY_SECTIONS_PRIORITY: i32 = -103;
+
+/// The priority of the built-in `metadata` transformer. It runs ahead of transformers with the default priority
+/// of 0, so that they see the metadata it extracts.
+pub const METADATA_PRIORITY: i32 = -100;
+
+/// The priority of the built-in `redaction` transformer. It runs after the `metadata` transformer, whose
+/// metadata marks the sections to redact, and ahead of the other transformers, so that they never see those
+/// sections.
+pub const REDACTION_PRIORITY: i32 = -95;
+
+/// The priority of the built-in `config-metadata` transformer. It runs after the `metadata` transformer, whose
+/// metadata it reads, and ahead of transformers with the default priority of 0, so that they see the
+/// configuration it sets.
+pub const CONFIG_METADATA_PRIORITY: i32 = -90;
+
+/// The priority of the built-in `include-entry` transformer. It runs after transformers with the default priority
+/// of 0, such as `title`, so that entries are included by their final titles and with the directives of those
+/// transformers already replaced.
+pub const INCLUDE_ENTRY_PRIORITY: i32 = 10;
+
+/// The priority of the built-in `heading-levels` transformer. It runs after the `include-entry` transformer, so
+/// that the sections of included entries are checked too, and ahead of the `numbering` transformer, so that
+/// sections are numbered by their final nesting.
+pub const HEADING_LEVELS_PRIORITY: i32 = 15;
+
+/// The priority of the built-in `wikilinks` transformer. It runs after the `include-entry` transformer, so that
+/// wikilinks in included entries are converted too.
+pub const WIKILINKS_PRIORITY: i32 = 20;
+
+/// The priority of the built-in `glossary` transformer. It runs after the `wikilinks` transformer, so that terms
+/// in the text of the links it converts are left alone.
+pub const GLOSSARY_PRIORITY: i32 = 25;
+
+/// The priority of the built-in `numbering` transformer. It runs after the `include-entry` transformer, so that
+/// the sections of included entries are numbered too.
+pub const NUMBERING_PRIORITY: i32 = 30;
+
+/// The priority of the built-in `title-case` transformer. It runs after the `include-entry` and `wikilinks`
+/// transformers, so that they find entries by their titles as written.
+pub const TITLE_CASE_PRIORITY: i32 = 35;
+
+/// A transformer takes a journal with parsed entries and transforms it prior to rendering.
+///
+/// The transform stage runs once for every renderer, applying only the transformers that support that
+/// renderer. Transforming per renderer costs a full pass over the journal for each renderer, so transformers
+/// should keep their work proportional to the size of the journal.
 pub trait Transformer {
     fn name(&self) -> &str;
 
     fn run(&self, ctx: &TransformerContext, journal: Journal) -> Result<Journal>;
 
-    // TODO: Do I need to add a "supports renderer" method?
+    /// The order this transformer runs in relative to other transformers, from lowest to highest. Transformers
+    /// with the same priority run in the order they were added. See `METADATA_PRIORITY` for the position of
+    /// the built-in `metadata` transformer.
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    /// The names of the renderers this transformer should run for, or `None` to run for every renderer.
+    fn supported_renderers(&self) -> Option<&[&str]> {
+        None
+    }
+
+    /// The keywords of the `{{#...}}` directives this transformer replaces, such as `npc` for `{{#npc Borin}}`.
+    /// The `directive` preprocessor leaves any directive in place, but fails on directives it does not know of
+    /// with `build.strict-directives`, unless a transformer of the build declares them here. Escaped directives
+    /// declared here keep their escape, for the transformer to remove with `replace_directives`.
+    fn directives(&self) -> &[&str] {
+        &[]
+    }
 }
 
 #[non_exhaustive]
@@ -19,10 +126,115 @@ pub struct TransformerContext {
     pub root: PathBuf,
 
     pub config: Config,
+
+    /// The name of the renderer the journal is being transformed for, or `None` for a dry run.
+    #[serde(default)]
+    pub renderer: Option<String>,
+
+    /// The table of contents the journal was loaded from, for resolving references between entries.
+    #[serde(default)]
+    pub table_of_contents: TableOfContents,
+
+    /// The formats metadata blocks can be parsed from, keyed by the block's language.
+    #[serde(skip)]
+    pub metadata_formats: MetadataFormats,
+
+    /// Free-form configuration set by the running transformer, merged into `config` once it has finished.
+    #[serde(skip)]
+    config_updates: RefCell<Table>,
 }
 
 impl TransformerContext {
-    pub(crate) fn new(root: PathBuf, config: Config) -> TransformerContext {
-        TransformerContext { root, config }
+    /// A context for transforming a journal outside of a build, such as in tests of a transformer. The journal's
+    /// root is only used to resolve paths, and no renderer is set.
+    pub fn new(root: PathBuf, config: Config) -> TransformerContext {
+        TransformerContext {
+            root,
+            config,
+            renderer: None,
+            table_of_contents: TableOfContents::default(),
+            metadata_formats: MetadataFormats::default(),
+            config_updates: RefCell::default(),
+        }
+    }
+
+    /// Set a free-form configuration key, as with `Config::merge`. The change is visible to the transformers
+    /// that run after this one and to the renderer, but not in `config` while the current transformer runs.
+    pub fn set_config(&self, key: impl Into<String>, value: Value) {
+        self.config_updates.borrow_mut().insert(key.into(), value);
+    }
+
+    /// Merge the configuration set by the last transformer into `config`.
+    pub(crate) fn apply_config_updates(&mut self) -> Result<()> {
+        for (key, value) in self.config_updates.take() {
+            self.config.merge(&key, value)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn into_config(self) -> Config {
+        self.config
+    }
+}
+
+/// Replace each `{{#keyword ...}}` directive in the text with what `replace` returns for the directive's
+/// arguments, the text between the keyword and the closing `}}`, or leave the directive as it is if `replace`
+/// returns `None`. Directives escaped with `DIRECTIVE_ESCAPE` are written out without the escape instead, and
+/// directives whose keyword merely starts with the given one, such as `{{#wordcounts}}`, are left alone.
+pub fn replace_directives(
+    text: &str,
+    keyword: &str,
+    mut replace: impl FnMut(&str) -> Result<Option<String>>,
+) -> Result<String> {
+    let open = format!("{{{{#{keyword}");
+    let mut replaced = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(&open) {
+        let arguments_start = start + open.len();
+        let Some(length) = rest[arguments_start..].find("}}") else {
+            break;
+        };
+
+        let arguments = &rest[arguments_start..arguments_start + length];
+        let end = arguments_start + length + "}}".len();
+
+        if !arguments.is_empty() && !arguments.starts_with(char::is_whitespace) {
+            replaced.push_str(&rest[..end]);
+        } else if let Some(preceding) = rest[..start].strip_suffix(DIRECTIVE_ESCAPE) {
+            replaced.push_str(preceding);
+            replaced.push_str(&rest[start..end]);
+        } else {
+            replaced.push_str(&rest[..start]);
+
+            match replace(arguments)? {
+                Some(replacement) => replaced.push_str(&replacement),
+                None => replaced.push_str(&rest[start..end]),
+            }
+        }
+
+        rest = &rest[end..];
+    }
+
+    replaced.push_str(rest);
+
+    Ok(replaced)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn replaces_directives_and_unescapes_escaped_ones() {
+        let replaced = replace_directives(
+            "{{#npc Borin}}, \\\\{{#npc Borin}}, {{#npcs}} and {{#npc}}",
+            "npc",
+            |arguments| Ok((!arguments.is_empty()).then(|| arguments.trim().to_uppercase())),
+        )
+        .expect("should replace");
+
+        assert_eq!("BORIN, {{#npc Borin}}, {{#npcs}} and {{#npc}}", replaced);
     }
 }
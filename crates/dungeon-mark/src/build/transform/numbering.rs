@@ -0,0 +1,295 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Transformer, TransformerContext, NUMBERING_PRIORITY};
+use crate::{
+    error::Result,
+    model::journal::{Journal, JournalItem, Section, SectionMetadata},
+};
+
+/// The metadata key the number of each section is stored under.
+pub const NUMBER_KEY: &str = "__number";
+
+/// Numbers the entries and sections of the journal hierarchically, such as `1.2.3`, for numbered rulebooks. The
+/// number of each entry is stored in `JournalEntry::number`, and the number of each section in a metadata block
+/// under `__number`, for renderers to put in front of headings.
+///
+/// Entries are numbered by their nesting in the table of contents, with the entries nested below an entry
+/// numbered below it. The sections of an entry are numbered below the entry by their own nesting, and the entries
+/// nested below an entry follow its top level sections, so that numbers are never shared. With
+/// `chapter-titles = "reset"`, numbering starts over after each chapter title. With `"increment"`, chapter titles
+/// take the next number, and the entries after them are numbered below it. The numbering is configured through the
+/// `[numbering]` table of `journal.toml`.
+pub struct NumberingTransformer;
+
+/// Configuration for the numbering transformer, read from the `[numbering]` table of `journal.toml`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NumberingConfig {
+    /// How each part of a number is written.
+    pub style: NumberingStyle,
+
+    /// How chapter titles affect the numbering.
+    pub chapter_titles: ChapterNumbering,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum NumberingStyle {
+    /// Every part is a number, such as `2.1.3`.
+    #[default]
+    Decimal,
+    /// The first part is a letter, continuing with `AA` after `Z`, and the rest are numbers, such as `B.1.3`.
+    Alphanumeric,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChapterNumbering {
+    /// Start numbering the entries after a chapter title over from 1.
+    #[default]
+    Reset,
+    /// Number chapter titles along with the entries, and number the entries after a chapter title below it.
+    Increment,
+}
+
+impl NumberingTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for NumberingTransformer {
+    fn name(&self) -> &str {
+        "numbering"
+    }
+
+    fn priority(&self) -> i32 {
+        NUMBERING_PRIORITY
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let config: NumberingConfig = ctx.config.get("numbering")?;
+
+        number_items(&mut journal.items, &[], 0, &config);
+
+        Ok(journal)
+    }
+}
+
+/// Number the items below the entry numbered `parent`, where the first entry takes the number after `start`.
+fn number_items(
+    items: &mut [JournalItem],
+    parent: &[usize],
+    start: usize,
+    config: &NumberingConfig,
+) {
+    let mut count = start;
+    let mut chapter = None;
+
+    for item in items {
+        match item {
+            JournalItem::ChapterTitle(_) => match config.chapter_titles {
+                ChapterNumbering::Reset => count = start,
+                ChapterNumbering::Increment => {
+                    let number = chapter.map_or(count, |(number, _)| number) + 1;
+                    chapter = Some((number, 0));
+                }
+            },
+            JournalItem::Entry(entry) => {
+                let mut number = parent.to_vec();
+
+                match chapter {
+                    Some((chapter_number, ref mut chapter_count)) => {
+                        *chapter_count += 1;
+                        number.extend([chapter_number, *chapter_count]);
+                    }
+                    None => {
+                        count += 1;
+                        number.push(count);
+                    }
+                }
+
+                entry.number = Some(format_number(&number, config.style));
+                number_sections(&mut entry.sections, &number, config.style);
+                number_items(&mut entry.children, &number, entry.sections.len(), config);
+            }
+            JournalItem::Separator(_) | JournalItem::Error(_) => {}
+        }
+    }
+}
+
+fn number_sections(sections: &mut [Section], parent: &[usize], style: NumberingStyle) {
+    for (index, section) in sections.iter_mut().enumerate() {
+        let mut number = parent.to_vec();
+        number.push(index + 1);
+
+        section.metadata.remove(NUMBER_KEY);
+        section.metadata.insert(
+            NUMBER_KEY,
+            SectionMetadata {
+                lang: String::new(),
+                data: format_number(&number, style),
+                attributes: Vec::new(),
+            },
+        );
+
+        number_sections(&mut section.sections, &number, style);
+    }
+}
+
+fn format_number(number: &[usize], style: NumberingStyle) -> String {
+    number
+        .iter()
+        .enumerate()
+        .map(|(index, part)| match style {
+            NumberingStyle::Alphanumeric if index == 0 => letters(*part),
+            _ => part.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The number written with letters, as `A` to `Z` followed by `AA`, `AB` and so on.
+fn letters(mut number: usize) -> String {
+    let mut letters = Vec::new();
+
+    while number > 0 {
+        number -= 1;
+        letters.push(char::from(b'A' + (number % 26) as u8));
+        number /= 26;
+    }
+
+    letters.iter().rev().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::journal::{ChapterTitle, JournalEntry, Metadata, SectionLevel};
+
+    fn section(title: &str, sections: Vec<Section>) -> Section {
+        Section {
+            title: String::from(title),
+            level: SectionLevel::H1,
+            body: String::new(),
+            metadata: Metadata::new(),
+            sections,
+            span: None,
+        }
+    }
+
+    fn entry(title: &str, sections: Vec<Section>, children: Vec<JournalItem>) -> JournalItem {
+        JournalItem::Entry(JournalEntry {
+            title: String::from(title),
+            sections,
+            children,
+            ..Default::default()
+        })
+    }
+
+    fn chapter(title: &str) -> JournalItem {
+        JournalItem::ChapterTitle(ChapterTitle {
+            title: String::from(title),
+            anchor: String::new(),
+        })
+    }
+
+    fn numbers(items: &[JournalItem]) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for item in items {
+            if let JournalItem::Entry(entry) = item {
+                lines.push(format!(
+                    "{} {}",
+                    entry.number.as_deref().unwrap_or_default(),
+                    entry.title
+                ));
+
+                for section in entry.iter_sections() {
+                    let number = section
+                        .metadata
+                        .get(NUMBER_KEY)
+                        .expect("should be numbered");
+                    lines.push(format!("{} {}", number.data, section.title));
+                }
+
+                lines.extend(numbers(&entry.children));
+            }
+        }
+
+        lines
+    }
+
+    fn number(mut items: Vec<JournalItem>, config: NumberingConfig) -> Vec<String> {
+        number_items(&mut items, &[], 0, &config);
+
+        numbers(&items)
+    }
+
+    fn rulebook() -> Vec<JournalItem> {
+        vec![
+            entry("Intro", Vec::new(), Vec::new()),
+            chapter("Rules"),
+            entry(
+                "Combat",
+                vec![section("Attacks", vec![section("Criticals", Vec::new())])],
+                vec![entry(
+                    "Grappling",
+                    vec![section("Escaping", Vec::new())],
+                    Vec::new(),
+                )],
+            ),
+            entry("Magic", Vec::new(), Vec::new()),
+            chapter("Empty"),
+            chapter("Appendix"),
+            entry("Tables", Vec::new(), Vec::new()),
+        ]
+    }
+
+    #[test]
+    fn restarts_numbering_at_chapter_titles() {
+        assert_eq!(
+            vec![
+                "1 Intro",
+                "1 Combat",
+                "1.1 Attacks",
+                "1.1.1 Criticals",
+                "1.2 Grappling",
+                "1.2.1 Escaping",
+                "2 Magic",
+                "1 Tables"
+            ],
+            number(rulebook(), NumberingConfig::default())
+        );
+    }
+
+    #[test]
+    fn numbers_entries_below_incrementing_chapter_titles() {
+        let config = NumberingConfig {
+            style: NumberingStyle::Alphanumeric,
+            chapter_titles: ChapterNumbering::Increment,
+        };
+
+        assert_eq!(
+            vec![
+                "A Intro",
+                "B.1 Combat",
+                "B.1.1 Attacks",
+                "B.1.1.1 Criticals",
+                "B.1.2 Grappling",
+                "B.1.2.1 Escaping",
+                "B.2 Magic",
+                "D.1 Tables"
+            ],
+            number(rulebook(), config)
+        );
+    }
+
+    #[test]
+    fn writes_letters_past_z() {
+        assert_eq!(
+            vec!["A", "Z", "AA", "AZ", "BA"],
+            [1, 26, 27, 52, 53].map(letters)
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+use std::mem;
+
+use super::{Transformer, TransformerContext, REDACTION_PRIORITY};
+use crate::{
+    error::Result,
+    model::journal::{Journal, JournalEntry, Metadata, Section},
+};
+
+/// Removes sections marked as secret, such as notes meant only for the game master, when `build.redact` is set.
+///
+/// A section is secret when its metadata holds a block under the configured key, `gm-only` by default. What
+/// happens to a secret section and the sections nested below it is set by `RedactionMode`. Both are configured
+/// through the `[redaction]` table of `journal.toml`.
+pub struct RedactionTransformer;
+
+/// Configuration for the redaction transformer, read from the `[redaction]` table of `journal.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct RedactionConfig {
+    /// The metadata key that marks a section as secret.
+    pub key: String,
+
+    /// What to do with secret sections.
+    pub mode: RedactionMode,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            key: String::from("gm-only"),
+            mode: RedactionMode::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RedactionMode {
+    /// Remove the secret section along with every section nested below it.
+    #[default]
+    Drop,
+    /// Remove the secret section, putting the sections nested below it in its place. The nested sections keep
+    /// their heading levels and are themselves redacted if they are secret.
+    Promote,
+    /// Keep the secret section's heading, removing its body, its metadata and every section nested below it.
+    Blank,
+}
+
+impl RedactionTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for RedactionTransformer {
+    fn name(&self) -> &str {
+        "redaction"
+    }
+
+    fn priority(&self) -> i32 {
+        REDACTION_PRIORITY
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        if !ctx.config.build.redact {
+            return Ok(journal);
+        }
+
+        let config: RedactionConfig = ctx.config.get("redaction")?;
+
+        journal.for_each_entry_mut(|entry| {
+            redact_entry(entry, &config, ctx.config.build.parse.excerpt_length)
+        });
+
+        Ok(journal)
+    }
+}
+
+/// Redact the secret sections of the entry, taking its excerpt again so that it holds no secret text.
+fn redact_entry(entry: &mut JournalEntry, config: &RedactionConfig, excerpt_length: usize) {
+    entry.sections = redact(mem::take(&mut entry.sections), config);
+    entry.update_excerpt(excerpt_length);
+}
+
+fn redact(sections: Vec<Section>, config: &RedactionConfig) -> Vec<Section> {
+    let mut redacted = Vec::with_capacity(sections.len());
+
+    for mut section in sections {
+        if !section.metadata.contains_key(&config.key) {
+            section.sections = redact(mem::take(&mut section.sections), config);
+            redacted.push(section);
+
+            continue;
+        }
+
+        match config.mode {
+            RedactionMode::Drop => {}
+            RedactionMode::Promote => redacted.extend(redact(section.sections, config)),
+            RedactionMode::Blank => {
+                section.body.clear();
+                section.metadata = Metadata::new();
+                section.sections.clear();
+                redacted.push(section);
+            }
+        }
+    }
+
+    redacted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::journal::{SectionLevel, SectionMetadata};
+
+    fn section(title: &str, level: SectionLevel, secret: bool, sections: Vec<Section>) -> Section {
+        let mut metadata = Metadata::new();
+
+        if secret {
+            metadata.insert(
+                "gm-only",
+                SectionMetadata {
+                    lang: String::new(),
+                    data: String::new(),
+                    attributes: Vec::new(),
+                },
+            );
+        }
+
+        Section {
+            title: String::from(title),
+            level,
+            body: format!("{title} body"),
+            metadata,
+            sections,
+            span: None,
+        }
+    }
+
+    /// A tavern whose secret cellar holds a public barrel and a secret smugglers' tunnel.
+    fn sections() -> Vec<Section> {
+        vec![section(
+            "Tavern",
+            SectionLevel::H1,
+            false,
+            vec![
+                section("Patrons", SectionLevel::H2, false, vec![]),
+                section(
+                    "Cellar",
+                    SectionLevel::H2,
+                    true,
+                    vec![
+                        section("Barrel", SectionLevel::H3, false, vec![]),
+                        section("Tunnel", SectionLevel::H3, true, vec![]),
+                    ],
+                ),
+            ],
+        )]
+    }
+
+    fn titles(sections: &[Section]) -> Vec<String> {
+        sections
+            .iter()
+            .flat_map(|section| {
+                std::iter::once(section.title.clone()).chain(titles(&section.sections))
+            })
+            .collect()
+    }
+
+    fn redact_with(mode: RedactionMode) -> Vec<Section> {
+        let config = RedactionConfig {
+            mode,
+            ..Default::default()
+        };
+
+        redact(sections(), &config)
+    }
+
+    #[test]
+    fn drops_secret_sections_with_their_subtree() {
+        let redacted = redact_with(RedactionMode::Drop);
+
+        assert_eq!(vec!["Tavern", "Patrons"], titles(&redacted));
+    }
+
+    #[test]
+    fn promotes_the_children_of_secret_sections() {
+        let redacted = redact_with(RedactionMode::Promote);
+
+        assert_eq!(vec!["Tavern", "Patrons", "Barrel"], titles(&redacted));
+        assert_eq!(2, redacted[0].sections.len());
+        assert_eq!(SectionLevel::H3, redacted[0].sections[1].level);
+    }
+
+    #[test]
+    fn blanks_secret_sections() {
+        let redacted = redact_with(RedactionMode::Blank);
+        let cellar = &redacted[0].sections[1];
+
+        assert_eq!(vec!["Tavern", "Patrons", "Cellar"], titles(&redacted));
+        assert_eq!("", cellar.body);
+        assert!(cellar.metadata.is_empty());
+    }
+
+    #[test]
+    fn leaves_secret_text_out_of_excerpts() {
+        let mut entry = JournalEntry::new(
+            "Town",
+            "# Secret\n\n```metadata,gm-only\nThe mayor is a lich.\n```\n\nThe lich rules the town.\n\n# Market\n\nFresh bread.\n",
+        )
+        .parse_with_metadata()
+        .expect("should parse");
+
+        assert_eq!(
+            Some("Secret The lich rules the town. Market Fresh bread."),
+            entry.excerpt.as_deref()
+        );
+
+        redact_entry(&mut entry, &RedactionConfig::default(), 200);
+
+        assert_eq!(Some("Market Fresh bread."), entry.excerpt.as_deref());
+    }
+}
@@ -0,0 +1,48 @@
+use anyhow::Context;
+
+use super::{Transformer, TransformerContext};
+use crate::{error::Result, model::journal::Journal};
+
+/// Applies a closure to the body of every section of every entry, as added by `JournalBuilder::map_section_bodies`.
+/// It runs with the default priority, after the built-in `metadata` transformer, so the closure never sees
+/// metadata blocks.
+pub(crate) struct SectionBodiesTransformer<F> {
+    map: F,
+}
+
+impl<F> SectionBodiesTransformer<F>
+where
+    F: Fn(&str) -> Result<String>,
+{
+    pub(crate) fn new(map: F) -> Self {
+        Self { map }
+    }
+}
+
+impl<F> Transformer for SectionBodiesTransformer<F>
+where
+    F: Fn(&str) -> Result<String>,
+{
+    fn name(&self) -> &str {
+        "map-section-bodies"
+    }
+
+    fn run(&self, _ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        journal.try_for_each_entry_mut(|entry| {
+            let title = entry.title.clone();
+
+            entry.try_for_each_mut(|section| {
+                section.body = (self.map)(&section.body).with_context(|| {
+                    format!(
+                        "Failed to map the body of section {:?} of entry {title:?}",
+                        section.title
+                    )
+                })?;
+
+                Ok(())
+            })
+        })?;
+
+        Ok(journal)
+    }
+}
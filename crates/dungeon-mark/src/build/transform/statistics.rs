@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+
+use super::{replace_directives, Transformer, TransformerContext};
+use crate::{build::preprocess::directive::is_exempt, error::Result, model::journal::Journal};
+
+pub(crate) const WORD_COUNT_DIRECTIVE: &str = "wordcount";
+pub(crate) const ENTRY_COUNT_DIRECTIVE: &str = "entrycount";
+pub(crate) const SECTION_COUNT_DIRECTIVE: &str = "sectioncount";
+
+/// Replaces statistics directives in the bodies of entries and sections with counts over the whole journal:
+/// - `{{#wordcount}}` The number of words in the journal, as counted by `Journal::word_count`.
+/// - `{{#entrycount}}` The number of entries in the journal, including nested entries.
+/// - `{{#sectioncount}}` The number of sections in the journal, including nested sections.
+///
+/// These directives are left in place by the `directive` preprocessor, as the counts are only known once every
+/// entry has been parsed. The counts are taken before any directive is replaced, so the directives themselves
+/// count as words. An escaped directive, `\{{#wordcount}}`, is written out as is, and entries exempt from directive
+/// expansion, as described by the `directive` preprocessor, are left unchanged. Numbers are written without
+/// grouping unless `thousands-separator` is set in the `[statistics]` table of `journal.toml`.
+///
+/// The counts are also stored, as a `JournalStats`, in the `statistics` key of the journal's scratch data, for
+/// renderers to read with `Journal::get_scratch`.
+pub struct StatisticsTransformer;
+
+/// Configuration for the statistics transformer, read from the `[statistics]` table of `journal.toml`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct StatisticsConfig {
+    /// The separator placed between each group of three digits, such as `,` for `12,345`.
+    pub thousands_separator: Option<String>,
+}
+
+impl StatisticsTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for StatisticsTransformer {
+    fn name(&self) -> &str {
+        "statistics"
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let config: StatisticsConfig = ctx.config.get("statistics")?;
+        let separator = config.thousands_separator.as_deref();
+
+        let stats = journal.stats();
+        journal.set_scratch("statistics", &stats)?;
+
+        let replacements = [
+            (
+                WORD_COUNT_DIRECTIVE,
+                format_count(stats.word_count, separator),
+            ),
+            (
+                ENTRY_COUNT_DIRECTIVE,
+                format_count(stats.entry_count, separator),
+            ),
+            (
+                SECTION_COUNT_DIRECTIVE,
+                format_count(stats.section_count, separator),
+            ),
+        ];
+        let replace = |text: &mut String| -> Result<()> {
+            for (keyword, count) in &replacements {
+                if text.contains(keyword) {
+                    *text = replace_directives(text, keyword, |arguments| {
+                        Ok(arguments.is_empty().then(|| count.clone()))
+                    })?;
+                }
+            }
+
+            Ok(())
+        };
+
+        journal.try_for_each_entry_mut(|entry| {
+            if entry.body.as_deref().is_some_and(is_exempt) {
+                return Ok(());
+            }
+
+            if let Some(ref mut body) = entry.body {
+                replace(body)?;
+            }
+
+            entry.try_for_each_mut(|section| replace(&mut section.body))
+        })?;
+
+        Ok(journal)
+    }
+}
+
+/// Format the count, placing the separator between each group of three digits if one is given.
+fn format_count(count: usize, separator: Option<&str>) -> String {
+    let digits = count.to_string();
+    let Some(separator) = separator else {
+        return digits;
+    };
+
+    let mut formatted = String::new();
+
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            formatted.push_str(separator);
+        }
+
+        formatted.push(digit);
+    }
+
+    formatted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        model::journal::{JournalEntry, JournalItem, JournalStats},
+    };
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    #[test]
+    fn formats_counts_with_an_optional_separator() {
+        assert_eq!("1234567", format_count(1234567, None));
+        assert_eq!("1,234,567", format_count(1234567, Some(",")));
+        assert_eq!("123", format_count(123, Some(",")));
+        assert_eq!("1 000", format_count(1000, Some(" ")));
+    }
+
+    #[test]
+    fn replaces_statistics_directives() {
+        let entry = |body: &str| {
+            let entry = JournalEntry {
+                body: Some(String::from(body)),
+                ..Default::default()
+            };
+
+            JournalItem::Entry(entry.parse().expect("should parse"))
+        };
+
+        let journal = Journal {
+            title: None,
+            items: vec![
+                entry("Words: {{#wordcount}}\n\n# Counts\n\n{{#entrycount}} and {{#sectioncount}}"),
+                entry("# One\n\nTwo *three* four\n\n## Five\n\n\\\\{{#wordcount}}"),
+            ],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        let ctx = TransformerContext::new(PathBuf::from("test"), Config::default());
+        let journal = StatisticsTransformer
+            .run(&ctx, journal)
+            .expect("should transform");
+
+        let JournalItem::Entry(ref entry) = journal.items[0] else {
+            panic!("first item was not an entry")
+        };
+
+        // NOTE: "Words:", the three directives, "and" and "Counts", then "One" through "Five" and the escaped
+        // directive.
+        assert_eq!(Some("Words: 12"), entry.body.as_deref().map(str::trim));
+        assert_eq!("2 and 3", entry.sections[0].body.trim());
+
+        let JournalItem::Entry(ref entry) = journal.items[1] else {
+            panic!("second item was not an entry")
+        };
+
+        assert_eq!("{{#wordcount}}", entry.sections[0].sections[0].body.trim());
+
+        let stats: JournalStats = journal
+            .get_scratch("statistics")
+            .expect("should read")
+            .expect("should be stored");
+        assert_eq!(2, stats.entry_count);
+        assert_eq!(12, stats.word_count);
+    }
+}
@@ -0,0 +1,202 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
+use toml::Value;
+
+use super::{Transformer, TransformerContext};
+use crate::{
+    error::Result,
+    model::journal::{Journal, JournalEntry, MetadataFormats, SectionMetadata},
+};
+
+/// Sets the tags of each entry from metadata blocks in its sections, and indexes the entries by tag in
+/// `Journal::tags`.
+///
+/// A tags block is a metadata block under the configured key, `tags` by default, holding a document that sets the
+/// same key to an array of strings, such as `tags = ["npc", "waterdeep"]`, or just the array, as in a `json` block.
+/// Blocks are parsed in the format of their language, as registered in `TransformerContext::metadata_formats`. The
+/// tags of every block of an entry are trimmed, lowercased and deduplicated, keeping the order they first appear in.
+/// The key can be changed through the `[tags]` table of `journal.toml`.
+pub struct TagsTransformer;
+
+/// Configuration for the tags transformer, read from the `[tags]` table of `journal.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TagsConfig {
+    /// The metadata key the tags are stored under.
+    pub key: String,
+}
+
+impl Default for TagsConfig {
+    fn default() -> Self {
+        Self {
+            key: String::from("tags"),
+        }
+    }
+}
+
+impl TagsTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for TagsTransformer {
+    fn name(&self) -> &str {
+        "tags"
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let config: TagsConfig = ctx.config.get("tags")?;
+
+        journal.try_for_each_entry_mut(|entry| {
+            entry.tags = entry_tags(&ctx.metadata_formats, entry, &config.key)
+                .with_context(|| format!("Failed to read the tags of entry \"{}\"", entry.title))?;
+
+            Ok(())
+        })?;
+
+        let mut index: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+
+        journal.for_each_entry(|entry| {
+            let Some(ref path) = entry.path else {
+                return;
+            };
+
+            for tag in &entry.tags {
+                index.entry(tag.clone()).or_default().push(path.clone());
+            }
+        });
+
+        journal.tags = index;
+
+        Ok(journal)
+    }
+}
+
+fn entry_tags(formats: &MetadataFormats, entry: &JournalEntry, key: &str) -> Result<Vec<String>> {
+    let mut tags: Vec<String> = Vec::new();
+
+    for section in entry.iter_sections() {
+        for metadata in section.metadata.get_all(key) {
+            for tag in parse_tags(formats, metadata, key)? {
+                let tag = tag.trim().to_lowercase();
+
+                if !tag.is_empty() && !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        }
+    }
+
+    Ok(tags)
+}
+
+fn parse_tags(
+    formats: &MetadataFormats,
+    metadata: &SectionMetadata,
+    key: &str,
+) -> Result<Vec<String>> {
+    let value = Value::try_from(formats.parse(metadata)?)?;
+
+    let tags = match value {
+        Value::Table(mut table) => table.remove(key).unwrap_or(Value::Array(Vec::new())),
+        value => value,
+    };
+
+    tags.try_into()
+        .with_context(|| format!("Expected `{key}` to be an array of strings"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config,
+        model::journal::{JournalItem, Metadata, Section},
+    };
+
+    fn entry(path: &str, blocks: &[(&str, &str)], children: Vec<JournalItem>) -> JournalItem {
+        let mut metadata = Metadata::new();
+
+        for (lang, data) in blocks {
+            metadata.insert(
+                "tags",
+                SectionMetadata {
+                    lang: String::from(*lang),
+                    data: String::from(*data),
+                    attributes: Vec::new(),
+                },
+            );
+        }
+
+        JournalItem::Entry(JournalEntry {
+            title: String::from(path),
+            path: Some(PathBuf::from(path)),
+            sections: vec![Section {
+                title: String::from("Notes"),
+                metadata,
+                ..Default::default()
+            }],
+            children,
+            ..Default::default()
+        })
+    }
+
+    fn run(journal: Journal) -> Result<Journal> {
+        let ctx = TransformerContext::new(PathBuf::from("test"), Config::default());
+
+        TagsTransformer.run(&ctx, journal)
+    }
+
+    #[test]
+    fn normalizes_and_indexes_tags() {
+        let journal = Journal {
+            title: None,
+            items: vec![
+                entry(
+                    "borin.md",
+                    &[
+                        ("toml", "tags = [\" NPC \", \"Waterdeep\"]"),
+                        ("json", "[\"npc\", \"dwarf\"]"),
+                    ],
+                    vec![entry("ilsa.md", &[("toml", "tags = [\"npc\"]")], vec![])],
+                ),
+                entry("dock.md", &[], vec![]),
+            ],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        let journal = run(journal).expect("should transform");
+
+        let JournalItem::Entry(ref borin) = journal.items[0] else {
+            panic!("first item was not an entry")
+        };
+
+        assert_eq!(vec!["npc", "waterdeep", "dwarf"], borin.tags);
+        assert_eq!(
+            BTreeMap::from([
+                (String::from("dwarf"), vec![PathBuf::from("borin.md")]),
+                (
+                    String::from("npc"),
+                    vec![PathBuf::from("borin.md"), PathBuf::from("ilsa.md")]
+                ),
+                (String::from("waterdeep"), vec![PathBuf::from("borin.md")]),
+            ]),
+            journal.tags
+        );
+    }
+
+    #[test]
+    fn rejects_tags_that_are_not_strings() {
+        let journal = Journal {
+            title: None,
+            items: vec![entry("borin.md", &[("toml", "tags = [1, 2]")], vec![])],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        assert!(run(journal).is_err());
+    }
+}
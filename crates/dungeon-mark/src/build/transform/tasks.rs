@@ -0,0 +1,204 @@
+use pulldown_cmark::{Event, Tag};
+use serde::{Deserialize, Serialize};
+
+use super::{Transformer, TransformerContext};
+use crate::{
+    cmark::CMarkParser,
+    error::Result,
+    model::journal::{Journal, Section, SectionMetadata},
+};
+
+/// Extracts the task list items of each section, such as `- [ ] Prepare the map`, into a `json` metadata block
+/// holding an array of `TaskItem`s. The items are left in the section's body. Sections without task items are
+/// left alone. The metadata key, `tasks` by default, can be changed through the `[tasks]` table of
+/// `journal.toml`.
+pub struct TasksTransformer;
+
+/// Configuration for the tasks transformer, read from the `[tasks]` table of `journal.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TasksConfig {
+    /// The metadata key the task items are stored under.
+    pub key: String,
+}
+
+impl Default for TasksConfig {
+    fn default() -> Self {
+        Self {
+            key: String::from("tasks"),
+        }
+    }
+}
+
+/// A single task list item of a section.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TaskItem {
+    /// The plain text of the item, excluding any lists nested in it.
+    pub text: String,
+    pub checked: bool,
+    /// How deeply the item's list is nested in other lists, starting at 0.
+    pub depth: usize,
+}
+
+impl TasksTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for TasksTransformer {
+    fn name(&self) -> &str {
+        "tasks"
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let config: TasksConfig = ctx.config.get("tasks")?;
+
+        journal.try_for_each_entry_mut(|entry| {
+            entry.try_for_each_mut(|section| extract_tasks(section, &config.key))
+        })?;
+
+        Ok(journal)
+    }
+}
+
+fn extract_tasks(section: &mut Section, key: &str) -> Result<()> {
+    let tasks = task_items(&section.body);
+
+    if !tasks.is_empty() {
+        let metadata = SectionMetadata {
+            lang: String::from("json"),
+            data: serde_json::to_string(&tasks)?,
+            attributes: Vec::new(),
+        };
+
+        section.metadata.insert(key, metadata);
+    }
+
+    Ok(())
+}
+
+/// Every task list item of the markdown in document order, where an item is followed by the items nested in it.
+fn task_items(markdown: &str) -> Vec<TaskItem> {
+    let mut parser = CMarkParser::new(markdown);
+    let mut tasks: Vec<TaskItem> = Vec::new();
+    // NOTE: For every open list item, the position of its task in `tasks` if it is a task item.
+    let mut items: Vec<Option<usize>> = Vec::new();
+    let mut depth = 0;
+
+    while let Some(event) = parser.next_event() {
+        match event {
+            Event::Start(Tag::List(_)) => depth += 1,
+            Event::End(Tag::List(_)) => depth -= 1,
+            Event::Start(Tag::Item) => items.push(None),
+            Event::End(Tag::Item) => {
+                items.pop();
+            }
+            Event::TaskListMarker(checked) => {
+                if let Some(item) = items.last_mut() {
+                    *item = Some(tasks.len());
+                    tasks.push(TaskItem {
+                        text: String::new(),
+                        checked,
+                        depth: depth - 1,
+                    });
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(Some(task)) = items.last() {
+                    tasks[*task].text.push_str(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(Some(task)) = items.last() {
+                    tasks[*task].text.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    tasks
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::journal::JournalEntry;
+
+    fn task(text: &str, checked: bool, depth: usize) -> TaskItem {
+        TaskItem {
+            text: String::from(text),
+            checked,
+            depth,
+        }
+    }
+
+    #[test]
+    fn extracts_checked_and_unchecked_items() {
+        let tasks = task_items("- [x] Roll *initiative*\n- [ ] Prepare `map`\n- Not a task\n");
+
+        assert_eq!(
+            vec![
+                task("Roll initiative", true, 0),
+                task("Prepare map", false, 0)
+            ],
+            tasks
+        );
+    }
+
+    #[test]
+    fn extracts_nested_items_in_document_order() {
+        let tasks = task_items(
+            "- [ ] Stat blocks\n  - [x] Goblins\n  - Plain note\n    - [ ] Boss\n- [x] Handouts\n",
+        );
+
+        assert_eq!(
+            vec![
+                task("Stat blocks", false, 0),
+                task("Goblins", true, 1),
+                task("Boss", false, 2),
+                task("Handouts", true, 0),
+            ],
+            tasks
+        );
+    }
+
+    #[test]
+    fn stores_tasks_as_metadata_and_keeps_the_body() {
+        let body = "- [ ] Prepare the map\n";
+        let mut section = Section {
+            title: String::from("Prep"),
+            body: String::from(body),
+            ..Default::default()
+        };
+
+        extract_tasks(&mut section, "tasks").expect("should extract tasks");
+
+        let metadata = section.metadata.get("tasks").expect("should have tasks");
+        let tasks: Vec<TaskItem> = serde_json::from_str(&metadata.data).expect("should be json");
+
+        assert_eq!(body, section.body);
+        assert_eq!("json", metadata.lang);
+        assert_eq!(vec![task("Prepare the map", false, 0)], tasks);
+    }
+
+    #[test]
+    fn keeps_task_markers_when_parsing_entries() {
+        let entry = JournalEntry {
+            body: Some(String::from(
+                "# Prep\n\n- [x] Roll initiative\n- [ ] Prepare the map\n",
+            )),
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        assert_eq!(
+            vec![
+                task("Roll initiative", true, 0),
+                task("Prepare the map", false, 0)
+            ],
+            task_items(&entry.sections[0].body)
+        );
+    }
+}
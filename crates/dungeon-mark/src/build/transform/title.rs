@@ -0,0 +1,149 @@
+use super::{replace_directives, Transformer, TransformerContext};
+use crate::{
+    build::preprocess::directive::is_exempt,
+    error::Result,
+    model::journal::{Journal, JournalEntry, SectionLevel},
+};
+
+const TITLE_DIRECTIVE: &str = "title";
+
+/// The directive that titles an entry after its first H1 heading.
+pub(crate) const DERIVED_TITLE_DIRECTIVE: &str = "{{#title}}";
+
+/// Resolves `{{#title}}` directives, which title an entry after the first H1 heading in the entry.
+///
+/// The title of an entry is chosen from the first of the following that is present:
+/// 1. An explicit `{{#title ...}}` directive, set by the `directive` preprocessor.
+/// 2. A `{{#title}}` directive, when the entry has an H1 heading.
+/// 3. The name of the entry's link in the table of contents.
+/// 4. The file stem of the entry's path.
+///
+/// The `directive` preprocessor leaves `{{#title}}` in place, as the entry is only split into sections once it
+/// has been parsed. Every `{{#title}}` in the entry's body and sections is removed, whether or not the entry has an
+/// H1 heading. An escaped directive, `\{{#title}}`, is written out as is, and entries exempt from directive
+/// expansion, as described by the `directive` preprocessor, are left unchanged.
+pub struct TitleTransformer;
+
+impl TitleTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for TitleTransformer {
+    fn name(&self) -> &str {
+        "title"
+    }
+
+    fn run(&self, _ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        journal.try_for_each_entry_mut(derive_title)?;
+
+        Ok(journal)
+    }
+}
+
+fn derive_title(entry: &mut JournalEntry) -> Result<()> {
+    if entry.body.as_deref().is_some_and(is_exempt) {
+        return Ok(());
+    }
+
+    let mut found = false;
+    let mut remove = |text: &mut String| -> Result<()> {
+        if text.contains(TITLE_DIRECTIVE) {
+            *text = replace_directives(text, TITLE_DIRECTIVE, |arguments| {
+                let derived = arguments.trim().is_empty();
+                found |= derived;
+
+                Ok(derived.then(String::new))
+            })?;
+        }
+
+        Ok(())
+    };
+
+    if let Some(ref mut body) = entry.body {
+        remove(body)?;
+    }
+
+    entry.try_for_each_mut(|section| remove(&mut section.body))?;
+
+    if !found {
+        return Ok(());
+    }
+
+    let heading = entry
+        .sections_at_level(SectionLevel::H1)
+        .next()
+        .map(|heading| heading.title.clone());
+
+    if let Some(heading) = heading {
+        entry.title = heading;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::journal::Section;
+
+    fn section(title: &str, level: SectionLevel, body: &str) -> Section {
+        Section {
+            title: String::from(title),
+            level,
+            body: String::from(body),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn titles_entries_after_their_first_h1() {
+        let mut entry = JournalEntry {
+            title: String::from("From TOC"),
+            body: Some(String::from("{{#title}}\nIntro")),
+            sections: vec![
+                section("Subtitle", SectionLevel::H2, "Text"),
+                section("The Sunken Keep", SectionLevel::H1, "More"),
+                section("Second", SectionLevel::H1, ""),
+            ],
+            ..Default::default()
+        };
+
+        derive_title(&mut entry).expect("should derive");
+
+        assert_eq!("The Sunken Keep", entry.title);
+        assert_eq!(Some("\nIntro"), entry.body.as_deref());
+    }
+
+    #[test]
+    fn keeps_the_title_without_an_h1() {
+        let mut entry = JournalEntry {
+            title: String::from("From TOC"),
+            sections: vec![section(
+                "Subtitle",
+                SectionLevel::H2,
+                "Text {{#title}}\\\\{{#title}}",
+            )],
+            ..Default::default()
+        };
+
+        derive_title(&mut entry).expect("should derive");
+
+        assert_eq!("From TOC", entry.title);
+        assert_eq!("Text {{#title}}", entry.sections[0].body);
+    }
+
+    #[test]
+    fn keeps_the_title_without_the_directive() {
+        let mut entry = JournalEntry {
+            title: String::from("From TOC"),
+            sections: vec![section("Heading", SectionLevel::H1, "Text")],
+            ..Default::default()
+        };
+
+        derive_title(&mut entry).expect("should derive");
+
+        assert_eq!("From TOC", entry.title);
+    }
+}
@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Transformer, TransformerContext, TITLE_CASE_PRIORITY};
+use crate::{
+    error::Result,
+    model::journal::{Journal, JournalItem, Section},
+};
+
+/// The words left lowercase by default, unless they start the title.
+const MINOR_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "into", "nor", "of", "on",
+    "or", "the", "to", "with",
+];
+
+/// Puts the titles of entries, sections and chapters in title case, for a rulebook whose headings are written
+/// inconsistently. It is not run by default; set `build.title-case`, or add `title-case` to `build.transformers`, to
+/// enable it. The chapter each entry records is put in title case along with the chapter titles themselves.
+///
+/// Every word of a title starts with a capital letter, except for the minor words of the `[title-case]` table of
+/// `journal.toml`, such as "of" and "the", which are lowercased unless they start the title or follow a colon, as
+/// in "Part One: The Town". The parts of a
+/// hyphenated word are capitalized on their own, as in "Half-Orc". Words with a capital letter past their first
+/// letter, such as acronyms like "NPC" and names like "McGregor", are left as they are, so an all caps word is
+/// taken to be an acronym. The rest of a word is never lowercased.
+///
+/// Chapter title anchors are made when the journal is loaded, so they keep following the titles as written, which
+/// only matters for the `preserve-case` slug strategy. The transformer runs after the `wikilinks` and
+/// `include-entry` transformers, which find entries by their titles as written.
+pub struct TitleCaseTransformer;
+
+/// Configuration for the title case transformer, read from the `[title-case]` table of `journal.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TitleCaseConfig {
+    /// The words left lowercase unless they start a title or follow a colon, compared without regard to case.
+    /// Defaults to common English articles, conjunctions and short prepositions, such as "a", "and" and "of".
+    pub minor_words: Vec<String>,
+}
+
+impl Default for TitleCaseConfig {
+    fn default() -> Self {
+        Self {
+            minor_words: MINOR_WORDS.iter().map(|word| word.to_string()).collect(),
+        }
+    }
+}
+
+impl TitleCaseTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for TitleCaseTransformer {
+    fn name(&self) -> &str {
+        "title-case"
+    }
+
+    fn priority(&self) -> i32 {
+        TITLE_CASE_PRIORITY
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let config: TitleCaseConfig = ctx.config.get("title-case")?;
+
+        title_case_items(&mut journal.items, &config.minor_words);
+
+        Ok(journal)
+    }
+}
+
+fn title_case_items(items: &mut [JournalItem], minor_words: &[String]) {
+    for item in items {
+        match item {
+            JournalItem::Entry(entry) => {
+                entry.title = title_case(&entry.title, minor_words);
+                entry.chapter = entry
+                    .chapter
+                    .as_deref()
+                    .map(|chapter| title_case(chapter, minor_words));
+                title_case_sections(&mut entry.sections, minor_words);
+            }
+            JournalItem::ChapterTitle(chapter) => {
+                chapter.title = title_case(&chapter.title, minor_words);
+            }
+            JournalItem::Separator(_) | JournalItem::Error(_) => {}
+        }
+
+        if let Some(children) = item.children_mut() {
+            title_case_items(children, minor_words);
+        }
+    }
+}
+
+fn title_case_sections(sections: &mut [Section], minor_words: &[String]) {
+    for section in sections {
+        section.title = title_case(&section.title, minor_words);
+        title_case_sections(&mut section.sections, minor_words);
+    }
+}
+
+/// Put the title in title case, as described by `TitleCaseTransformer`, keeping the whitespace between its words.
+fn title_case(title: &str, minor_words: &[String]) -> String {
+    let mut cased = String::with_capacity(title.len());
+    let mut first = true;
+
+    for part in title.split_inclusive(char::is_whitespace) {
+        let word = part.trim_end();
+        let lowercase = letters(word).to_lowercase();
+        let minor = !first
+            && minor_words
+                .iter()
+                .any(|minor| minor.to_lowercase() == lowercase);
+
+        match minor && !has_inner_capital(word) {
+            true => cased.push_str(&word.to_lowercase()),
+            false => cased.push_str(&capitalize_word(word)),
+        }
+
+        cased.push_str(&part[word.len()..]);
+        // NOTE: A word after a colon starts a subtitle, as does the first word after leading whitespace.
+        first = (first && word.is_empty()) || word.ends_with(':');
+    }
+
+    cased
+}
+
+/// Capitalize each hyphenated part of the word, unless the word has a capital letter past its first letter.
+fn capitalize_word(word: &str) -> String {
+    if has_inner_capital(word) {
+        return String::from(word);
+    }
+
+    word.split('-')
+        .map(capitalize)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Uppercase the first letter of the word, skipping leading punctuation such as quotes and parentheses.
+fn capitalize(word: &str) -> String {
+    match word
+        .char_indices()
+        .find(|(_, character)| character.is_alphanumeric())
+    {
+        Some((index, letter)) => {
+            let rest = &word[index + letter.len_utf8()..];
+            format!("{}{}{rest}", &word[..index], letter.to_uppercase())
+        }
+        None => String::from(word),
+    }
+}
+
+/// Whether a letter past the first letter of the word, or of a part of a hyphenated word, is uppercase.
+fn has_inner_capital(word: &str) -> bool {
+    word.split('-').any(|part| {
+        part.chars()
+            .filter(|character| character.is_alphabetic())
+            .skip(1)
+            .any(char::is_uppercase)
+    })
+}
+
+/// The word without the punctuation around it, such as the quotes of `"the`.
+fn letters(word: &str) -> &str {
+    word.trim_matches(|character: char| !character.is_alphanumeric())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::journal::{ChapterTitle, JournalEntry};
+
+    fn title_case_default(title: &str) -> String {
+        title_case(title, &TitleCaseConfig::default().minor_words)
+    }
+
+    #[test]
+    fn title_cases_mixed_case_titles() {
+        assert_eq!(
+            "The Tomb of the Serpent King",
+            title_case_default("the tomb Of the serpent king")
+        );
+        assert_eq!(
+            "A Guide to Half-Orc Lairs and \"the Deep\"",
+            title_case_default("a guide To half-orc lairs and \"the deep\"")
+        );
+        assert_eq!("Of Mice and Men", title_case_default("of mice and men"));
+        assert_eq!("Into  the Woods", title_case_default("into  the woods"));
+        assert_eq!(
+            "Dungeons: A Guide to the Deep",
+            title_case_default("dungeons: a guide to the deep")
+        );
+    }
+
+    #[test]
+    fn compares_minor_words_without_regard_to_case() {
+        let minor_words = [String::from("über"), String::from("ÉT")];
+
+        assert_eq!(
+            "Reise über Berge ét Täler",
+            title_case("reise Über berge Ét täler", &minor_words)
+        );
+    }
+
+    #[test]
+    fn keeps_acronyms_and_inner_capitals() {
+        assert_eq!(
+            "Running the NPCs of D&D with McGregor's iPad",
+            title_case_default("running the NPCs of D&D with McGregor's iPad")
+        );
+    }
+
+    #[test]
+    fn title_cases_entries_sections_and_chapters() {
+        let entry = JournalEntry {
+            children: vec![JournalItem::Entry(JournalEntry::new("the cellar", ""))],
+            chapter: Some(String::from("part one: the town")),
+            ..JournalEntry::new(
+                "the prancing pony",
+                "# rooms for rent\n\n## the common room\n",
+            )
+        }
+        .parse()
+        .expect("should parse");
+        let mut items = vec![
+            JournalItem::ChapterTitle(ChapterTitle {
+                title: String::from("part one: the town"),
+                anchor: String::from("part-one-the-town"),
+            }),
+            JournalItem::Entry(entry),
+        ];
+
+        title_case_items(&mut items, &[String::from("the"), String::from("for")]);
+
+        let JournalItem::ChapterTitle(ref chapter) = items[0] else {
+            panic!("first item was not a chapter title")
+        };
+        let JournalItem::Entry(ref entry) = items[1] else {
+            panic!("second item was not an entry")
+        };
+        let JournalItem::Entry(ref child) = entry.children[0] else {
+            panic!("child was not an entry")
+        };
+
+        assert_eq!("Part One: The Town", chapter.title);
+        assert_eq!(Some("Part One: The Town"), entry.chapter.as_deref());
+        assert_eq!("The Prancing Pony", entry.title);
+        assert_eq!("Rooms for Rent", entry.sections[0].title);
+        assert_eq!("The Common Room", entry.sections[0].sections[0].title);
+        assert_eq!("The Cellar", child.title);
+    }
+}
@@ -0,0 +1,487 @@
+use anyhow::Context;
+use pulldown_cmark::{Event, Parser, Tag};
+use std::{
+    mem,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+use super::{Transformer, TransformerContext, WIKILINKS_PRIORITY};
+use crate::{
+    cmark::{parser_options, splice, SlugStrategy, Slugger, TextRun},
+    error::Result,
+    model::journal::{Journal, JournalEntry, JournalItem},
+};
+
+const OPEN_SEQUENCE: &str = "[[";
+const CLOSE_SEQUENCE: &str = "]]";
+const ALIAS_SEPARATOR: char = '|';
+const HEADING_SEPARATOR: char = '#';
+
+/// Converts Obsidian style wikilinks in the bodies of entries to standard Markdown links, for journals written in
+/// Obsidian. It is not run by default; add `wikilinks` to `build.transformers` to enable it.
+///
+/// - `[[Tavern]]` links to the entry titled `Tavern`, or, failing that, to the entry whose file is named
+///   `Tavern`, ignoring case.
+/// - `[[Tavern|the tavern]]` links to the same entry with the text `the tavern`.
+/// - `[[Tavern#Cellar]]` links to the section titled `Cellar` of that entry, with the anchor the `html` renderer
+///   gives the section, and `[[#Cellar]]` links to a section of the entry the link is in.
+///
+/// Links point to the entry's file, relative to the file of the entry they are in, such as `../town/tavern.md`.
+/// A wikilink that no entry or more than one entry matches is left as it is, and a warning is logged, as is a
+/// heading that the entry does not have, in which case the link points to the entry alone. Wikilinks in code and
+/// HTML blocks, and embeds such as `![[map.png]]`, are left unchanged.
+///
+/// The transformer runs after the `include-entry` transformer, so that wikilinks in included entries are
+/// converted too, relative to the entry that includes them.
+pub struct WikilinksTransformer;
+
+impl WikilinksTransformer {
+    pub(crate) fn new() -> Self {
+        Self
+    }
+}
+
+impl Transformer for WikilinksTransformer {
+    fn name(&self) -> &str {
+        "wikilinks"
+    }
+
+    fn priority(&self) -> i32 {
+        WIKILINKS_PRIORITY
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let targets = LinkTargets::new(&journal, ctx.config.build.slug_strategy);
+
+        journal.try_for_each_entry_mut(|entry| {
+            let Some(path) = entry.path.clone() else {
+                return Ok(());
+            };
+            let title = entry.title.clone();
+            let convert = |markdown: &str| {
+                targets
+                    .convert(markdown, &path, &title)
+                    .with_context(|| format!("Failed to convert the wikilinks of \"{title}\""))
+            };
+
+            if let Some(ref mut body) = entry.body {
+                *body = convert(body)?;
+            }
+
+            entry.try_for_each_mut(|section| {
+                section.body = convert(&section.body)?;
+
+                Ok(())
+            })
+        })?;
+
+        Ok(journal)
+    }
+}
+
+/// An entry that wikilinks can point to.
+struct LinkTarget {
+    title: String,
+    /// The file name of the entry without its extension.
+    stem: Option<String>,
+    path: PathBuf,
+    /// The title and anchor of every section of the entry, in document order.
+    anchors: Vec<(String, String)>,
+}
+
+impl LinkTarget {
+    fn new(entry: &JournalEntry, path: &Path, strategy: SlugStrategy) -> Self {
+        let mut slugger = Slugger::with_strategy(strategy);
+        let anchors = entry
+            .iter_sections()
+            .map(|section| (section.title.clone(), section.anchor(&mut slugger)))
+            .collect();
+
+        Self {
+            title: entry.title.clone(),
+            stem: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned()),
+            path: path.to_path_buf(),
+            anchors,
+        }
+    }
+
+    /// The anchor of the first section titled `heading`, ignoring case.
+    fn anchor(&self, heading: &str) -> Option<&str> {
+        self.anchors
+            .iter()
+            .find(|(title, _)| eq_ignore_case(title, heading))
+            .map(|(_, anchor)| anchor.as_str())
+    }
+}
+
+/// Every entry of the journal with a path, as wikilinks see them before any is converted.
+struct LinkTargets {
+    targets: Vec<LinkTarget>,
+}
+
+impl LinkTargets {
+    fn new(journal: &Journal, strategy: SlugStrategy) -> Self {
+        let targets = journal
+            .iter()
+            .filter_map(|item| match item {
+                JournalItem::Entry(entry) => entry
+                    .path
+                    .as_deref()
+                    .map(|path| LinkTarget::new(entry, path, strategy)),
+                _ => None,
+            })
+            .collect();
+
+        Self { targets }
+    }
+
+    /// Find the entry a wikilink names, by its exact title, or else by its title or file name ignoring case.
+    fn find(&self, name: &str) -> std::result::Result<&LinkTarget, String> {
+        let mut matches = self
+            .targets
+            .iter()
+            .filter(|target| target.title == name)
+            .collect::<Vec<_>>();
+
+        if matches.is_empty() {
+            matches = self
+                .targets
+                .iter()
+                .filter(|target| {
+                    eq_ignore_case(&target.title, name)
+                        || target
+                            .stem
+                            .as_deref()
+                            .is_some_and(|stem| eq_ignore_case(stem, name))
+                })
+                .collect();
+        }
+
+        match matches.as_slice() {
+            [target] => Ok(target),
+            [] => Err(format!("no entry is titled or named \"{name}\"")),
+            _ => Err(format!("more than one entry is titled or named \"{name}\"")),
+        }
+    }
+
+    fn by_path(&self, path: &Path) -> Option<&LinkTarget> {
+        self.targets.iter().find(|target| target.path == path)
+    }
+
+    /// Convert every wikilink in the Markdown of the entry at `path`, titled `title`. Only the wikilinks are
+    /// rewritten, leaving the rest of the Markdown byte for byte as it is.
+    fn convert(&self, markdown: &str, path: &Path, title: &str) -> Result<String> {
+        let mut replacements = Vec::new();
+        let mut text = TextRun::default();
+        let mut in_code_block = false;
+
+        // NOTE: Brackets that do not form a link, and escaped characters, are split into text events of their
+        // own, so adjacent text is joined before looking for wikilinks.
+        for (event, range) in Parser::new_ext(markdown, parser_options()).into_offset_iter() {
+            match event {
+                Event::Text(ref fragment) if !in_code_block => {
+                    if !text.follows(markdown, &range) {
+                        self.find_wikilinks(&mem::take(&mut text), path, title, &mut replacements);
+                    }
+
+                    text.push(markdown, fragment, range);
+                    continue;
+                }
+                Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+                Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+                _ => {}
+            }
+
+            self.find_wikilinks(&mem::take(&mut text), path, title, &mut replacements);
+        }
+
+        self.find_wikilinks(&text, path, title, &mut replacements);
+
+        Ok(splice(markdown, replacements))
+    }
+
+    /// Find the wikilinks in the text, adding the source range of each with the Markdown link that replaces it.
+    fn find_wikilinks(
+        &self,
+        text: &TextRun,
+        path: &Path,
+        title: &str,
+        replacements: &mut Vec<(Range<usize>, String)>,
+    ) {
+        let mut position = 0;
+
+        let run = text.text();
+
+        while let Some(start) = run[position..]
+            .find(OPEN_SEQUENCE)
+            .map(|start| position + start)
+        {
+            let Some(length) = run[start..].find(CLOSE_SEQUENCE) else {
+                break;
+            };
+
+            let inner = &run[start + OPEN_SEQUENCE.len()..start + length];
+            let link = match run[..start].ends_with('!') || inner.contains('[') {
+                true => None,
+                false => self.link(inner, path, title),
+            };
+
+            position = match link {
+                Some(_) => start + length + CLOSE_SEQUENCE.len(),
+                None => start + OPEN_SEQUENCE.len(),
+            };
+
+            if let Some((link_text, destination)) = link {
+                let range = text.source_range(start..position);
+
+                replacements.push((range, format!("[{link_text}]({destination})")));
+            }
+        }
+    }
+
+    /// The text and destination of the link for the inside of a wikilink, or `None` if it cannot be resolved.
+    fn link(&self, wikilink: &str, path: &Path, title: &str) -> Option<(String, String)> {
+        let (target, text) = match wikilink.split_once(ALIAS_SEPARATOR) {
+            Some((target, text)) => (target.trim(), Some(text.trim())),
+            None => (wikilink.trim(), None),
+        };
+        let (name, heading) = match target.split_once(HEADING_SEPARATOR) {
+            Some((name, heading)) => (name.trim(), Some(heading.trim())),
+            None => (target, None),
+        };
+
+        let found = match name.is_empty() {
+            true => self
+                .by_path(path)
+                .ok_or_else(|| format!("\"{title}\" has no file to link to")),
+            false => self.find(name),
+        };
+        let target = match found {
+            Ok(target) => target,
+            Err(reason) => {
+                log::warn!("Unresolved wikilink [[{wikilink}]] in \"{title}\": {reason}");
+                return None;
+            }
+        };
+
+        let mut destination = match name.is_empty() {
+            true => String::new(),
+            false => relative_path(path, &target.path),
+        };
+
+        if let Some(heading) = heading {
+            match target.anchor(heading) {
+                Some(anchor) => {
+                    destination.push(HEADING_SEPARATOR);
+                    destination.push_str(anchor);
+                }
+                None => log::warn!(
+                    "Unresolved heading of wikilink [[{wikilink}]] in \"{title}\": \"{}\" has no section titled \
+                     \"{heading}\"",
+                    target.title
+                ),
+            }
+        }
+
+        let text = match (text, heading) {
+            (Some(text), _) => String::from(text),
+            (None, Some(heading)) if name.is_empty() => String::from(heading),
+            (None, Some(heading)) => format!("{name} > {heading}"),
+            (None, None) => String::from(name),
+        };
+
+        Some((text, destination))
+    }
+}
+
+/// The path of the file at `to` relative to the directory of the file at `from`, with `/` separators and spaces
+/// encoded so that the path can be a link destination.
+pub(crate) fn relative_path(from: &Path, to: &Path) -> String {
+    let normal = |path: &Path| {
+        path.components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut from = normal(from);
+    from.pop();
+    let to = normal(to);
+
+    let common = from
+        .iter()
+        .zip(&to)
+        .take_while(|(from, to)| from == to)
+        .count();
+
+    std::iter::repeat_n(String::from(".."), from.len() - common)
+        .chain(to[common..].iter().cloned())
+        .collect::<Vec<_>>()
+        .join("/")
+        .replace(' ', "%20")
+}
+
+fn eq_ignore_case(left: &str, right: &str) -> bool {
+    left.to_lowercase() == right.to_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::journal::SectionMetadata;
+    use std::collections::BTreeMap;
+
+    fn entry(title: &str, path: &str, body: &str) -> JournalItem {
+        let entry = JournalEntry {
+            title: String::from(title),
+            path: Some(PathBuf::from(path)),
+            body: Some(String::from(body)),
+            ..Default::default()
+        };
+
+        JournalItem::Entry(entry.parse().expect("should parse"))
+    }
+
+    fn journal() -> Journal {
+        let mut tavern = entry(
+            "The Rusty Tankard",
+            "./town/tavern.md",
+            "# Cellar\n\nDark\n\n# Cellar\n\nDarker",
+        );
+        if let JournalItem::Entry(ref mut tavern) = tavern {
+            tavern.sections[0].metadata.insert(
+                "slug",
+                SectionMetadata {
+                    lang: String::new(),
+                    data: String::from("deep-cellar"),
+                    attributes: Vec::new(),
+                },
+            );
+        }
+
+        Journal {
+            title: None,
+            items: vec![
+                entry("Town", "./town.md", "# Market\n\nStalls"),
+                tavern,
+                entry("Notes", "./notes/session one.md", ""),
+            ],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        }
+    }
+
+    fn convert(markdown: &str, path: &str) -> String {
+        LinkTargets::new(&journal(), SlugStrategy::default())
+            .convert(markdown, Path::new(path), "Test")
+            .expect("should convert")
+    }
+
+    #[test]
+    fn converts_wikilinks_by_title_and_file_name() {
+        assert_eq!(
+            "See [Town](town.md), [the inn](town/tavern.md) and [tavern](town/tavern.md).",
+            convert(
+                "See [[Town]], [[The Rusty Tankard|the inn]] and [[tavern]].",
+                "./index.md"
+            )
+        );
+        assert_eq!(
+            "[town](../town.md) [Notes](../notes/session%20one.md)",
+            convert("[[town]] [[Notes]]", "./town/tavern.md")
+        );
+    }
+
+    #[test]
+    fn converts_wikilinks_to_headings() {
+        assert_eq!(
+            "[Town > Market](town.md#market) [cellar](town/tavern.md#deep-cellar) [Market](#market)",
+            convert(
+                "[[Town#Market]] [[tavern#cellar|cellar]] [[#Market]]",
+                "./town.md"
+            )
+        );
+        assert_eq!(
+            "[Town > Docks](town.md)",
+            convert("[[Town#Docks]]", "./index.md")
+        );
+    }
+
+    #[test]
+    fn leaves_unresolved_and_skipped_wikilinks() {
+        for markdown in [
+            "[[Dungeon]] and ![[map.png]]",
+            "`[[Town]]`",
+            "```\n[[Town]]\n```",
+            "<div>\n[[Town]]\n</div>",
+        ] {
+            assert_eq!(markdown, convert(markdown, "./index.md"));
+        }
+    }
+
+    #[test]
+    fn leaves_the_rest_of_the_markdown_as_it_is() {
+        for markdown in [
+            "| Name | HP |\n|------|----|\n| Snik | 7 |\n\n\\*not emphasis\\*",
+            "| Place | Notes |\n| --- | --- |\n| [[Town]] | \\*busy\\* |",
+            "\\*Start\\* in \\[\\[Town\\]\\]",
+        ] {
+            let expected = markdown
+                .replace("[[Town]]", "[Town](town.md)")
+                .replace("\\[\\[Town\\]\\]", "[Town](town.md)");
+
+            assert_eq!(expected, convert(markdown, "./index.md"));
+        }
+    }
+
+    #[test]
+    fn leaves_ambiguous_wikilinks() {
+        let mut journal = journal();
+        journal.items.push(entry("town", "./other/town.md", ""));
+        journal.items.push(entry("Harbor", "./a/harbor.md", ""));
+        journal.items.push(entry("Harbor", "./b/harbor.md", ""));
+        let targets = LinkTargets::new(&journal, SlugStrategy::default());
+
+        assert_eq!(
+            "[Town](town.md) [[TOWN]] [[Harbor]]",
+            targets
+                .convert(
+                    "[[Town]] [[TOWN]] [[Harbor]]",
+                    Path::new("./index.md"),
+                    "Test"
+                )
+                .expect("should convert")
+        );
+    }
+
+    #[test]
+    fn converts_wikilinks_in_entries() {
+        let mut journal = journal();
+        journal.items.push(entry(
+            "Index",
+            "./index.md",
+            "Start in [[Town]]\n\n# Places\n\n[[The Rusty Tankard#Cellar]]",
+        ));
+
+        let journal = WikilinksTransformer
+            .run(
+                &TransformerContext::new(PathBuf::from("test"), Default::default()),
+                journal,
+            )
+            .expect("should transform");
+        let JournalItem::Entry(ref index) = journal.items[3] else {
+            panic!("fourth item was not an entry")
+        };
+
+        assert_eq!(Some("Start in [Town](town.md)"), index.body.as_deref());
+        assert_eq!(
+            "[The Rusty Tankard > Cellar](town/tavern.md#deep-cellar)",
+            index.sections[0].body.trim()
+        );
+    }
+}
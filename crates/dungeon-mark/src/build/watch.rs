@@ -0,0 +1,173 @@
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+use super::ignore::IgnorePatterns;
+use crate::error::Result;
+
+/// How long the file system must be quiet before a batch of changes triggers a rebuild.
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Watches the journal's source directory and configuration file for changes.
+pub(crate) struct JournalWatcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    watched: Vec<PathBuf>,
+    ignored: PathBuf,
+    /// The `journal.ignore` patterns, matched against paths relative to the watched directory they are in.
+    ignore: IgnorePatterns,
+}
+
+impl JournalWatcher {
+    /// Create a watcher that ignores any changes made under the `ignored` path (such as the build output).
+    pub(crate) fn new(ignored: PathBuf) -> Result<Self> {
+        let (sender, events) = channel();
+        let watcher = notify::recommended_watcher(move |event| {
+            // NOTE: The receiver is only dropped along with the watcher itself.
+            let _ = sender.send(event);
+        })?;
+
+        Ok(Self {
+            watcher,
+            events,
+            watched: Vec::new(),
+            ignored,
+            ignore: IgnorePatterns::default(),
+        })
+    }
+
+    /// Replace the set of watched paths, and the patterns of the files and directories in them whose changes are
+    /// ignored. Paths that do not exist are skipped.
+    pub(crate) fn watch(&mut self, paths: &[&Path], ignore: IgnorePatterns) -> Result<()> {
+        self.ignore = ignore;
+
+        for path in self.watched.drain(..) {
+            let _ = self.watcher.unwatch(&path);
+        }
+
+        for path in paths {
+            if !path.exists() {
+                continue;
+            }
+
+            self.watcher.watch(path, RecursiveMode::Recursive)?;
+            self.watched.push(path.to_path_buf());
+        }
+
+        Ok(())
+    }
+
+    /// Block until a relevant change happens, then wait for the file system to settle before returning.
+    /// Returns an error if the underlying watcher has shut down.
+    pub(crate) fn wait_for_change(&self) -> Result<()> {
+        loop {
+            let event = self.events.recv()?;
+
+            if self.is_relevant(event) {
+                break;
+            }
+        }
+
+        loop {
+            match self.events.recv_timeout(DEBOUNCE) {
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => return Ok(()),
+                Err(RecvTimeoutError::Disconnected) => {
+                    anyhow::bail!("File system watcher disconnected")
+                }
+            }
+        }
+    }
+
+    fn is_relevant(&self, event: notify::Result<Event>) -> bool {
+        match event {
+            Ok(event) if event.kind.is_access() => false,
+            Ok(event) => event.paths.iter().any(|path| !self.is_ignored(path)),
+            Err(err) => {
+                log::warn!("File system watcher error: {err}");
+                false
+            }
+        }
+    }
+
+    /// Whether the path is under the ignored path, or it or a directory above it in its watched directory matches
+    /// the `journal.ignore` patterns.
+    fn is_ignored(&self, path: &Path) -> bool {
+        if path.starts_with(&self.ignored) {
+            return true;
+        }
+
+        let Some(relative) = self
+            .watched
+            .iter()
+            .find_map(|watched| path.strip_prefix(watched).ok())
+        else {
+            return false;
+        };
+
+        relative
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| {
+                let is_dir = ancestor != relative || path.is_dir();
+
+                self.ignore.is_ignored(ancestor, is_dir)
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use notify::{event::CreateKind, EventKind};
+    use std::{fs, thread};
+
+    #[test]
+    fn reports_changes_outside_of_ignored_path() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let source = dir.path().join("src");
+        fs::create_dir_all(&source).expect("should create source dir");
+
+        let mut watcher = JournalWatcher::new(dir.path().join("build")).expect("should watch");
+        watcher
+            .watch(&[&source], IgnorePatterns::default())
+            .expect("should watch source");
+
+        let file = source.join("entry.md");
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            fs::write(file, "# Entry").expect("should write entry");
+        });
+
+        watcher.wait_for_change().expect("should observe change");
+        writer.join().expect("writer should finish");
+    }
+
+    #[test]
+    fn ignores_changes_matching_the_ignore_patterns() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let source = dir.path().join("src");
+        fs::create_dir_all(source.join(".obsidian")).expect("should create source dir");
+
+        let ignore = IgnorePatterns::new(&[String::from(".obsidian"), String::from("*.psd")])
+            .expect("should compile patterns");
+        let mut watcher = JournalWatcher::new(dir.path().join("build")).expect("should watch");
+        watcher
+            .watch(&[&source], ignore)
+            .expect("should watch source");
+
+        let created = |path: PathBuf| {
+            watcher.is_relevant(Ok(
+                Event::new(EventKind::Create(CreateKind::File)).add_path(path)
+            ))
+        };
+
+        assert!(!created(source.join(".obsidian/workspace.json")));
+        assert!(!created(source.join("maps/town.psd")));
+        assert!(!created(dir.path().join("build/html/index.html")));
+        assert!(created(source.join("town.md")));
+    }
+}
@@ -0,0 +1,165 @@
+use pulldown_cmark::{Event, LinkType, Tag};
+use std::ops::Range;
+
+use super::CMarkParser;
+
+/// How the destination of a link or image is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// Written at the link, as in `[text](destination)`.
+    Inline,
+    /// Taken from a reference definition, as in `[text][label]`, `[label][]` or `[label]`.
+    Reference,
+    /// A URL written as the link itself, as in `<https://example.com>`.
+    Autolink,
+    /// An email address written as the link itself, as in `<gm@example.com>`.
+    Email,
+}
+
+/// The destination of a link or image in a Markdown source, as found by `links`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub destination: String,
+    pub kind: LinkKind,
+    /// Whether the destination is that of an image rather than a link.
+    pub image: bool,
+    /// The byte range of the destination in the source, for rewriting it in place. `None` for reference links,
+    /// whose destination is written in their reference definition, and for destinations written with escapes or
+    /// entities, which do not appear in the source as they are.
+    pub span: Option<Range<usize>>,
+}
+
+impl Link {
+    /// Whether the destination is an `http` or `https` URL, pointing outside of the journal.
+    pub fn is_external(&self) -> bool {
+        scheme(&self.destination).is_some_and(|scheme| {
+            scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+        })
+    }
+
+    /// Whether the destination starts with a URI scheme, such as `https:`, `mailto:` or `data:`.
+    pub fn has_scheme(&self) -> bool {
+        scheme(&self.destination).is_some()
+    }
+}
+
+/// Every link and image destination in the Markdown source, whatever way it is written, in the order the links and
+/// images start. An image inside of a link comes after the link, even though its destination is written first.
+pub fn links(source: &str) -> Vec<Link> {
+    let mut parser = CMarkParser::new(source);
+    let mut links = Vec::new();
+
+    while let Some(event) = parser.next_event() {
+        let (link_type, destination, image) = match event {
+            Event::Start(Tag::Link(link_type, destination, _)) => (link_type, destination, false),
+            Event::Start(Tag::Image(link_type, destination, _)) => (link_type, destination, true),
+            _ => continue,
+        };
+
+        let kind = match link_type {
+            LinkType::Inline => LinkKind::Inline,
+            LinkType::Autolink => LinkKind::Autolink,
+            LinkType::Email => LinkKind::Email,
+            LinkType::Reference
+            | LinkType::ReferenceUnknown
+            | LinkType::Collapsed
+            | LinkType::CollapsedUnknown
+            | LinkType::Shortcut
+            | LinkType::ShortcutUnknown => LinkKind::Reference,
+        };
+
+        // NOTE: The destination follows the text of the link, so the last match is the destination.
+        let range = parser.range();
+        let span = match kind {
+            LinkKind::Reference => None,
+            _ => source[range.clone()]
+                .rfind(destination.as_ref())
+                .map(|offset| range.start + offset..range.start + offset + destination.len()),
+        };
+
+        links.push(Link {
+            destination: destination.into_string(),
+            kind,
+            image,
+            span,
+        });
+    }
+
+    links
+}
+
+/// The URI scheme of a destination, such as `https` or `data`.
+fn scheme(destination: &str) -> Option<&str> {
+    let (scheme, _) = destination.split_once(':')?;
+
+    let valid = scheme.starts_with(|character: char| character.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || "+-.".contains(character));
+
+    valid.then_some(scheme)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn link(destination: &str, kind: LinkKind, image: bool, span: Option<Range<usize>>) -> Link {
+        Link {
+            destination: String::from(destination),
+            kind,
+            image,
+            span,
+        }
+    }
+
+    #[test]
+    fn finds_destinations_however_they_are_written() {
+        let source =
+            "[Inn](inn.md) ![Map](map.png) [Docs][docs] <https://example.com> <gm@example.com>\n\n\
+                      | Cell |\n| --- |\n| [Row](row.md) |\n\n[docs]: https://docs.example.com";
+
+        assert_eq!(
+            vec![
+                link("inn.md", LinkKind::Inline, false, Some(6..12)),
+                link("map.png", LinkKind::Inline, true, Some(21..28)),
+                link("https://docs.example.com", LinkKind::Reference, false, None),
+                link(
+                    "https://example.com",
+                    LinkKind::Autolink,
+                    false,
+                    Some(44..63)
+                ),
+                link("gm@example.com", LinkKind::Email, false, Some(66..80)),
+                link("row.md", LinkKind::Inline, false, Some(108..114)),
+            ],
+            links(source)
+        );
+    }
+
+    #[test]
+    fn finds_images_inside_of_links() {
+        let source = "[![Icon](icon.png)](deed.pdf)";
+
+        assert_eq!(
+            vec![
+                link("deed.pdf", LinkKind::Inline, false, Some(20..28)),
+                link("icon.png", LinkKind::Inline, true, Some(9..17)),
+            ],
+            links(source)
+        );
+    }
+
+    #[test]
+    fn classifies_external_destinations() {
+        let external =
+            |destination: &str| link(destination, LinkKind::Inline, false, None).is_external();
+
+        assert!(external("https://example.com"));
+        assert!(external("HTTP://example.com"));
+        assert!(!external("mailto:gm@example.com"));
+        assert!(!external("data:image/png;base64,AAAA"));
+        assert!(!external("inn.md#https:"));
+        assert!(link("mailto:gm@example.com", LinkKind::Inline, false, None).has_scheme());
+    }
+}
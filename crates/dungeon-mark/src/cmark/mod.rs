@@ -1,17 +1,155 @@
 //! Useful utilities for parsing and working with CommonMark files.
 
+mod links;
 mod parser;
+mod slug;
+mod text;
 
+pub use links::*;
 pub use parser::*;
+pub use slug::*;
+pub use text::*;
 
-use pulldown_cmark::Event;
-use pulldown_cmark_to_cmark::{cmark_with_options, Options};
-use std::borrow::Borrow;
+use pulldown_cmark::{Alignment, Event, Tag};
+use pulldown_cmark_to_cmark::{cmark_resume_with_options, Options, State};
+use std::{borrow::Borrow, fmt, fs, io, iter, path::Path};
+
+use anyhow::Context;
 
 use crate::error::Result;
 
+/// The number of characters at the end of a partially written buffer that `stringify` reports when it fails.
+const PARTIAL_BUFFER_TAIL: usize = 80;
+/// The fewest dashes and colons a table's delimiter row cell can have while still holding any alignment.
+const MIN_DELIMITER_WIDTH: usize = 3;
+/// The fewest backticks a code block's fence can have.
+const MIN_FENCE_LENGTH: usize = 3;
+
+/// The byte order mark of a UTF-16 little endian source, as saved by some Windows editors.
+const UTF16_LE_BOM: [u8; 2] = [0xFF, 0xFE];
+/// The byte order mark of a UTF-16 big endian source.
+const UTF16_BE_BOM: [u8; 2] = [0xFE, 0xFF];
+
+/// Read a Markdown source file, removing a leading UTF-8 byte order mark and converting `\r\n` line endings to
+/// `\n`, so that neither leaks into titles or shifts the offsets of what follows. See `decode_source` for the
+/// encodings that are read.
+pub fn read_source(path: impl AsRef<Path>) -> io::Result<String> {
+    fs::read(path).and_then(|bytes| decode_source(&bytes))
+}
+
+/// Decode the bytes of a Markdown source and normalize it as `normalize_source` does. Sources starting with a
+/// UTF-16 byte order mark are decoded as UTF-16 of that byte order, and any other source as UTF-8. A source that
+/// is not valid in its encoding fails with an `InvalidData` error naming the encoding.
+pub fn decode_source(bytes: &[u8]) -> io::Result<String> {
+    let (encoding, source) = if let Some(bytes) = bytes.strip_prefix(&UTF16_LE_BOM) {
+        ("UTF-16LE", decode_utf16(bytes, u16::from_le_bytes))
+    } else if let Some(bytes) = bytes.strip_prefix(&UTF16_BE_BOM) {
+        ("UTF-16BE", decode_utf16(bytes, u16::from_be_bytes))
+    } else {
+        ("UTF-8", String::from_utf8(bytes.to_vec()).ok())
+    };
+
+    source.map(normalize_source).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Source is not valid {encoding}. Sources must be UTF-8, or UTF-16 starting with a byte order mark"
+            ),
+        )
+    })
+}
+
+/// Decode UTF-16 code units of two bytes each, or `None` for an odd number of bytes or unpaired surrogates.
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+
+    char::decode_utf16(units).collect::<Result<_, _>>().ok()
+}
+
+/// Remove a leading UTF-8 byte order mark and convert `\r\n` line endings to `\n`. See `read_source`.
+pub fn normalize_source(source: String) -> String {
+    let source = match source.strip_prefix('\u{feff}') {
+        Some(stripped) => stripped.to_string(),
+        None => source,
+    };
+
+    if source.contains('\r') {
+        source.replace("\r\n", "\n")
+    } else {
+        source
+    }
+}
+
+/// Replace every tab with the spaces up to the next tab stop, placed every `tab_width` columns, so that the source
+/// reads the same to pulldown-cmark and to the Markdown written back out of it. Tabs in the text of code blocks are
+/// expanded too, as that is where they read differently the most. A `tab_width` of 0 leaves the source unchanged.
+pub fn expand_tabs(source: &str, tab_width: usize) -> String {
+    if tab_width == 0 || !source.contains('\t') {
+        return String::from(source);
+    }
+
+    let mut expanded = String::with_capacity(source.len());
+    let mut column = 0;
+
+    for character in source.chars() {
+        match character {
+            '\t' => {
+                let spaces = tab_width - column % tab_width;
+                expanded.extend(iter::repeat_n(' ', spaces));
+                column += spaces;
+            }
+            '\n' => {
+                expanded.push(character);
+                column = 0;
+            }
+            _ => {
+                expanded.push(character);
+                column += 1;
+            }
+        }
+    }
+
+    expanded
+}
+
+/// The text of the Markdown without its syntax, for search indexes, excerpts and word counts. The text of inline
+/// markup, code spans and link text is kept, while link destinations and image markup other than the alt text are
+/// dropped. Blocks and lines are separated by a single space.
+pub fn plain_text(markdown: &str) -> String {
+    let mut parser = CMarkParser::new(markdown);
+    let mut text = String::new();
+
+    while let Some(event) = parser.next_event() {
+        match event {
+            Event::Text(content) | Event::Code(content) => text.push_str(&content),
+            // NOTE: Separate the text of blocks and lines, but not of inline markup such as `*emphasis*`.
+            Event::SoftBreak
+            | Event::HardBreak
+            | Event::End(
+                Tag::Paragraph
+                | Tag::Heading(..)
+                | Tag::BlockQuote
+                | Tag::CodeBlock(_)
+                | Tag::Item
+                | Tag::TableCell,
+            ) if !text.is_empty() && !text.ends_with(' ') => text.push(' '),
+            _ => {}
+        }
+    }
+
+    text.truncate(text.trim_end().len());
+    text
+}
+
 pub trait EventIteratorExt {
-    /// Consume an event collection and return a stringified representation.
+    /// Consume an event collection and return a stringified representation. On failure, the error includes the
+    /// end of what was written before the failure.
     fn stringify(self) -> Result<String>;
 }
 
@@ -23,13 +161,346 @@ where
     fn stringify(self) -> Result<String> {
         // TODO: Is there a safe default buffer capacity? Does it matter?
         let mut buffer = String::new();
-        let options = Options {
-            code_block_token_count: 3,
-            ..Default::default()
-        };
 
-        cmark_with_options(self, &mut buffer, options)?;
+        if let Err(err) = write_markdown(self, &mut buffer) {
+            return Err(err)
+                .with_context(|| format!("Failed to write Markdown after: {:?}", tail(&buffer)));
+        }
 
         Ok(buffer)
     }
 }
+
+/// Write the events as Markdown. Tables, strikethrough and task lists need no options of their own, but the
+/// delimiter row that `pulldown_cmark_to_cmark` writes below a table's header is sized by the header's text in
+/// bytes and drops alignments for narrow or non-ASCII headers, so it is written here instead.
+///
+/// Every code block is written as a fenced code block, including indented ones, as indentation is not kept
+/// through nested lists and block quotes. The fence is made longer than any run of backticks that starts a line
+/// of the code, so that code such as a transcript holding Markdown of its own is kept intact.
+fn write_markdown<'a, E: Borrow<Event<'a>>>(
+    events: impl Iterator<Item = E>,
+    buffer: &mut String,
+) -> fmt::Result {
+    let options = Options {
+        code_block_token_count: MIN_FENCE_LENGTH,
+        ..Default::default()
+    };
+    let mut state: Option<State<'static>> = None;
+    let mut header = TableHeader::default();
+    let mut code_block: Vec<Event<'a>> = Vec::new();
+
+    for event in events {
+        let event = event.borrow();
+
+        // NOTE: The fence length depends on the code, so code blocks are only written once they have ended.
+        if matches!(event, Event::Start(Tag::CodeBlock(_))) || !code_block.is_empty() {
+            code_block.push(event.clone());
+
+            if let Event::End(Tag::CodeBlock(_)) = event {
+                let options = Options {
+                    code_block_token_count: fence_length(&code_block),
+                    ..options.clone()
+                };
+
+                state = Some(cmark_resume_with_options(
+                    code_block.drain(..),
+                    &mut *buffer,
+                    state,
+                    options,
+                )?);
+            }
+
+            continue;
+        }
+
+        let start = buffer.len();
+        header.observe(event);
+
+        state = Some(cmark_resume_with_options(
+            iter::once(event),
+            &mut *buffer,
+            state,
+            options.clone(),
+        )?);
+
+        if let Event::End(Tag::TableHead) = event {
+            header.rewrite_delimiter_row(buffer, start);
+        }
+    }
+
+    state.unwrap_or_default().finalize(buffer)?;
+
+    Ok(())
+}
+
+/// The alignments and text widths of the columns of the table being written.
+#[derive(Default)]
+struct TableHeader {
+    alignments: Vec<Alignment>,
+    widths: Vec<usize>,
+    in_head: bool,
+}
+
+impl TableHeader {
+    fn observe(&mut self, event: &Event<'_>) {
+        match event {
+            Event::Start(Tag::Table(alignments)) => {
+                self.alignments = alignments.clone();
+                self.widths.clear();
+            }
+            Event::Start(Tag::TableHead) => self.in_head = true,
+            Event::End(Tag::TableHead) => self.in_head = false,
+            Event::Start(Tag::TableCell) if self.in_head => self.widths.push(0),
+            Event::Text(text) | Event::Code(text) if self.in_head => {
+                if let Some(width) = self.widths.last_mut() {
+                    *width += text.chars().count();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Replace the delimiter row written after `start`, which follows the first line break, with one that keeps
+    /// every column's alignment.
+    fn rewrite_delimiter_row(&self, buffer: &mut String, start: usize) {
+        let Some(row_start) = buffer[start..].find('\n').and_then(|newline| {
+            buffer[start + newline..]
+                .find('|')
+                .map(|bar| start + newline + bar)
+        }) else {
+            return;
+        };
+
+        buffer.truncate(row_start);
+
+        for (alignment, width) in self.alignments.iter().zip(&self.widths) {
+            let width = (*width).max(MIN_DELIMITER_WIDTH);
+            let (left, right) = match alignment {
+                Alignment::None => ('-', '-'),
+                Alignment::Left => (':', '-'),
+                Alignment::Center => (':', ':'),
+                Alignment::Right => ('-', ':'),
+            };
+
+            buffer.push('|');
+            buffer.push(left);
+            buffer.push_str(&"-".repeat(width - 2));
+            buffer.push(right);
+        }
+
+        buffer.push('|');
+    }
+}
+
+/// The length of a fence for the code block's events, one backtick longer than the longest run of backticks that
+/// starts a line of its code.
+fn fence_length(code_block: &[Event<'_>]) -> usize {
+    let code: String = code_block
+        .iter()
+        .filter_map(|event| match event {
+            Event::Text(text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect();
+
+    code.lines()
+        .map(|line| line.trim_start().chars().take_while(|c| *c == '`').count() + 1)
+        .fold(MIN_FENCE_LENGTH, usize::max)
+}
+
+/// The last `PARTIAL_BUFFER_TAIL` characters of the buffer.
+fn tail(buffer: &str) -> &str {
+    let start = buffer
+        .char_indices()
+        .rev()
+        .nth(PARTIAL_BUFFER_TAIL - 1)
+        .map_or(0, |(index, _)| index);
+
+    &buffer[start..]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalizes_byte_order_marks_and_line_endings() {
+        let source = String::from("\u{feff}# Title\r\n\r\nBody\r\n");
+
+        assert_eq!("# Title\n\nBody\n", normalize_source(source));
+        assert_eq!(
+            "\u{feff}",
+            normalize_source(String::from("\u{feff}\u{feff}"))
+        );
+    }
+
+    #[test]
+    fn expands_tabs_to_the_next_tab_stop() {
+        assert_eq!("    a\n  b c   d", expand_tabs("\ta\n  b\tc\td", 4));
+        assert_eq!("  é x", expand_tabs("\té\tx", 2));
+        assert_eq!("\ta", expand_tabs("\ta", 0));
+    }
+
+    #[test]
+    fn decodes_sources_by_their_byte_order_mark() {
+        let utf16 = |bytes: fn(u16) -> [u8; 2], bom: [u8; 2]| {
+            bom.into_iter()
+                .chain("# Café\r\n".encode_utf16().flat_map(bytes))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            "# Café\n",
+            decode_source(&utf16(u16::to_le_bytes, UTF16_LE_BOM)).expect("should decode")
+        );
+        assert_eq!(
+            "# Café\n",
+            decode_source(&utf16(u16::to_be_bytes, UTF16_BE_BOM)).expect("should decode")
+        );
+        assert_eq!(
+            "# Café\n",
+            decode_source("\u{feff}# Café\r\n".as_bytes()).expect("should decode")
+        );
+    }
+
+    #[test]
+    fn names_the_encoding_of_invalid_sources() {
+        for (bytes, encoding) in [
+            (&[b'#', 0xFF, b'\n'][..], "UTF-8"),
+            (&[0xFF, 0xFE, b'#'][..], "UTF-16LE"),
+            (&[0xFE, 0xFF, 0xDC, 0x00][..], "UTF-16BE"),
+        ] {
+            let err = decode_source(bytes).expect_err("should fail");
+
+            assert_eq!(io::ErrorKind::InvalidData, err.kind());
+            assert!(
+                err.to_string()
+                    .starts_with(&format!("Source is not valid {encoding}.")),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn reports_the_tail_of_a_partial_buffer() {
+        let buffer = format!("{}{}", "a".repeat(100), "é".repeat(PARTIAL_BUFFER_TAIL - 1));
+
+        assert_eq!(
+            format!("a{}", "é".repeat(PARTIAL_BUFFER_TAIL - 1)),
+            tail(&buffer)
+        );
+        assert_eq!("short", tail("short"));
+    }
+
+    fn round_trip(source: &str) -> (String, bool) {
+        let events: Vec<_> = CMarkParser::new(source).collect_until(|_| false);
+        let markdown = events.iter().stringify().expect("should stringify");
+        let reparsed: Vec<_> = CMarkParser::new(&markdown).collect_until(|_| false);
+        let alignments = |events: &[Event<'_>]| -> Vec<Vec<Alignment>> {
+            events
+                .iter()
+                .filter_map(|event| match event {
+                    Event::Start(Tag::Table(alignments)) => Some(alignments.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let same = alignments(&events) == alignments(&reparsed);
+        (markdown, same)
+    }
+
+    #[test]
+    fn round_trips_table_alignments() {
+        let source = "| Left | Center | Right | None |\n|:--|:-:|--:|---|\n| a | b | c | d |\n";
+        let (markdown, same) = round_trip(source);
+
+        assert_eq!(
+            "|Left|Center|Right|None|\n|:---|:----:|----:|----|\n|a|b|c|d|",
+            markdown
+        );
+        assert!(same);
+    }
+
+    #[test]
+    fn round_trips_alignments_of_narrow_and_non_ascii_headers() {
+        let source =
+            "Text\n\n| A | B | Ölmühle |  |\n|--:|:-:|------:|:-:|\n| 1 | 2 | 3 | 4 |\n\nAfter";
+        let (markdown, same) = round_trip(source);
+
+        assert!(
+            markdown.contains("|A|B|Ölmühle||\n|--:|:-:|------:|:-:|"),
+            "{markdown}"
+        );
+        assert!(same);
+    }
+
+    #[test]
+    fn round_trips_tables_in_nested_blocks() {
+        let (markdown, same) = round_trip("> | A |\n> |:-:|\n> | 1 |\n");
+
+        assert!(markdown.contains("> |:-:|"), "{markdown}");
+        assert!(same);
+    }
+
+    #[test]
+    fn round_trips_strikethrough_and_task_lists() {
+        let source = "Some ~~struck~~ text\n\n* [x] done\n* [ ] todo";
+        let (markdown, _) = round_trip(source);
+
+        assert_eq!(source, markdown);
+    }
+
+    #[test]
+    fn round_trips_fenced_code_blocks_holding_fences() {
+        let source = "`````markdown\n````md\n```rust\nfn main() {}\n```\n````\n`````";
+        let (markdown, _) = round_trip(source);
+
+        assert_eq!(source, markdown.trim());
+        assert_eq!(markdown.trim(), round_trip(&markdown).0.trim());
+    }
+
+    #[test]
+    fn keeps_the_code_of_indented_code_blocks() {
+        let code_blocks = |events: &[Event<'_>]| -> Vec<String> {
+            let mut blocks = Vec::new();
+            let mut in_block = false;
+
+            for event in events {
+                match event {
+                    Event::Start(Tag::CodeBlock(_)) => {
+                        blocks.push(String::new());
+                        in_block = true;
+                    }
+                    Event::Text(text) if in_block => blocks
+                        .last_mut()
+                        .expect("should be in a block")
+                        .push_str(text),
+                    Event::End(Tag::CodeBlock(_)) => in_block = false,
+                    _ => {}
+                }
+            }
+
+            blocks
+        };
+
+        let source =
+            "Transcript\n\n    $ cat notes.md\n    ```\n      echo hi\n    ````\n\n\n    done\n\n\
+                      * Item\n\n      nested\n\n> Quote\n>\n>     quoted\n>       deeper\n";
+        let events: Vec<_> = CMarkParser::new(source).collect_until(|_| false);
+        let markdown = events.iter().stringify().expect("should stringify");
+        let reparsed: Vec<_> = CMarkParser::new(&markdown).collect_until(|_| false);
+
+        assert_eq!(
+            vec![
+                "$ cat notes.md\n```\n  echo hi\n````\n\n\ndone\n",
+                "nested\n",
+                "quoted\n  deeper\n"
+            ],
+            code_blocks(&events)
+        );
+        assert_eq!(code_blocks(&events), code_blocks(&reparsed));
+        assert!(markdown.contains("`````\n$ cat notes.md"));
+    }
+}
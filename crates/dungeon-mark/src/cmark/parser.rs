@@ -1,49 +1,147 @@
 use pulldown_cmark::{Event, OffsetIter, Options, Parser};
 
-use std::{fmt::Display, iter::Peekable};
+use std::{collections::VecDeque, fmt::Display, ops::Range};
 
+/// The extensions to CommonMark that every Markdown source is parsed with: strikethrough, tables and task lists.
+/// Anything reading Markdown with `pulldown_cmark::Parser` directly, such as to render it to HTML, must use them
+/// too, or tables and strikethrough come out as literal text.
+pub fn parser_options() -> Options {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    options
+}
+
+/// Render the Markdown to HTML, appending it to `html`, with the extensions of `parser_options`.
+pub fn push_html(html: &mut String, markdown: &str) {
+    pulldown_cmark::html::push_html(html, Parser::new_ext(markdown, parser_options()));
+}
+
+/// Escape the text for HTML or XML, appending it to `html`.
+pub fn push_escaped(html: &mut String, text: &str) {
+    // NOTE: Writing to a `String` cannot fail.
+    let _ = pulldown_cmark::escape::escape_html(&mut *html, text);
+}
+
+/// A parser over the events of a Markdown source, with lookahead and positions.
+///
+/// Raw HTML blocks are reported one line at a time, so nothing in the events of consecutive blocks tells them
+/// apart once written back out, and a `<div>` block would swallow a following `<script>` block, which ends at
+/// its closing tag rather than at a blank line. An `Event::Html("\n")` is emitted between the lines of separate
+/// HTML blocks, so that the blank line between them is kept when the events are written as Markdown.
 pub struct CMarkParser<'a> {
     source: &'a str,
-    events: Peekable<OffsetIter<'a, 'a>>,
+    events: OffsetIter<'a, 'a>,
+    /// Events that have been peeked but not yet consumed, in stream order.
+    peeked: VecDeque<(Event<'a>, Range<usize>)>,
     offset: usize,
+    /// The byte offset in the source of the end of the last consumed event.
+    end: usize,
+    /// The end of the last event pulled from `events`, if it was raw HTML.
+    html_end: Option<usize>,
+    /// An event pulled from `events` that is held back for the blank line emitted before it.
+    held: Option<(Event<'a>, Range<usize>)>,
 }
 
 impl<'a> CMarkParser<'a> {
     pub fn new(source: &str) -> CMarkParser<'_> {
-        let mut options = Options::empty();
-        options.insert(Options::ENABLE_STRIKETHROUGH);
-        options.insert(Options::ENABLE_TABLES);
-
-        let events = Parser::new(source).into_offset_iter().peekable();
+        let events = Parser::new_ext(source, parser_options()).into_offset_iter();
 
         CMarkParser {
             source,
             events,
+            peeked: VecDeque::new(),
             offset: 0,
+            end: 0,
+            html_end: None,
+            held: None,
         }
     }
 
-    /// Provides the line and column of the last emitted event.
+    /// Provides the line and column of the last consumed event. Peeking does not move the position.
     pub fn position(&self) -> Position {
-        let previous = self.source[..self.offset].as_bytes();
+        let previous = &self.source.as_bytes()[..self.offset];
         let line = memchr::Memchr::new(b'\n', previous).count() + 1;
         let start_of_line = memchr::memrchr(b'\n', previous).unwrap_or(0);
-        let column = self.source[start_of_line..self.offset].chars().count();
+        let preceding = &self.source[start_of_line..self.offset];
+
+        Position {
+            line,
+            column: preceding.chars().count(),
+            column_utf16: preceding.encode_utf16().count(),
+        }
+    }
+
+    /// The byte offset in the source of the start of the last consumed event.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
 
-        Position { line, column }
+    /// The byte range in the source of the last consumed event. The range of a start tag covers everything up to
+    /// its end tag, such as the text and destination of a link.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.end
+    }
+
+    /// The byte offset in the source of the start of the next event, or the length of the source at the end of
+    /// the stream.
+    pub fn next_offset(&mut self) -> usize {
+        match self.peek_event() {
+            Some(_) => self.peeked[0].1.start,
+            None => self.source.len(),
+        }
     }
 
     /// Peek the next event in the stream without consuming it.
     pub fn peek_event(&mut self) -> Option<&Event<'a>> {
-        self.events.peek().map(|(event, _)| event)
+        self.peek_nth(0)
+    }
+
+    /// Peek the event `n` events ahead in the stream without consuming any, where `peek_nth(0)` is the next event.
+    pub fn peek_nth(&mut self, n: usize) -> Option<&Event<'a>> {
+        while self.peeked.len() <= n {
+            let event = self.pull()?;
+            self.peeked.push_back(event);
+        }
+
+        self.peeked.get(n).map(|(event, _)| event)
     }
 
     /// Consume the next event in stream.
     pub fn next_event(&mut self) -> Option<Event<'a>> {
-        self.events.next().map(|(event, range)| {
-            self.offset = range.start;
-            event
-        })
+        let (event, range) = self.peeked.pop_front().or_else(|| self.pull())?;
+        self.offset = range.start;
+        self.end = range.end;
+
+        Some(event)
+    }
+
+    /// Pull the next event from the underlying parser, emitting a blank line between separate HTML blocks.
+    fn pull(&mut self) -> Option<(Event<'a>, Range<usize>)> {
+        if let Some(held) = self.held.take() {
+            return Some(held);
+        }
+
+        let (event, range) = self.events.next()?;
+        let Event::Html(_) = event else {
+            self.html_end = None;
+            return Some((event, range));
+        };
+
+        let html_end = self.html_end.replace(range.end);
+
+        // NOTE: Lines of the same block follow each other directly, or after the prefix of a container.
+        match html_end {
+            Some(end) if self.source[end..range.start].contains('\n') => {
+                let start = range.start;
+                self.held = Some((event, range));
+
+                Some((Event::Html("\n".into()), start..start))
+            }
+            _ => Some((event, range)),
+        }
     }
 
     /// Iterates over the stream, returning any events where `delimeter` returns `false`.
@@ -59,6 +157,12 @@ impl<'a> CMarkParser<'a> {
         })
     }
 
+    /// Consumes every event up to the first one where `delimeter` returns `true`, which is not consumed.
+    /// This is an eager form of `iter_until`, leaving the parser free to use while the events are held.
+    pub fn collect_until(&mut self, delimeter: impl Fn(&Event<'a>) -> bool + 'a) -> Vec<Event<'a>> {
+        self.iter_until(delimeter).collect()
+    }
+
     /// Iterates over the stream, returning any events where `delimeter` returns `false`.
     /// Once `delimeter` returns `true` the iterator ends, but the matched event is consumed, but not included.
     pub fn iter_until_and_consume(
@@ -76,7 +180,11 @@ impl<'a> CMarkParser<'a> {
 #[derive(Debug, Clone, Copy)]
 pub struct Position {
     pub line: usize,
+    /// The column, counted in characters.
     pub column: usize,
+    /// The column counted in UTF-16 code units, as editors and the language server protocol count them. It is
+    /// larger than `column` when the line has characters outside of the basic multilingual plane, such as emoji.
+    pub column_utf16: usize,
 }
 
 impl Display for Position {
@@ -84,3 +192,102 @@ impl Display for Position {
         write!(formatter, "line: {}, column: {}", self.line, self.column)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pulldown_cmark::Tag;
+
+    #[test]
+    fn collects_events_until_the_delimeter() {
+        let mut parser = CMarkParser::new("Some *text*\n\n# Heading");
+
+        let events = parser.collect_until(|event| matches!(event, Event::Start(Tag::Heading(..))));
+
+        assert_eq!(
+            vec![
+                Event::Start(Tag::Paragraph),
+                Event::Text("Some ".into()),
+                Event::Start(Tag::Emphasis),
+                Event::Text("text".into()),
+                Event::End(Tag::Emphasis),
+                Event::End(Tag::Paragraph),
+            ],
+            events
+        );
+        assert!(matches!(
+            parser.next_event(),
+            Some(Event::Start(Tag::Heading(..)))
+        ));
+    }
+
+    #[test]
+    fn peeks_ahead_without_consuming() {
+        let mut parser = CMarkParser::new("# Heading\n\nText");
+        parser.next_event();
+        let position = parser.position();
+
+        assert_eq!(Some(&Event::Text("Heading".into())), parser.peek_nth(0));
+        assert_eq!(Some(&Event::Start(Tag::Paragraph)), parser.peek_nth(2));
+        assert_eq!(None, parser.peek_nth(10));
+        assert_eq!(Some(&Event::Text("Heading".into())), parser.peek_event());
+        assert_eq!(position.line, parser.position().line);
+        assert_eq!(position.column, parser.position().column);
+
+        assert_eq!(Some(Event::Text("Heading".into())), parser.next_event());
+        assert!(matches!(
+            parser.next_event(),
+            Some(Event::End(Tag::Heading(..)))
+        ));
+        assert_eq!(Some(Event::Start(Tag::Paragraph)), parser.next_event());
+        assert_eq!(3, parser.position().line);
+    }
+
+    #[test]
+    fn counts_columns_in_characters_and_utf16_code_units() {
+        let mut parser = CMarkParser::new(
+            "# Title
+
+Café 🐉 *lair*",
+        );
+        parser
+            .iter_until_and_consume(|event| *event == Event::Start(Tag::Emphasis))
+            .for_each(drop);
+        parser.next_event();
+        let position = parser.position();
+
+        assert_eq!(3, position.line);
+        assert_eq!(9, position.column);
+        assert_eq!(10, position.column_utf16);
+    }
+
+    #[test]
+    fn collects_the_remaining_events_without_a_delimeter() {
+        let mut parser = CMarkParser::new("Text");
+
+        assert_eq!(3, parser.collect_until(|_| false).len());
+        assert_eq!(None, parser.peek_event());
+    }
+
+    #[test]
+    fn separates_consecutive_html_blocks() {
+        let mut parser = CMarkParser::new(
+            "<div>\n  <p>Widget</p>\n</div>\n\n<script>\nlet a;\n\nlet b;\n</script>\n",
+        );
+
+        assert_eq!(
+            vec![
+                Event::Html("<div>\n".into()),
+                Event::Html("  <p>Widget</p>\n".into()),
+                Event::Html("</div>\n".into()),
+                Event::Html("\n".into()),
+                Event::Html("<script>\n".into()),
+                Event::Html("let a;\n".into()),
+                Event::Html("\n".into()),
+                Event::Html("let b;\n".into()),
+                Event::Html("</script>\n".into()),
+            ],
+            parser.collect_until(|_| false)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// How text such as a heading, a chapter title or the title of a draft is turned into a slug, chosen by
+/// `build.slug-strategy` so that anchors, links and file names all agree.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SlugStrategy {
+    /// Lowercase the text, keep alphanumerics, `-` and `_`, turn spaces into `-` and drop everything else, the way
+    /// GitHub does.
+    #[default]
+    Github,
+    /// Like `Github`, but spaces and `-` are turned into `_`.
+    Underscore,
+    /// Like `Github`, but the case of the text is kept.
+    PreserveCase,
+}
+
+impl SlugStrategy {
+    /// Convert the text into a slug with this strategy.
+    pub fn slugify(self, text: &str) -> String {
+        let characters = text.trim().chars().filter_map(|character| match character {
+            ' ' | '-' if self == Self::Underscore => Some('_'),
+            ' ' => Some('-'),
+            '-' | '_' => Some(character),
+            _ if character.is_alphanumeric() => Some(character),
+            _ => None,
+        });
+
+        match self {
+            Self::PreserveCase => characters.collect(),
+            Self::Github | Self::Underscore => characters.flat_map(char::to_lowercase).collect(),
+        }
+    }
+}
+
+/// Convert heading text into a URL fragment the way GitHub does, see `SlugStrategy::Github`.
+pub fn slugify(text: &str) -> String {
+    SlugStrategy::Github.slugify(text)
+}
+
+/// Produces slugs that are unique within a single document, by suffixing repeats with `-1`, `-2` and so on.
+#[derive(Debug, Default)]
+pub struct Slugger {
+    seen: HashMap<String, usize>,
+    strategy: SlugStrategy,
+}
+
+impl Slugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A slugger that slugifies text with the given strategy rather than the GitHub style.
+    pub fn with_strategy(strategy: SlugStrategy) -> Self {
+        Self {
+            seen: HashMap::new(),
+            strategy,
+        }
+    }
+
+    /// Slugify the text, making the result unique among every slug this slugger has produced.
+    pub fn slug(&mut self, text: &str) -> String {
+        self.unique(self.strategy.slugify(text))
+    }
+
+    /// Make an already computed slug unique among every slug this slugger has produced.
+    pub fn unique(&mut self, slug: String) -> String {
+        let mut candidate = slug.clone();
+
+        while let Some(count) = self.seen.get_mut(&candidate) {
+            *count += 1;
+            candidate = format!("{slug}-{count}");
+        }
+
+        self.seen.insert(candidate.clone(), 0);
+        candidate
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn slugifies_like_github() {
+        assert_eq!("the-rusty-anchor", slugify("The Rusty Anchor"));
+        assert_eq!("whats-in-the-box", slugify("What's in the box?"));
+        assert_eq!("café_1-2", slugify(" Café_1-2 "));
+    }
+
+    #[test]
+    fn slugifies_with_each_strategy() {
+        let cases = [
+            (
+                SlugStrategy::Github,
+                [
+                    "the-rusty-anchor",
+                    "whats-in-the-box-set",
+                    "café_1-2",
+                    "ünter-straße",
+                ],
+            ),
+            (
+                SlugStrategy::Underscore,
+                [
+                    "the_rusty_anchor",
+                    "whats_in_the_box_set",
+                    "café_1_2",
+                    "ünter_straße",
+                ],
+            ),
+            (
+                SlugStrategy::PreserveCase,
+                [
+                    "The-Rusty-Anchor",
+                    "Whats-in-the-Box-set",
+                    "Café_1-2",
+                    "Ünter-Straße",
+                ],
+            ),
+        ];
+
+        for (strategy, expected) in cases {
+            let actual = [
+                "The Rusty Anchor",
+                "What's in the Box-set?",
+                " Café_1-2 ",
+                "Ünter Straße!",
+            ]
+            .map(|text| strategy.slugify(text));
+
+            assert_eq!(expected, actual, "{strategy:?}");
+        }
+    }
+
+    #[test]
+    fn deduplicates_repeated_slugs() {
+        let mut slugger = Slugger::new();
+
+        assert_eq!("notes", slugger.slug("Notes"));
+        assert_eq!("notes-1", slugger.slug("Notes"));
+        assert_eq!("notes-2", slugger.slug("Notes"));
+        assert_eq!("notes-1-1", slugger.slug("Notes 1"));
+    }
+
+    #[test]
+    fn deduplicates_slugs_of_the_strategy() {
+        let mut slugger = Slugger::with_strategy(SlugStrategy::Underscore);
+
+        assert_eq!("rusty_anchor", slugger.slug("Rusty Anchor"));
+        assert_eq!("rusty_anchor-1", slugger.slug("Rusty-Anchor"));
+    }
+}
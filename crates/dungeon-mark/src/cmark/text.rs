@@ -0,0 +1,127 @@
+use std::ops::Range;
+
+/// Adjacent text of a Markdown source as its parser reports it, along with where each fragment of the text is
+/// written, for rewriting parts of the text in place while leaving the rest of the source byte for byte as it is.
+///
+/// Punctuation such as brackets and escaped characters are split into text events of their own, so a run joins
+/// the text events that follow each other in the source, directly or past the backslash escaping the next one.
+#[derive(Debug, Default)]
+pub struct TextRun {
+    text: String,
+    /// The offset in `text` and the source range of each fragment, and whether the fragment is escaped with a
+    /// backslash, which its source range leaves out.
+    fragments: Vec<(usize, Range<usize>, bool)>,
+}
+
+impl TextRun {
+    /// The text of the run, with escapes and entities resolved.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether the text event at `range` of the Markdown continues the run. An empty run continues with anything.
+    pub fn follows(&self, markdown: &str, range: &Range<usize>) -> bool {
+        match self.fragments.last() {
+            Some((_, last, _)) => matches!(&markdown[last.end..range.start], "" | "\\"),
+            None => true,
+        }
+    }
+
+    /// Add the text event `fragment` at `range` of the Markdown to the run, which it must follow.
+    pub fn push(&mut self, markdown: &str, fragment: &str, range: Range<usize>) {
+        let escaped = markdown[..range.start].ends_with('\\')
+            && self
+                .fragments
+                .last()
+                .is_none_or(|(_, last, _)| last.end < range.start);
+
+        self.fragments.push((self.text.len(), range, escaped));
+        self.text.push_str(fragment);
+    }
+
+    /// The range in the source of the `range` of the text, including the backslash of an escaped character it
+    /// starts with. A fragment written differently from its text, such as an entity, is covered as a whole.
+    pub fn source_range(&self, range: Range<usize>) -> Range<usize> {
+        self.source_offset(range.start, false)..self.source_offset(range.end, true)
+    }
+
+    fn source_offset(&self, index: usize, end: bool) -> usize {
+        let fragment = match end {
+            true => self
+                .fragments
+                .iter()
+                .rposition(|(start, ..)| *start < index),
+            false => self
+                .fragments
+                .iter()
+                .rposition(|(start, ..)| *start <= index),
+        }
+        .unwrap_or_default();
+        let Some((start, ref range, escaped)) = self.fragments.get(fragment).cloned() else {
+            return 0;
+        };
+        let length = self
+            .fragments
+            .get(fragment + 1)
+            .map_or(self.text.len(), |(next, ..)| *next)
+            - start;
+
+        match length == range.len() {
+            false if end => range.end,
+            true if end || index > start => range.start + index - start,
+            _ if escaped => range.start - 1,
+            _ => range.start,
+        }
+    }
+}
+
+/// Replace the source ranges of the Markdown, which must be in order and not overlap, with their replacements.
+pub fn splice(markdown: &str, replacements: Vec<(Range<usize>, String)>) -> String {
+    let mut spliced = String::with_capacity(markdown.len());
+    let mut position = 0;
+
+    for (range, replacement) in replacements {
+        spliced.push_str(&markdown[position..range.start]);
+        spliced.push_str(&replacement);
+        position = range.end;
+    }
+
+    spliced.push_str(&markdown[position..]);
+    spliced
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cmark::parser_options;
+    use pulldown_cmark::{Event, Parser};
+
+    fn run(markdown: &str) -> TextRun {
+        let mut run = TextRun::default();
+
+        for (event, range) in Parser::new_ext(markdown, parser_options()).into_offset_iter() {
+            if let Event::Text(fragment) = event {
+                assert!(run.follows(markdown, &range), "{fragment:?} should follow");
+                run.push(markdown, &fragment, range);
+            }
+        }
+
+        run
+    }
+
+    #[test]
+    fn maps_text_back_to_its_source() {
+        let markdown = "\\[\\[Town\\]\\] &amp; \\*Inn\\*";
+        let run = run(markdown);
+
+        assert_eq!("[[Town]] & *Inn*", run.text());
+        assert_eq!(0..12, run.source_range(0..8));
+        assert_eq!(4..8, run.source_range(2..6));
+        assert_eq!(13..18, run.source_range(9..10));
+        assert_eq!(19..26, run.source_range(11..16));
+        assert_eq!(
+            "[Town](town.md) &amp; \\*Inn\\*",
+            splice(markdown, vec![(0..12, String::from("[Town](town.md)"))])
+        );
+    }
+}
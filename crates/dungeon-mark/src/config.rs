@@ -1,52 +1,290 @@
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
 use std::{
-    fs,
+    collections::BTreeMap,
+    fs, mem,
     path::{Path, PathBuf},
     str::FromStr,
+    time::Duration,
 };
-use toml::value::Table;
+use toml::{value::Table, Value};
 
-use crate::error::{Error, Result};
+use crate::{
+    cmark::SlugStrategy,
+    error::{Error, Result},
+    model::journal::{JournalEntry, ParseOptions},
+};
 
 #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     /// Configuration for the journal itself.
+    #[serde(default)]
     pub journal: JournalConfig,
 
     /// Configuration for the build process.
+    #[serde(default)]
     pub build: BuildConfig,
 
+    /// Per-language editions of the journal from the `[language.<code>]` tables, keyed by language code.
+    #[serde(default)]
+    pub language: BTreeMap<String, LanguageConfig>,
+
+    /// Named locations outside of the source that table of contents links can point into, from the `[roots]`
+    /// table, keyed by name. Paths are relative to the journal root. See `resolve_location`.
+    #[serde(default)]
+    pub roots: BTreeMap<String, PathBuf>,
+
     #[serde(flatten)]
     rest: Table,
+
+    /// Paths of keys in the structured sections that did not match any known field.
+    #[serde(skip)]
+    unknown_keys: Vec<String>,
 }
 
+/// The sections of the configuration file that have a known structure, as opposed to free-form sections.
+const STRUCTURED_SECTIONS: &[&str] = &["journal", "build", "language", "roots"];
+pub(crate) const CONFIG_FILE: &str = "journal.toml";
+/// Personal overrides of the configuration, usually kept out of version control.
+pub(crate) const LOCAL_CONFIG_FILE: &str = "journal.local.toml";
+
 impl Config {
-    /// Load the config file from the specified path.
+    /// Load the config file from the specified path. When a `journal.local.toml` sits next to `journal.toml`, it
+    /// is merged over it as described by `merge_config`, except that every value it sets is merged, even one
+    /// equal to its default, so that it can turn off an option `journal.toml` turned on.
     pub fn load(path: impl AsRef<Path>) -> Result<Config> {
-        let path = path.as_ref().join("journal.toml");
-        let config: Self = fs::read_to_string(&path)
-            .with_context(|| format!("Failed to open journal.toml: {}", path.display()))?
-            .parse()
-            .with_context(|| "Failed to deserialize journal.toml")?;
+        let path = path.as_ref().join(CONFIG_FILE);
+        let mut table = read_table(&path)?;
+
+        let local_path = path.with_file_name(LOCAL_CONFIG_FILE);
+        if local_path.exists() {
+            merge_tables(&mut table, read_table(&local_path)?);
+        }
+
+        let config =
+            Self::from_table(table).with_context(|| "Failed to deserialize journal.toml")?;
+
+        config
+            .validate()
+            .with_context(|| format!("Invalid configuration: {}", path.display()))?;
 
         Ok(config)
     }
 
+    /// Merge another configuration over this one, such as per-environment overrides over a shared base:
+    /// - Tables, including the free-form ones, are merged recursively, with the values of `other` replacing
+    ///   scalars of the same key.
+    /// - Arrays, such as `journal.authors`, are replaced as a whole rather than concatenated.
+    /// - `build.renderers` is merged by renderer name, merging a renderer into the one of the same name and
+    ///   appending renderers with new names.
+    ///
+    /// As a typed configuration cannot tell a value that was set from one left at its default, values of `other`
+    /// that equal their defaults, such as a `build.clean` of `false`, are not merged.
+    pub fn merge_config(&mut self, other: Config) -> Result<()> {
+        let mut table = config_table(&*self)?;
+        let mut other_table = config_table(&other)?;
+        remove_defaults(&mut other_table, &config_table(&Config::default())?);
+
+        let renderer_defaults = Value::try_from(RendererConfig::default())?;
+        if let Some(Value::Array(renderers)) = other_table
+            .get_mut("build")
+            .and_then(|build| build.get_mut("renderers"))
+        {
+            for renderer in renderers.iter_mut().filter_map(Value::as_table_mut) {
+                if let Some(defaults) = renderer_defaults.as_table() {
+                    remove_defaults(renderer, defaults);
+                }
+            }
+        }
+
+        merge_tables(&mut table, other_table);
+
+        let mut merged = Self::from_table(table)?;
+        merged.unknown_keys = mem::take(&mut self.unknown_keys);
+        merged.unknown_keys.extend(other.unknown_keys);
+        *self = merged;
+
+        Ok(())
+    }
+
+    /// The configuration of each language edition of the journal, ordered by language code. Each has its
+    /// `journal.language` set to the edition's code and `journal.source` pointing at the edition's sources.
+    /// Without any `[language.<code>]` tables this is just the configuration itself.
+    pub fn editions(&self) -> Vec<Config> {
+        if self.language.is_empty() {
+            return vec![self.clone()];
+        }
+
+        self.language
+            .iter()
+            .map(|(code, language)| {
+                let mut config = self.clone();
+                config.journal.language = Some(code.clone());
+                config.journal.source = match language.source {
+                    Some(ref source) => JournalSource::Single(source.clone()),
+                    None => self.journal.source.join(code),
+                };
+
+                if language.title.is_some() {
+                    config.journal.title = language.title.clone();
+                }
+
+                config
+            })
+            .collect()
+    }
+
+    /// Split the location of a table of contents link into the directory it is relative to, itself relative to
+    /// the journal root, and the path within that directory. A location such as `@bestiary/goblin.md` is relative
+    /// to the `bestiary` path of the `[roots]` table, and any other location to `journal.source`, or to the first
+    /// of its locations when it lists several. Fails if the location names a root that is not configured.
+    pub fn resolve_location<'a>(&'a self, location: &'a Path) -> Result<(&'a Path, &'a Path)> {
+        let mut components = location.components();
+
+        let Some(name) = components
+            .next()
+            .and_then(|component| component.as_os_str().to_str())
+            .and_then(|component| component.strip_prefix('@'))
+        else {
+            return Ok((self.journal.source.primary(), location));
+        };
+
+        let Some(root) = self.roots.get(name) else {
+            anyhow::bail!(
+                "The location {} uses the root @{name}, which is not in the [roots] table",
+                location.display()
+            )
+        };
+
+        Ok((root, components.as_path()))
+    }
+
+    /// The directory of the entry's file, relative to the journal root, which paths written in the entry are
+    /// relative to. This is the `source_dir` recorded when the entry was loaded, or for an entry made some other
+    /// way, the directory of its path resolved with `resolve_location`. Fails for an entry without a path.
+    pub fn resolve_entry_dir(&self, entry: &JournalEntry) -> Result<PathBuf> {
+        if let Some(ref source_dir) = entry.source_dir {
+            return Ok(source_dir.clone());
+        }
+
+        let Some(ref location) = entry.path else {
+            anyhow::bail!("The journal entry {} has no file path", entry.title);
+        };
+
+        let (source, path) = self.resolve_location(location)?;
+        let mut entry_dir = source.join(path);
+        entry_dir.pop();
+
+        Ok(entry_dir)
+    }
+
+    /// Merge a value into a free-form key of the configuration. Tables are merged recursively into any table
+    /// already stored under the key, while any other value replaces what was there. The structured sections
+    /// (`journal`, `build`, `language` and `roots`) cannot be set this way.
+    pub fn merge(&mut self, key: &str, value: Value) -> Result<()> {
+        if STRUCTURED_SECTIONS.contains(&key) {
+            anyhow::bail!("The [{key}] section of the configuration cannot be merged into");
+        }
+
+        match self.rest.get_mut(key) {
+            Some(existing) => merge_value(existing, value),
+            None => {
+                self.rest.insert(key.to_string(), value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set the value at a dotted key path, such as `custom.npc.level`, creating any tables along the path that are
+    /// missing. Keys within the structured sections, such as `journal.source`, update the typed configuration
+    /// and fail if the key is not recognized or the value does not fit it. As with `try_get`, keys that contain a
+    /// dot themselves cannot be set.
+    pub fn set<S: Serialize>(&mut self, key: &str, value: S) -> Result<()> {
+        let value = Value::try_from(value)
+            .with_context(|| format!("Failed to serialize configuration key: {key}"))?;
+        let section = key.split('.').next().unwrap_or_default();
+
+        if !STRUCTURED_SECTIONS.contains(&section) {
+            return insert_value(&mut self.rest, key, value);
+        }
+
+        let mut config = Value::try_from(&*self)?;
+        let table = config
+            .as_table_mut()
+            .expect("the configuration serializes to a table");
+        insert_value(table, key, value)?;
+
+        let mut updated: Config = config
+            .try_into()
+            .with_context(|| format!("Invalid value for configuration key: {key}"))?;
+
+        // NOTE: A key the typed configuration does not recognize is dropped by the round trip.
+        let known = Value::try_from(&updated)?;
+        if key
+            .split('.')
+            .try_fold(&known, |value, segment| value.get(segment))
+            .is_none()
+        {
+            anyhow::bail!("Unrecognized configuration key: {key}");
+        }
+
+        updated.unknown_keys = mem::take(&mut self.unknown_keys);
+        *self = updated;
+
+        Ok(())
+    }
+
+    /// Check that the structured sections of the configuration (`[journal]`, `[build]`, `[language]` and `[roots]`)
+    /// only contain known keys. Free-form sections, such as those read by renderers through `get`, are not checked.
+    pub fn validate(&self) -> Result<()> {
+        if !self.unknown_keys.is_empty() {
+            anyhow::bail!(
+                "Unrecognized configuration keys: {}",
+                self.unknown_keys.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+
     /// Attempt to retrieve the specified key and deserialize it to the target type.
     /// The target type must implement `Default` which will be returned in the event
-    /// that the specified key could not be found.
+    /// that the specified key could not be found. See `try_get` for the format of the key.
     pub fn get<'de, D>(&self, key: &str) -> Result<D>
     where
         D: Deserialize<'de> + Default,
     {
-        let Some(item) = self.rest.get(key).cloned() else {
-            return Ok(Default::default());
+        Ok(self.try_get(key)?.unwrap_or_default())
+    }
+
+    /// Attempt to retrieve the specified key and deserialize it to the target type.
+    /// Returns `None` only if the key is absent, so an explicitly set value that happens
+    /// to equal the type's default can be told apart from an unset one.
+    ///
+    /// The key is a dotted path into the free-form tables, such as `custom.npc.defaults`, and is absent if any
+    /// of its segments is missing or is not a table. As the key is split on every `.`, keys that contain a dot
+    /// themselves cannot be retrieved.
+    pub fn try_get<'de, D>(&self, key: &str) -> Result<Option<D>>
+    where
+        D: Deserialize<'de>,
+    {
+        let mut segments = key.split('.');
+        let mut item = segments.next().and_then(|segment| self.rest.get(segment));
+
+        for segment in segments {
+            item = item.and_then(|item| item.as_table()?.get(segment));
+        }
+
+        let Some(item) = item.cloned() else {
+            return Ok(None);
         };
 
-        let item = item.try_into()?;
+        let item = item
+            .try_into()
+            .with_context(|| format!("Failed to deserialize configuration key: {key}"))?;
 
-        Ok(item)
+        Ok(Some(item))
     }
 }
 
@@ -54,21 +292,225 @@ impl FromStr for Config {
     type Err = Error;
 
     fn from_str(source: &str) -> Result<Self, Self::Err> {
-        toml::from_str(source).with_context(|| "Attempted to parse invalid configuration file")
+        let raw: Table = toml::from_str(source)
+            .with_context(|| "Attempted to parse invalid configuration file")?;
+
+        Self::from_table(raw)
+    }
+}
+
+impl Config {
+    fn from_table(raw: Table) -> Result<Self> {
+        let mut config: Config = Value::Table(raw.clone())
+            .try_into()
+            .with_context(|| "Attempted to parse invalid configuration file")?;
+
+        // NOTE: Any key that was set in a structured section but does not survive a round trip through
+        // the typed configuration was not recognized by it.
+        let known = Value::try_from(&config)?;
+        for section in STRUCTURED_SECTIONS {
+            if let (Some(raw), Some(known)) = (raw.get(*section), known.get(*section)) {
+                unknown_keys(section, raw, known, &mut config.unknown_keys);
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Insert the value at the dotted key path, creating any missing tables along it.
+fn insert_value(table: &mut Table, key: &str, value: Value) -> Result<()> {
+    let mut segments: Vec<_> = key.split('.').collect();
+    let last = segments.pop().unwrap_or_default();
+    let mut table = table;
+
+    for segment in segments {
+        table = table
+            .entry(segment)
+            .or_insert_with(|| Value::Table(Table::new()))
+            .as_table_mut()
+            .with_context(|| format!("Cannot set {key} as {segment} is not a table"))?;
+    }
+
+    table.insert(last.to_string(), value);
+
+    Ok(())
+}
+
+fn read_table(path: &Path) -> Result<Table> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Failed to open {name}: {}", path.display()))?;
+
+    toml::from_str(&source).with_context(|| format!("Failed to deserialize {name}"))
+}
+
+fn config_table(config: &Config) -> Result<Table> {
+    match Value::try_from(config)? {
+        Value::Table(table) => Ok(table),
+        _ => anyhow::bail!("The configuration did not serialize to a table"),
+    }
+}
+
+/// Merge the table of a configuration over another, as described by `Config::merge_config`.
+fn merge_tables(existing: &mut Table, mut table: Table) {
+    let renderers = table
+        .get_mut("build")
+        .and_then(Value::as_table_mut)
+        .and_then(|build| build.remove("renderers"));
+
+    for (key, value) in table {
+        match existing.get_mut(&key) {
+            Some(existing) => merge_value(existing, value),
+            None => {
+                existing.insert(key, value);
+            }
+        }
+    }
+
+    let Some(Value::Array(renderers)) = renderers else {
+        return;
+    };
+
+    let Some(build) = existing
+        .entry("build")
+        .or_insert_with(|| Value::Table(Table::new()))
+        .as_table_mut()
+    else {
+        return;
+    };
+
+    let Value::Array(existing) = build
+        .entry("renderers")
+        .or_insert_with(|| Value::Array(Vec::new()))
+    else {
+        build.insert(String::from("renderers"), Value::Array(renderers));
+        return;
+    };
+
+    for renderer in renderers {
+        let name = renderer.get("name").cloned();
+
+        match existing
+            .iter_mut()
+            .find(|existing| name.is_some() && existing.get("name") == name.as_ref())
+        {
+            Some(existing) => merge_value(existing, renderer),
+            None => existing.push(renderer),
+        }
+    }
+}
+
+/// Remove the values of the table that equal the value of the same key in `defaults`, along with tables that
+/// are left empty.
+fn remove_defaults(table: &mut Table, defaults: &Table) {
+    let mut removed = Vec::new();
+
+    for (key, value) in table.iter_mut() {
+        let Some(default) = defaults.get(key) else {
+            continue;
+        };
+
+        let is_default = match (value, default) {
+            (Value::Table(table), Value::Table(defaults)) => {
+                remove_defaults(table, defaults);
+                table.is_empty()
+            }
+            (value, default) => value == default,
+        };
+
+        if is_default {
+            removed.push(key.clone());
+        }
+    }
+
+    for key in removed {
+        table.remove(&key);
+    }
+}
+
+fn merge_value(existing: &mut Value, value: Value) {
+    match (existing, value) {
+        (Value::Table(existing), Value::Table(table)) => {
+            for (key, value) in table {
+                match existing.get_mut(&key) {
+                    Some(existing) => merge_value(existing, value),
+                    None => {
+                        existing.insert(key, value);
+                    }
+                }
+            }
+        }
+        (existing, value) => *existing = value,
+    }
+}
+
+fn unknown_keys(path: &str, raw: &Value, known: &Value, unknown: &mut Vec<String>) {
+    match (raw, known) {
+        (Value::Table(raw), Value::Table(known)) => {
+            for (key, value) in raw {
+                let path = format!("{path}.{key}");
+
+                match known.get(key) {
+                    Some(known) => unknown_keys(&path, value, known, unknown),
+                    None => unknown.push(path),
+                }
+            }
+        }
+        (Value::Array(raw), Value::Array(known)) => {
+            for (index, (raw, known)) in raw.iter().zip(known).enumerate() {
+                unknown_keys(&format!("{path}[{index}]"), raw, known, unknown);
+            }
+        }
+        _ => {}
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct JournalConfig {
-    /// Optional title for the compendium.
+    /// Optional title for the compendium. When set, this takes precedence over the title heading of the
+    /// table of contents, and becomes the title of the journal handed to renderers.
     pub title: Option<String>,
-    /// List of authors for the compendium.
+    /// List of authors for the compendium. These are not used by the build itself, and are passed along to
+    /// renderers through the configuration for use in page metadata, title pages and the like.
     pub authors: Vec<String>,
-    /// Optional description of the compendium.
+    /// Optional description of the compendium. Like `authors`, this is only passed along to renderers.
     pub description: Option<String>,
-    /// Relative path to the source location of the compendium.
-    pub source: PathBuf,
+    /// Relative path to the source location of the compendium, or a list of them to merge into one journal, such
+    /// as `["players", "gm"]`. Each source has its own table of contents, and the items of each are loaded in turn
+    /// with their links resolved against their own source location.
+    pub source: JournalSource,
+    /// When `source` lists several locations, start the items of each with a chapter title named after its
+    /// directory.
+    pub source_chapters: bool,
+    /// Name of the table of contents file, relative to the source location.
+    pub summary_file: PathBuf,
+    /// The extensions tried, in order, for a table of contents link without an extension, such as
+    /// `[Keep](keep)`. Defaults to `md`.
+    pub extensions: Vec<String>,
+    /// The file a table of contents link to a directory, such as `[Chapter](chapter/)`, loads the entry from,
+    /// relative to that directory. Defaults to `index.md`.
+    pub index_file: PathBuf,
+    /// Keep every separator of the table of contents where it is written, rather than collapsing consecutive
+    /// separators into one and removing the separators at its start and end.
+    pub keep_separators: bool,
+    /// Optional language code of the journal's content. When building language editions, this is set
+    /// to the code of the edition being built.
+    pub language: Option<String>,
+    /// Glob patterns of files and directories to skip when walking the file system, such as when looking for
+    /// orphaned entries in the source location or copying `build.assets`. Patterns are matched against paths
+    /// relative to the directory being walked, with `/` as the separator, and an ignored directory is skipped
+    /// along with everything below it.
+    ///
+    /// - `*` matches any characters except `/`, `?` matches a single character and `[abc]` or `[!abc]` match
+    ///   one character that is, or is not, in the brackets.
+    /// - A pattern without a `/`, such as `.obsidian` or `*.psd`, matches the name of a file or directory at
+    ///   any depth.
+    /// - A pattern with a `/` before its end, such as `places/*.md` or `/notes`, matches the whole relative
+    ///   path, where `**` matches any number of directories, as in `archive/**/*.md`.
+    /// - A pattern ending in `/`, such as `drafts/`, only matches directories.
+    pub ignore: Vec<String>,
 }
 
 impl Default for JournalConfig {
@@ -77,15 +519,134 @@ impl Default for JournalConfig {
             title: None,
             authors: Vec::new(),
             description: None,
-            source: PathBuf::from("./src"),
+            source: JournalSource::Single(PathBuf::from("./src")),
+            source_chapters: false,
+            summary_file: PathBuf::from("JOURNAL.md"),
+            extensions: vec![String::from("md")],
+            index_file: PathBuf::from("index.md"),
+            keep_separators: false,
+            language: None,
+            ignore: Vec::new(),
+        }
+    }
+}
+
+/// The source location of the journal, written in `journal.toml` either as a single path or as a list of paths.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum JournalSource {
+    /// A single source location.
+    Single(PathBuf),
+    /// Several source locations, merged into one journal in order.
+    Merged(Vec<PathBuf>),
+}
+
+impl JournalSource {
+    /// Every source location, in order.
+    pub fn paths(&self) -> &[PathBuf] {
+        match self {
+            Self::Single(path) => std::slice::from_ref(path),
+            Self::Merged(paths) => paths,
+        }
+    }
+
+    /// The first source location, or the journal root for an empty list.
+    pub fn primary(&self) -> &Path {
+        self.paths().first().map_or(Path::new(""), PathBuf::as_path)
+    }
+
+    /// The same source locations, each with `path` joined onto it.
+    pub fn join(&self, path: impl AsRef<Path>) -> Self {
+        match self {
+            Self::Single(source) => Self::Single(source.join(path)),
+            Self::Merged(sources) => Self::Merged(
+                sources
+                    .iter()
+                    .map(|source| source.join(path.as_ref()))
+                    .collect(),
+            ),
         }
     }
 }
 
+impl<P: Into<PathBuf>> From<P> for JournalSource {
+    fn from(path: P) -> Self {
+        Self::Single(path.into())
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct BuildConfig {
     pub renderers: Vec<RendererConfig>,
+    /// The renderer to run when `renderers` is empty and none were added to the `JournalBuilder`, so that a new
+    /// journal builds something. Defaults to `html`. Set it to an empty string to run no renderers at all, in
+    /// which case the build warns that it writes no output.
+    pub default_renderer: Option<String>,
+    /// The names of the preprocessors to run, in order. Defaults to the built-in `directive` preprocessor.
+    pub preprocessors: Option<Vec<String>>,
+    /// The names of the transformers to run, in order. Defaults to the built-in `conditional`, `metadata`,
+    /// `redaction` and `title` transformers.
+    pub transformers: Option<Vec<String>>,
+    /// Options for parsing journal entries, from the `[build.parse]` table.
+    pub parse: ParseOptions,
+    /// Remove each renderer's destination directory before running the renderer, so that no stale files remain.
+    pub clean: bool,
+    /// The active profile, such as `print` or `online`, for `{{#if profile=...}}` directives.
+    pub profile: Option<String>,
+    /// The seed of the random numbers drawn while preprocessing, such as by `{{#roll}}` directives, so that every
+    /// build generates the same content. Changing the seed reshuffles all generated content. Without a seed, every
+    /// build draws different numbers.
+    pub seed: Option<u64>,
+    /// Hand draft entries, linked from the table of contents without a location such as `[Title]()`, to renderers.
+    /// By default drafts are removed from the journal once it has been transformed, along with the entries nested
+    /// below them, so that unfinished pages stay out of the output.
+    pub render_drafts: bool,
+    /// Remove sections marked as secret from the journal before rendering, as configured by the `[redaction]` table.
+    pub redact: bool,
+    /// Put the titles of entries, sections and chapters in title case, as configured by the `[title-case]` table,
+    /// by running the `title-case` transformer even when `transformers` does not list it.
+    pub title_case: bool,
+    /// Optional directory of static assets, such as stylesheets and images, relative to the journal root. Its
+    /// contents are copied into each renderer's destination once the renderer has run.
+    pub assets: Option<PathBuf>,
+    /// Replace files in a renderer's destination with assets of the same name, rather than keeping the files the
+    /// renderer wrote.
+    pub overwrite_assets: bool,
+    /// Write a `sourcemap.json` into each renderer's destination once the renderer has run, mapping every entry
+    /// to its source file and the spans of its sections, for tracing rendered output back to its source. See
+    /// `SourceMap` for the schema.
+    pub source_map: bool,
+    /// Fail the build if the journal has structural problems once it has been transformed, as checked by
+    /// `Journal::validate`, instead of rendering it. Entries with more than one `{{#title ...}}` directive also fail
+    /// the build, rather than being warned about.
+    pub strict: bool,
+    /// Fail the build if a metadata block does not parse in the format of its language, as the `metadata`
+    /// transformer extracts it, rather than when the block is read.
+    pub strict_metadata: bool,
+    /// Fail the build if an entry has a directive whose keyword no preprocessor or transformer recognizes, such as
+    /// a misspelled `{{#titel ...}}`, rather than leaving the directive in the entry as it is.
+    pub strict_directives: bool,
+    /// Fail the build if the source directory has entries that the table of contents does not link to, as found
+    /// by `JournalBuilder::orphaned_files`.
+    pub fail_on_orphans: bool,
+    /// Keep building when an entry fails to preprocess or parse, replacing the entry with a `JournalItem::Error`
+    /// and collecting the error in `RenderOutput::errors`, rather than failing the build. The entries nested below
+    /// a failed entry are still built.
+    pub tolerant: bool,
+    /// How section anchors, chapter title anchors and the file names of drafts are slugified, one of `github`,
+    /// `underscore` or `preserve-case`. Defaults to `github`.
+    pub slug_strategy: SlugStrategy,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LanguageConfig {
+    /// Relative path to the source location of this language's edition. Defaults to a directory named
+    /// after the language code inside the journal's source location.
+    pub source: Option<PathBuf>,
+    /// Optional title of this language's edition, replacing the journal's title.
+    pub title: Option<String>,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
@@ -94,4 +655,386 @@ pub struct RendererConfig {
     pub name: String,
     /// Optional command, if this is not set the name will be used as a fallback for the command to run.
     pub command: Option<String>,
+    /// Optional number of seconds the command may run for before it is killed and the build fails.
+    pub timeout_secs: Option<u64>,
+    /// Write the render context to the command as newline delimited JSON, one journal item per line, rather than
+    /// as a single JSON document. See `CommandRenderer` for the format.
+    pub streaming: bool,
+    /// How many times to run the command again when it exits with a failure, for commands that depend on flaky
+    /// services. Commands that time out are not retried.
+    pub retries: u32,
+    /// How many milliseconds to wait before the first retry. The delay doubles after each retry.
+    pub retry_delay_ms: u64,
+}
+
+impl RendererConfig {
+    /// How long the renderer's command may run for, from `timeout-secs`.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_secs.map(Duration::from_secs)
+    }
+
+    /// How long to wait before the first retry of the renderer's command, from `retry-delay-ms`.
+    pub fn retry_delay(&self) -> Duration {
+        Duration::from_millis(self.retry_delay_ms)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gets_dotted_key_paths() {
+        let config: Config = "[custom.npc.defaults]\nlevel = 3\n\n[custom.flag]\nset = true"
+            .parse()
+            .expect("should parse");
+
+        #[derive(Debug, Default, PartialEq, Deserialize)]
+        struct Defaults {
+            level: u32,
+        }
+
+        let defaults: Defaults = config.get("custom.npc.defaults").expect("should get");
+        let level: Option<u32> = config
+            .try_get("custom.npc.defaults.level")
+            .expect("should get");
+        let missing: Option<u32> = config.try_get("custom.npc.other").expect("should get");
+        let not_table: Option<u32> = config
+            .try_get("custom.npc.defaults.level.deeper")
+            .expect("should get");
+
+        assert_eq!(Defaults { level: 3 }, defaults);
+        assert_eq!(Some(3), level);
+        assert_eq!(None, missing);
+        assert_eq!(None, not_table);
+    }
+
+    #[test]
+    fn sets_free_form_and_structured_keys() {
+        let mut config: Config = "[custom]\nname = \"Borin\"".parse().expect("should parse");
+
+        config.set("custom.npc.level", 3).expect("should set");
+        config.set("journal.source", "pages").expect("should set");
+        config.set("build.clean", true).expect("should set");
+
+        let level: Option<u32> = config.try_get("custom.npc.level").expect("should get");
+        let name: Option<String> = config.try_get("custom.name").expect("should get");
+
+        assert_eq!(Some(3), level);
+        assert_eq!(Some(String::from("Borin")), name);
+        assert_eq!(JournalSource::from("pages"), config.journal.source);
+        assert!(config.build.clean);
+    }
+
+    #[test]
+    fn rejects_invalid_structured_keys() {
+        let mut config = Config::default();
+
+        assert!(config.set("journal.unknown", 1).is_err());
+        assert!(config.set("build.clean", "yes").is_err());
+        assert!(config.set("custom.name", "Borin").is_ok());
+        assert!(config.set("custom.name.first", "Borin").is_err());
+    }
+
+    #[test]
+    fn resolves_locations_against_named_roots() {
+        let config: Config = "[roots]\nbestiary = \"../bestiary\""
+            .parse()
+            .expect("should parse");
+
+        assert_eq!(
+            (Path::new("../bestiary"), Path::new("goblins/goblin.md")),
+            config
+                .resolve_location(Path::new("@bestiary/goblins/goblin.md"))
+                .expect("should resolve")
+        );
+        assert_eq!(
+            (Path::new("./src"), Path::new("town.md")),
+            config
+                .resolve_location(Path::new("town.md"))
+                .expect("should resolve")
+        );
+        assert!(config
+            .resolve_location(Path::new("@spells/fireball.md"))
+            .is_err());
+    }
+
+    #[test]
+    fn reads_renderer_timeouts() {
+        let config: Config = "[[build.renderers]]\nname = \"pdf\"\ntimeout-secs = 30"
+            .parse()
+            .expect("should parse");
+
+        assert_eq!(
+            Some(Duration::from_secs(30)),
+            config.build.renderers[0].timeout()
+        );
+    }
+
+    #[test]
+    fn try_get_distinguishes_missing_from_default() {
+        let config: Config = "[section]\nvalue = 0".parse().expect("should parse");
+
+        #[derive(Debug, Default, PartialEq, Deserialize)]
+        struct Section {
+            value: u32,
+        }
+
+        assert_eq!(
+            Some(Section { value: 0 }),
+            config.try_get("section").expect("should deserialize")
+        );
+        assert_eq!(
+            None,
+            config
+                .try_get::<Section>("missing")
+                .expect("should deserialize")
+        );
+        assert_eq!(
+            Section::default(),
+            config.get("missing").expect("should deserialize")
+        );
+    }
+
+    #[test]
+    fn validate_reports_unknown_keys_in_structured_sections() {
+        let source = r#"
+            [journal]
+            source-dir = "src"
+
+            [build.parse]
+            max-dept = 3
+
+            [[build.renderers]]
+            name = "html"
+            comand = "dmark-html"
+
+            [free-form]
+            anything = true
+        "#;
+        let config: Config = source.parse().expect("should parse");
+        let err = config.validate().expect_err("should be invalid");
+        let message = err.to_string();
+
+        assert!(message.contains("journal.source-dir"));
+        assert!(message.contains("build.parse.max-dept"));
+        assert!(message.contains("build.renderers[0].comand"));
+        assert!(!message.contains("free-form"));
+    }
+
+    #[test]
+    fn merges_values_into_free_form_keys() {
+        let mut config: Config = "[campaign]\nname = \"Old\"\nlevel = 1"
+            .parse()
+            .expect("should parse");
+        let value: Value = toml::from_str("name = \"New\"\nparty = [\"Borin\"]").unwrap();
+
+        config.merge("campaign", value).expect("should merge");
+        config
+            .merge("setting", Value::from("Greyhawk"))
+            .expect("should merge");
+
+        let campaign: Option<Value> = config.try_get("campaign").expect("should deserialize");
+        let expected: Value =
+            toml::from_str("name = \"New\"\nlevel = 1\nparty = [\"Borin\"]").unwrap();
+
+        assert_eq!(Some(expected), campaign);
+        assert_eq!(
+            String::from("Greyhawk"),
+            config.get::<String>("setting").expect("should deserialize")
+        );
+        assert!(config.merge("build", Value::from(true)).is_err());
+    }
+
+    #[test]
+    fn merges_configurations() {
+        let mut config: Config = r#"
+            [journal]
+            title = "Campaign"
+            authors = ["Ada", "Borin"]
+
+            [build]
+            clean = true
+
+            [[build.renderers]]
+            name = "html"
+            timeout-secs = 30
+
+            [[build.renderers]]
+            name = "pandoc"
+
+            [campaign]
+            name = "Old"
+            party = { size = 4, level = 1 }
+        "#
+        .parse()
+        .expect("should parse");
+        let other: Config = r#"
+            [journal]
+            authors = ["Cyra"]
+
+            [build]
+            clean = false
+            profile = "print"
+
+            [[build.renderers]]
+            name = "html"
+            command = "html-renderer"
+
+            [[build.renderers]]
+            name = "search-index"
+
+            [campaign.party]
+            level = 3
+            members = ["Dain"]
+        "#
+        .parse()
+        .expect("should parse");
+
+        config.merge_config(other).expect("should merge");
+
+        let renderers: Vec<_> = config
+            .build
+            .renderers
+            .iter()
+            .map(|renderer| {
+                (
+                    renderer.name.as_str(),
+                    renderer.command.as_deref(),
+                    renderer.timeout_secs,
+                )
+            })
+            .collect();
+        let campaign: Option<Value> = config.try_get("campaign").expect("should deserialize");
+        let expected: Value =
+            toml::from_str("name = \"Old\"\nparty = { size = 4, level = 3, members = [\"Dain\"] }")
+                .unwrap();
+
+        assert_eq!(Some("Campaign"), config.journal.title.as_deref());
+        assert_eq!(vec![String::from("Cyra")], config.journal.authors);
+        assert!(config.build.clean);
+        assert_eq!(Some("print"), config.build.profile.as_deref());
+        assert_eq!(
+            vec![
+                ("html", Some("html-renderer"), Some(30)),
+                ("pandoc", None, None),
+                ("search-index", None, None)
+            ],
+            renderers
+        );
+        assert_eq!(Some(expected), campaign);
+    }
+
+    #[test]
+    fn validate_accepts_known_keys() {
+        let source = r#"
+            [journal]
+            title = "Journal"
+            source = "src"
+
+            [build]
+            preprocessors = []
+
+            [build.parse]
+            normalize-heading-levels = true
+            max-depth = 3
+
+            [[build.renderers]]
+            name = "html"
+            command = "dmark-html"
+        "#;
+        let config: Config = source.parse().expect("should parse");
+
+        config.validate().expect("should be valid");
+    }
+
+    #[test]
+    fn parses_a_list_of_sources() {
+        let config: Config = "[journal]\nsource = [\"players\", \"gm\"]"
+            .parse()
+            .expect("should parse");
+        config.validate().expect("should be valid");
+
+        assert_eq!(
+            [PathBuf::from("players"), PathBuf::from("gm")],
+            config.journal.source.paths()
+        );
+        assert_eq!(
+            JournalSource::Merged(vec![PathBuf::from("players/fr"), PathBuf::from("gm/fr")]),
+            config.journal.source.join("fr")
+        );
+    }
+
+    #[test]
+    fn resolves_the_directories_of_entries() {
+        let config: Config =
+            "[journal]\nsource = [\"players\", \"gm\"]\n\n[roots]\nbestiary = \"monsters\""
+                .parse()
+                .expect("should parse");
+        let entry = |path: &str, source_dir: Option<&str>| JournalEntry {
+            path: Some(PathBuf::from(path)),
+            source_dir: source_dir.map(PathBuf::from),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            PathBuf::from("gm/town"),
+            config
+                .resolve_entry_dir(&entry("town/secrets.md", Some("gm/town")))
+                .expect("should resolve")
+        );
+        assert_eq!(
+            PathBuf::from("players/town"),
+            config
+                .resolve_entry_dir(&entry("town/inn.md", None))
+                .expect("should resolve")
+        );
+        assert_eq!(
+            PathBuf::from("monsters"),
+            config
+                .resolve_entry_dir(&entry("@bestiary/goblin.md", None))
+                .expect("should resolve")
+        );
+        assert!(config.resolve_entry_dir(&JournalEntry::default()).is_err());
+    }
+
+    #[test]
+    fn editions_use_per_language_sources() {
+        let source = r#"
+            [journal]
+            source = "src"
+            title = "Journal"
+
+            [language.fr]
+            title = "Journal de campagne"
+
+            [language.en]
+            source = "english"
+        "#;
+        let config: Config = source.parse().expect("should parse");
+        config.validate().expect("should be valid");
+
+        let editions: Vec<_> = config
+            .editions()
+            .into_iter()
+            .map(|config| config.journal)
+            .map(|journal| (journal.language, journal.source, journal.title))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (
+                    Some(String::from("en")),
+                    JournalSource::from("english"),
+                    Some(String::from("Journal"))
+                ),
+                (
+                    Some(String::from("fr")),
+                    JournalSource::from("src/fr"),
+                    Some(String::from("Journal de campagne"))
+                ),
+            ],
+            editions
+        );
+    }
 }
@@ -1,3 +1,10 @@
+//! Dungeon Mark builds journals from markdown sources.
+//!
+//! The public API is made up of [`build`], which drives the load, preprocess, parse, transform and
+//! render pipeline, [`model`], which holds the journal types passed between those stages, [`config`], which
+//! reads `journal.toml`, [`cmark`], which holds the CommonMark utilities the stages share, and [`error`], the
+//! error and result types every stage returns.
+
 #![deny(rust_2018_idioms)]
 #![deny(clippy::all)]
 #![allow(clippy::module_inception)]
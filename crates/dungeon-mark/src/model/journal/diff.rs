@@ -0,0 +1,376 @@
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use super::{Journal, JournalEntry, Metadata, Section, SectionLevel};
+
+/// The differences between two journals, as produced by `Journal::diff`.
+///
+/// Entries are matched by their path, so entries without a path are not compared. Added and modified entries
+/// are listed in the document order of the newer journal, removed entries in that of the older journal.
+#[derive(Debug, Default, Clone, Serialize, PartialEq, Eq)]
+pub struct JournalDiff {
+    pub added: Vec<EntrySummary>,
+    pub removed: Vec<EntrySummary>,
+    pub modified: Vec<EntryDiff>,
+}
+
+impl JournalDiff {
+    /// Whether the journals have no differences.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+/// An entry that was added to or removed from a journal.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct EntrySummary {
+    pub path: PathBuf,
+    pub title: String,
+}
+
+/// The differences between two versions of the entry at `path`. Nested entries are compared on their own and
+/// do not make their parent modified.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct EntryDiff {
+    pub path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<Change<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<Change<Option<String>>>,
+    pub sections: Vec<SectionDiff>,
+}
+
+/// A value that differs between the older and the newer journal.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+impl<T: Clone + PartialEq> Change<T> {
+    fn between(old: &T, new: &T) -> Option<Change<T>> {
+        (old != new).then(|| Change {
+            old: old.clone(),
+            new: new.clone(),
+        })
+    }
+}
+
+/// A change to a single section of an entry. Sections are matched by `path`, the titles of the section and the
+/// sections it is nested below, starting from the outermost. Sections sharing a path are matched in document
+/// order, and a renamed section is reported as removed under its old path and added under its new one.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct SectionDiff {
+    pub path: Vec<String>,
+    #[serde(flatten)]
+    pub change: SectionChange,
+}
+
+/// How a section changed. Nested sections are reported separately, so `Added` and `Modified` only cover the
+/// section's own body and metadata.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "change", rename_all = "kebab-case")]
+pub enum SectionChange {
+    Added {
+        level: SectionLevel,
+        body: String,
+        metadata: Metadata,
+    },
+    Removed,
+    Modified {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        level: Option<Change<SectionLevel>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        body: Option<Change<String>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        metadata: Option<Change<Metadata>>,
+    },
+}
+
+impl Journal {
+    /// Compare this journal against a newer version of it. See `JournalDiff` for how entries and sections are
+    /// matched.
+    pub fn diff(&self, other: &Journal) -> JournalDiff {
+        let old_entries = entries_by_path(self);
+        let new_entries = entries_by_path(other);
+        let mut diff = JournalDiff::default();
+
+        for (path, new) in &new_entries {
+            match old_entries.iter().find(|(old_path, _)| old_path == path) {
+                Some((_, old)) => diff.modified.extend(diff_entry(path, old, new)),
+                None => diff.added.push(summary(path, new)),
+            }
+        }
+
+        for (path, old) in &old_entries {
+            if !new_entries.iter().any(|(new_path, _)| new_path == path) {
+                diff.removed.push(summary(path, old));
+            }
+        }
+
+        diff
+    }
+}
+
+/// Every entry of the journal that has a path, in document order, keeping only the first entry for each path.
+fn entries_by_path(journal: &Journal) -> Vec<(&Path, &JournalEntry)> {
+    let mut entries: Vec<(&Path, &JournalEntry)> = Vec::new();
+
+    journal.for_each_entry(|entry| {
+        if let Some(path) = entry.path.as_deref() {
+            if !entries.iter().any(|(existing, _)| *existing == path) {
+                entries.push((path, entry));
+            }
+        }
+    });
+
+    entries
+}
+
+fn summary(path: &Path, entry: &JournalEntry) -> EntrySummary {
+    EntrySummary {
+        path: path.to_path_buf(),
+        title: entry.title.clone(),
+    }
+}
+
+fn diff_entry(path: &Path, old: &JournalEntry, new: &JournalEntry) -> Option<EntryDiff> {
+    let diff = EntryDiff {
+        path: path.to_path_buf(),
+        title: Change::between(&old.title, &new.title),
+        body: Change::between(&old.body, &new.body),
+        sections: diff_sections(&old.sections, &new.sections),
+    };
+
+    let unchanged = diff.title.is_none() && diff.body.is_none() && diff.sections.is_empty();
+
+    (!unchanged).then_some(diff)
+}
+
+fn diff_sections(old: &[Section], new: &[Section]) -> Vec<SectionDiff> {
+    let old = flatten_sections(old);
+    let new = flatten_sections(new);
+    let mut matched = vec![false; old.len()];
+    let mut occurrences: HashMap<&[String], usize> = HashMap::new();
+    let mut diffs = Vec::new();
+
+    for (path, section) in &new {
+        let occurrence = occurrences.entry(path.as_slice()).or_default();
+        let found = old
+            .iter()
+            .enumerate()
+            .filter(|(_, (old_path, _))| old_path == path)
+            .nth(*occurrence);
+        *occurrence += 1;
+
+        let change = match found {
+            Some((index, (_, old_section))) => {
+                matched[index] = true;
+
+                let level = Change::between(&old_section.level, &section.level);
+                let body = Change::between(&old_section.body, &section.body);
+                let metadata = Change::between(&old_section.metadata, &section.metadata);
+
+                if level.is_none() && body.is_none() && metadata.is_none() {
+                    continue;
+                }
+
+                SectionChange::Modified {
+                    level,
+                    body,
+                    metadata,
+                }
+            }
+            None => SectionChange::Added {
+                level: section.level,
+                body: section.body.clone(),
+                metadata: section.metadata.clone(),
+            },
+        };
+
+        diffs.push(SectionDiff {
+            path: path.clone(),
+            change,
+        });
+    }
+
+    for ((path, _), matched) in old.into_iter().zip(matched) {
+        if !matched {
+            diffs.push(SectionDiff {
+                path,
+                change: SectionChange::Removed,
+            });
+        }
+    }
+
+    diffs
+}
+
+/// Every section at any nesting depth in document order, along with its path.
+fn flatten_sections(sections: &[Section]) -> Vec<(Vec<String>, &Section)> {
+    fn flatten<'a>(
+        sections: &'a [Section],
+        parent: &[String],
+        flattened: &mut Vec<(Vec<String>, &'a Section)>,
+    ) {
+        for section in sections {
+            let mut path = parent.to_vec();
+            path.push(section.title.clone());
+
+            flattened.push((path.clone(), section));
+            flatten(&section.sections, &path, flattened);
+        }
+    }
+
+    let mut flattened = Vec::new();
+    flatten(sections, &[], &mut flattened);
+
+    flattened
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::journal::{JournalItem, SectionMetadata};
+    use std::collections::BTreeMap;
+
+    fn section(title: &str, body: &str, sections: Vec<Section>) -> Section {
+        Section {
+            title: String::from(title),
+            body: String::from(body),
+            sections,
+            ..Default::default()
+        }
+    }
+
+    fn entry(path: &str, sections: Vec<Section>, children: Vec<JournalItem>) -> JournalItem {
+        JournalItem::Entry(JournalEntry {
+            title: String::from(path),
+            path: Some(PathBuf::from(path)),
+            sections,
+            children,
+            ..Default::default()
+        })
+    }
+
+    fn journal(items: Vec<JournalItem>) -> Journal {
+        Journal {
+            title: None,
+            items,
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn reports_added_and_removed_entries() {
+        let old = journal(vec![entry(
+            "town.md",
+            vec![],
+            vec![entry("tavern.md", vec![], vec![])],
+        )]);
+        let new = journal(vec![entry(
+            "town.md",
+            vec![],
+            vec![entry("smithy.md", vec![], vec![])],
+        )]);
+
+        let diff = old.diff(&new);
+
+        assert_eq!(vec![PathBuf::from("smithy.md")], paths(&diff.added));
+        assert_eq!(vec![PathBuf::from("tavern.md")], paths(&diff.removed));
+        assert!(diff.modified.is_empty());
+        assert!(old.diff(&old).is_empty());
+    }
+
+    fn paths(entries: &[EntrySummary]) -> Vec<PathBuf> {
+        entries.iter().map(|entry| entry.path.clone()).collect()
+    }
+
+    #[test]
+    fn reports_section_changes_by_path() {
+        let old = journal(vec![entry(
+            "town.md",
+            vec![
+                section("Tavern", "Quiet", vec![section("Patrons", "Few", vec![])]),
+                section("Smithy", "Hot", vec![]),
+            ],
+            vec![],
+        )]);
+
+        let mut patrons = section("Patrons", "Few", vec![]);
+        patrons.metadata.insert(
+            "npc",
+            SectionMetadata {
+                lang: String::from("toml"),
+                data: String::from("name = \"Borin\"\n"),
+                attributes: Vec::new(),
+            },
+        );
+        let new = journal(vec![entry(
+            "town.md",
+            vec![
+                section("Tavern", "Loud", vec![patrons]),
+                section("Temple", "Holy", vec![]),
+            ],
+            vec![],
+        )]);
+
+        let diff = old.diff(&new);
+        let sections = &diff.modified[0].sections;
+        let paths: Vec<_> = sections
+            .iter()
+            .map(|section| section.path.join("/"))
+            .collect();
+
+        assert_eq!(vec!["Tavern", "Tavern/Patrons", "Temple", "Smithy"], paths);
+        assert_eq!(
+            SectionChange::Modified {
+                level: None,
+                body: Some(Change {
+                    old: String::from("Quiet"),
+                    new: String::from("Loud"),
+                }),
+                metadata: None,
+            },
+            sections[0].change
+        );
+        assert!(matches!(
+            sections[1].change,
+            SectionChange::Modified {
+                metadata: Some(_),
+                body: None,
+                ..
+            }
+        ));
+        assert!(matches!(sections[2].change, SectionChange::Added { .. }));
+        assert_eq!(SectionChange::Removed, sections[3].change);
+    }
+
+    #[test]
+    fn serializes_section_changes_with_a_tag() {
+        let old = journal(vec![entry(
+            "town.md",
+            vec![section("Tavern", "", vec![])],
+            vec![],
+        )]);
+        let new = journal(vec![entry("town.md", vec![], vec![])]);
+
+        let json = serde_json::to_value(old.diff(&new)).expect("should serialize");
+
+        assert_eq!(
+            serde_json::json!({
+                "added": [],
+                "removed": [],
+                "modified": [{
+                    "path": "town.md",
+                    "sections": [{ "path": ["Tavern"], "change": "removed" }],
+                }],
+            }),
+            json
+        );
+    }
+}
@@ -1,13 +1,20 @@
 use anyhow::Context;
+use indexmap::IndexMap;
 use pulldown_cmark::{Event, HeadingLevel, Tag};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, path::PathBuf};
+use sha2::{Digest, Sha256};
+use std::{fs, ops::Range, path::PathBuf, slice};
 
 use crate::{
-    cmark::{CMarkParser, EventIteratorExt as _},
+    cmark::{
+        decode_source, expand_tabs, plain_text, push_escaped, push_html, CMarkParser,
+        EventIteratorExt as _, SlugStrategy, Slugger,
+    },
     error::Result,
 };
 
+use super::{extract_metadata, JournalItem, DEFAULT_METADATA_SENTINEL};
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub enum SectionLevel {
     #[default]
@@ -32,11 +39,62 @@ impl From<HeadingLevel> for SectionLevel {
     }
 }
 
+impl From<SectionLevel> for HeadingLevel {
+    fn from(value: SectionLevel) -> Self {
+        match value {
+            SectionLevel::H1 => HeadingLevel::H1,
+            SectionLevel::H2 => HeadingLevel::H2,
+            SectionLevel::H3 => HeadingLevel::H3,
+            SectionLevel::H4 => HeadingLevel::H4,
+            SectionLevel::H5 => HeadingLevel::H5,
+            SectionLevel::H6 => HeadingLevel::H6,
+        }
+    }
+}
+
+impl TryFrom<u8> for SectionLevel {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            1 => Ok(SectionLevel::H1),
+            2 => Ok(SectionLevel::H2),
+            3 => Ok(SectionLevel::H3),
+            4 => Ok(SectionLevel::H4),
+            5 => Ok(SectionLevel::H5),
+            6 => Ok(SectionLevel::H6),
+            _ => anyhow::bail!("Section level must be between 1 and 6, found {value}"),
+        }
+    }
+}
+
+impl SectionLevel {
+    /// The heading level as a number from 1 to 6.
+    pub fn as_u8(&self) -> u8 {
+        *self as u8
+    }
+
+    /// The next deeper heading level, such as H2 for H1, or `None` for H6.
+    pub fn next(&self) -> Option<SectionLevel> {
+        SectionLevel::try_from(self.as_u8() + 1).ok()
+    }
+
+    /// The next shallower heading level, such as H1 for H2, or `None` for H1.
+    pub fn prev(&self) -> Option<SectionLevel> {
+        SectionLevel::try_from(self.as_u8() - 1).ok()
+    }
+
+    /// Every heading level, from H1 to H6.
+    pub fn all() -> impl Iterator<Item = SectionLevel> {
+        std::iter::successors(Some(SectionLevel::H1), SectionLevel::next)
+    }
+}
+
 /// A `Section` represents all text following a heading in a `JournalEntry`.
 /// Any headings that have a lower-level than the `Section` that follow the section
 /// will be nested inside this section. Any `Section` with the same level as the
 /// current section will be a sibling section in the parent `Section` or `JournalEntry`.
-#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize, Eq)]
 pub struct Section {
     /// The title of the section as provided by the heading.
     pub title: String,
@@ -46,15 +104,327 @@ pub struct Section {
     /// or sibling sections.
     pub body: String,
     /// Metadata associated with a section.
-    pub metadata: HashMap<String, SectionMetadata>,
+    pub metadata: Metadata,
     /// Any child sections that are nested below the current section.
     pub sections: Vec<Section>,
+    /// The byte range of the section in the text it was parsed from, from the start of its heading to the end of
+    /// its last nested section. As entries are parsed after preprocessing, the range is within the preprocessed
+    /// text, which only matches the file when no preprocessor changed it. `None` for sections that were not
+    /// parsed. Spans are ignored when comparing sections.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<Range<usize>>,
+}
+
+impl PartialEq for Section {
+    fn eq(&self, other: &Self) -> bool {
+        self.title == other.title
+            && self.level == other.level
+            && self.body == other.body
+            && self.metadata == other.metadata
+            && self.sections == other.sections
+    }
+}
+
+impl Section {
+    /// The deepest nesting of sections starting at, and including, this section.
+    pub fn depth(&self) -> usize {
+        1 + self.sections.iter().map(Section::depth).max().unwrap_or(0)
+    }
+
+    /// The number of words in the section's title and body, and in all nested sections. Words are runs of
+    /// non-whitespace characters in the text of the Markdown, so markup such as `*` or link destinations is not
+    /// counted. Metadata is not counted.
+    pub fn word_count(&self) -> usize {
+        count_words(&self.title)
+            + count_words(&self.body)
+            + self.sections.iter().map(Section::word_count).sum::<usize>()
+    }
+
+    /// The text of the section's body without Markdown syntax, for search indexes and summaries. The text of
+    /// inline markup, code spans and link text is kept, while link destinations and image markup other than the
+    /// alt text are dropped. Blocks and lines are separated by a single space. The title and nested sections are
+    /// not included.
+    pub fn plain_text(&self) -> String {
+        plain_text(&self.body)
+    }
+
+    /// The anchor of the section within its entry's page: its `slug` metadata if it has one, otherwise a slug of
+    /// its title with the slugger's strategy, made unique within the page by the slugger.
+    pub fn anchor(&self, slugger: &mut Slugger) -> String {
+        match self.metadata.get("slug") {
+            Some(slug) => slugger.unique(String::from(slug.trimmed())),
+            None => slugger.slug(&self.title),
+        }
+    }
+
+    /// Render the section to an HTML fragment: its heading, with the section's anchor as its id, followed by its
+    /// body and all nested sections. Metadata is not rendered.
+    pub fn to_html(&self) -> String {
+        self.to_html_with_strategy(SlugStrategy::default())
+    }
+
+    /// Render the section to an HTML fragment like `to_html`, slugifying the titles of sections without `slug`
+    /// metadata with the given strategy.
+    pub fn to_html_with_strategy(&self, strategy: SlugStrategy) -> String {
+        let mut html = String::new();
+        self.write_html(&mut html, &mut Slugger::with_strategy(strategy));
+
+        html
+    }
+
+    fn write_html(&self, html: &mut String, slugger: &mut Slugger) {
+        let level = self.level.as_u8();
+
+        html.push_str(&format!("<h{level} id=\""));
+        push_escaped(html, &self.anchor(slugger));
+        html.push_str("\">");
+        push_escaped(html, &self.title);
+        html.push_str(&format!("</h{level}>\n"));
+        push_html(html, &self.body);
+
+        for section in &self.sections {
+            section.write_html(html, slugger);
+        }
+    }
+
+    /// Reconstruct the Markdown source of the section: its heading, body, metadata blocks and all nested
+    /// sections. Metadata blocks are emitted as fenced code blocks tagged `lang,metadata,key` (or `metadata,key`
+    /// when there is no language) following the body, since their original position within the body is not kept.
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        self.write_markdown(&mut markdown);
+
+        markdown.truncate(markdown.trim_end().len());
+        markdown
+    }
+
+    fn write_markdown(&self, markdown: &mut String) {
+        markdown.push_str(&"#".repeat(self.level.as_u8().into()));
+        markdown.push(' ');
+        markdown.push_str(&self.title);
+        markdown.push_str("\n\n");
+
+        if !self.body.trim().is_empty() {
+            markdown.push_str(self.body.trim_end());
+            markdown.push_str("\n\n");
+        }
+
+        for (key, metadata) in self.metadata.iter() {
+            metadata.write_markdown(key, markdown);
+        }
+
+        for section in &self.sections {
+            section.write_markdown(markdown);
+        }
+    }
+
+    /// Write the Markdown of the section's heading, body and nested sections, without its metadata, for the
+    /// entry's excerpt.
+    fn write_text(&self, markdown: &mut String) {
+        markdown.push_str(&"#".repeat(self.level.as_u8().into()));
+        markdown.push(' ');
+        markdown.push_str(&self.title);
+        markdown.push_str("\n\n");
+        markdown.push_str(self.body.trim_end());
+        markdown.push_str("\n\n");
+
+        for section in &self.sections {
+            section.write_text(markdown);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SectionMetadata {
     pub lang: String,
+    /// The raw contents of the fenced block, exactly as the Markdown parser reports them: every line of the block
+    /// followed by a newline, including the last, so that non-empty data always ends with `\n`. Indentation of
+    /// the fence itself is removed from each line, but no other whitespace is added or removed, so leading blank
+    /// lines and trailing spaces are kept. An empty block has empty data. See `trimmed` for the data without
+    /// surrounding whitespace.
     pub data: String,
+    /// Any additional tokens from the fenced block's info string following the key, such as `hidden`.
+    #[serde(default)]
+    pub attributes: Vec<String>,
+}
+
+impl SectionMetadata {
+    fn write_markdown(&self, key: &str, markdown: &mut String) {
+        let longest_run = self
+            .data
+            .split(|character| character != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or_default();
+        let fence = "`".repeat(3.max(longest_run + 1));
+        let tag = [self.lang.as_str(), "metadata", key]
+            .into_iter()
+            .skip_while(|part| part.is_empty())
+            .chain(self.attributes.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        markdown.push_str(&fence);
+        markdown.push_str(&tag);
+        markdown.push('\n');
+        markdown.push_str(&self.data);
+
+        if !self.data.ends_with('\n') {
+            markdown.push('\n');
+        }
+
+        markdown.push_str(&fence);
+        markdown.push_str("\n\n");
+    }
+
+    /// The data without leading and trailing whitespace, including the newline that ends every non-empty block,
+    /// for reading single values such as a slug.
+    pub fn trimmed(&self) -> &str {
+        self.data.trim()
+    }
+
+    /// Check whether the metadata block was tagged with the given attribute.
+    pub fn has_attribute(&self, attribute: &str) -> bool {
+        self.attributes.iter().any(|attr| attr == attribute)
+    }
+}
+
+/// The metadata blocks attached to a `Section`, grouped by key.
+/// Keys are kept in the order they were first inserted and a key may hold multiple blocks, which are kept
+/// in the order they were inserted. Metadata is serialized in that same order, so serializing a journal twice
+/// gives the same output.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Metadata(IndexMap<String, Vec<SectionMetadata>>);
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the first metadata block stored under `key`.
+    pub fn get(&self, key: &str) -> Option<&SectionMetadata> {
+        self.get_all(key).first()
+    }
+
+    /// Get every metadata block stored under `key`, in insertion order.
+    pub fn get_all(&self, key: &str) -> &[SectionMetadata] {
+        self.0.get(key).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Append a metadata block under `key`, keeping any blocks already stored under it.
+    pub fn insert(&mut self, key: impl Into<String>, metadata: SectionMetadata) {
+        self.0.entry(key.into()).or_default().push(metadata);
+    }
+
+    /// Remove and return every metadata block stored under `key`.
+    pub fn remove(&mut self, key: &str) -> Vec<SectionMetadata> {
+        self.0.shift_remove(key).unwrap_or_default()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// Iterate over the distinct keys in insertion order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    /// Iterate over every metadata block along with its key, grouped by key in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &SectionMetadata)> {
+        self.0
+            .iter()
+            .flat_map(|(key, blocks)| blocks.iter().map(move |block| (key.as_str(), block)))
+    }
+
+    /// The total number of metadata blocks across all keys.
+    pub fn len(&self) -> usize {
+        self.0.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Extend<(String, SectionMetadata)> for Metadata {
+    fn extend<T: IntoIterator<Item = (String, SectionMetadata)>>(&mut self, iter: T) {
+        for (key, metadata) in iter {
+            self.insert(key, metadata);
+        }
+    }
+}
+
+impl Extend<Metadata> for Metadata {
+    fn extend<T: IntoIterator<Item = Metadata>>(&mut self, iter: T) {
+        for metadata in iter {
+            for (key, blocks) in metadata.0 {
+                self.0.entry(key).or_default().extend(blocks);
+            }
+        }
+    }
+}
+
+impl FromIterator<(String, SectionMetadata)> for Metadata {
+    fn from_iter<T: IntoIterator<Item = (String, SectionMetadata)>>(iter: T) -> Self {
+        let mut metadata = Self::new();
+        metadata.extend(iter);
+
+        metadata
+    }
+}
+
+/// The marker, as the content of an HTML comment, that ends the excerpt of an entry.
+const EXCERPT_MARKER: &str = "more";
+const DEFAULT_EXCERPT_LENGTH: usize = 200;
+const ELLIPSIS: char = '…';
+const DEFAULT_TAB_WIDTH: usize = 4;
+
+/// Options controlling how a `JournalEntry` body is parsed into sections.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ParseOptions {
+    /// Shift every heading in an entry so that the shallowest heading becomes H1, preserving the relative
+    /// depth of all other headings. For example an entry using only `###` and `####` headings is parsed
+    /// as if it used `#` and `##`.
+    ///
+    /// Sections are nested by comparing heading levels, so a heading is only ever nested below a preceding
+    /// heading with a lower level. A deeper-then-shallower sequence such as `###` followed by `#` always
+    /// produces sibling sections, with or without normalization.
+    pub normalize_heading_levels: bool,
+    /// The maximum depth sections may be nested to, where a top level section has a depth of 1.
+    /// Parsing fails with an error if an entry nests sections any deeper. Unlimited by default.
+    pub max_depth: Option<usize>,
+    /// The most characters of an entry's plain text used for its excerpt when the entry has no `<!-- more -->`
+    /// marker. See `JournalEntry::excerpt`. Defaults to 200, and 0 leaves entries without a marker unexcerpted.
+    pub excerpt_length: usize,
+    /// Replace the tabs of an entry with spaces up to the next tab stop when it is loaded, before anything else
+    /// reads it, so that tab-indented code blocks and tables parse and round trip through `stringify` the same way.
+    /// The tabs inside code blocks are expanded as well. See `cmark::expand_tabs`.
+    pub expand_tabs: bool,
+    /// The number of columns between tab stops for `expand_tabs`. Defaults to 4, the width CommonMark uses.
+    pub tab_width: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            normalize_heading_levels: false,
+            max_depth: None,
+            excerpt_length: DEFAULT_EXCERPT_LENGTH,
+            expand_tabs: false,
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
+}
+
+/// Hash the raw contents of an entry's file for `JournalEntry::content_hash`.
+fn hash_content(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
 }
 
 /// A `JournalEntry` is an in-memory representation of a single Markdown file on disk.
@@ -70,44 +440,317 @@ pub struct JournalEntry {
     pub sections: Vec<Section>,
     /// The location of this journal entry relative to the `JOURNAL.md` file.
     pub path: Option<PathBuf>,
+    /// The directory of the entry's file, relative to the journal root, which paths written in the entry, such as
+    /// those of `{{#include}}` directives and images, are relative to. Set when the journal is loaded, as the
+    /// entry's path does not tell which location of a `journal.source` list it was loaded from, and `None` for
+    /// entries without a file, such as drafts. See `Config::resolve_entry_dir`.
+    #[serde(default)]
+    pub source_dir: Option<PathBuf>,
     /// The nesting level of the journal entry (up to H6).
     pub level: u8,
+    /// The journal items nested below this entry in the table of contents, in document order.
+    #[serde(default)]
+    pub children: Vec<JournalItem>,
+    /// The normalized tags of the journal entry, as set by the `tags` transformer.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The SHA-256 hash of the entry's file, as lowercase hex, computed from the raw bytes when the entry is
+    /// loaded. Renderers can compare it between builds to skip regenerating unchanged pages. Note that the hash
+    /// does not cover files pulled in by directives such as `{{#include}}`. `None` for entries that were not
+    /// loaded from a file.
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// A short plain text excerpt of the entry for index cards and link previews, set when the entry is parsed.
+    /// It is the text before a `<!-- more -->` marker when the entry has one, or else the start of the entry's
+    /// plain text, cut at a word to fit `excerpt_length` of the `[build.parse]` options and followed by an
+    /// ellipsis. Whitespace is collapsed to single spaces. `None` for entries without any text. It is taken again
+    /// once metadata has been extracted and once secret sections have been redacted, so that neither shows up in
+    /// it.
+    #[serde(default)]
+    pub excerpt: Option<String>,
+    /// The hierarchical number of the entry, such as `1.2`, as set by the `numbering` transformer.
+    #[serde(default)]
+    pub number: Option<String>,
+    /// The title of the chapter the entry belongs to: the closest chapter title before it in the table of
+    /// contents, for renderers building breadcrumbs. Nested entries belong to the chapter of their parent. Set when
+    /// the journal is loaded, and `None` for entries before the first chapter title.
+    #[serde(default)]
+    pub chapter: Option<String>,
+    /// The number of levels the entry's headings are shifted by when it is parsed, as set by the entry's link in
+    /// the table of contents. A positive offset makes headings deeper, and shifted headings never go past H1 or
+    /// H6. The offset is applied after `normalize_heading_levels`, and sections are nested by their shifted levels.
+    #[serde(default)]
+    pub heading_offset: i8,
+    /// Whether the entry is front matter, body or back matter, for renderers that leave front and back matter out
+    /// of numbering and the like. Set when the journal is loaded, as described by `Matter`.
+    #[serde(default)]
+    pub matter: Matter,
+    /// The title attribute of the entry's link in the table of contents, for tooltips in navigation. Set when the
+    /// journal is loaded, and `None` for links without one.
+    #[serde(default)]
+    pub link_title: Option<String>,
+    /// Whether the entry is a draft, linked from the table of contents without a location such as `[Title]()`,
+    /// which has not been written yet. Drafts have no path nor body, and are left out of the journal handed to
+    /// renderers unless `build.render-drafts` is set.
+    #[serde(default)]
+    pub draft: bool,
+}
+
+/// The part of the journal an entry belongs to, as told by its place in the table of contents. In a table of
+/// contents with chapter titles, the entries before the first chapter title are front matter, such as a foreword,
+/// and the entries after a separator that follows the last chapter title are back matter, such as appendices:
+///
+/// ```markdown
+/// - [Foreword](./foreword.md)
+///
+/// # Rules
+///
+/// - [Combat](./combat.md)
+///
+/// ---
+///
+/// - [Appendix](./appendix.md)
+/// ```
+///
+/// Every other entry, and every entry of a table of contents without chapter titles, is body matter. Nested
+/// entries belong to the same part as the entry they are nested below.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Matter {
+    Front,
+    #[default]
+    Body,
+    Back,
 }
 
 impl JournalEntry {
+    /// A top level journal entry with the given title and unparsed Markdown body, for assembling a journal in
+    /// memory. The entry has no path, so directives that read files relative to it fail.
+    pub fn new(title: impl Into<String>, body: impl Into<String>) -> JournalEntry {
+        JournalEntry {
+            title: title.into(),
+            body: Some(body.into()),
+            level: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Load the journal entry at `path`, relative to `source_path`, without parsing it. An empty `title` falls back
+    /// to the file stem of the path.
     pub fn load(
         title: String,
         source_path: impl Into<PathBuf>,
         path: impl Into<PathBuf>,
         level: u8,
+    ) -> Result<JournalEntry> {
+        Self::load_with_options(title, source_path, path, level, &ParseOptions::default())
+    }
+
+    /// Load the journal entry like `load`, applying the options that concern its source, such as `expand_tabs`.
+    pub fn load_with_options(
+        title: String,
+        source_path: impl Into<PathBuf>,
+        path: impl Into<PathBuf>,
+        level: u8,
+        options: &ParseOptions,
     ) -> Result<JournalEntry> {
         let source_path = source_path.into();
         let path = path.into();
         let file_path = source_path.join(&path);
-        let body = fs::read_to_string(&file_path)
+        let bytes = fs::read(&file_path)
             .with_context(|| format!("Failed to open journal entry: {}", file_path.display()))?;
+        let content_hash = hash_content(&bytes);
+        let mut body = decode_source(&bytes)
+            .with_context(|| format!("Failed to open journal entry: {}", file_path.display()))?;
+
+        if options.expand_tabs {
+            body = expand_tabs(&body, options.tab_width);
+        }
+
+        let title = match path.file_stem() {
+            Some(stem) if title.trim().is_empty() => stem.to_string_lossy().into_owned(),
+            _ => title,
+        };
 
         let document = Self {
             title,
             path: Some(path),
+            source_dir: None,
             body: Some(body),
             sections: Vec::new(),
             level,
+            children: Vec::new(),
+            tags: Vec::new(),
+            content_hash: Some(content_hash),
+            excerpt: None,
+            number: None,
+            chapter: None,
+            heading_offset: 0,
+            matter: Matter::Body,
+            link_title: None,
+            draft: false,
         };
 
         Ok(document)
     }
 
-    pub fn parse(mut self) -> Result<JournalEntry> {
+    pub fn parse(self) -> Result<JournalEntry> {
+        self.parse_with_options(&ParseOptions::default())
+    }
+
+    /// Parse the journal entry's body into sections using the provided options.
+    pub fn parse_with_options(mut self, options: &ParseOptions) -> Result<JournalEntry> {
         let Some(body) = self.body else {
             return Ok(self);
         };
 
-        let parser = JournalEntryParser::new(&body);
-        let (body, sections) = parser.parse()?;
+        let parser = JournalEntryParser::new(&body, options, self.heading_offset);
+        let (parsed_body, sections) = parser.parse()?;
         self.sections.extend(sections);
+        self.body = parsed_body;
+        self.update_excerpt(options.excerpt_length);
+
+        Ok(self)
+    }
+
+    /// Replace the journal entry's body and sections with those parsed from the Markdown, such as after a
+    /// transformer rewrote the Markdown from `to_markdown`, so that the sections never fall out of step with the
+    /// body. The title, path and level of the entry are kept. The headings of the Markdown already have their final
+    /// levels, so they are not shifted by the entry's heading offset. The entry is left unchanged if the Markdown
+    /// fails to parse.
+    pub fn set_body(&mut self, body: String) -> Result<()> {
+        self.set_body_with_options(body, &ParseOptions::default())
+    }
+
+    /// Replace the journal entry's body and sections like `set_body`, parsing the Markdown with the given options,
+    /// such as `ctx.config.build.parse` in a transformer. Heading levels are only normalized for an entry without a
+    /// heading offset, as those of an entry with one were normalized before they were shifted.
+    pub fn set_body_with_options(&mut self, body: String, options: &ParseOptions) -> Result<()> {
+        let options = ParseOptions {
+            normalize_heading_levels: options.normalize_heading_levels && self.heading_offset == 0,
+            ..options.clone()
+        };
+        let (parsed_body, sections) = JournalEntryParser::new(&body, &options, 0).parse()?;
+
+        self.body = parsed_body;
+        self.sections = sections;
+        self.update_excerpt(options.excerpt_length);
+
+        Ok(())
+    }
+
+    /// Set the excerpt of the journal entry from its current body and sections, as described by
+    /// `JournalEntry::excerpt`, such as after a transformer removed metadata or secret sections that an excerpt
+    /// taken earlier would still hold. The metadata of sections is never part of the excerpt.
+    pub fn update_excerpt(&mut self, excerpt_length: usize) {
+        let mut markdown = String::new();
+
+        if let Some(ref body) = self.body {
+            markdown.push_str(body.trim_end());
+            markdown.push_str("\n\n");
+        }
+
+        for section in &self.sections {
+            section.write_text(&mut markdown);
+        }
+
+        self.excerpt = excerpt(&markdown, excerpt_length);
+    }
+
+    /// The deepest nesting of sections in the journal entry, where an entry with only top level sections
+    /// has a depth of 1 and an entry without sections has a depth of 0.
+    pub fn depth(&self) -> usize {
+        self.sections.iter().map(Section::depth).max().unwrap_or(0)
+    }
+
+    /// The number of words in the journal entry's top level body and sections, counted as with
+    /// `Section::word_count`. The entry's title and nested entries are not counted.
+    pub fn word_count(&self) -> usize {
+        self.body.as_deref().map_or(0, count_words)
+            + self.sections.iter().map(Section::word_count).sum::<usize>()
+    }
+
+    /// The Markdown of the first paragraph of the journal entry's body, as renderers style it as a lead. Only a
+    /// paragraph before the first heading counts, and paragraphs in block quotes, lists and other blocks are
+    /// skipped. `None` for entries without a body or whose body has no such paragraph, such as one that is only
+    /// headings.
+    pub fn lead(&self) -> Option<&str> {
+        let body = self.body.as_deref()?;
 
-        Ok(Self { body, ..self })
+        lead_range(body).map(|range| body[range].trim())
+    }
+
+    /// Parse the journal entry like `parse`, additionally extracting metadata blocks from every section
+    /// the same way the `metadata` transformer does, using the default `metadata` sentinel.
+    pub fn parse_with_metadata(self) -> Result<JournalEntry> {
+        let mut entry = self.parse()?;
+        entry.try_for_each_mut(|section| extract_metadata(section, DEFAULT_METADATA_SENTINEL))?;
+        entry.update_excerpt(ParseOptions::default().excerpt_length);
+
+        Ok(entry)
+    }
+
+    /// Reconstruct the Markdown source of the journal entry from its top level body and sections.
+    /// See `Section::to_markdown` for how sections are written.
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+
+        if let Some(body) = self.body.as_deref().filter(|body| !body.trim().is_empty()) {
+            markdown.push_str(body.trim_end());
+            markdown.push_str("\n\n");
+        }
+
+        for section in &self.sections {
+            section.write_markdown(&mut markdown);
+        }
+
+        markdown.truncate(markdown.trim_end().len());
+        markdown
+    }
+
+    /// Render the top level body and sections of the journal entry to an HTML fragment, as the `html` renderer
+    /// writes it into the entry's page. See `Section::to_html` for how sections are written, with their anchors
+    /// unique across the whole entry. The entry's title and nested entries are not rendered.
+    pub fn to_html(&self) -> String {
+        self.to_html_with_strategy(SlugStrategy::default())
+    }
+
+    /// Render the journal entry to an HTML fragment like `to_html`, slugifying the titles of sections without
+    /// `slug` metadata with the given strategy.
+    pub fn to_html_with_strategy(&self, strategy: SlugStrategy) -> String {
+        self.to_html_with_slugger(&mut Slugger::with_strategy(strategy))
+    }
+
+    /// Render the journal entry to an HTML fragment like `to_html`, making the section anchors with `slugger`, so
+    /// that they are unique among the anchors of several entries rendered into one page.
+    pub fn to_html_with_slugger(&self, slugger: &mut Slugger) -> String {
+        let mut html = String::new();
+
+        if let Some(ref body) = self.body {
+            push_html(&mut html, body);
+        }
+
+        for section in &self.sections {
+            section.write_html(&mut html, slugger);
+        }
+
+        html
+    }
+
+    /// Iterate over every section in the journal entry at any nesting depth, in document order, where each
+    /// section is immediately followed by the sections nested below it.
+    pub fn iter_sections(&self) -> Sections<'_> {
+        Sections {
+            stack: vec![self.sections.iter()],
+        }
+    }
+
+    /// Iterate over every section, at any nesting depth, whose heading level is `level`, in document order.
+    /// This filters on the Markdown heading level (`#`, `##`, ...) of each section, not on how deeply it is
+    /// nested, so an `##` heading that is not nested below any `#` heading is still yielded for `H2`.
+    pub fn sections_at_level(&self, level: SectionLevel) -> impl Iterator<Item = &Section> {
+        self.iter_sections()
+            .filter(move |section| section.level == level)
     }
 
     /// Iterate over a flattened representation of all sections in a journal entry, providing a mutable reference
@@ -155,17 +798,54 @@ where
     Ok(())
 }
 
+/// An iterator over every section of a journal entry in document order. See `JournalEntry::iter_sections`.
+pub struct Sections<'a> {
+    stack: Vec<slice::Iter<'a, Section>>,
+}
+
+impl<'a> Iterator for Sections<'a> {
+    type Item = &'a Section;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let sections = self.stack.last_mut()?;
+            let Some(section) = sections.next() else {
+                self.stack.pop();
+                continue;
+            };
+
+            self.stack.push(section.sections.iter());
+
+            return Some(section);
+        }
+    }
+}
+
 struct JournalEntryParser<'a> {
     parser: CMarkParser<'a>,
+    level_shift: i32,
+    max_depth: Option<usize>,
 }
 
 impl<'a> JournalEntryParser<'a> {
-    fn new(source: &'a str) -> Self {
+    fn new(source: &'a str, options: &ParseOptions, heading_offset: i8) -> Self {
+        let normalization = if options.normalize_heading_levels {
+            min_heading_level(source).map_or(0, |level| level as i32 - 1)
+        } else {
+            0
+        };
+
         Self {
             parser: CMarkParser::new(source),
+            level_shift: i32::from(heading_offset) - normalization,
+            max_depth: options.max_depth,
         }
     }
 
+    fn section_level(&self, level: HeadingLevel) -> SectionLevel {
+        shift_level(level, self.level_shift)
+    }
+
     fn parse(mut self) -> Result<(Option<String>, Vec<Section>)> {
         let body = self.parse_body()?;
         let sections = self.parse_sections()?;
@@ -176,21 +856,12 @@ impl<'a> JournalEntryParser<'a> {
     fn parse_body(&mut self) -> Result<Option<String>> {
         let mut events = Vec::new();
 
-        loop {
-            match self.parser.peek_event() {
-                Some(Event::Start(Tag::Heading(..))) => break,
-                Some(_) => {
-                    let event = self
-                        .parser
-                        .next_event()
-                        .expect("event was empty, when it shouldn't have been");
-                    events.push(event);
-                }
-                None => {
-                    self.parser.next_event();
-                    break;
-                }
-            }
+        // NOTE: The body ends at the first heading, or at the end of an entry without headings, which may be empty.
+        while !matches!(
+            self.parser.peek_event(),
+            Some(Event::Start(Tag::Heading(..))) | None
+        ) {
+            events.extend(self.parser.next_event());
         }
 
         let body = events
@@ -208,10 +879,12 @@ impl<'a> JournalEntryParser<'a> {
         loop {
             match self.parser.next_event() {
                 Some(Event::Start(Tag::Heading(heading_level, ..))) => {
-                    let section = self.parse_section(heading_level)?;
+                    let section = self.parse_section(heading_level, 1)?;
                     sections.push(section)
                 }
-                Some(_) => (), // TODO: Ignore for now!
+                // NOTE: Only headings are left here, as the body and the sections consume every other event, raw
+                // HTML included.
+                Some(_) => (),
                 None => break,
             }
         }
@@ -219,7 +892,9 @@ impl<'a> JournalEntryParser<'a> {
         Ok(sections)
     }
 
-    fn parse_section(&mut self, level: HeadingLevel) -> Result<Section> {
+    fn parse_section(&mut self, level: HeadingLevel, depth: usize) -> Result<Section> {
+        let start = self.parser.offset();
+        let position = self.parser.position();
         let title = self
             .parser
             .iter_until_and_consume(|event| {
@@ -228,7 +903,17 @@ impl<'a> JournalEntryParser<'a> {
                     Event::End(Tag::Heading(..))
                 }
             })
-            .stringify()?;
+            .stringify()
+            .with_context(|| {
+                format!("Failed to stringify the heading of a section at {position}")
+            })?;
+
+        if let Some(max_depth) = self.max_depth.filter(|max_depth| depth > *max_depth) {
+            anyhow::bail!(
+                "Section \"{title}\" at {} is nested {depth} levels deep, exceeding the maximum depth of {max_depth}",
+                self.parser.position()
+            );
+        }
 
         let body = self
             .parser
@@ -238,16 +923,26 @@ impl<'a> JournalEntryParser<'a> {
                     Event::Start(Tag::Heading(..))
                 }
             })
-            .stringify()?;
+            .stringify()
+            .with_context(|| {
+                format!(
+                    "Failed to stringify the body of section \"{title}\" at {position} (bytes {start}..{})",
+                    self.parser.offset()
+                )
+            })?;
 
         let mut sections = Vec::new();
+        let level_shift = self.level_shift;
 
         loop {
             match self.parser.peek_event() {
-                Some(Event::Start(Tag::Heading(heading_level, ..))) if *heading_level > level => {
+                Some(Event::Start(Tag::Heading(heading_level, ..)))
+                    if shift_level(*heading_level, level_shift)
+                        > shift_level(level, level_shift) =>
+                {
                     let heading_level = *heading_level;
                     self.parser.next_event();
-                    sections.push(self.parse_section(heading_level)?);
+                    sections.push(self.parse_section(heading_level, depth + 1)?);
                 }
                 Some(_) => break,
                 None => break,
@@ -256,38 +951,218 @@ impl<'a> JournalEntryParser<'a> {
 
         Ok(Section {
             title,
-            level: level.into(),
+            level: self.section_level(level),
             body,
-            metadata: HashMap::new(),
+            metadata: Metadata::new(),
             sections,
+            span: Some(start..self.parser.next_offset()),
         })
     }
 }
 
+fn count_words(markdown: &str) -> usize {
+    plain_text(markdown).split_whitespace().count()
+}
+
+/// The source range of the first top level paragraph before any heading, as described by `JournalEntry::lead`.
+fn lead_range(source: &str) -> Option<Range<usize>> {
+    let mut parser = CMarkParser::new(source);
+    let mut depth = 0usize;
+
+    while let Some(event) = parser.next_event() {
+        match event {
+            Event::Start(Tag::Heading(..)) if depth == 0 => return None,
+            Event::Start(Tag::Paragraph) if depth == 0 => {
+                let start = parser.offset();
+                parser
+                    .iter_until_and_consume(|event| *event == Event::End(Tag::Paragraph))
+                    .for_each(drop);
+
+                return Some(start..parser.next_offset());
+            }
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// The excerpt of an entry's source, as described by `JournalEntry::excerpt`.
+fn excerpt(source: &str, length: usize) -> Option<String> {
+    let text = match excerpt_marker_offset(source) {
+        Some(offset) => plain_text(&source[..offset]),
+        None if length == 0 => return None,
+        None => truncate_text(&plain_text(source), length),
+    };
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    (!text.is_empty()).then_some(text)
+}
+
+/// The byte offset of the first `<!-- more -->` marker in the source, ignoring any in code.
+fn excerpt_marker_offset(source: &str) -> Option<usize> {
+    let mut parser = CMarkParser::new(source);
+
+    while let Some(event) = parser.next_event() {
+        let Event::Html(html) = event else {
+            continue;
+        };
+
+        let is_marker = html
+            .trim()
+            .strip_prefix("<!--")
+            .and_then(|comment| comment.strip_suffix("-->"))
+            .is_some_and(|comment| comment.trim() == EXCERPT_MARKER);
+
+        if is_marker {
+            return Some(parser.offset());
+        }
+    }
+
+    None
+}
+
+/// Cut the text to at most `length` characters, backing up to the end of a word, followed by an ellipsis. Text
+/// that already fits is left as it is.
+fn truncate_text(text: &str, length: usize) -> String {
+    let Some((end, _)) = text.char_indices().nth(length) else {
+        return String::from(text);
+    };
+
+    let mut cut = &text[..end];
+    if !text[end..].starts_with(char::is_whitespace) {
+        cut = cut
+            .rsplit_once(char::is_whitespace)
+            .map_or(cut, |(cut, _)| cut);
+    }
+
+    format!("{}{ELLIPSIS}", cut.trim_end())
+}
+
+/// The section level of a heading shifted by `level_shift` levels, kept between H1 and H6.
+fn shift_level(level: HeadingLevel, level_shift: i32) -> SectionLevel {
+    let level = (level as i32 + level_shift).clamp(1, 6);
+    let level = HeadingLevel::try_from(level as usize)
+        .expect("heading levels are clamped to H1 through H6");
+
+    level.into()
+}
+
+fn min_heading_level(source: &str) -> Option<HeadingLevel> {
+    let mut parser = CMarkParser::new(source);
+
+    std::iter::from_fn(|| parser.next_event())
+        .filter_map(|event| match event {
+            Event::Start(Tag::Heading(level, ..)) => Some(level),
+            _ => None,
+        })
+        .min()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     #[test]
-    fn parses_top_level_body() {
-        let input = "Top level body.\nWith multiple lines.\n\nIncluding heard breaks.";
+    fn renders_sections_with_unique_anchors() {
         let entry = JournalEntry {
-            body: Some(String::from(input)),
+            body: Some(String::from(
+                "Intro\n\n# Notes\n\nBody\n\n## Notes\n\nNested *body*\n\n# Custom <Tag>\n",
+            )),
             ..Default::default()
         };
-        let entry = entry.parse().expect("should parse");
+        let mut entry = entry.parse().expect("should parse");
+        entry.sections[1].metadata.insert(
+            "slug",
+            SectionMetadata {
+                lang: String::new(),
+                data: String::from("my-anchor\n"),
+                attributes: Vec::new(),
+            },
+        );
 
-        let expected = Some(String::from(input));
+        let expected = "<p>Intro</p>\n\
+            <h1 id=\"notes\">Notes</h1>\n<p>Body</p>\n\
+            <h2 id=\"notes-1\">Notes</h2>\n<p>Nested <em>body</em></p>\n\
+            <h1 id=\"my-anchor\">Custom &lt;Tag&gt;</h1>\n";
 
-        assert_eq!(expected, entry.body);
+        assert_eq!(expected, entry.to_html());
+        assert_eq!(
+            "<h2 id=\"notes\">Notes</h2>\n<p>Nested <em>body</em></p>\n",
+            entry.sections[0].sections[0].to_html()
+        );
     }
 
     #[test]
-    fn parses_top_level_sections() {
-        let input = "# First Top Level
-# Second Top Level";
-        let entry = JournalEntry {
-            body: Some(String::from(input)),
+    fn renders_sections_to_html_with_a_slug_strategy() {
+        let entry = JournalEntry::new("Keep", "# Great Hall\n\nStone")
+            .parse()
+            .expect("should parse");
+
+        assert_eq!(
+            "<h1 id=\"great_hall\">Great Hall</h1>\n<p>Stone</p>\n",
+            entry.sections[0].to_html_with_strategy(SlugStrategy::Underscore)
+        );
+    }
+
+    #[test]
+    fn renders_tables_strikethrough_and_task_lists_to_html() {
+        let entry = JournalEntry::new(
+            "Goblins",
+            "| Name | HP |\n| --- | --- |\n| Snik | 7 |\n\n# Loot\n\n~~Gold~~ Copper\n\n- [x] Looted\n",
+        )
+        .parse()
+        .expect("should parse");
+        let html = entry.to_html();
+
+        assert!(html.contains("<table>"), "{html}");
+        assert!(html.contains("<td>Snik</td>"), "{html}");
+        assert!(html.contains("<del>Gold</del>"), "{html}");
+        assert!(html.contains("checkbox"), "{html}");
+    }
+
+    #[test]
+    fn parses_top_level_body() {
+        let input = "Top level body.\nWith multiple lines.\n\nIncluding heard breaks.";
+        let entry = JournalEntry {
+            body: Some(String::from(input)),
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        let expected = Some(String::from(input));
+
+        assert_eq!(expected, entry.body);
+    }
+
+    #[test]
+    fn parses_empty_bodies() {
+        let options = ParseOptions {
+            normalize_heading_levels: true,
+            ..Default::default()
+        };
+
+        for input in ["", "\n  \n\n"] {
+            let entry = JournalEntry {
+                body: Some(String::from(input)),
+                ..Default::default()
+            };
+            let entry = entry.parse_with_options(&options).expect("should parse");
+
+            assert_eq!(None, entry.body, "{input:?}");
+            assert!(entry.sections.is_empty(), "{input:?}");
+            assert_eq!(None, entry.excerpt, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn parses_top_level_sections() {
+        let input = "# First Top Level
+# Second Top Level";
+        let entry = JournalEntry {
+            body: Some(String::from(input)),
             ..Default::default()
         };
         let entry = entry.parse().expect("should parse");
@@ -297,21 +1172,75 @@ mod test {
                 title: String::from("First Top Level"),
                 level: SectionLevel::H1,
                 body: String::from(""),
-                metadata: HashMap::new(),
+                metadata: Metadata::new(),
                 sections: Vec::new(),
+                span: None,
             },
             Section {
                 title: String::from("Second Top Level"),
                 level: SectionLevel::H1,
                 body: String::from(""),
-                metadata: HashMap::new(),
+                metadata: Metadata::new(),
                 sections: Vec::new(),
+                span: None,
             },
         ];
 
         assert_eq!(expected, entry.sections);
     }
 
+    #[test]
+    fn counts_words_in_text_only() {
+        let entry = JournalEntry {
+            body: Some(String::from(
+                "A *bold*ly [linked](https://example.com/long/path) intro.\n\n# The Tavern\n\n- One\n- Two",
+            )),
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        assert_eq!(4, count_words(entry.body.as_deref().unwrap_or_default()));
+        assert_eq!(4, entry.sections[0].word_count());
+        assert_eq!(8, entry.word_count());
+    }
+
+    #[test]
+    fn strips_markdown_to_plain_text() {
+        let section = Section {
+            title: String::from("The Tavern"),
+            body: String::from(
+                "A *bold* [link](https://example.com) with `code`.\n\n![A map](map.png \"Map\")\n\n- One\n- Two",
+            ),
+            ..Default::default()
+        };
+
+        assert_eq!("A bold link with code. A map One Two", section.plain_text());
+    }
+
+    #[test]
+    fn records_the_span_of_each_section() {
+        let input = "Intro\n\n# Town\n\nStreets\n\n## Tavern\n\nAle\n\n# Dungeon\n";
+        let entry = JournalEntry {
+            body: Some(String::from(input)),
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        let spans: Vec<_> = entry
+            .iter_sections()
+            .map(|section| &input[section.span.clone().expect("parsed sections have spans")])
+            .collect();
+
+        assert_eq!(
+            vec![
+                "# Town\n\nStreets\n\n## Tavern\n\nAle\n\n",
+                "## Tavern\n\nAle\n\n",
+                "# Dungeon\n",
+            ],
+            spans
+        );
+    }
+
     #[test]
     fn parses_top_level_sections_where_sections_have_reverse_ordering() {
         let input = "### First Top Level
@@ -328,22 +1257,25 @@ mod test {
                 title: String::from("First Top Level"),
                 level: SectionLevel::H3,
                 body: String::from(""),
-                metadata: HashMap::new(),
+                metadata: Metadata::new(),
                 sections: Vec::new(),
+                span: None,
             },
             Section {
                 title: String::from("Second Top Level"),
                 level: SectionLevel::H2,
                 body: String::from(""),
-                metadata: HashMap::new(),
+                metadata: Metadata::new(),
                 sections: Vec::new(),
+                span: None,
             },
             Section {
                 title: String::from("Third Top Level"),
                 level: SectionLevel::H1,
                 body: String::from(""),
-                metadata: HashMap::new(),
+                metadata: Metadata::new(),
                 sections: Vec::new(),
+                span: None,
             },
         ];
 
@@ -366,22 +1298,25 @@ mod test {
                 title: String::from("First Top Level"),
                 level: SectionLevel::H2,
                 body: String::from(""),
-                metadata: HashMap::new(),
+                metadata: Metadata::new(),
                 sections: Vec::new(),
+                span: None,
             },
             Section {
                 title: String::from("Second Top Level"),
                 level: SectionLevel::H2,
                 body: String::from(""),
-                metadata: HashMap::new(),
+                metadata: Metadata::new(),
                 sections: Vec::new(),
+                span: None,
             },
             Section {
                 title: String::from("Third Top Level"),
                 level: SectionLevel::H2,
                 body: String::from(""),
-                metadata: HashMap::new(),
+                metadata: Metadata::new(),
                 sections: Vec::new(),
+                span: None,
             },
         ];
 
@@ -389,8 +1324,35 @@ mod test {
     }
 
     #[test]
-    fn parses_top_level_sections_with_nested_sections() {
-        let input = "# First Top Level
+    fn converts_section_levels_to_and_from_heading_levels() {
+        for level in 1..=6u8 {
+            let section_level = SectionLevel::try_from(level).expect("should be a valid level");
+            let heading_level = HeadingLevel::from(section_level);
+
+            assert_eq!(level, section_level.as_u8());
+            assert_eq!(Ok(heading_level), HeadingLevel::try_from(level as usize));
+            assert_eq!(section_level, SectionLevel::from(heading_level));
+        }
+
+        assert!(SectionLevel::try_from(0).is_err());
+        assert!(SectionLevel::try_from(7).is_err());
+    }
+
+    #[test]
+    fn steps_between_section_levels() {
+        assert_eq!(Some(SectionLevel::H2), SectionLevel::H1.next());
+        assert_eq!(None, SectionLevel::H6.next());
+        assert_eq!(Some(SectionLevel::H5), SectionLevel::H6.prev());
+        assert_eq!(None, SectionLevel::H1.prev());
+        assert_eq!(
+            vec![1, 2, 3, 4, 5, 6],
+            SectionLevel::all()
+                .map(|level| level.as_u8())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    const NESTED_SECTIONS: &str = "# First Top Level
 Test
 ## First Nested
 Test
@@ -400,8 +1362,11 @@ Test
 Test
 # Second Top Level
 Test";
+
+    #[test]
+    fn parses_top_level_sections_with_nested_sections() {
         let entry = JournalEntry {
-            body: Some(String::from(input)),
+            body: Some(String::from(NESTED_SECTIONS)),
             ..Default::default()
         };
         let entry = entry.parse().expect("should parse");
@@ -411,39 +1376,696 @@ Test";
                 title: String::from("First Top Level"),
                 level: SectionLevel::H1,
                 body: String::from("Test"),
-                metadata: HashMap::new(),
+                metadata: Metadata::new(),
                 sections: vec![
                     Section {
                         title: String::from("First Nested"),
                         level: SectionLevel::H2,
                         body: String::from("Test"),
-                        metadata: HashMap::new(),
+                        metadata: Metadata::new(),
                         sections: vec![Section {
                             title: String::from("Inner Nested"),
                             level: SectionLevel::H3,
                             body: String::from("Test"),
-                            metadata: HashMap::new(),
+                            metadata: Metadata::new(),
                             sections: Vec::new(),
+                            span: None,
                         }],
+                        span: None,
                     },
                     Section {
                         title: String::from("Second Nested"),
                         level: SectionLevel::H2,
                         body: String::from("Test"),
-                        metadata: HashMap::new(),
+                        metadata: Metadata::new(),
                         sections: Vec::new(),
+                        span: None,
                     },
                 ],
+                span: None,
             },
             Section {
                 title: String::from("Second Top Level"),
                 level: SectionLevel::H1,
                 body: String::from("Test"),
-                metadata: HashMap::new(),
+                metadata: Metadata::new(),
                 sections: Vec::new(),
+                span: None,
             },
         ];
 
         assert_eq!(expected, entry.sections);
     }
+
+    #[test]
+    fn iterates_sections_at_every_depth_in_document_order() {
+        let entry = JournalEntry {
+            body: Some(String::from(NESTED_SECTIONS)),
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        let titles: Vec<_> = entry
+            .iter_sections()
+            .map(|section| section.title.as_str())
+            .collect();
+
+        assert_eq!(
+            vec![
+                "First Top Level",
+                "First Nested",
+                "Inner Nested",
+                "Second Nested",
+                "Second Top Level"
+            ],
+            titles
+        );
+    }
+
+    #[test]
+    fn filters_sections_by_heading_level() {
+        let entry = JournalEntry {
+            body: Some(format!("## Orphaned\n{NESTED_SECTIONS}")),
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        let titles = |level| -> Vec<_> {
+            entry
+                .sections_at_level(level)
+                .map(|section| section.title.as_str())
+                .collect()
+        };
+
+        assert_eq!(
+            vec!["First Top Level", "Second Top Level"],
+            titles(SectionLevel::H1)
+        );
+        assert_eq!(
+            vec!["Orphaned", "First Nested", "Second Nested"],
+            titles(SectionLevel::H2)
+        );
+        assert_eq!(vec!["Inner Nested"], titles(SectionLevel::H3));
+        assert!(titles(SectionLevel::H4).is_empty());
+    }
+
+    fn assert_round_trips(input: &str) {
+        let entry = JournalEntry {
+            body: Some(String::from(input)),
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        let round_tripped = JournalEntry {
+            body: Some(entry.to_markdown()),
+            ..Default::default()
+        };
+        let round_tripped = round_tripped.parse().expect("should parse round trip");
+
+        assert_eq!(entry, round_tripped);
+    }
+
+    #[test]
+    fn round_trips_entries_through_markdown() {
+        let inputs = [
+            "Top level body.\nWith multiple lines.\n\nIncluding hard breaks.",
+            "# First Top Level\n# Second Top Level",
+            "### First Top Level\n## Second Top Level\n# Third Top Level",
+            "# First Top Level\nTest\n## First Nested\nTest\n### Inner Nested\nTest\n## Second Nested\nTest\n# Second Top Level\nTest",
+            "Preamble\n\n# Heading with *emphasis*\n\n* A list\n* of items\n\n```rust\nfn main() {}\n```\n\n> A quote",
+        ];
+
+        for input in inputs {
+            assert_round_trips(input);
+        }
+    }
+
+    #[test]
+    fn finds_the_lead_paragraph() {
+        let lead = |body: &str| {
+            let entry = JournalEntry {
+                body: Some(String::from(body)),
+                ..Default::default()
+            };
+
+            entry.lead().map(String::from)
+        };
+
+        assert_eq!(
+            Some(String::from("The *Prancing* Pony\nsits by the road.")),
+            lead("The *Prancing* Pony\nsits by the road.\n\nSix rooms.\n\n# Rooms")
+        );
+        assert_eq!(
+            Some(String::from("Ale and bread.")),
+            lead("<!-- note -->\n\n> Quoted\n\n- Listed\n\nAle and bread.")
+        );
+        assert_eq!(
+            Some(String::from("Ale and bread.")),
+            lead("Ale and bread.\n<!-- more -->")
+        );
+        assert_eq!(None, lead("# Inn\n\nAle and bread."));
+        assert_eq!(None, lead("# Inn\n\n## Rooms"));
+        assert_eq!(None, lead(""));
+        assert_eq!(None, JournalEntry::default().lead());
+
+        let entry = JournalEntry {
+            body: Some(String::from("Ale and bread.\n\n# Rooms\n\nSix rooms.")),
+            ..Default::default()
+        };
+        assert_eq!(
+            Some("Ale and bread."),
+            entry.parse().expect("should parse").lead()
+        );
+    }
+
+    #[test]
+    fn excerpts_entries_when_parsing() {
+        let parse = |body: &str, excerpt_length: usize| {
+            let entry = JournalEntry {
+                body: Some(String::from(body)),
+                ..Default::default()
+            };
+            let options = ParseOptions {
+                excerpt_length,
+                ..Default::default()
+            };
+
+            entry
+                .parse_with_options(&options)
+                .expect("should parse")
+                .excerpt
+        };
+
+        assert_eq!(
+            Some(String::from("The Prancing Pony sits by the road.")),
+            parse(
+                "The *Prancing* Pony\nsits by the road.\n\n<!-- more -->\n\n# Rooms\n\nSix rooms.",
+                10
+            )
+        );
+        assert_eq!(
+            Some(String::from("Inn Ale and…")),
+            parse("# Inn\n\nAle and bread.", 12)
+        );
+        assert_eq!(
+            Some(String::from("Inn Ale and bread.")),
+            parse("# Inn\n\nAle and bread.", 200)
+        );
+        assert_eq!(
+            Some(String::from("Inn…")),
+            parse("# Inn\n\n```\n<!-- more -->\n```\n\nAle", 4)
+        );
+        assert_eq!(None, parse("# Inn\n\nAle and bread.", 0));
+        assert_eq!(None, parse("", 200));
+    }
+
+    #[test]
+    fn keeps_indented_code_blocks_holding_fences() {
+        let entry = JournalEntry {
+            body: Some(String::from(
+                "# Setup\n\n    $ cat notes.md\n    ```\n    echo hi\n    ```\n",
+            )),
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        assert_eq!(
+            "````\n$ cat notes.md\n```\necho hi\n```\n````",
+            entry.sections[0].body.trim()
+        );
+
+        let round_tripped = JournalEntry {
+            body: Some(entry.to_markdown()),
+            ..Default::default()
+        };
+        let round_tripped = round_tripped.parse().expect("should parse round trip");
+
+        assert_eq!(
+            entry.sections[0].body.trim(),
+            round_tripped.sections[0].body.trim()
+        );
+    }
+
+    #[test]
+    fn writes_metadata_as_fenced_blocks() {
+        let mut metadata = Metadata::new();
+        metadata.insert(
+            "stats",
+            SectionMetadata {
+                lang: String::from("toml"),
+                data: String::from("hp = 10\n"),
+                attributes: vec![String::from("hidden")],
+            },
+        );
+        metadata.insert(
+            "note",
+            SectionMetadata {
+                lang: String::new(),
+                data: String::from("```\nnested\n```"),
+                attributes: Vec::new(),
+            },
+        );
+
+        let section = Section {
+            title: String::from("Goblin"),
+            level: SectionLevel::H2,
+            body: String::from("A small creature."),
+            metadata,
+            sections: vec![Section {
+                title: String::from("Tactics"),
+                level: SectionLevel::H3,
+                body: String::from("Hit and run."),
+                ..Default::default()
+            }],
+            span: None,
+        };
+
+        let expected = "## Goblin
+
+A small creature.
+
+```toml,metadata,stats,hidden
+hp = 10
+```
+
+````metadata,note
+```
+nested
+```
+````
+
+### Tactics
+
+Hit and run.";
+
+        assert_eq!(expected, section.to_markdown());
+    }
+
+    #[test]
+    fn parses_with_metadata() {
+        let input = "# Goblin\nA small creature.\n```toml,metadata,stats\nhp = 10\n```\n## Tactics\n```metadata,note\nRuns.\n```";
+        let entry = JournalEntry {
+            body: Some(String::from(input)),
+            ..Default::default()
+        };
+        let entry = entry.parse_with_metadata().expect("should parse");
+
+        let goblin = &entry.sections[0];
+        let tactics = &goblin.sections[0];
+
+        assert_eq!(
+            Some("hp = 10\n"),
+            goblin.metadata.get("stats").map(|m| m.data.as_str())
+        );
+        assert_eq!(
+            Some("Runs.\n"),
+            tactics.metadata.get("note").map(|m| m.data.as_str())
+        );
+        assert!(!goblin.body.contains("hp = 10"));
+    }
+
+    #[test]
+    fn keeps_raw_metadata_whitespace() {
+        let input = "# Goblin\n  ```metadata,slug\n\n    goblin-stats  \n  ```\n## Tactics\n```metadata,empty\n```";
+        let entry = JournalEntry {
+            body: Some(String::from(input)),
+            ..Default::default()
+        };
+        let entry = entry.parse_with_metadata().expect("should parse");
+
+        let slug = entry.sections[0]
+            .metadata
+            .get("slug")
+            .expect("should have a slug");
+        let empty = entry.sections[0].sections[0]
+            .metadata
+            .get("empty")
+            .expect("should have a block");
+
+        assert_eq!("\n  goblin-stats  \n", slug.data);
+        assert_eq!("goblin-stats", slug.trimmed());
+        assert_eq!("", empty.data);
+    }
+
+    #[test]
+    fn round_trips_metadata_through_markdown() {
+        let input = "# Goblin\nA small creature.\n```toml,metadata,stats,hidden\nhp = 10\n```\n## Tactics\n```metadata,note\nRuns.\n```";
+        let entry = JournalEntry {
+            body: Some(String::from(input)),
+            ..Default::default()
+        };
+        let entry = entry.parse_with_metadata().expect("should parse");
+
+        let round_tripped = JournalEntry {
+            body: Some(entry.to_markdown()),
+            ..Default::default()
+        };
+        let round_tripped = round_tripped
+            .parse_with_metadata()
+            .expect("should parse round trip");
+
+        assert_eq!(entry, round_tripped);
+    }
+
+    #[test]
+    fn parses_setext_headings() {
+        let input = "First Top Level\n===============\nTop body\n\nFirst Nested\n------------\nNested body\n\nSecond Top Level\n================\nLast body";
+        let entry = JournalEntry {
+            body: Some(String::from(input)),
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        let expected = vec![
+            Section {
+                title: String::from("First Top Level"),
+                level: SectionLevel::H1,
+                body: String::from("Top body"),
+                metadata: Metadata::new(),
+                sections: vec![Section {
+                    title: String::from("First Nested"),
+                    level: SectionLevel::H2,
+                    body: String::from("Nested body"),
+                    metadata: Metadata::new(),
+                    sections: Vec::new(),
+                    span: None,
+                }],
+                span: None,
+            },
+            Section {
+                title: String::from("Second Top Level"),
+                level: SectionLevel::H1,
+                body: String::from("Last body"),
+                metadata: Metadata::new(),
+                sections: Vec::new(),
+                span: None,
+            },
+        ];
+
+        assert_eq!(None, entry.body);
+        assert_eq!(expected, entry.sections);
+    }
+
+    #[test]
+    fn keeps_thematic_breaks_following_setext_headings_in_body() {
+        let input = "Title\n=====\nBody\n\n---\n\nAfter the break";
+        let entry = JournalEntry {
+            body: Some(String::from(input)),
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        assert_eq!(1, entry.sections.len());
+        assert_eq!("Title", entry.sections[0].title);
+        assert_eq!(SectionLevel::H1, entry.sections[0].level);
+        assert!(entry.sections[0].body.starts_with("Body"));
+        assert!(entry.sections[0].body.ends_with("After the break"));
+        assert!(!entry.sections[0].body.contains("====="));
+    }
+
+    #[test]
+    fn normalizes_heading_levels_to_start_at_h1() {
+        let input = "### First\n#### Nested\n### Second";
+        let entry = JournalEntry {
+            body: Some(String::from(input)),
+            ..Default::default()
+        };
+        let options = ParseOptions {
+            normalize_heading_levels: true,
+            ..Default::default()
+        };
+        let entry = entry.parse_with_options(&options).expect("should parse");
+
+        let expected = vec![
+            Section {
+                title: String::from("First"),
+                level: SectionLevel::H1,
+                body: String::from(""),
+                metadata: Metadata::new(),
+                sections: vec![Section {
+                    title: String::from("Nested"),
+                    level: SectionLevel::H2,
+                    body: String::from(""),
+                    metadata: Metadata::new(),
+                    sections: Vec::new(),
+                    span: None,
+                }],
+                span: None,
+            },
+            Section {
+                title: String::from("Second"),
+                level: SectionLevel::H1,
+                body: String::from(""),
+                metadata: Metadata::new(),
+                sections: Vec::new(),
+                span: None,
+            },
+        ];
+
+        assert_eq!(expected, entry.sections);
+    }
+
+    #[test]
+    fn normalizing_reverse_ordered_headings_keeps_them_as_siblings() {
+        let input = "#### First Top Level\n### Second Top Level\n## Third Top Level";
+        let entry = JournalEntry {
+            body: Some(String::from(input)),
+            ..Default::default()
+        };
+        let options = ParseOptions {
+            normalize_heading_levels: true,
+            ..Default::default()
+        };
+        let entry = entry.parse_with_options(&options).expect("should parse");
+
+        let levels: Vec<_> = entry.sections.iter().map(|section| section.level).collect();
+
+        assert_eq!(
+            vec![SectionLevel::H3, SectionLevel::H2, SectionLevel::H1],
+            levels
+        );
+        assert!(entry
+            .sections
+            .iter()
+            .all(|section| section.sections.is_empty()));
+    }
+
+    fn offset_levels(input: &str, heading_offset: i8) -> Vec<SectionLevel> {
+        let entry = JournalEntry {
+            body: Some(String::from(input)),
+            heading_offset,
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        entry.iter_sections().map(|section| section.level).collect()
+    }
+
+    #[test]
+    fn shifts_heading_levels_by_the_heading_offset() {
+        assert_eq!(
+            vec![SectionLevel::H3, SectionLevel::H4, SectionLevel::H3],
+            offset_levels("# First\n## Nested\n# Second", 2)
+        );
+        assert_eq!(
+            vec![SectionLevel::H1, SectionLevel::H2],
+            offset_levels("### First\n#### Nested", -2)
+        );
+    }
+
+    #[test]
+    fn clamps_shifted_heading_levels() {
+        assert_eq!(
+            vec![SectionLevel::H5, SectionLevel::H6, SectionLevel::H6],
+            offset_levels("### Three\n##### Five\n###### Six", 2)
+        );
+        assert_eq!(
+            vec![SectionLevel::H1, SectionLevel::H1, SectionLevel::H2],
+            offset_levels("# One\n## Two\n### Three", -1)
+        );
+
+        // NOTE: Headings clamped to the same level are siblings, rather than nested below each other.
+        let entry = JournalEntry {
+            body: Some(String::from("# One\n## Two\n### Three")),
+            heading_offset: -1,
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        assert_eq!(2, entry.sections.len());
+        assert_eq!("Three", entry.sections[1].sections[0].title);
+    }
+
+    #[test]
+    fn applies_heading_offsets_after_normalizing() {
+        let entry = JournalEntry {
+            body: Some(String::from("### First\n#### Nested")),
+            heading_offset: 1,
+            ..Default::default()
+        };
+        let options = ParseOptions {
+            normalize_heading_levels: true,
+            ..Default::default()
+        };
+        let mut entry = entry.parse_with_options(&options).expect("should parse");
+
+        assert_eq!(vec![SectionLevel::H2, SectionLevel::H3], levels(&entry));
+
+        let markdown = entry.to_markdown();
+        entry
+            .set_body_with_options(markdown, &options)
+            .expect("should parse again");
+
+        assert_eq!(vec![SectionLevel::H2, SectionLevel::H3], levels(&entry));
+        assert_eq!(1, entry.heading_offset);
+    }
+
+    fn levels(entry: &JournalEntry) -> Vec<SectionLevel> {
+        entry.iter_sections().map(|section| section.level).collect()
+    }
+
+    const ALL_LEVELS: &str =
+        "# One\n## Two\n### Three\n#### Four\n##### Five\n###### Six\n# One Again";
+
+    #[test]
+    fn reports_deepest_section_nesting() {
+        let entry = JournalEntry {
+            body: Some(String::from(ALL_LEVELS)),
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        assert_eq!(6, entry.depth());
+        assert_eq!(0, JournalEntry::default().depth());
+    }
+
+    #[test]
+    fn fails_when_exceeding_max_depth() {
+        let entry = JournalEntry {
+            body: Some(String::from(ALL_LEVELS)),
+            ..Default::default()
+        };
+        let options = ParseOptions {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let err = entry
+            .parse_with_options(&options)
+            .expect_err("should exceed max depth");
+
+        assert!(err.to_string().contains("\"Three\""));
+        assert!(err.to_string().contains("maximum depth of 2"));
+    }
+
+    #[test]
+    fn hashes_the_raw_file_contents() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        std::fs::write(dir.path().join("lf.md"), "# Heading\n").expect("failed to write entry");
+        std::fs::write(dir.path().join("crlf.md"), "# Heading\r\n").expect("failed to write entry");
+
+        let load = |path| {
+            JournalEntry::load(String::from("Entry"), dir.path(), path, 1)
+                .expect("should load entry")
+        };
+        let lf = load("lf.md");
+        let crlf = load("crlf.md");
+
+        assert_eq!(
+            Some("14fb5ebdf36c86541b6ca44a58c0944efac709d9ea967d29e460d689cf552659"),
+            lf.content_hash.as_deref()
+        );
+        assert_eq!(lf.body, crlf.body);
+        assert_ne!(lf.content_hash, crlf.content_hash);
+        assert_eq!(None, JournalEntry::default().content_hash);
+    }
+
+    #[test]
+    fn expands_tabs_when_loading() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        std::fs::write(
+            dir.path().join("code.md"),
+            "# Spell\n\n\tcast(\"fireball\") {\n\t\tdamage\t8d6\n\t}\n",
+        )
+        .expect("failed to write entry");
+
+        let options = ParseOptions {
+            expand_tabs: true,
+            ..Default::default()
+        };
+        let entry = JournalEntry::load_with_options(
+            String::from("Entry"),
+            dir.path(),
+            "code.md",
+            1,
+            &options,
+        )
+        .expect("should load entry")
+        .parse_with_options(&options)
+        .expect("should parse entry");
+
+        let markdown = entry.to_markdown();
+
+        assert_eq!(
+            "```\ncast(\"fireball\") {\n    damage  8d6\n}\n```",
+            entry.sections[0].body
+        );
+        assert_eq!(
+            "# Spell\n\n```\ncast(\"fireball\") {\n    damage  8d6\n}\n```",
+            markdown
+        );
+    }
+
+    #[test]
+    fn keeps_raw_html_in_bodies() {
+        let source = "<div class=\"aside\">\n\nA *rumour*\n\n</div>\n\n<!-- Secret: the barkeep is a spy -->\n\n\
+                      # Tavern\n\nAle <!-- and mead --> and <span>bread</span>.\n\n\
+                      <div class=\"widget\">\n  <p>Dice roller</p>\n</div>\n\n\
+                      <script>\nroll(20);\n\nroll(6);\n</script>\n\nAfter";
+        let entry = JournalEntry {
+            body: Some(String::from(source)),
+            ..Default::default()
+        };
+        let entry = entry.parse().expect("should parse");
+
+        assert_eq!(
+            Some("<div class=\"aside\">\n\nA *rumour*\n\n</div>\n\n<!-- Secret: the barkeep is a spy -->"),
+            entry.body.as_deref().map(str::trim)
+        );
+        assert_eq!(
+            "Ale <!-- and mead --> and <span>bread</span>.\n\n\
+             <div class=\"widget\">\n  <p>Dice roller</p>\n</div>\n\n\
+             <script>\nroll(20);\n\nroll(6);\n</script>\n\nAfter",
+            entry.sections[0].body
+        );
+        assert_round_trips(source);
+    }
+
+    #[test]
+    fn sets_a_body_with_new_sections() {
+        let mut entry = JournalEntry {
+            title: String::from("Inn"),
+            body: Some(String::from("Intro\n\n# Taproom\n\nAle\n")),
+            path: Some(PathBuf::from("inn.md")),
+            level: 2,
+            ..Default::default()
+        }
+        .parse()
+        .expect("should parse");
+
+        entry
+            .set_body(String::from(
+                "Welcome\n\n# Cellar\n\nBarrels\n\n## Vault\n\nGold\n",
+            ))
+            .expect("should set body");
+
+        assert_eq!(Some("Welcome"), entry.body.as_deref());
+        assert_eq!(1, entry.sections.len());
+        assert_eq!("Cellar", entry.sections[0].title);
+        assert_eq!("Barrels", entry.sections[0].body);
+        assert_eq!("Vault", entry.sections[0].sections[0].title);
+        assert_eq!(
+            ("Inn", Some(PathBuf::from("inn.md")), 2),
+            (entry.title.as_str(), entry.path.clone(), entry.level)
+        );
+    }
 }
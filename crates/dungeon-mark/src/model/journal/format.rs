@@ -0,0 +1,136 @@
+use std::{collections::HashMap, fmt};
+
+use crate::error::Result;
+
+use super::SectionMetadata;
+
+/// Parses the data of a metadata block into a JSON value.
+pub type MetadataParser = fn(&str) -> Result<serde_json::Value>;
+
+/// The format of metadata blocks that have no language, such as `metadata,key`.
+const DEFAULT_FORMAT: &str = "toml";
+
+/// A registry of metadata formats, mapping the language of a metadata block to the parser for its data.
+/// `toml`, `json` and `yaml` (also as `yml`) are registered by default, and blocks without a language are
+/// parsed as TOML.
+#[derive(Clone)]
+pub struct MetadataFormats {
+    parsers: HashMap<String, MetadataParser>,
+}
+
+impl MetadataFormats {
+    /// Create a registry with the default formats.
+    pub fn new() -> Self {
+        let mut formats = Self {
+            parsers: HashMap::new(),
+        };
+
+        formats
+            .register("toml", |data| Ok(toml::from_str(data)?))
+            .register("json", |data| Ok(serde_json::from_str(data)?))
+            .register("yaml", |data| Ok(serde_yaml::from_str(data)?))
+            .register("yml", |data| Ok(serde_yaml::from_str(data)?));
+
+        formats
+    }
+
+    /// Register the parser for a language, replacing any parser already registered for it.
+    pub fn register(&mut self, lang: impl Into<String>, parser: MetadataParser) -> &mut Self {
+        self.parsers.insert(lang.into(), parser);
+
+        self
+    }
+
+    /// The parser registered for a language.
+    pub fn get(&self, lang: &str) -> Option<MetadataParser> {
+        self.parsers.get(lang).copied()
+    }
+
+    /// Parse the data of a metadata block with the parser registered for its language.
+    pub fn parse(&self, metadata: &SectionMetadata) -> Result<serde_json::Value> {
+        let lang = match metadata.lang.as_str() {
+            "" => DEFAULT_FORMAT,
+            lang => lang,
+        };
+
+        let Some(parser) = self.get(lang) else {
+            anyhow::bail!("Unsupported metadata format: {lang}")
+        };
+
+        parser(&metadata.data)
+    }
+}
+
+impl Default for MetadataFormats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MetadataFormats {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut langs: Vec<_> = self.parsers.keys().collect();
+        langs.sort();
+
+        formatter
+            .debug_struct("MetadataFormats")
+            .field("langs", &langs)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata(lang: &str, data: &str) -> SectionMetadata {
+        SectionMetadata {
+            lang: String::from(lang),
+            data: String::from(data),
+            attributes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parses_the_default_formats() {
+        let formats = MetadataFormats::new();
+        let expected = json!({ "name": "Borin", "level": 3 });
+
+        for (lang, data) in [
+            ("toml", "name = \"Borin\"\nlevel = 3"),
+            ("", "name = \"Borin\"\nlevel = 3"),
+            ("json", r#"{ "name": "Borin", "level": 3 }"#),
+            ("yaml", "name: Borin\nlevel: 3"),
+            ("yml", "name: Borin\nlevel: 3"),
+        ] {
+            let actual = formats
+                .parse(&metadata(lang, data))
+                .unwrap_or_else(|err| panic!("{lang} should parse: {err}"));
+
+            assert_eq!(expected, actual, "{lang}");
+        }
+    }
+
+    #[test]
+    fn parses_registered_formats() {
+        let mut formats = MetadataFormats::new();
+        formats.register("lines", |data| Ok(data.lines().collect()));
+
+        assert_eq!(
+            json!(["a", "b"]),
+            formats
+                .parse(&metadata("lines", "a\nb"))
+                .expect("should parse")
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_formats() {
+        let err = MetadataFormats::new()
+            .parse(&metadata("ron", "(name: \"Borin\")"))
+            .expect_err("should fail");
+
+        assert!(err.to_string().contains("Unsupported metadata format: ron"));
+    }
+}
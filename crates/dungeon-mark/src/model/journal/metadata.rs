@@ -0,0 +1,226 @@
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+
+use super::{Metadata, Section, SectionMetadata};
+use crate::{
+    cmark::{CMarkParser, EventIteratorExt},
+    error::Result,
+};
+
+/// The token that marks a fenced code block as metadata, unless the `[metadata]` table of `journal.toml` changes it.
+pub(crate) const DEFAULT_METADATA_SENTINEL: &str = "metadata";
+
+/// Extract every metadata block tagged with `sentinel` from the section's body into its metadata.
+pub(crate) fn extract_metadata(section: &mut Section, sentinel: &str) -> Result<()> {
+    let mut body = Vec::new();
+    let mut metadata = Metadata::new();
+    let mut events = CMarkParser::new(&section.body);
+
+    while let Some(event) = events.peek_event() {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(tag)))
+                if is_metadata_block(tag, sentinel) =>
+            {
+                let MetadataTag {
+                    lang,
+                    key,
+                    attributes,
+                } = parse_metadata_tag(tag, sentinel)
+                    .expect("is_metadata_block invariant was violated");
+                events.next_event();
+
+                let data = events
+                    .iter_until_and_consume(|event| {
+                        matches! {
+                            event,
+                            Event::End(Tag::CodeBlock(CodeBlockKind::Fenced(_)))
+                        }
+                    })
+                    .filter_map(|event| match event {
+                        Event::Text(text) => Some(text.into_string()),
+                        _ => None,
+                    })
+                    .collect::<String>();
+                let section_meta = SectionMetadata {
+                    lang,
+                    data,
+                    attributes,
+                };
+
+                metadata.insert(key, section_meta);
+                body.push(String::from("\n\n")); // Replace the missing code block with a hard break.
+            }
+            _ => {
+                let text = events
+                    .iter_until(|event| {
+                        matches! {
+                            event,
+                            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(tag))) if is_metadata_block(tag, sentinel)
+                        }
+                    })
+                    .stringify()?;
+
+                body.push(text);
+            }
+        }
+    }
+
+    // Consume the end of the event stream.
+    events.next_event();
+
+    section.body = body.into_iter().collect();
+    section.metadata.extend([metadata]);
+
+    Ok(())
+}
+
+fn is_metadata_block(tag: &str, sentinel: &str) -> bool {
+    parse_metadata_tag(tag, sentinel).is_some()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct MetadataTag {
+    lang: String,
+    key: String,
+    attributes: Vec<String>,
+}
+
+/// Parse a fenced code block's info string into its language, key and trailing attributes, accepting the
+/// `lang,sentinel,key,attributes...` and `sentinel,key,attributes...` forms, as well as `lang,sentinel` and a bare
+/// `sentinel` without a key.
+fn parse_metadata_tag(tag: &str, sentinel: &str) -> Option<MetadataTag> {
+    let mut parts: Vec<_> = tag.split(',').map(|part| part.trim()).collect();
+
+    // NOTE: A trailing comma leaves an empty token behind.
+    if parts.len() > 1 && parts.last() == Some(&"") {
+        parts.pop();
+    }
+
+    let (lang, key, attributes) = match &parts[..] {
+        [token] if *token == sentinel => ("", "", &[][..]),
+        [lang, token] if *token == sentinel => (*lang, "", &[][..]),
+        [token, key, attributes @ ..] if *token == sentinel => ("", *key, attributes),
+        [lang, token, key, attributes @ ..] if *token == sentinel => (*lang, *key, attributes),
+        _ => return None,
+    };
+
+    let tag = MetadataTag {
+        lang: lang.to_string(),
+        key: key.to_string(),
+        attributes: attributes
+            .iter()
+            .map(|attribute| attribute.to_string())
+            .collect(),
+    };
+
+    Some(tag)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_three_part_metadata_tag() {
+        let actual = parse_metadata_tag("toml, metadata, stats", "metadata");
+        let expected = MetadataTag {
+            lang: String::from("toml"),
+            key: String::from("stats"),
+            attributes: Vec::new(),
+        };
+
+        assert_eq!(Some(expected), actual);
+    }
+
+    #[test]
+    fn parses_two_part_metadata_tag_with_empty_lang() {
+        let actual = parse_metadata_tag("metadata,stats", "metadata");
+        let expected = MetadataTag {
+            lang: String::new(),
+            key: String::from("stats"),
+            attributes: Vec::new(),
+        };
+
+        assert_eq!(Some(expected), actual);
+    }
+
+    #[test]
+    fn parses_trailing_attributes() {
+        let actual = parse_metadata_tag("toml,metadata,stats,hidden, gm", "metadata");
+        let expected = MetadataTag {
+            lang: String::from("toml"),
+            key: String::from("stats"),
+            attributes: vec![String::from("hidden"), String::from("gm")],
+        };
+
+        assert_eq!(Some(expected), actual);
+
+        let actual = parse_metadata_tag("metadata,stats,hidden", "metadata");
+        let expected = MetadataTag {
+            lang: String::new(),
+            key: String::from("stats"),
+            attributes: vec![String::from("hidden")],
+        };
+
+        assert_eq!(Some(expected), actual);
+    }
+
+    #[test]
+    fn parses_metadata_tags_without_a_key() {
+        for (tag, lang) in [
+            ("metadata", ""),
+            (",metadata,", ""),
+            ("toml , metadata", "toml"),
+        ] {
+            let expected = MetadataTag {
+                lang: String::from(lang),
+                key: String::new(),
+                attributes: Vec::new(),
+            };
+
+            assert_eq!(Some(expected), parse_metadata_tag(tag, "metadata"), "{tag}");
+        }
+    }
+
+    #[test]
+    fn parses_spaced_metadata_tags_with_a_trailing_comma() {
+        let expected = MetadataTag {
+            lang: String::from("toml"),
+            key: String::from("key"),
+            attributes: Vec::new(),
+        };
+
+        assert_eq!(
+            Some(expected),
+            parse_metadata_tag(" toml, metadata , key ,", "metadata")
+        );
+    }
+
+    #[test]
+    fn extracts_bare_metadata_blocks_under_the_empty_key() {
+        let mut section = Section {
+            title: String::from("test"),
+            body: String::from(
+                "Note
+```metadata
+Check the map.
+```",
+            ),
+            ..Default::default()
+        };
+
+        extract_metadata(&mut section, "metadata").expect("should extract metadata");
+
+        assert_eq!(
+            Some("Check the map.\n"),
+            section.metadata.get("").map(|m| m.data.as_str())
+        );
+        assert!(!section.body.contains("Check the map."));
+    }
+
+    #[test]
+    fn rejects_tags_without_sentinel() {
+        assert_eq!(None, parse_metadata_tag("toml", "metadata"));
+        assert_eq!(None, parse_metadata_tag("toml,stats", "metadata"));
+        assert_eq!(None, parse_metadata_tag("toml,metadata,stats", "meta"));
+    }
+}
@@ -1,24 +1,1170 @@
+mod diff;
 mod entry;
+mod format;
+mod metadata;
 
+pub use diff::*;
 pub use entry::*;
+pub use format::*;
+pub(crate) use metadata::{extract_metadata, DEFAULT_METADATA_SENTINEL};
 
-use serde::{Deserialize, Serialize};
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs, mem,
+    path::{Path, PathBuf},
+    slice,
+};
+
+use crate::{cmark::SlugStrategy, error::Result, model::toc::Separator};
+
+/// The version of the file format written by `Journal::save`. It changes whenever a journal saved by one version
+/// of the crate could not be read back as the same journal by another.
+pub const JOURNAL_FORMAT_VERSION: u32 = 1;
+
+/// A journal as saved by `Journal::save`.
+#[derive(Serialize)]
+struct SavedJournal<'a> {
+    version: u32,
+    journal: &'a Journal,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ChapterTitle {
     pub title: String,
+    /// The slug of the title, unique among the chapter titles and entry titles of the journal, so that renderers
+    /// can link to the chapter with `#anchor`. Repeated titles are suffixed with `-1`, `-2` and so on, in
+    /// document order.
+    #[serde(default)]
+    pub anchor: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum JournalItem {
     Entry(JournalEntry),
     ChapterTitle(ChapterTitle),
-    Separator,
+    Separator(Separator),
+    /// An entry that failed to build, in place of the entry, as left by a build with `build.tolerant` set.
+    Error(EntryError),
+}
+
+/// An entry that failed to preprocess or parse in a tolerant build, kept in the journal in place of the entry so
+/// that the rest of the journal can still be rendered. See `BuildConfig::tolerant`.
+#[non_exhaustive]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EntryError {
+    /// The title of the entry.
+    pub title: String,
+    /// The location of the entry relative to the `JOURNAL.md` file.
+    pub path: Option<PathBuf>,
+    /// The nesting level of the entry.
+    pub level: u8,
+    /// The error the entry failed with, followed by its causes, on a single line.
+    pub message: String,
+    /// The journal items nested below the entry, which are built as usual.
+    #[serde(default)]
+    pub children: Vec<JournalItem>,
+}
+
+impl EntryError {
+    /// Replace the entry with an error placeholder, taking its nested items along.
+    pub fn new(entry: JournalEntry, error: &anyhow::Error) -> Self {
+        Self {
+            title: entry.title,
+            path: entry.path,
+            level: entry.level,
+            message: format!("{error:#}"),
+            children: entry.children,
+        }
+    }
+}
+
+impl JournalItem {
+    /// The items nested below an entry or an error placeholder.
+    pub fn children(&self) -> &[JournalItem] {
+        match self {
+            JournalItem::Entry(entry) => &entry.children,
+            JournalItem::Error(error) => &error.children,
+            _ => &[],
+        }
+    }
+
+    pub(crate) fn children_mut(&mut self) -> Option<&mut Vec<JournalItem>> {
+        match self {
+            JournalItem::Entry(entry) => Some(&mut entry.children),
+            JournalItem::Error(error) => Some(&mut error.children),
+            _ => None,
+        }
+    }
 }
 
 #[non_exhaustive]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Journal {
+    /// The title of the journal: `journal.title` from the configuration if it is set, otherwise the title
+    /// heading of the table of contents.
     pub title: Option<String>,
     pub items: Vec<JournalItem>,
+    /// The paths of the entries with each tag, in document order, as set by the `tags` transformer.
+    #[serde(default)]
+    pub tags: BTreeMap<String, Vec<PathBuf>>,
+    /// Aggregate results of transformers for renderers, such as the journal's statistics, keyed by the name of the
+    /// transformer that wrote them. See `Journal::set_scratch`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scratch: BTreeMap<String, serde_json::Value>,
+}
+
+/// A summary of the size of a journal, as returned by `Journal::stats`. Every count includes nested entries.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalStats {
+    pub entry_count: usize,
+    pub chapter_title_count: usize,
+    pub separator_count: usize,
+    /// The number of entries that failed to build, as left by a tolerant build.
+    pub error_count: usize,
+    /// The number of sections of every entry, including nested sections.
+    pub section_count: usize,
+    /// The deepest nesting of sections in any entry, as with `JournalEntry::depth`.
+    pub max_section_depth: usize,
+    /// The number of words in the journal, as counted by `Journal::word_count`.
+    pub word_count: usize,
+}
+
+/// A single entry of the journal flattened into its Markdown, as returned by `Journal::to_documents`, for consumers
+/// that do not need the section tree.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Document {
+    /// The path of the entry, relative to its source location, if it was loaded from a file.
+    pub path: Option<PathBuf>,
+    pub title: String,
+    /// The Markdown of the entry, as reconstructed by `JournalEntry::to_markdown`.
+    pub content: String,
+}
+
+impl Journal {
+    /// A journal of the given items, for assembling a journal in memory rather than loading it from disk.
+    pub fn new(title: Option<String>, items: Vec<JournalItem>) -> Journal {
+        Journal {
+            title,
+            items,
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        }
+    }
+
+    /// A journal of the given top level entries, in order, for tests and tools that generate content rather than
+    /// loading it from disk. The entries are used as they are, so parse them first to split them into sections.
+    ///
+    /// ```
+    /// use dungeon_mark::model::journal::{Journal, JournalEntry};
+    ///
+    /// let tavern = JournalEntry::new("The Tavern", "Ale and bread.\n\n## Cellar\n\nBarrels.").parse()?;
+    /// let journal = Journal::from_entries(Some(String::from("Campaign")), vec![tavern]);
+    ///
+    /// assert_eq!(1, journal.stats().entry_count);
+    /// assert_eq!(1, journal.stats().section_count);
+    /// # Ok::<(), dungeon_mark::error::Error>(())
+    /// ```
+    pub fn from_entries(title: Option<String>, entries: Vec<JournalEntry>) -> Journal {
+        Journal::new(title, entries.into_iter().map(JournalItem::Entry).collect())
+    }
+
+    /// Store `value` under `key` of the journal's scratch data, for renderers to read with `get_scratch`, such as an
+    /// index a transformer built over the whole journal. A transformer owns the key named after it, and writing a
+    /// key again replaces its value as a whole, so when several transformers write the same key, the one that runs
+    /// last wins. Values are stored as JSON, which is how command renderers receive them.
+    pub fn set_scratch(&mut self, key: impl Into<String>, value: &impl Serialize) -> Result<()> {
+        let key = key.into();
+        let value = serde_json::to_value(value)
+            .with_context(|| format!("Failed to store the scratch data {key}"))?;
+
+        self.scratch.insert(key, value);
+
+        Ok(())
+    }
+
+    /// Read the scratch data stored under `key` by `set_scratch`, or `None` if no transformer stored any.
+    pub fn get_scratch<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        self.scratch
+            .get(key)
+            .map(|value| {
+                T::deserialize(value)
+                    .with_context(|| format!("Failed to read the scratch data {key}"))
+            })
+            .transpose()
+    }
+
+    /// Iterate over every item in the journal in document order, where each entry is immediately followed
+    /// by the items nested below it.
+    pub fn iter(&self) -> JournalItems<'_> {
+        JournalItems {
+            stack: vec![self.items.iter()],
+        }
+    }
+
+    /// The entries before and after the entry at `entry_path` in document order, as for the "previous" and "next"
+    /// links at the bottom of a page. Chapter titles, separators and entries that failed to build are skipped, so
+    /// the neighbors of the first entry of a chapter are the last entry of the chapter before it and the entry
+    /// after it, nested or not. Drafts are neighbors like any other entry, and can be addressed through
+    /// `neighbors_of`, as they have no path. Both are `None` when no entry is at `entry_path`.
+    pub fn neighbors(&self, entry_path: &Path) -> (Option<&JournalEntry>, Option<&JournalEntry>) {
+        let entry_path = entry_path.strip_prefix(".").unwrap_or(entry_path);
+        let entry = self.entries().find(|entry| {
+            entry
+                .path
+                .as_deref()
+                .is_some_and(|path| path.strip_prefix(".").unwrap_or(path) == entry_path)
+        });
+
+        match entry {
+            Some(entry) => self.neighbors_of(entry),
+            None => (None, None),
+        }
+    }
+
+    /// The entries before and after `entry` in document order, like `neighbors`, where `entry` is one of the
+    /// journal's own entries, such as a draft. Both are `None` when `entry` is not in the journal.
+    pub fn neighbors_of(
+        &self,
+        entry: &JournalEntry,
+    ) -> (Option<&JournalEntry>, Option<&JournalEntry>) {
+        let mut previous = None;
+        let mut entries = self.entries();
+
+        for current in entries.by_ref() {
+            if std::ptr::eq(current, entry) {
+                return (previous, entries.next());
+            }
+
+            previous = Some(current);
+        }
+
+        (None, None)
+    }
+
+    /// Every entry in the journal in document order.
+    fn entries(&self) -> impl Iterator<Item = &JournalEntry> {
+        self.iter().filter_map(|item| match item {
+            JournalItem::Entry(entry) => Some(entry),
+            _ => None,
+        })
+    }
+
+    /// Save the journal to `path` as pretty printed JSON, for tools that cache or pass around a processed journal
+    /// rather than building it again. The file is an object with the `version` of the format, which is
+    /// `JOURNAL_FORMAT_VERSION`, and the `journal` itself, serialized as it is for command renderers.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let saved = SavedJournal {
+            version: JOURNAL_FORMAT_VERSION,
+            journal: self,
+        };
+        let json = serde_json::to_string_pretty(&saved)?;
+
+        fs::write(path, json).with_context(|| format!("Failed to save journal: {}", path.display()))
+    }
+
+    /// Open a journal saved by `Journal::save`. Fails if the file was saved in a different version of the format.
+    pub fn open(path: impl AsRef<Path>) -> Result<Journal> {
+        #[derive(Deserialize)]
+        struct Saved {
+            version: Option<u32>,
+            journal: serde_json::Value,
+        }
+
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to open journal: {}", path.display()))?;
+        let saved: Saved = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to read journal: {}", path.display()))?;
+
+        match saved.version {
+            Some(JOURNAL_FORMAT_VERSION) => {}
+            Some(version) => anyhow::bail!(
+                "The journal {} was saved in format version {version}, but only version \
+                 {JOURNAL_FORMAT_VERSION} can be opened",
+                path.display()
+            ),
+            None => anyhow::bail!(
+                "The journal {} has no format version, and was not saved by Journal::save",
+                path.display()
+            ),
+        }
+
+        serde_json::from_value(saved.journal)
+            .with_context(|| format!("Failed to read journal: {}", path.display()))
+    }
+
+    /// The journal as pretty printed JSON for snapshot tests, such as with `insta`, that is the same on every run
+    /// and platform. Object keys are sorted, `\r\n` line endings are normalized to `\n`, and the content hashes of
+    /// entries and spans of sections are left out, as they change with the line endings of the files the journal
+    /// was loaded from. Paths are the relative paths of the entries, as they are in the journal.
+    pub fn to_snapshot_string(&self) -> String {
+        let mut journal = self.clone();
+        journal.for_each_entry_mut(|entry| {
+            entry.content_hash = None;
+            entry.for_each_mut(|section| section.span = None);
+        });
+
+        // NOTE: Converting to a value sorts the keys of every object.
+        let mut value = serde_json::to_value(&journal).expect("journals serialize to JSON");
+        normalize_line_endings(&mut value);
+
+        let mut snapshot =
+            serde_json::to_string_pretty(&value).expect("JSON values serialize to strings");
+        snapshot.push('\n');
+
+        snapshot
+    }
+
+    /// A plain text outline of the journal, with one line for its title, each chapter title, separator, entry and
+    /// section, and no bodies. Entries are indented by two spaces for every entry they are nested below, and sections
+    /// are indented below their entry by their level and marked with `#` for each level. Sections deeper than
+    /// `max_depth` are left out.
+    pub fn to_outline(&self, max_depth: Option<SectionLevel>) -> String {
+        let mut outline = String::new();
+
+        if let Some(ref title) = self.title {
+            outline.push_str(title);
+            outline.push('\n');
+        }
+
+        push_outline_items(&mut outline, &self.items, 0, max_depth);
+
+        outline
+    }
+
+    /// The number of words in every entry of the journal, including nested entries, counted as with
+    /// `JournalEntry::word_count`.
+    pub fn word_count(&self) -> usize {
+        self.iter()
+            .map(|item| match item {
+                JournalItem::Entry(entry) => entry.word_count(),
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Count the items, sections and words of the journal in a single pass, for dashboards and reports.
+    pub fn stats(&self) -> JournalStats {
+        let mut stats = JournalStats::default();
+
+        for item in self.iter() {
+            match item {
+                JournalItem::Entry(entry) => {
+                    stats.entry_count += 1;
+                    stats.section_count += entry.iter_sections().count();
+                    stats.max_section_depth = stats.max_section_depth.max(entry.depth());
+                    stats.word_count += entry.word_count();
+                }
+                JournalItem::ChapterTitle(_) => stats.chapter_title_count += 1,
+                JournalItem::Separator(_) => stats.separator_count += 1,
+                JournalItem::Error(_) => stats.error_count += 1,
+            }
+        }
+
+        stats
+    }
+
+    /// The metadata keys used by the sections of every entry, grouped by the language of their fenced blocks,
+    /// such as `toml` for `toml,metadata,stats`. Blocks tagged without a language, `metadata,key`, are grouped
+    /// under an empty language. Only metadata already extracted by the `metadata` transformer is included.
+    pub fn metadata_keys(&self) -> BTreeMap<String, BTreeSet<String>> {
+        let mut keys = BTreeMap::<String, BTreeSet<String>>::new();
+
+        self.for_each_entry(|entry| {
+            for section in entry.iter_sections() {
+                for (key, metadata) in section.metadata.iter() {
+                    keys.entry(metadata.lang.clone())
+                        .or_default()
+                        .insert(String::from(key));
+                }
+            }
+        });
+
+        keys
+    }
+
+    /// Every entry of the journal, including nested entries, as a flat list of documents in document order. Chapter
+    /// titles and separators are left out.
+    pub fn to_documents(&self) -> Vec<Document> {
+        let mut documents = Vec::new();
+
+        self.for_each_entry(|entry| {
+            documents.push(Document {
+                path: entry.path.clone(),
+                title: entry.title.clone(),
+                content: entry.to_markdown(),
+            })
+        });
+
+        documents
+    }
+
+    /// The entries that failed to build, including nested entries, in document order, as left by a build with
+    /// `build.tolerant` set.
+    pub fn errors(&self) -> Vec<&EntryError> {
+        self.iter()
+            .filter_map(|item| match item {
+                JournalItem::Error(error) => Some(error),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Call the provided closure with every entry in the journal, including nested entries, in document order.
+    /// Unlike `try_for_each_entry_mut`, an entry the closure fails for is replaced by an `EntryError` with the
+    /// error, and the remaining entries are still visited, including those nested below the failed entry.
+    pub fn for_each_entry_tolerant<F>(&mut self, mut func: F)
+    where
+        F: FnMut(&mut JournalEntry) -> Result<()>,
+    {
+        for_each_entry_tolerant(&mut func, &mut self.items)
+    }
+
+    /// Call the provided closure with every entry in the journal, including nested entries, in document order.
+    pub fn for_each_entry<'a, F>(&'a self, mut func: F)
+    where
+        F: FnMut(&'a JournalEntry),
+    {
+        for item in self.iter() {
+            if let JournalItem::Entry(entry) = item {
+                func(entry);
+            }
+        }
+    }
+
+    /// Call the provided closure with every entry in the journal, including nested entries, in document order.
+    /// Stops iterating on the first closure to return an error.
+    pub fn try_for_each_entry<'a, F>(&'a self, mut func: F) -> Result<()>
+    where
+        F: FnMut(&'a JournalEntry) -> Result<()>,
+    {
+        for item in self.iter() {
+            if let JournalItem::Entry(entry) = item {
+                func(entry)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Call the provided closure with every entry in the journal, including nested entries, in document order.
+    pub fn for_each_entry_mut<F>(&mut self, mut func: F)
+    where
+        F: FnMut(&mut JournalEntry),
+    {
+        for_each_entry_mut(&mut func, &mut self.items)
+    }
+
+    /// Call the provided closure with every entry in the journal, including nested entries, in document order.
+    /// Stops iterating on the first closure to return an error.
+    pub fn try_for_each_entry_mut<F>(&mut self, mut func: F) -> Result<()>
+    where
+        F: FnMut(&mut JournalEntry) -> Result<()>,
+    {
+        try_for_each_entry_mut(&mut func, &mut self.items)
+    }
+
+    /// Check the journal for structural problems, returning an error that lists every problem found:
+    /// - An entry without a title.
+    /// - An entry whose title has the same slug as another entry's title or a chapter title's anchor, so that
+    ///   links to either are ambiguous.
+    /// - An entry without a path that also has no body or sections, usually a link whose file was never written.
+    /// - An entry whose level is not deeper than the entry it is nested below, or a section whose level is not
+    ///   deeper than the section it is nested below.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_with_strategy(SlugStrategy::default())
+    }
+
+    /// Check the journal for structural problems like `validate`, comparing the slugs of titles made with the
+    /// given strategy.
+    pub fn validate_with_strategy(&self, strategy: SlugStrategy) -> Result<()> {
+        let mut validator = Validator {
+            strategy,
+            ..Default::default()
+        };
+        validator.validate_items(&self.items, None);
+
+        if validator.problems.is_empty() {
+            return Ok(());
+        }
+
+        anyhow::bail!(
+            "The journal has {} problem(s):\n- {}",
+            validator.problems.len(),
+            validator.problems.join("\n- ")
+        )
+    }
+}
+
+/// Collects the problems found by `Journal::validate`.
+#[derive(Default)]
+struct Validator {
+    /// The description of the first entry or chapter title with each slug.
+    slugs: HashMap<String, String>,
+    problems: Vec<String>,
+    strategy: SlugStrategy,
+}
+
+impl Validator {
+    fn validate_items(&mut self, items: &[JournalItem], parent: Option<&JournalEntry>) {
+        for item in items {
+            match item {
+                JournalItem::Entry(entry) => {
+                    self.validate_entry(entry, parent);
+                    self.validate_items(&entry.children, Some(entry));
+                }
+                JournalItem::ChapterTitle(chapter) => {
+                    let anchor = match chapter.anchor.is_empty() {
+                        true => self.strategy.slugify(&chapter.title),
+                        false => chapter.anchor.clone(),
+                    };
+
+                    self.check_slug(anchor, format!("chapter title \"{}\"", chapter.title));
+                }
+                JournalItem::Separator(_) => {}
+                JournalItem::Error(error) => self.validate_items(&error.children, parent),
+            }
+        }
+    }
+
+    fn validate_entry(&mut self, entry: &JournalEntry, parent: Option<&JournalEntry>) {
+        let name = match (entry.title.trim().is_empty(), &entry.path) {
+            (false, _) => format!("entry \"{}\"", entry.title),
+            (true, Some(path)) => format!("entry at {}", path.display()),
+            (true, None) => String::from("untitled entry"),
+        };
+
+        if entry.title.trim().is_empty() {
+            self.problems.push(format!("The {name} has no title"));
+        } else {
+            self.check_slug(self.strategy.slugify(&entry.title), name.clone());
+        }
+
+        let empty_body = entry
+            .body
+            .as_deref()
+            .is_none_or(|body| body.trim().is_empty());
+        if entry.path.is_none() && !entry.draft && empty_body && entry.sections.is_empty() {
+            self.problems
+                .push(format!("The {name} has no file and no content"));
+        }
+
+        if let Some(parent) = parent.filter(|parent| entry.level <= parent.level) {
+            self.problems.push(format!(
+                "The {name} is nested below \"{}\" but has level {}, which is not deeper than {}",
+                parent.title, entry.level, parent.level
+            ));
+        }
+
+        self.validate_sections(&name, &entry.sections, None);
+    }
+
+    fn validate_sections(&mut self, name: &str, sections: &[Section], parent: Option<&Section>) {
+        for section in sections {
+            if let Some(parent) = parent.filter(|parent| section.level <= parent.level) {
+                self.problems.push(format!(
+                    "The section \"{}\" of the {name} is nested below \"{}\" but is not at a deeper level",
+                    section.title, parent.title
+                ));
+            }
+
+            self.validate_sections(name, &section.sections, Some(section));
+        }
+    }
+
+    fn check_slug(&mut self, slug: String, name: String) {
+        match self.slugs.get(&slug) {
+            Some(first) => self.problems.push(format!(
+                "The {name} has the same slug `{slug}` as the {first}"
+            )),
+            None => {
+                self.slugs.insert(slug, name);
+            }
+        }
+    }
+}
+
+fn normalize_line_endings(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) if text.contains("\r\n") => {
+            *text = text.replace("\r\n", "\n");
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(normalize_line_endings),
+        serde_json::Value::Object(values) => values.values_mut().for_each(normalize_line_endings),
+        _ => {}
+    }
+}
+
+fn push_outline_items(
+    outline: &mut String,
+    items: &[JournalItem],
+    depth: usize,
+    max_depth: Option<SectionLevel>,
+) {
+    for item in items {
+        outline.push_str(&"  ".repeat(depth));
+
+        match item {
+            JournalItem::Entry(entry) => {
+                outline.push_str(&entry.title);
+                outline.push('\n');
+                push_outline_sections(outline, &entry.sections, depth, max_depth);
+                push_outline_items(outline, &entry.children, depth + 1, max_depth);
+            }
+            JournalItem::ChapterTitle(chapter) => {
+                outline.push_str(&chapter.title);
+                outline.push('\n');
+            }
+            JournalItem::Separator(_) => outline.push_str("---\n"),
+            JournalItem::Error(error) => {
+                outline.push_str(&format!("{} (failed to build)\n", error.title));
+                push_outline_items(outline, &error.children, depth + 1, max_depth);
+            }
+        }
+    }
+}
+
+fn push_outline_sections(
+    outline: &mut String,
+    sections: &[Section],
+    depth: usize,
+    max_depth: Option<SectionLevel>,
+) {
+    for section in sections {
+        if max_depth.is_some_and(|max_depth| section.level > max_depth) {
+            continue;
+        }
+
+        let level = usize::from(section.level.as_u8());
+
+        outline.push_str(&"  ".repeat(depth + level));
+        outline.push_str(&"#".repeat(level));
+        outline.push(' ');
+        outline.push_str(&section.title);
+        outline.push('\n');
+        push_outline_sections(outline, &section.sections, depth, max_depth);
+    }
+}
+
+fn for_each_entry_mut<F>(func: &mut F, items: &mut [JournalItem])
+where
+    F: FnMut(&mut JournalEntry),
+{
+    for item in items {
+        if let JournalItem::Entry(entry) = item {
+            func(entry);
+        }
+
+        if let Some(children) = item.children_mut() {
+            for_each_entry_mut(func, children);
+        }
+    }
+}
+
+fn try_for_each_entry_mut<F>(func: &mut F, items: &mut [JournalItem]) -> Result<()>
+where
+    F: FnMut(&mut JournalEntry) -> Result<()>,
+{
+    for item in items {
+        if let JournalItem::Entry(entry) = item {
+            func(entry)?;
+        }
+
+        if let Some(children) = item.children_mut() {
+            try_for_each_entry_mut(func, children)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn for_each_entry_tolerant<F>(func: &mut F, items: &mut [JournalItem])
+where
+    F: FnMut(&mut JournalEntry) -> Result<()>,
+{
+    for item in items.iter_mut() {
+        if let JournalItem::Entry(entry) = item {
+            if let Err(error) = func(entry) {
+                *item = JournalItem::Error(EntryError::new(mem::take(entry), &error));
+            }
+        }
+
+        if let Some(children) = item.children_mut() {
+            for_each_entry_tolerant(func, children);
+        }
+    }
+}
+
+/// An iterator over the items of a journal in document order. See `Journal::iter`.
+pub struct JournalItems<'a> {
+    stack: Vec<slice::Iter<'a, JournalItem>>,
+}
+
+impl<'a> Iterator for JournalItems<'a> {
+    type Item = &'a JournalItem;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let items = self.stack.last_mut()?;
+            let Some(item) = items.next() else {
+                self.stack.pop();
+                continue;
+            };
+
+            self.stack.push(item.children().iter());
+
+            return Some(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(title: &str, children: Vec<JournalItem>) -> JournalItem {
+        JournalItem::Entry(JournalEntry {
+            title: String::from(title),
+            children,
+            ..Default::default()
+        })
+    }
+
+    fn titles(journal: &Journal) -> Vec<&str> {
+        journal
+            .iter()
+            .map(|item| match item {
+                JournalItem::Entry(entry) => entry.title.as_str(),
+                JournalItem::ChapterTitle(chapter) => chapter.title.as_str(),
+                JournalItem::Separator(_) => "---",
+                JournalItem::Error(error) => error.title.as_str(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn iterates_nested_items_in_document_order() {
+        let journal = Journal {
+            title: None,
+            items: vec![
+                entry("Town", vec![entry("Tavern", vec![entry("Cellar", vec![])])]),
+                JournalItem::Separator(Separator::default()),
+                entry("Dungeon", vec![entry("Crypt", vec![])]),
+            ],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        assert_eq!(
+            vec!["Town", "Tavern", "Cellar", "---", "Dungeon", "Crypt"],
+            titles(&journal)
+        );
+    }
+
+    #[test]
+    fn snapshots_the_same_journal_regardless_of_line_endings() {
+        let snapshot = |body: &str| {
+            let entry = JournalEntry {
+                path: Some(PathBuf::from("./tavern.md")),
+                content_hash: Some(format!("{:x}", body.len())),
+                ..JournalEntry::new("Tavern", body)
+            }
+            .parse()
+            .expect("should parse");
+
+            Journal::from_entries(None, vec![entry]).to_snapshot_string()
+        };
+
+        let snapshot_with_lf = snapshot("Ale\n\n# Cellar\n\nBarrels\nand rats\n");
+
+        assert_eq!(
+            snapshot_with_lf,
+            snapshot("Ale\r\n\r\n# Cellar\r\n\r\nBarrels\r\nand rats\r\n")
+        );
+        assert!(
+            snapshot_with_lf.contains("\"Barrels\\nand rats\""),
+            "{snapshot_with_lf}"
+        );
+        assert!(!snapshot_with_lf.contains("span"), "{snapshot_with_lf}");
+    }
+
+    #[test]
+    fn saves_and_opens_journals() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let path = dir.path().join("journal.json");
+        let journal = Journal {
+            title: Some(String::from("Campaign")),
+            items: vec![
+                entry("Town", vec![entry("Tavern", vec![])]),
+                JournalItem::Separator(Separator::default()),
+            ],
+            tags: BTreeMap::from([(String::from("npc"), vec![PathBuf::from("tavern.md")])]),
+            scratch: BTreeMap::from([(
+                String::from("statistics"),
+                serde_json::json!({"entry_count": 2}),
+            )]),
+        };
+
+        journal.save(&path).expect("should save");
+
+        assert_eq!(journal, Journal::open(&path).expect("should open"));
+
+        let json = fs::read_to_string(&path).expect("should read");
+        fs::write(&path, json.replace("\"version\": 1", "\"version\": 99")).expect("should write");
+        let err = Journal::open(&path).expect_err("should fail on another version");
+
+        assert!(err.to_string().contains("format version 99"), "{err}");
+    }
+
+    #[test]
+    fn flattens_entries_into_documents() {
+        let mut town = entry("Town", vec![entry("Tavern", vec![])]);
+
+        if let JournalItem::Entry(ref mut town) = town {
+            town.path = Some(PathBuf::from("town.md"));
+            town.body = Some(String::from("A quiet town.\n"));
+        }
+
+        let journal = Journal {
+            title: None,
+            items: vec![JournalItem::Separator(Separator::default()), town],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        assert_eq!(
+            vec![
+                Document {
+                    path: Some(PathBuf::from("town.md")),
+                    title: String::from("Town"),
+                    content: String::from("A quiet town."),
+                },
+                Document {
+                    path: None,
+                    title: String::from("Tavern"),
+                    content: String::new(),
+                },
+            ],
+            journal.to_documents()
+        );
+    }
+
+    #[test]
+    fn outlines_titles_without_bodies() {
+        let section = |title: &str, level, sections| Section {
+            title: String::from(title),
+            level,
+            body: String::from("Hidden body"),
+            sections,
+            ..Default::default()
+        };
+
+        let mut town = entry("Town", vec![entry("Tavern", vec![])]);
+        if let JournalItem::Entry(ref mut town) = town {
+            town.sections = vec![section(
+                "Streets",
+                SectionLevel::H1,
+                vec![section("Market", SectionLevel::H2, vec![])],
+            )];
+        }
+
+        let journal = Journal {
+            title: Some(String::from("Campaign")),
+            items: vec![
+                JournalItem::ChapterTitle(ChapterTitle {
+                    title: String::from("Part One"),
+                    anchor: String::from("part-one"),
+                }),
+                town,
+                JournalItem::Separator(Separator::default()),
+            ],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        assert_eq!(
+            "Campaign\nPart One\nTown\n  # Streets\n    ## Market\n  Tavern\n---\n",
+            journal.to_outline(None)
+        );
+        assert_eq!(
+            "Campaign\nPart One\nTown\n  # Streets\n  Tavern\n---\n",
+            journal.to_outline(Some(SectionLevel::H1))
+        );
+    }
+
+    #[test]
+    fn summarizes_the_journal() {
+        let parsed = |title: &str, body: &str, children| {
+            let entry = JournalEntry {
+                title: String::from(title),
+                body: Some(String::from(body)),
+                children,
+                ..Default::default()
+            };
+
+            JournalItem::Entry(entry.parse().expect("should parse"))
+        };
+        let journal = Journal {
+            title: None,
+            items: vec![
+                JournalItem::ChapterTitle(ChapterTitle {
+                    title: String::from("Part One"),
+                    anchor: String::new(),
+                }),
+                parsed(
+                    "Town",
+                    "Busy streets\n\n# Tavern\n\nAle\n\n## Cellar\n\n### Vault",
+                    vec![parsed("Dock", "# Pier\n\nBoats", vec![])],
+                ),
+                JournalItem::Separator(Separator::default()),
+                parsed("Notes", "Loose notes", vec![]),
+            ],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        assert_eq!(
+            JournalStats {
+                entry_count: 3,
+                chapter_title_count: 1,
+                separator_count: 1,
+                error_count: 0,
+                section_count: 4,
+                max_section_depth: 3,
+                word_count: 10,
+            },
+            journal.stats()
+        );
+        assert_eq!(journal.word_count(), journal.stats().word_count);
+    }
+
+    #[test]
+    fn lists_metadata_keys_by_language() {
+        let parsed = |title: &str, body: &str, children| {
+            let entry = JournalEntry {
+                title: String::from(title),
+                body: Some(String::from(body)),
+                children,
+                ..Default::default()
+            };
+
+            JournalItem::Entry(entry.parse_with_metadata().expect("should parse"))
+        };
+        let journal = Journal {
+            title: None,
+            items: vec![
+                parsed(
+                    "Town",
+                    "# Tavern\n\n```toml,metadata,stats\nac = 12\n```\n\n\
+                     ## Cellar\n\n```json,metadata,loot\n{}\n```\n\n```toml,metadata,stats\nac = 14\n```",
+                    vec![parsed(
+                        "Dock",
+                        "# Pier\n\n```toml,metadata,npc\nname = \"Ada\"\n```\n\n```metadata,notes\nQuiet\n```",
+                        vec![],
+                    )],
+                ),
+                parsed("Notes", "Loose notes", vec![]),
+            ],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        let set = |keys: &[&str]| {
+            keys.iter()
+                .copied()
+                .map(String::from)
+                .collect::<BTreeSet<_>>()
+        };
+
+        assert_eq!(
+            BTreeMap::from([
+                (String::new(), set(&["notes"])),
+                (String::from("json"), set(&["loot"])),
+                (String::from("toml"), set(&["npc", "stats"])),
+            ]),
+            journal.metadata_keys()
+        );
+    }
+
+    #[test]
+    fn visits_nested_entries_mutably() {
+        let mut journal = Journal {
+            title: None,
+            items: vec![entry("Town", vec![entry("Tavern", vec![])])],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        journal.for_each_entry_mut(|entry| entry.title.make_ascii_uppercase());
+
+        assert_eq!(vec!["TOWN", "TAVERN"], titles(&journal));
+    }
+
+    #[test]
+    fn visits_nested_entries_until_an_error() {
+        let journal = Journal {
+            title: None,
+            items: vec![
+                entry("Town", vec![entry("Tavern", vec![])]),
+                JournalItem::Separator(Separator::default()),
+                entry("Dungeon", vec![]),
+            ],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        let mut visited = Vec::new();
+        journal.for_each_entry(|entry| visited.push(entry.title.as_str()));
+        assert_eq!(vec!["Town", "Tavern", "Dungeon"], visited);
+
+        let mut visited = Vec::new();
+        let err = journal
+            .try_for_each_entry(|entry| {
+                visited.push(entry.title.as_str());
+
+                match entry.title.as_str() {
+                    "Tavern" => anyhow::bail!("Stopped at the tavern"),
+                    _ => Ok(()),
+                }
+            })
+            .expect_err("should stop");
+        assert_eq!("Stopped at the tavern", err.to_string());
+        assert_eq!(vec!["Town", "Tavern"], visited);
+    }
+
+    #[test]
+    fn validates_without_problems() {
+        let mut journal = Journal {
+            title: None,
+            items: vec![entry("Town", vec![entry("Tavern", vec![])])],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        journal.for_each_entry_mut(|entry| {
+            entry.path = Some(PathBuf::from(format!("{}.md", entry.title)));
+            entry.level = match entry.title.as_str() {
+                "Town" => 1,
+                _ => 2,
+            };
+        });
+
+        assert!(journal.validate().is_ok());
+    }
+
+    #[test]
+    fn lists_every_structural_problem() {
+        let mut nested = entry("The Tavern", vec![]);
+        if let JournalItem::Entry(ref mut nested) = nested {
+            nested.path = Some(PathBuf::from("tavern.md"));
+            nested.sections = vec![Section {
+                title: String::from("Rooms"),
+                level: SectionLevel::H2,
+                sections: vec![Section {
+                    title: String::from("Cellar"),
+                    level: SectionLevel::H1,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }];
+        }
+
+        let journal = Journal {
+            title: None,
+            items: vec![
+                JournalItem::ChapterTitle(ChapterTitle {
+                    title: String::from("The Tavern"),
+                    anchor: String::from("the-tavern"),
+                }),
+                entry("", vec![]),
+                entry("Town", vec![nested]),
+            ],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+
+        let err = journal.validate().expect_err("should fail");
+
+        assert_eq!(
+            "The journal has 6 problem(s):\n\
+             - The untitled entry has no title\n\
+             - The untitled entry has no file and no content\n\
+             - The entry \"Town\" has no file and no content\n\
+             - The entry \"The Tavern\" has the same slug `the-tavern` as the chapter title \"The Tavern\"\n\
+             - The entry \"The Tavern\" is nested below \"Town\" but has level 0, which is not deeper than 0\n\
+             - The section \"Cellar\" of the entry \"The Tavern\" is nested below \"Rooms\" but is not at a deeper level",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn finds_the_neighbors_of_entries_in_document_order() {
+        let entry_at = |title: &str, path: Option<&str>, children| {
+            JournalItem::Entry(JournalEntry {
+                title: String::from(title),
+                path: path.map(PathBuf::from),
+                children,
+                ..Default::default()
+            })
+        };
+        let journal = Journal {
+            title: None,
+            items: vec![
+                entry_at(
+                    "Town",
+                    Some("town.md"),
+                    vec![entry_at(
+                        "Tavern",
+                        Some("./town/tavern.md"),
+                        vec![entry_at("Cellar", None, vec![])],
+                    )],
+                ),
+                JournalItem::Separator(Separator::default()),
+                JournalItem::ChapterTitle(ChapterTitle {
+                    title: String::from("Part II"),
+                    anchor: String::from("part-ii"),
+                }),
+                entry_at("Dungeon", Some("dungeon.md"), vec![]),
+            ],
+            tags: BTreeMap::new(),
+            scratch: BTreeMap::new(),
+        };
+        let titles = |(previous, next): (Option<&JournalEntry>, Option<&JournalEntry>)| {
+            (
+                previous.map(|entry| entry.title.clone()),
+                next.map(|entry| entry.title.clone()),
+            )
+        };
+
+        assert_eq!(
+            (None, Some(String::from("Tavern"))),
+            titles(journal.neighbors(Path::new("town.md")))
+        );
+        assert_eq!(
+            (Some(String::from("Town")), Some(String::from("Cellar"))),
+            titles(journal.neighbors(Path::new("town/tavern.md")))
+        );
+        assert_eq!(
+            (Some(String::from("Cellar")), None),
+            titles(journal.neighbors(Path::new("./dungeon.md")))
+        );
+        assert_eq!(
+            (None, None),
+            titles(journal.neighbors(Path::new("missing.md")))
+        );
+
+        let JournalItem::Entry(ref cellar) = journal.items[0].children()[0].children()[0] else {
+            panic!("cellar was not an entry")
+        };
+
+        assert_eq!(
+            (Some(String::from("Tavern")), Some(String::from("Dungeon"))),
+            titles(journal.neighbors_of(cellar))
+        );
+    }
 }
@@ -2,17 +2,21 @@ use anyhow::{anyhow, bail, Context};
 use pulldown_cmark::{Event, HeadingLevel, Tag};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     fmt::Display,
-    fs,
-    path::{Path, PathBuf},
+    mem,
+    path::{Component, Path, PathBuf},
 };
 
 use crate::{
-    cmark::{CMarkParser, EventIteratorExt},
+    cmark::{read_source, CMarkParser, EventIteratorExt, Position},
     error::{Error, Result},
 };
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The prefix of link titles setting the heading offset of the linked entry.
+const HEADING_OFFSET_PREFIX: &str = "heading-offset=";
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct TableOfContents {
     /// An optional title for the TOC.
     pub title: Option<String>,
@@ -21,10 +25,27 @@ pub struct TableOfContents {
 }
 
 impl TableOfContents {
-    /// Load the table of contents from JOURNAL.md relative to the provided path.
-    pub fn load(source_path: impl AsRef<Path>) -> Result<Self> {
-        let journal_path = source_path.as_ref().join("JOURNAL.md");
-        let source = fs::read_to_string(&journal_path)
+    /// Load the table of contents from the summary file (usually JOURNAL.md) relative to the provided path.
+    /// Every link must point inside the source directory, and no two links may point to the same location.
+    /// Separators are collapsed as with `collapse_separators`.
+    ///
+    /// A link to a directory, written with a trailing `/` such as `[Chapter](chapter/)`, points at the index file of
+    /// that directory, and the locations of the links nested below it are relative to that directory rather than to
+    /// the source directory. See `Link::is_directory`.
+    pub fn load(source_path: impl AsRef<Path>, summary_file: impl AsRef<Path>) -> Result<Self> {
+        let mut toc = Self::load_exact(source_path, summary_file)?;
+        toc.collapse_separators();
+
+        Ok(toc)
+    }
+
+    /// Load the table of contents like `load`, keeping every separator where it is written.
+    pub fn load_exact(
+        source_path: impl AsRef<Path>,
+        summary_file: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let journal_path = source_path.as_ref().join(summary_file);
+        let source = read_source(&journal_path)
             .with_context(|| format!("Failed to open {}", journal_path.display()))?;
 
         let (title, items) = TOCParser::new(&source)
@@ -33,6 +54,134 @@ impl TableOfContents {
 
         Ok(Self { title, items })
     }
+
+    /// Load the table of contents from an mdBook style summary file (usually SUMMARY.md) relative to the
+    /// provided path. The following subset of mdBook's syntax is supported:
+    ///
+    /// - An optional `# Title` heading at the start of the file, which becomes the title.
+    /// - Prefix and suffix chapters, which are links outside of a list such as `[Introduction](intro.md)`.
+    ///   These become top level links.
+    /// - Numbered chapters, which are (nested) list items containing a single link.
+    /// - Draft chapters, which are links without a location such as `[Title]()`. These become links without
+    ///   a location.
+    /// - Part titles, which are `# Part Title` headings. These become section titles.
+    /// - Separators, which are `---` rules. These become separators, labeled by an optional
+    ///   `<!-- separator: label -->` comment immediately before the rule.
+    ///
+    /// HTML comments are ignored. Any other construct, such as text outside of a link or code blocks, is an error,
+    /// as are links outside of the source directory and links to a location that is already linked.
+    pub fn load_summary(
+        source_path: impl AsRef<Path>,
+        summary_file: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let summary_path = source_path.as_ref().join(summary_file);
+        let source = read_source(&summary_path)
+            .with_context(|| format!("Failed to open {}", summary_path.display()))?;
+
+        let (title, items) = TOCParser::new_summary(&source)
+            .parse()
+            .with_context(|| format!("Failed to parse {}", summary_path.display()))?;
+
+        Ok(Self { title, items })
+    }
+
+    /// Collapse each run of consecutive separators into a single separator, keeping the first label of the run, and
+    /// remove the separators at the start and end of the table of contents, which separate nothing.
+    pub fn collapse_separators(&mut self) {
+        let mut items: Vec<TOCItem> = Vec::with_capacity(self.items.len());
+
+        for item in mem::take(&mut self.items) {
+            match (items.last_mut(), item) {
+                (None, TOCItem::Separator(_)) => {}
+                (Some(TOCItem::Separator(previous)), TOCItem::Separator(separator)) => {
+                    if previous.label.is_none() {
+                        previous.label = separator.label;
+                    }
+                }
+                (_, item) => items.push(item),
+            }
+        }
+
+        if items.last().is_some_and(TOCItem::is_separator) {
+            items.pop();
+        }
+
+        self.items = items;
+    }
+
+    /// Write the table of contents as a JOURNAL.md that loads back into an equal table of contents, for tools
+    /// that edit it. The title and section titles are written as H1 headings, links as (nested) lists and
+    /// separators as `---` rules, preceded by a `<!-- separator: label -->` comment when labeled. The nesting
+    /// level of links is taken from where they are nested, not from `Link::level`.
+    ///
+    /// The first H1 heading of a JOURNAL.md is its title, so a table of contents without a title that starts with
+    /// a section title loads back with that section title as its title.
+    pub fn to_markdown(&self) -> String {
+        let mut blocks = Vec::new();
+        let mut list = String::new();
+
+        if let Some(ref title) = self.title {
+            blocks.push(format!("# {title}"));
+        }
+
+        for item in &self.items {
+            let block = match item {
+                TOCItem::Link(link) => {
+                    write_link(&mut list, link, 0);
+                    continue;
+                }
+                TOCItem::SectionTitle(section) => format!("# {}", section.title),
+                TOCItem::Separator(Separator { label: Some(label) }) => {
+                    format!("<!-- separator: {label} -->\n---")
+                }
+                TOCItem::Separator(_) => String::from("---"),
+            };
+
+            if !list.is_empty() {
+                blocks.push(mem::take(&mut list).trim_end().to_string());
+            }
+
+            blocks.push(block);
+        }
+
+        if !list.is_empty() {
+            blocks.push(list.trim_end().to_string());
+        }
+
+        let mut markdown = blocks.join("\n\n");
+        markdown.push('\n');
+        markdown
+    }
+}
+
+/// Write a link as a list item indented to `depth`, followed by the links nested below it.
+fn write_link(markdown: &mut String, link: &Link, depth: usize) {
+    let location = link
+        .location
+        .as_ref()
+        .map(|location| location.to_string_lossy().replace(' ', "%20"))
+        .unwrap_or_default();
+
+    let title = match link.title {
+        _ if link.heading_offset != 0 => {
+            format!(" \"{HEADING_OFFSET_PREFIX}{}\"", link.heading_offset)
+        }
+        Some(ref title) => format!(" \"{}\"", title.replace('"', "\\\"")),
+        None => String::new(),
+    };
+
+    markdown.push_str(&format!(
+        "{:indent$}- [{}]({location}{title})\n",
+        "",
+        link.name,
+        indent = depth * 2
+    ));
+
+    for item in &link.nested_items {
+        if let TOCItem::Link(nested) = item {
+            write_link(markdown, nested, depth + 1);
+        }
+    }
 }
 
 #[non_exhaustive]
@@ -46,6 +195,29 @@ pub struct Link {
     pub nested_items: Vec<TOCItem>,
     /// The nesting level of this link.
     pub level: u8,
+    /// The number of levels the headings of the linked entry are shifted by when it is parsed, set with a
+    /// `"heading-offset=N"` link title, such as `[Goblins](./goblins.md "heading-offset=1")`.
+    #[serde(default)]
+    pub heading_offset: i8,
+    /// The title attribute of the link, such as `The old keep` for `[Keep](keep.md "The old keep")`, for tooltips
+    /// in navigation. `None` for links without a title and for heading offset titles.
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+impl Link {
+    /// A link without a location is a draft, whose entry has not been written yet.
+    pub fn is_draft(&self) -> bool {
+        self.location.is_none()
+    }
+
+    /// A link whose location ends with a `/`, such as `chapter/`, points at a directory, and the entry is loaded from
+    /// the index file inside it, as set by `journal.index-file`.
+    pub fn is_directory(&self) -> bool {
+        self.location
+            .as_deref()
+            .is_some_and(|location| location.to_string_lossy().ends_with(['/', '\\']))
+    }
 }
 
 #[non_exhaustive]
@@ -55,6 +227,22 @@ pub struct SectionTitle {
     pub title: String,
 }
 
+/// A separator between unnamed sections of the table of contents, provided by a `---` rule.
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Separator {
+    /// An optional label describing the kind of break, taken from a `<!-- separator: label -->` comment
+    /// immediately before the rule. Renderers can use it to tell page breaks from thin rules, for example.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl Separator {
+    pub fn new(label: Option<String>) -> Self {
+        Self { label }
+    }
+}
+
 /// A table of contents item which is either a link, a separator, or a section title.
 #[non_exhaustive]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -64,7 +252,7 @@ pub enum TOCItem {
     /// Section title for a portion of the table of contents.
     SectionTitle(SectionTitle),
     /// A separator between unnamed sections.
-    Separator,
+    Separator(Separator),
 }
 
 impl TOCItem {
@@ -96,20 +284,50 @@ impl TOCItem {
         }
     }
 
+    pub fn maybe_separator(&self) -> Option<&Separator> {
+        match self {
+            TOCItem::Separator(ref separator) => Some(separator),
+            _ => None,
+        }
+    }
+
     pub fn is_separator(&self) -> bool {
-        matches! { self, TOCItem::Separator }
+        matches! { self, TOCItem::Separator(_) }
     }
 }
 
 struct TOCParser<'a> {
     parser: CMarkParser<'a>,
+    summary: bool,
+    /// The position of the first link to each location, keyed by the normalized location.
+    locations: HashMap<PathBuf, Position>,
+    /// The directory that the locations of the links being parsed are relative to, when they are nested below a
+    /// link to a directory.
+    base: PathBuf,
 }
 
 impl<'a> TOCParser<'a> {
     fn new(source: &'a str) -> Self {
         let parser = CMarkParser::new(source);
 
-        Self { parser }
+        Self {
+            parser,
+            summary: false,
+            locations: HashMap::new(),
+            base: PathBuf::new(),
+        }
+    }
+
+    /// Create a parser for mdBook style summary files, see `TableOfContents::load_summary`.
+    fn new_summary(source: &'a str) -> Self {
+        let parser = CMarkParser::new(source);
+
+        Self {
+            parser,
+            summary: true,
+            locations: HashMap::new(),
+            base: PathBuf::new(),
+        }
     }
 
     fn parse(mut self) -> Result<(Option<String>, Vec<TOCItem>)> {
@@ -182,6 +400,8 @@ impl<'a> TOCParser<'a> {
 
     fn parse_toc_items(&mut self, level: u8) -> Result<Vec<TOCItem>> {
         let mut items = Vec::new();
+        // NOTE: Whether the events are inside of the last list item, where a list is nested below that item.
+        let mut in_item = false;
 
         loop {
             match self.parser.peek_event() {
@@ -189,8 +409,14 @@ impl<'a> TOCParser<'a> {
                 Some(Event::Start(Tag::Item)) => {
                     self.parser.next_event();
 
-                    let item = self.parse_toc_item(level)?;
+                    let position = self.parser.position();
+                    let item = self.parse_toc_item(level, position)?;
                     items.push(item);
+                    in_item = true;
+                }
+                Some(Event::End(Tag::Item)) => {
+                    self.parser.next_event();
+                    in_item = false;
                 }
                 Some(Event::Start(Tag::List(..))) => {
                     self.parser.next_event();
@@ -199,14 +425,45 @@ impl<'a> TOCParser<'a> {
                         continue;
                     }
 
-                    if let Some(last_item) = items.last_mut().and_then(TOCItem::maybe_link_mut) {
-                        last_item.nested_items = self.parse_toc_items(level + 1)?;
+                    let last_item = items.last_mut().and_then(TOCItem::maybe_link_mut);
+
+                    // NOTE: A list nested below anything but a link, such as a separator inside of a list item,
+                    // would otherwise be read as siblings of the items around it.
+                    if in_item && last_item.is_none() {
+                        bail!(self.parse_error(
+                            "A nested list must directly follow the link of its parent item."
+                        ))
+                    }
+
+                    if let Some(last_item) = last_item {
+                        let base = match last_item.location {
+                            Some(ref location) if last_item.is_directory() => {
+                                join_location(&self.base, location)
+                            }
+                            _ => self.base.clone(),
+                        };
+                        let base = mem::replace(&mut self.base, base);
+                        let nested_items = self.parse_toc_items(level + 1);
+                        self.base = base;
+
+                        last_item.nested_items = nested_items?;
                     }
                 }
                 Some(Event::End(Tag::List(..))) => {
                     self.parser.next_event();
                     break;
                 }
+                Some(Event::Start(Tag::Paragraph)) if self.summary && level == 1 => {
+                    self.parser.next_event();
+
+                    let links = self.parse_summary_links()?;
+                    items.extend(links);
+                }
+                Some(Event::Start(_)) if self.summary => {
+                    self.parser.next_event();
+
+                    bail!(self.parse_error("Unsupported element in summary."))
+                }
                 Some(Event::Start(other_tag)) => {
                     let other_tag = other_tag.clone();
 
@@ -216,9 +473,24 @@ impl<'a> TOCParser<'a> {
                         }
                     }
                 }
+                Some(Event::Html(html)) => {
+                    let label = parse_separator_label(html);
+                    let label = match self.parser.peek_nth(1) {
+                        Some(Event::Rule) => label,
+                        _ => None,
+                    };
+
+                    self.parser.next_event();
+
+                    if label.is_some() {
+                        // NOTE: Consume the rule the label belongs to.
+                        self.parser.next_event();
+                        items.push(TOCItem::Separator(Separator::new(label)));
+                    }
+                }
                 Some(Event::Rule) => {
                     self.parser.next_event();
-                    items.push(TOCItem::Separator)
+                    items.push(TOCItem::Separator(Separator::default()))
                 }
                 Some(_) => {
                     self.parser.next_event();
@@ -230,26 +502,57 @@ impl<'a> TOCParser<'a> {
         Ok(items)
     }
 
-    fn parse_toc_item(&mut self, level: u8) -> Result<TOCItem> {
+    /// Parse a list item starting at `position`, which must only contain a link and the items nested below it.
+    fn parse_toc_item(&mut self, level: u8, position: Position) -> Result<TOCItem> {
         loop {
             match self.parser.next_event() {
                 Some(Event::Start(Tag::Paragraph)) => continue,
-                Some(Event::Start(Tag::Link(_, href, _))) => {
-                    let link = self.parse_link(href.to_string(), level)?;
+                Some(Event::Start(Tag::Link(_, href, title))) => {
+                    let link = self.parse_link(href.to_string(), &title, level)?;
 
                     return Ok(TOCItem::Link(link));
                 }
-                _ => {
-                    bail!(
-                        self.parse_error("Items in the table of contents must only contain links.")
-                    )
+                // NOTE: Report the item itself, as the offending event can be on a later line, such as the nested
+                // list of an item without a link.
+                _ => bail!(parse_error(
+                    position,
+                    "Items in the table of contents must only contain links."
+                )),
+            }
+        }
+    }
+
+    /// Parse the prefix or suffix chapters of an mdBook summary, which are links in a paragraph.
+    fn parse_summary_links(&mut self) -> Result<Vec<TOCItem>> {
+        let mut items = Vec::new();
+
+        loop {
+            match self.parser.next_event() {
+                Some(Event::Start(Tag::Link(_, href, title))) => {
+                    let link = self.parse_link(href.to_string(), &title, 1)?;
+                    items.push(TOCItem::Link(link));
                 }
+                Some(Event::End(Tag::Paragraph)) | None => break,
+                Some(Event::SoftBreak | Event::HardBreak) => continue,
+                Some(Event::Text(text)) if text.trim().is_empty() => continue,
+                Some(Event::Html(_)) => continue,
+                _ => bail!(self.parse_error(
+                    "Prefix and suffix chapters in a summary must only contain links."
+                )),
             }
         }
+
+        Ok(items)
     }
 
-    fn parse_link(&mut self, href: String, level: u8) -> Result<Link> {
+    fn parse_link(&mut self, href: String, title: &str, level: u8) -> Result<Link> {
+        let position = self.parser.position();
         let href = href.replace("%20", " ");
+        let heading_offset = self.parse_heading_offset(title)?;
+        let title = match title.trim() {
+            title if title.is_empty() || title.starts_with(HEADING_OFFSET_PREFIX) => None,
+            title => Some(String::from(title)),
+        };
         let name: String = self
             .parser
             .iter_until_and_consume(|event| matches! {event, Event::End(Tag::Link(..))})
@@ -265,32 +568,128 @@ impl<'a> TOCParser<'a> {
             Some(PathBuf::from(href))
         };
 
+        if let Some(ref location) = location {
+            self.check_location(&join_location(&self.base, location), position)?;
+        }
+
         let link = Link {
             name,
             location,
             nested_items: Vec::new(),
             // TODO: Track parent level.
             level,
+            heading_offset,
+            title,
         };
 
         Ok(link)
     }
 
+    /// Read the heading offset from a link title of the form `heading-offset=N`. Other titles are ignored.
+    fn parse_heading_offset(&self, title: &str) -> Result<i8> {
+        let Some(offset) = title.trim().strip_prefix(HEADING_OFFSET_PREFIX) else {
+            return Ok(0);
+        };
+
+        offset.trim().parse().map_err(|_| {
+            self.parse_error(format!(
+                "Expected a heading offset between -128 and 127, found: {offset}"
+            ))
+        })
+    }
+
+    /// Reject locations outside of the source directory, and locations that another link already points to,
+    /// comparing locations once `.` and `..` components have been resolved.
+    fn check_location(&mut self, location: &Path, position: Position) -> Result<()> {
+        let Some(normalized) = normalize_location(location) else {
+            bail!(self.parse_error(format!(
+                "The location {} is outside of the source directory.",
+                location.display()
+            )))
+        };
+
+        if let Some(first) = self.locations.get(&normalized) {
+            bail!(self.parse_error(format!(
+                "The location {} is already linked at {first}.",
+                location.display()
+            )))
+        }
+
+        self.locations.insert(normalized, position);
+
+        Ok(())
+    }
+
     fn parse_error(&self, message: impl Display) -> Error {
-        let position = self.parser.position();
+        parse_error(self.parser.position(), message)
+    }
+}
 
-        anyhow!(
-            "failed to parse JOURNAL.md line: {}, column: {}: {}",
-            position.line,
-            position.column,
-            message
-        )
+fn parse_error(position: Position, message: impl Display) -> Error {
+    anyhow!(
+        "failed to parse JOURNAL.md line: {}, column: {}: {}",
+        position.line,
+        position.column,
+        message
+    )
+}
+
+/// The location of a link nested below a link to a directory, relative to the source directory rather than to
+/// `base`, the location of that directory. Locations in a named root, such as `@bestiary/goblin.md`, are kept as
+/// they are, as is every location when `base` is empty.
+pub(crate) fn join_location(base: &Path, location: &Path) -> PathBuf {
+    let rooted = location
+        .to_str()
+        .is_some_and(|location| location.starts_with('@'));
+
+    match rooted || base.as_os_str().is_empty() {
+        true => location.to_path_buf(),
+        false => base.join(location.strip_prefix(".").unwrap_or(location)),
+    }
+}
+
+/// Resolve the `.` and `..` components of a location relative to the source directory, without touching the
+/// file system. Returns `None` for absolute locations and locations that `..` takes outside of the directory.
+fn normalize_location(location: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+
+    for component in location.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(part) => normalized.push(part),
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(normalized)
+}
+
+/// Parse the label out of a `<!-- separator: label -->` comment, returning `None` for any other HTML.
+fn parse_separator_label(html: &str) -> Option<String> {
+    let label = html
+        .trim()
+        .strip_prefix("<!--")?
+        .strip_suffix("-->")?
+        .trim()
+        .strip_prefix("separator:")?
+        .trim();
+
+    if label.is_empty() {
+        None
+    } else {
+        Some(label.to_string())
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::fs;
 
     // Convenience function to parse out TOC.
     fn parse(source: &str) -> (Option<String>, Vec<TOCItem>) {
@@ -305,6 +704,15 @@ mod test {
         assert_eq!("Journal Title", title.expect("toc title was empty"))
     }
 
+    #[test]
+    fn parses_title_without_items() {
+        assert_eq!(
+            (Some(String::from("Journal Title")), Vec::new()),
+            parse("# Journal Title\n\n<!-- Nothing yet -->\n")
+        );
+        assert_eq!((None, Vec::new()), parse(""));
+    }
+
     #[test]
     fn skips_comments_and_parses_title() {
         let input = r"<!-- # Journal Title -->
@@ -328,12 +736,16 @@ mod test {
                 location: Some(PathBuf::from("entry1.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
             TOCItem::Link(Link {
                 name: String::from("Entry 2"),
                 location: Some(PathBuf::from("entry2.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
         ];
 
@@ -354,12 +766,16 @@ mod test {
                 location: Some(PathBuf::from("entry1.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
             TOCItem::Link(Link {
                 name: String::from("Entry 2"),
                 location: Some(PathBuf::from("entry2.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
         ];
 
@@ -381,13 +797,17 @@ mod test {
                 location: Some(PathBuf::from("entry1.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
-            TOCItem::Separator,
+            TOCItem::Separator(Separator::default()),
             TOCItem::Link(Link {
                 name: String::from("Entry 2"),
                 location: Some(PathBuf::from("entry2.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
         ];
 
@@ -409,6 +829,8 @@ mod test {
                 location: Some(PathBuf::from("entry1.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
             TOCItem::SectionTitle(SectionTitle {
                 title: String::from("Next Section"),
@@ -418,6 +840,8 @@ mod test {
                 location: Some(PathBuf::from("entry2.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
         ];
 
@@ -439,12 +863,16 @@ mod test {
                 location: Some(PathBuf::from("entry1.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
             TOCItem::Link(Link {
                 name: String::from("Entry 2"),
                 location: Some(PathBuf::from("entry2.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
         ];
 
@@ -470,14 +898,20 @@ mod test {
                     location: Some(PathBuf::from("sub_entry1.md")),
                     nested_items: Vec::new(),
                     level: 2,
+                    heading_offset: 0,
+                    title: None,
                 })],
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
             TOCItem::Link(Link {
                 name: String::from("Entry 2"),
                 location: Some(PathBuf::from("entry2.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
         ];
 
@@ -500,6 +934,8 @@ This is a paragraph.
                 location: Some(PathBuf::from("entry1.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
             TOCItem::SectionTitle(SectionTitle {
                 title: String::from("Next Section"),
@@ -509,6 +945,8 @@ This is a paragraph.
                 location: Some(PathBuf::from("entry2.md")),
                 nested_items: Vec::new(),
                 level: 1,
+                heading_offset: 0,
+                title: None,
             }),
         ];
 
@@ -531,8 +969,12 @@ This is a paragraph.
                 location: Some(PathBuf::from("entry2.md")),
                 nested_items: Vec::new(),
                 level: 2,
+                heading_offset: 0,
+                title: None,
             })],
             level: 1,
+            heading_offset: 0,
+            title: None,
         })];
 
         assert_eq!(items, expected);
@@ -548,8 +990,444 @@ This is a paragraph.
             location: Some(PathBuf::from("entry1.md")),
             nested_items: Vec::new(),
             level: 1,
+            heading_offset: 0,
+            title: None,
         })];
 
         assert_eq!(items, expected);
     }
+
+    #[test]
+    fn captures_link_titles() {
+        let input = r#"
+- [Keep](keep.md "The old keep")
+- [Moat](moat.md)
+- [Goblins](goblins.md "heading-offset=1")
+"#;
+
+        let (title, items) = parse(input);
+        let titles: Vec<_> = items
+            .iter()
+            .map(|item| match item {
+                TOCItem::Link(link) => link.title.as_deref(),
+                _ => panic!("item was not a link"),
+            })
+            .collect();
+
+        assert_eq!(vec![Some("The old keep"), None, None], titles);
+
+        let toc = TableOfContents { title, items };
+        assert_eq!(
+            "- [Keep](keep.md \"The old keep\")\n- [Moat](moat.md)\n- [Goblins](goblins.md \"heading-offset=1\")\n",
+            toc.to_markdown()
+        );
+    }
+
+    #[test]
+    fn parses_heading_offsets_from_link_titles() {
+        let input = r#"
+- [Goblins](goblins.md "heading-offset=2")
+- [Kobolds](kobolds.md "heading-offset=-1")
+- [Orcs](orcs.md "Fearsome orcs")
+"#;
+
+        let (title, items) = parse(input);
+        let offsets: Vec<_> = items
+            .iter()
+            .map(|item| match item {
+                TOCItem::Link(link) => link.heading_offset,
+                _ => panic!("item was not a link"),
+            })
+            .collect();
+
+        assert_eq!(vec![2, -1, 0], offsets);
+
+        let toc = TableOfContents { title, items };
+        assert_eq!(
+            "- [Goblins](goblins.md \"heading-offset=2\")\n- [Kobolds](kobolds.md \"heading-offset=-1\")\n- [Orcs](orcs.md \"Fearsome orcs\")\n",
+            toc.to_markdown()
+        );
+
+        let err = TOCParser::new("- [Goblins](goblins.md \"heading-offset=high\")")
+            .parse()
+            .expect_err("should fail");
+        assert!(format!("{err:#}")
+            .contains("Expected a heading offset between -128 and 127, found: high"));
+    }
+
+    #[test]
+    fn loads_configured_summary_file() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        fs::write(
+            dir.path().join("INDEX.md"),
+            "# Index\n* [Entry 1](entry1.md)",
+        )
+        .expect("should write summary");
+
+        let toc = TableOfContents::load(dir.path(), "INDEX.md").expect("should load");
+
+        assert_eq!(Some(String::from("Index")), toc.title);
+        assert_eq!(1, toc.items.len());
+    }
+
+    #[test]
+    fn loads_summary_file_with_byte_order_mark() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        fs::write(
+            dir.path().join("JOURNAL.md"),
+            "\u{feff}# Journal\r\n\r\n- [Entry 1](entry1.md)\r\n",
+        )
+        .expect("should write summary");
+
+        let toc = TableOfContents::load(dir.path(), "JOURNAL.md").expect("should load");
+
+        assert_eq!(Some(String::from("Journal")), toc.title);
+        assert_eq!(1, toc.items.len());
+    }
+
+    #[test]
+    fn fails_naming_missing_summary_file() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let err = TableOfContents::load(dir.path(), "SUMMARY.md").expect_err("should fail");
+
+        assert!(err.to_string().contains("SUMMARY.md"));
+    }
+
+    fn parse_summary(source: &str) -> Result<(Option<String>, Vec<TOCItem>)> {
+        TOCParser::new_summary(source).parse()
+    }
+
+    fn link(name: &str, location: Option<&str>, level: u8, nested_items: Vec<TOCItem>) -> TOCItem {
+        TOCItem::Link(Link {
+            name: String::from(name),
+            location: location.map(PathBuf::from),
+            nested_items,
+            level,
+            heading_offset: 0,
+            title: None,
+        })
+    }
+
+    #[test]
+    fn parses_mdbook_summary() {
+        let input = r#"# Summary
+
+[Introduction](intro.md)
+
+# Part One
+
+- [Chapter 1](chapter_1.md)
+  - [Section 1.1](chapter_1/section_1.md)
+- [Draft Chapter]()
+
+---
+
+# Part Two
+
+- [Chapter 2](chapter_2.md)
+
+<!-- Appendices -->
+[Appendix A](appendix_a.md)
+[Appendix B](appendix_b.md)
+"#;
+
+        let (title, items) = parse_summary(input).expect("summary should parse");
+        let expected = vec![
+            link("Introduction", Some("intro.md"), 1, Vec::new()),
+            TOCItem::SectionTitle(SectionTitle {
+                title: String::from("Part One"),
+            }),
+            link(
+                "Chapter 1",
+                Some("chapter_1.md"),
+                1,
+                vec![link(
+                    "Section 1.1",
+                    Some("chapter_1/section_1.md"),
+                    2,
+                    Vec::new(),
+                )],
+            ),
+            link("Draft Chapter", None, 1, Vec::new()),
+            TOCItem::Separator(Separator::default()),
+            TOCItem::SectionTitle(SectionTitle {
+                title: String::from("Part Two"),
+            }),
+            link("Chapter 2", Some("chapter_2.md"), 1, Vec::new()),
+            link("Appendix A", Some("appendix_a.md"), 1, Vec::new()),
+            link("Appendix B", Some("appendix_b.md"), 1, Vec::new()),
+        ];
+
+        assert_eq!(Some(String::from("Summary")), title);
+        assert_eq!(expected, items);
+        assert!(items[3].maybe_link().expect("should be a link").is_draft());
+    }
+
+    #[test]
+    fn labels_separators_with_a_preceding_comment() {
+        let input = r#"
+* [Entry 1](entry1.md)
+
+<!-- separator: page-break -->
+---
+
+* [Entry 2](entry2.md)
+
+---
+
+* [Entry 3](entry3.md)
+"#;
+
+        let (_, items) = parse(input);
+        let separators: Vec<_> = items.iter().filter_map(TOCItem::maybe_separator).collect();
+
+        assert_eq!(
+            vec![
+                &Separator::new(Some(String::from("page-break"))),
+                &Separator::default()
+            ],
+            separators
+        );
+        assert_eq!(5, items.len());
+    }
+
+    fn separated(items: &[TOCItem]) -> Vec<String> {
+        items
+            .iter()
+            .map(|item| match item {
+                TOCItem::Link(link) => link.name.clone(),
+                TOCItem::SectionTitle(title) => format!("# {}", title.title),
+                TOCItem::Separator(separator) => {
+                    format!("--- {}", separator.label.as_deref().unwrap_or_default())
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn collapses_doubled_leading_and_trailing_separators() {
+        let input = r#"
+---
+
+* [Entry 1](entry1.md)
+
+---
+
+<!-- separator: page-break -->
+---
+
+---
+
+* [Entry 2](entry2.md)
+
+---
+
+# Part Two
+
+* [Entry 3](entry3.md)
+
+---
+
+---
+"#;
+
+        let (title, items) = parse(input);
+        let mut toc = TableOfContents { title, items };
+
+        assert_eq!(
+            vec![
+                "--- ",
+                "Entry 1",
+                "--- ",
+                "--- page-break",
+                "--- ",
+                "Entry 2",
+                "--- ",
+                "# Part Two",
+                "Entry 3",
+                "--- ",
+                "--- "
+            ],
+            separated(&toc.items)
+        );
+
+        toc.collapse_separators();
+
+        assert_eq!(
+            vec![
+                "Entry 1",
+                "--- page-break",
+                "Entry 2",
+                "--- ",
+                "# Part Two",
+                "Entry 3"
+            ],
+            separated(&toc.items)
+        );
+    }
+
+    #[test]
+    fn keeps_every_separator_when_loading_exactly() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        fs::write(
+            dir.path().join("JOURNAL.md"),
+            "---\n\n* [Entry 1](entry1.md)\n\n---\n\n---\n",
+        )
+        .expect("should write summary");
+
+        let exact = TableOfContents::load_exact(dir.path(), "JOURNAL.md").expect("should load");
+        let collapsed = TableOfContents::load(dir.path(), "JOURNAL.md").expect("should load");
+
+        assert_eq!(4, exact.items.len());
+        assert_eq!(vec!["Entry 1"], separated(&collapsed.items));
+    }
+
+    #[test]
+    fn ignores_comments_not_followed_by_a_rule() {
+        let input = r#"
+* [Entry 1](entry1.md)
+
+<!-- separator: page-break -->
+
+* [Entry 2](entry2.md)
+
+<!-- a note -->
+---
+"#;
+
+        let (_, items) = parse(input);
+
+        assert_eq!(3, items.len());
+        assert_eq!(Some(&Separator::default()), items[2].maybe_separator());
+    }
+
+    #[test]
+    fn parses_separator_labels() {
+        assert_eq!(
+            Some(String::from("page-break")),
+            parse_separator_label("<!--separator:page-break-->\n")
+        );
+        assert_eq!(None, parse_separator_label("<!-- separator: -->"));
+        assert_eq!(None, parse_separator_label("<!-- page-break -->"));
+        assert_eq!(None, parse_separator_label("<hr>"));
+    }
+
+    #[test]
+    fn rejects_text_outside_of_links_in_summary() {
+        let input = "# Summary\n\nSome prose [Introduction](intro.md)";
+
+        assert!(parse_summary(input).is_err());
+    }
+
+    #[test]
+    fn rejects_unsupported_elements_in_summary() {
+        let input = "# Summary\n\n```\ncode\n```";
+
+        assert!(parse_summary(input).is_err());
+    }
+
+    #[test]
+    fn writes_markdown_that_parses_back_into_the_same_items() {
+        let source = "# Journal\n\n- [Town](./town.md)\n  - [The *Tavern*](./town/the%20tavern.md)\n    - [Cellar]()\n- [Dock](dock.md)\n\n---\n\n# Part Two\n\n- [Dungeon](dungeon.md)\n\n<!-- separator: page -->\n---\n\n- [Crypt](crypt.md)\n";
+        let (title, items) = parse(source);
+        let toc = TableOfContents { title, items };
+
+        let markdown = toc.to_markdown();
+        let (title, items) = parse(&markdown);
+
+        assert_eq!(source, markdown);
+        assert_eq!(toc, TableOfContents { title, items });
+    }
+
+    #[test]
+    fn rejects_duplicate_locations() {
+        let input = "- [Town](./town.md)\n  - [Again](town/../town.md)\n";
+        let err = TOCParser::new(input)
+            .parse()
+            .expect_err("duplicate locations should fail");
+
+        let message = format!("{err:#}");
+        assert!(message.contains("line: 2"), "{message}");
+        assert!(
+            message.contains("already linked at line: 1, column: 2"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn rejects_locations_outside_of_the_source() {
+        for location in ["../secrets.md", "./town/../../secrets.md", "/etc/passwd"] {
+            let input = format!("- [Escape]({location})\n");
+
+            assert!(
+                TOCParser::new(&input).parse().is_err(),
+                "{location} should be rejected"
+            );
+        }
+
+        let (_, items) = parse("- [Inside](./town/../tavern.md)\n- [Draft]()\n- [Draft]()\n");
+        assert_eq!(3, items.len());
+    }
+
+    #[test]
+    fn checks_locations_nested_below_directories_relative_to_the_directory() {
+        let (_, items) = parse(
+            "- [Town](./town/)\n  - [Overview](./overview.md)\n- [Dungeon](dungeon/)\n  - [Overview](overview.md)\n",
+        );
+        let TOCItem::Link(ref town) = items[0] else {
+            panic!("first item was not a link")
+        };
+
+        assert!(town.is_directory());
+        assert!(!link("Town", Some("town.md"), 1, Vec::new())
+            .maybe_link()
+            .expect("should be a link")
+            .is_directory());
+
+        let err = TOCParser::new("- [Town](town/)\n  - [Inn](inn.md)\n- [Again](town/inn.md)\n")
+            .parse()
+            .expect_err("duplicate locations should fail");
+
+        assert!(
+            format!("{err:#}").contains("already linked at line: 2"),
+            "{err:#}"
+        );
+        assert_eq!(
+            PathBuf::from("@bestiary/goblin.md"),
+            join_location(Path::new("town"), Path::new("@bestiary/goblin.md"))
+        );
+    }
+
+    #[test]
+    fn reports_the_line_of_nested_items_without_links() {
+        for bad_item in ["  - Notes", "  -", "  - **Notes**", "  - ![Map](./map.png)"] {
+            let input = format!(
+                "# Journal\n\n- [Town](./town.md)\n  - [Tavern](./tavern.md)\n{bad_item}\n    - [Cellar](./cellar.md)\n- [Dungeon](./dungeon.md)\n"
+            );
+            let err = TOCParser::new(&input)
+                .parse()
+                .expect_err("items without links should fail");
+
+            let message = format!("{err:#}");
+            assert!(message.contains("line: 5,"), "{bad_item}: {message}");
+        }
+    }
+
+    #[test]
+    fn reports_the_line_of_nested_lists_following_a_separator() {
+        let input = "- [Town](./town.md)\n\n  ---\n\n  - [Tavern](./tavern.md)\n- [Dungeon](./dungeon.md)\n";
+        let err = TOCParser::new(input)
+            .parse()
+            .expect_err("nested lists without a parent link should fail");
+
+        let message = format!("{err:#}");
+        assert!(message.contains("line: 5, column: 3"), "{message}");
+    }
+
+    #[test]
+    fn parses_top_level_lists_following_a_separator() {
+        let (_, items) = parse("- [Town](./town.md)\n\n---\n\n- [Dungeon](./dungeon.md)\n");
+
+        assert_eq!(3, items.len());
+    }
 }
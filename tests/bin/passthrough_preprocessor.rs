@@ -0,0 +1,11 @@
+//! A preprocessor that returns the journal it was given unchanged, for testing `CommandPreprocessor`.
+
+use dungeon_mark::model::journal::Journal;
+use std::io;
+
+fn main() {
+    let (_ctx, journal): (serde_json::Value, Journal) =
+        serde_json::from_reader(io::stdin()).expect("failed to read preprocessor input");
+
+    serde_json::to_writer(io::stdout(), &journal).expect("failed to write preprocessor output");
+}
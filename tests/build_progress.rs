@@ -0,0 +1,50 @@
+use crate::common::TestRenderer;
+use dungeon_mark::build::{BuildEvent, JournalBuilder};
+use std::{cell::RefCell, rc::Rc};
+
+mod common;
+
+#[test]
+fn it_reports_each_stage_of_the_build() {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let renderer = TestRenderer::default();
+    let mut journal_builder =
+        JournalBuilder::load(common::test_dir()).expect("failed to load journal");
+
+    journal_builder.with_renderer(renderer).with_progress({
+        let events = events.clone();
+
+        move |event| events.borrow_mut().push(format!("{event:?}"))
+    });
+    journal_builder.build().expect("failed to build journal");
+
+    assert_eq!(
+        vec![
+            format!("{:?}", BuildEvent::EditionStarted { language: None }),
+            format!("{:?}", BuildEvent::EntriesLoaded { count: 1 }),
+            format!(
+                "{:?}",
+                BuildEvent::PreprocessorStarted { name: "directive" }
+            ),
+            format!("{:?}", BuildEvent::EntriesParsed { count: 1 }),
+        ],
+        events.borrow()[..4]
+    );
+    assert_eq!(
+        Some(&format!(
+            "{:?}",
+            BuildEvent::RendererFinished {
+                name: "test_renderer",
+                files: 0
+            }
+        )),
+        events.borrow().last()
+    );
+    assert!(events.borrow().contains(&format!(
+        "{:?}",
+        BuildEvent::TransformerStarted {
+            name: "title",
+            renderer: Some("test_renderer")
+        }
+    )));
+}
@@ -0,0 +1,45 @@
+use crate::common::TestRenderer;
+use dungeon_mark::{build::JournalBuilder, config::Config};
+use std::fs;
+
+mod common;
+
+fn config(source: &str) -> Config {
+    format!("[journal]\nsource = \"{source}\"\n\n[build]\nclean = true\n")
+        .parse()
+        .expect("failed to parse config")
+}
+
+#[test]
+fn it_removes_stale_files_before_rendering() {
+    let test_dir = common::copy_test_dir();
+    let destination = test_dir.path().join("build/test_renderer");
+    fs::create_dir_all(&destination).expect("failed to create destination");
+    fs::write(destination.join("stale.html"), "stale").expect("failed to write stale file");
+
+    let mut journal_builder = JournalBuilder::load_with_config(test_dir.path(), config("journal"))
+        .expect("failed to load journal");
+    journal_builder.with_renderer(TestRenderer::default());
+    journal_builder.build().expect("failed to build journal");
+
+    assert!(destination.is_dir());
+    assert!(!destination.join("stale.html").exists());
+}
+
+#[test]
+fn it_refuses_to_clean_a_destination_containing_the_source() {
+    let test_dir = common::copy_test_dir();
+    let source = test_dir.path().join("build/test_renderer/src");
+    fs::create_dir_all(&source).expect("failed to create source");
+    fs::write(source.join("JOURNAL.md"), "# Journal\n").expect("failed to write summary");
+
+    let mut journal_builder =
+        JournalBuilder::load_with_config(test_dir.path(), config("build/test_renderer/src"))
+            .expect("failed to load journal");
+    journal_builder.with_renderer(TestRenderer::default());
+
+    let err = journal_builder.build().expect_err("build should fail");
+
+    assert!(err.to_string().contains("Refusing to clean"));
+    assert!(source.join("JOURNAL.md").exists());
+}
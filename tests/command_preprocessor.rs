@@ -0,0 +1,37 @@
+use crate::common::TestRenderer;
+use dungeon_mark::build::{preprocess::CommandPreprocessor, JournalBuilder};
+
+mod common;
+
+const PASSTHROUGH: &str = env!("CARGO_BIN_EXE_passthrough-preprocessor");
+
+#[test]
+fn it_round_trips_the_journal_through_a_command_preprocessor() {
+    let expected = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(common::test_dir()).expect("failed to load");
+    journal_builder.with_renderer(expected.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(common::test_dir()).expect("failed to load");
+    journal_builder
+        .with_preprocessor(CommandPreprocessor::new(
+            String::from("passthrough"),
+            Some(format!("\"{PASSTHROUGH}\"")),
+        ))
+        .with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    assert_eq!(
+        format!("{:?}", expected.journal()),
+        format!("{:?}", renderer.journal())
+    );
+}
+
+#[test]
+fn it_fails_when_the_command_fails() {
+    let mut journal_builder = JournalBuilder::load(common::test_dir()).expect("failed to load");
+    journal_builder.with_preprocessor(CommandPreprocessor::new(String::from("false"), None));
+
+    assert!(journal_builder.build().is_err());
+}
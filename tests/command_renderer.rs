@@ -0,0 +1,107 @@
+use dungeon_mark::{build::JournalBuilder, config::RendererConfig};
+use std::fs;
+
+mod common;
+
+#[test]
+fn it_runs_an_inline_command_renderer() {
+    let test_dir = common::copy_test_dir();
+    let mut journal_builder =
+        JournalBuilder::load(test_dir.path()).expect("failed to load journal");
+
+    let output = test_dir.path().join("captured.json");
+    let command = format!("sh -c 'cat > \"{}\"'", output.display());
+
+    journal_builder.with_command_renderer("capture", &command);
+    journal_builder.build().expect("failed to build journal");
+
+    let captured = fs::read_to_string(output).expect("renderer should have written its input");
+    let ctx: serde_json::Value = serde_json::from_str(&captured).expect("input should be json");
+
+    assert_eq!("build/capture", ctx["destination"]);
+}
+
+#[test]
+fn it_streams_the_journal_one_item_per_line() {
+    let test_dir = common::copy_test_dir();
+    let mut journal_builder =
+        JournalBuilder::load(test_dir.path()).expect("failed to load journal");
+
+    let output = test_dir.path().join("captured.ndjson");
+    let command = format!("sh -c 'cat > \"{}\"'", output.display());
+
+    journal_builder.with_config_override(|config| {
+        config.build.renderers.push(RendererConfig {
+            name: String::from("capture"),
+            command: Some(command),
+            streaming: true,
+            ..Default::default()
+        })
+    });
+    journal_builder.build().expect("failed to build journal");
+
+    let captured = fs::read_to_string(output).expect("renderer should have written its input");
+    let lines: Vec<serde_json::Value> = captured
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each line should be json"))
+        .collect();
+
+    assert_eq!(2, lines.len());
+    assert_eq!("build/capture", lines[0]["destination"]);
+    assert_eq!(serde_json::json!([]), lines[0]["journal"]["items"]);
+    assert_eq!("Entry 1", lines[1]["Entry"]["title"]);
+}
+
+#[cfg(unix)]
+fn flaky_renderer(attempts: &std::path::Path, failures: u32, retries: u32) -> RendererConfig {
+    // NOTE: Counts its attempts in a file, and fails until it has failed `failures` times.
+    let script = format!(
+        "echo x >> \"{0}\"; test $(wc -l < \"{0}\") -gt {failures}",
+        attempts.display()
+    );
+
+    RendererConfig {
+        name: String::from("flaky"),
+        command: Some(format!("sh -c '{script}'")),
+        retries,
+        retry_delay_ms: 1,
+        ..Default::default()
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn it_retries_a_failing_renderer() {
+    let test_dir = common::copy_test_dir();
+    let attempts = test_dir.path().join("attempts");
+    let mut journal_builder =
+        JournalBuilder::load(test_dir.path()).expect("failed to load journal");
+
+    let renderer = flaky_renderer(&attempts, 2, 3);
+    journal_builder.with_config_override(move |config| config.build.renderers.push(renderer));
+    journal_builder
+        .build()
+        .expect("renderer should succeed on a retry");
+
+    let attempts = fs::read_to_string(attempts).expect("renderer should have run");
+    assert_eq!(3, attempts.lines().count());
+}
+
+#[cfg(unix)]
+#[test]
+fn it_fails_once_a_renderer_runs_out_of_retries() {
+    let test_dir = common::copy_test_dir();
+    let attempts = test_dir.path().join("attempts");
+    let mut journal_builder =
+        JournalBuilder::load(test_dir.path()).expect("failed to load journal");
+
+    let renderer = flaky_renderer(&attempts, 5, 1);
+    journal_builder.with_config_override(move |config| config.build.renderers.push(renderer));
+    let err = journal_builder
+        .build()
+        .expect_err("renderer should run out of retries");
+
+    assert!(format!("{err:#}").contains("Renderer flaky failed"));
+    let attempts = fs::read_to_string(attempts).expect("renderer should have run");
+    assert_eq!(2, attempts.lines().count());
+}
@@ -1,10 +1,16 @@
 use dungeon_mark::{
-    build::render::{RenderContext, Renderer},
+    build::render::{RenderContext, RenderOutput, Renderer},
     config::Config,
     error::Result,
     model::journal::Journal,
 };
-use std::{cell::RefCell, env, path::PathBuf, rc::Rc};
+use std::{
+    cell::RefCell,
+    env, fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+use tempfile::TempDir;
 
 #[derive(Clone, Default)]
 pub struct TestRenderer(Rc<RefCell<Option<Journal>>>, Rc<RefCell<Option<Config>>>);
@@ -26,11 +32,11 @@ impl Renderer for TestRenderer {
         "test_renderer"
     }
 
-    fn render(&self, ctx: RenderContext) -> Result<()> {
-        *self.0.borrow_mut() = Some(ctx.journal.clone());
+    fn render(&self, ctx: RenderContext) -> Result<RenderOutput> {
+        *self.0.borrow_mut() = Some(Journal::clone(&ctx.journal));
         *self.1.borrow_mut() = Some(ctx.config.clone());
 
-        Ok(())
+        Ok(RenderOutput::default())
     }
 }
 
@@ -43,3 +49,27 @@ pub fn test_dir() -> PathBuf {
 
     current_dir.join("data")
 }
+
+/// Copy the test journal into a temporary directory so that tests can freely modify it.
+#[allow(dead_code)] // Avoid a false positive on the dead code analysis.
+pub fn copy_test_dir() -> TempDir {
+    let temp_dir = tempfile::tempdir().expect("Unable to create temporary directory");
+    copy_dir(&test_dir(), temp_dir.path());
+
+    temp_dir
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).expect("Unable to create directory");
+
+    for entry in fs::read_dir(from).expect("Unable to read directory") {
+        let entry = entry.expect("Unable to read directory entry");
+        let target = to.join(entry.file_name());
+
+        if entry.path().is_dir() {
+            copy_dir(&entry.path(), &target);
+        } else {
+            fs::copy(entry.path(), target).expect("Unable to copy file");
+        }
+    }
+}
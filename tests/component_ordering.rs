@@ -0,0 +1,134 @@
+use crate::common::TestRenderer;
+use dungeon_mark::{
+    build::{
+        render::Renderer,
+        transform::{Transformer, TransformerContext},
+        JournalBuilder,
+    },
+    error::Result,
+    model::journal::{Journal, JournalItem},
+};
+use std::{cell::RefCell, fs, rc::Rc};
+
+mod common;
+
+/// Records its name, and whether the metadata transformer has already run, each time it is run.
+struct RecordingTransformer {
+    name: &'static str,
+    priority: i32,
+    log: Rc<RefCell<Vec<String>>>,
+}
+
+impl Transformer for RecordingTransformer {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn run(&self, _ctx: &TransformerContext, journal: Journal) -> Result<Journal> {
+        let JournalItem::Entry(ref entry) = journal.items[0] else {
+            panic!("first item was not an entry")
+        };
+        let has_metadata = !entry.sections[0].metadata.is_empty();
+
+        self.log
+            .borrow_mut()
+            .push(format!("{}:{has_metadata}", self.name));
+
+        Ok(journal)
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+}
+
+#[test]
+fn it_runs_transformers_by_priority_then_insertion_order() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(source.join("JOURNAL.md"), "- [Tavern](./tavern.md)\n")
+        .expect("failed to write table of contents");
+    fs::write(
+        source.join("tavern.md"),
+        "# Tavern\n\n```metadata,npc\nBorin\n```\n",
+    )
+    .expect("failed to write entry");
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let transformer = |name, priority| RecordingTransformer {
+        name,
+        priority,
+        log: log.clone(),
+    };
+
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder
+        .with_transformer(transformer("late", 10))
+        .with_transformer(transformer("default-a", 0))
+        .with_transformer(transformer("early", -200))
+        .with_transformer(transformer("default-b", 0))
+        .with_renderer(TestRenderer::default());
+    journal_builder.build().expect("failed to build journal");
+
+    assert_eq!(
+        vec![
+            "early:false",
+            "default-a:true",
+            "default-b:true",
+            "late:true"
+        ],
+        *log.borrow()
+    );
+}
+
+#[test]
+fn it_runs_boxed_transformers_in_the_same_order() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(source.join("JOURNAL.md"), "- [Tavern](./tavern.md)\n")
+        .expect("failed to write table of contents");
+    fs::write(
+        source.join("tavern.md"),
+        "# Tavern\n\n```metadata,npc\nBorin\n```\n",
+    )
+    .expect("failed to write entry");
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let plugins: Vec<Box<dyn Transformer>> = vec![
+        Box::new(RecordingTransformer {
+            name: "late",
+            priority: 10,
+            log: log.clone(),
+        }),
+        Box::new(RecordingTransformer {
+            name: "early",
+            priority: -200,
+            log: log.clone(),
+        }),
+    ];
+    let renderer = TestRenderer::default();
+    let boxed_renderer: Box<dyn Renderer> = Box::new(renderer.clone());
+
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    for plugin in plugins {
+        journal_builder.with_transformer_boxed(plugin);
+    }
+    journal_builder
+        .with_transformer(RecordingTransformer {
+            name: "default",
+            priority: 0,
+            log: log.clone(),
+        })
+        .with_renderer_boxed(boxed_renderer);
+    journal_builder.build().expect("failed to build journal");
+
+    assert_eq!(
+        vec!["early:false", "default:true", "late:true"],
+        *log.borrow()
+    );
+    assert_eq!(1, renderer.journal().items.len());
+}
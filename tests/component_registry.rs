@@ -0,0 +1,230 @@
+use crate::common::TestRenderer;
+use dungeon_mark::{
+    build::{
+        preprocess::{Preprocessor, PreprocessorContext},
+        transform::{Transformer, TransformerContext},
+        JournalBuilder,
+    },
+    config::Config,
+    error::Result,
+    model::{
+        journal::{Journal, JournalItem},
+        toc::TableOfContents,
+    },
+};
+
+use std::fs;
+
+mod common;
+
+struct TitleTransformer;
+
+impl Transformer for TitleTransformer {
+    fn name(&self) -> &str {
+        "title"
+    }
+
+    fn run(&self, _ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        journal.title = Some(String::from("Transformed"));
+
+        Ok(journal)
+    }
+}
+
+fn config(build: &str) -> Config {
+    format!("[journal]\nsource = \"journal\"\n\n[build]\n{build}")
+        .parse()
+        .expect("failed to parse config")
+}
+
+#[test]
+fn it_runs_registered_transformers_enabled_by_config() {
+    let renderer = TestRenderer::default();
+    let config = config(r#"transformers = ["metadata", "title"]"#);
+    let mut journal_builder =
+        JournalBuilder::load_with_config(common::test_dir(), config).expect("failed to load");
+
+    journal_builder
+        .register_transformer("title", || Box::new(TitleTransformer))
+        .with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    assert_eq!(Some(String::from("Transformed")), renderer.journal().title);
+}
+
+#[test]
+fn it_can_disable_built_in_preprocessors() {
+    let renderer = TestRenderer::default();
+    let config = config("preprocessors = []");
+    let mut journal_builder =
+        JournalBuilder::load_with_config(common::test_dir(), config).expect("failed to load");
+
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let journal = renderer.journal();
+    let entry = journal.items[0].clone();
+    let body = format!("{entry:?}");
+
+    assert!(body.contains("{{#include include.md}}"));
+}
+
+#[test]
+fn it_fails_on_unknown_component_names() {
+    let config = config(r#"transformers = ["unknown"]"#);
+    let journal_builder =
+        JournalBuilder::load_with_config(common::test_dir(), config).expect("failed to load");
+
+    let err = journal_builder.build().expect_err("build should fail");
+
+    assert!(err.to_string().contains("Unknown transformer: unknown"));
+}
+
+/// Appends the name of the first link in the table of contents to the journal's title.
+struct TableOfContentsReader;
+
+fn first_link(table_of_contents: &TableOfContents) -> String {
+    table_of_contents
+        .items
+        .iter()
+        .find_map(|item| item.maybe_link())
+        .map(|link| link.name.clone())
+        .unwrap_or_default()
+}
+
+impl Preprocessor for TableOfContentsReader {
+    fn name(&self) -> &str {
+        "toc-reader"
+    }
+
+    fn run(&self, ctx: &PreprocessorContext, mut journal: Journal) -> Result<Journal> {
+        journal.title = Some(first_link(&ctx.table_of_contents));
+
+        Ok(journal)
+    }
+}
+
+impl Transformer for TableOfContentsReader {
+    fn name(&self) -> &str {
+        "toc-reader"
+    }
+
+    fn run(&self, ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        let title = journal.title.unwrap_or_default();
+        journal.title = Some(format!("{title} {}", first_link(&ctx.table_of_contents)));
+
+        Ok(journal)
+    }
+}
+
+#[test]
+fn it_gives_components_the_table_of_contents() {
+    let renderer = TestRenderer::default();
+    let mut journal_builder =
+        JournalBuilder::load_with_config(common::test_dir(), config("")).expect("failed to load");
+
+    journal_builder
+        .with_preprocessor(TableOfContentsReader)
+        .with_transformer(TableOfContentsReader)
+        .with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    assert_eq!(
+        Some(String::from("Entry 1 Entry 1")),
+        renderer.journal().title
+    );
+}
+
+#[test]
+fn it_maps_section_bodies_after_extracting_metadata() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Keep](./keep.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        source.join("keep.md"),
+        "# Keep\n\nA ruined tower.\n\n```toml,metadata,keep\nfloors = 3\n```\n\n## Dungeon\n\nDamp cells.",
+    )
+    .expect("failed to write entry");
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+
+    journal_builder
+        .map_section_bodies(|body| Ok(body.to_uppercase()))
+        .with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let journal = renderer.journal();
+    let JournalItem::Entry(ref keep) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+    let bodies: Vec<_> = keep
+        .iter_sections()
+        .map(|section| section.body.trim())
+        .collect();
+
+    assert_eq!(vec!["A RUINED TOWER.", "DAMP CELLS."], bodies);
+    assert!(keep.sections[0].metadata.get("keep").is_some());
+}
+
+struct NpcTransformer;
+
+impl Transformer for NpcTransformer {
+    fn name(&self) -> &str {
+        "npc"
+    }
+
+    fn run(&self, _ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        journal.for_each_entry_mut(|entry| {
+            entry.for_each_mut(|section| {
+                section.body = section.body.replace("{{#npc Borin}}", "Borin the smith")
+            })
+        });
+
+        Ok(journal)
+    }
+
+    fn directives(&self) -> &[&str] {
+        &["npc"]
+    }
+}
+
+#[test]
+fn it_accepts_the_directives_transformers_declare_when_strict() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(
+        root.path().join("journal.toml"),
+        "[build]\nstrict-directives = true\n",
+    )
+    .expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Keep](./keep.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("keep.md"), "# Keep\n\nAsk {{#npc Borin}}.")
+        .expect("failed to write entry");
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+
+    journal_builder
+        .with_transformer(NpcTransformer)
+        .with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let journal = renderer.journal();
+    let JournalItem::Entry(ref keep) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!("Ask Borin the smith.", keep.sections[0].body.trim());
+}
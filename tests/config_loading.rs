@@ -1,6 +1,7 @@
 use crate::common::TestRenderer;
-use dungeon_mark::build::JournalBuilder;
+use dungeon_mark::{build::JournalBuilder, config::Config};
 use serde::Deserialize;
+use std::fs;
 
 mod common;
 
@@ -30,3 +31,32 @@ fn it_loads_custom_configuration() {
 
     assert_eq!(expected, actual);
 }
+
+#[test]
+fn it_merges_local_configuration_over_journal_toml() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    fs::write(
+        root.path().join("journal.toml"),
+        "[build]\nclean = true\n\n[campaign]\nname = \"Shared\"\nsetting = \"Greyhawk\"\n",
+    )
+    .expect("failed to write config");
+    fs::write(
+        root.path().join("journal.local.toml"),
+        "[build]\nclean = false\n\n[campaign]\nname = \"Local\"\n",
+    )
+    .expect("failed to write local config");
+
+    let config = Config::load(root.path()).expect("failed to load config");
+
+    assert!(!config.build.clean);
+    assert_eq!(
+        String::from("Local"),
+        config.get::<String>("campaign.name").expect("should get")
+    );
+    assert_eq!(
+        String::from("Greyhawk"),
+        config
+            .get::<String>("campaign.setting")
+            .expect("should get")
+    );
+}
@@ -0,0 +1,73 @@
+use dungeon_mark::{
+    build::{
+        render::{RenderContext, RenderOutput, Renderer},
+        JournalBuilder,
+    },
+    error::Result,
+};
+use serde::Deserialize;
+use std::{cell::RefCell, fs, rc::Rc};
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+struct CampaignConfig {
+    setting: String,
+    level: u32,
+}
+
+/// A renderer that captures the `[campaign]` configuration it is given.
+#[derive(Clone, Default)]
+struct CampaignRenderer(Rc<RefCell<Option<CampaignConfig>>>);
+
+impl Renderer for CampaignRenderer {
+    fn name(&self) -> &str {
+        "campaign"
+    }
+
+    fn render(&self, ctx: RenderContext) -> Result<RenderOutput> {
+        *self.0.borrow_mut() = Some(ctx.config.get("campaign")?);
+
+        Ok(RenderOutput::default())
+    }
+}
+
+#[test]
+fn it_merges_entry_metadata_into_the_renderer_config() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(
+        root.path().join("journal.toml"),
+        "[build]\ntransformers = [\"metadata\", \"config-metadata\"]\n\n\
+         [campaign]\nsetting = \"Greyhawk\"\nlevel = 1\n",
+    )
+    .expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Campaign](./campaign.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        source.join("campaign.md"),
+        "# Settings\n\n```toml,metadata,config\n[campaign]\nlevel = 3\n```\n",
+    )
+    .expect("failed to write entry");
+
+    let renderer = CampaignRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let campaign = renderer
+        .0
+        .borrow_mut()
+        .take()
+        .expect("renderer was not run");
+
+    assert_eq!(
+        CampaignConfig {
+            setting: String::from("Greyhawk"),
+            level: 3,
+        },
+        campaign
+    );
+}
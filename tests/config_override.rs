@@ -0,0 +1,59 @@
+use dungeon_mark::{build::JournalBuilder, model::journal::JournalItem};
+use std::fs;
+
+fn write_source(source: &std::path::Path, entry: &str) {
+    fs::create_dir_all(source).expect("failed to create source dir");
+    fs::write(
+        source.join("JOURNAL.md"),
+        format!("# Journal\n\n- [{entry}](./entry.md)\n"),
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("entry.md"), entry).expect("failed to write entry");
+}
+
+#[test]
+fn it_builds_from_an_overridden_config() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    write_source(&root.path().join("src"), "Original");
+    write_source(&root.path().join("drafts"), "Draft");
+
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_config_override(|config| {
+        config
+            .set("journal.source", "drafts")
+            .expect("failed to set source");
+        config.journal.title = Some(String::from("Drafts"));
+    });
+    let journal = journal_builder
+        .build_dry_run()
+        .expect("failed to process journal");
+
+    let JournalItem::Entry(ref entry) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!(Some("Drafts"), journal.title.as_deref());
+    assert_eq!("Draft", entry.title);
+}
+
+#[test]
+fn it_builds_from_an_explicit_source() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let elsewhere = tempfile::tempdir().expect("failed to create temp dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    write_source(&root.path().join("src"), "Original");
+    write_source(elsewhere.path(), "Elsewhere");
+
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_source(elsewhere.path());
+    let journal = journal_builder
+        .build_dry_run()
+        .expect("failed to process journal");
+
+    let JournalItem::Entry(ref entry) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!("Elsewhere", entry.title);
+}
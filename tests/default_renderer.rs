@@ -0,0 +1,39 @@
+use dungeon_mark::{build::JournalBuilder, config::Config};
+
+mod common;
+
+fn config(build: &str) -> Config {
+    format!("[journal]\nsource = \"journal\"\n\n[build]\n{build}")
+        .parse()
+        .expect("failed to parse config")
+}
+
+#[test]
+fn it_renders_html_when_no_renderers_are_configured() {
+    let test_dir = common::copy_test_dir();
+    let journal_builder = JournalBuilder::load_with_config(test_dir.path(), config(""))
+        .expect("failed to load journal");
+
+    assert_eq!(vec!["html"], journal_builder.list_renderers());
+
+    let render_output = journal_builder.build().expect("failed to build journal");
+
+    assert!(render_output
+        .files
+        .contains(&test_dir.path().join("build/html/index.html")));
+}
+
+#[test]
+fn it_renders_nothing_when_the_default_renderer_is_empty() {
+    let test_dir = common::copy_test_dir();
+    let journal_builder =
+        JournalBuilder::load_with_config(test_dir.path(), config("default-renderer = \"\"\n"))
+            .expect("failed to load journal");
+
+    assert!(journal_builder.list_renderers().is_empty());
+
+    let render_output = journal_builder.build().expect("failed to build journal");
+
+    assert!(render_output.files.is_empty());
+    assert!(!test_dir.path().join("build/html").exists());
+}
@@ -0,0 +1,65 @@
+use crate::common::TestRenderer;
+use dungeon_mark::{
+    build::JournalBuilder,
+    model::journal::{JournalEntry, JournalItem},
+};
+use std::fs;
+
+mod common;
+
+/// Build a journal with a draft that has an entry nested below it, returning the entries handed to the renderer
+/// as their titles and whether they are drafts, with nested entries following their parent.
+fn build(render_drafts: bool) -> Vec<(String, bool)> {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(
+        root.path().join("journal.toml"),
+        format!("[build]\nrender-drafts = {render_drafts}\nstrict = true\n"),
+    )
+    .expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md)\n- [Dungeon]()\n  - [Crypt](./crypt.md)\n- [Dock](./dock.md)\n",
+    )
+    .expect("failed to write table of contents");
+
+    for entry in ["town", "crypt", "dock"] {
+        fs::write(source.join(format!("{entry}.md")), "# Heading").expect("failed to write entry");
+    }
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    renderer
+        .journal()
+        .iter()
+        .filter_map(|item| match item {
+            JournalItem::Entry(JournalEntry { title, draft, .. }) => Some((title.clone(), *draft)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn it_leaves_drafts_out_by_default() {
+    assert_eq!(
+        vec![(String::from("Town"), false), (String::from("Dock"), false)],
+        build(false)
+    );
+}
+
+#[test]
+fn it_renders_drafts_when_enabled() {
+    assert_eq!(
+        vec![
+            (String::from("Town"), false),
+            (String::from("Dungeon"), true),
+            (String::from("Crypt"), false),
+            (String::from("Dock"), false),
+        ],
+        build(true)
+    );
+}
@@ -0,0 +1,64 @@
+use crate::common::TestRenderer;
+use dungeon_mark::{build::JournalBuilder, config::Config};
+
+mod common;
+
+#[test]
+fn it_returns_the_processed_journal_without_rendering() {
+    let renderer = TestRenderer::default();
+    let test_dir = common::test_dir();
+
+    let mut journal_builder = JournalBuilder::load(&test_dir).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+    let expected = renderer.journal();
+
+    let mut journal_builder = JournalBuilder::load(&test_dir).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    let actual = journal_builder
+        .build_dry_run()
+        .expect("failed to process journal");
+
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn it_hands_over_the_processed_journal_and_config() {
+    let renderer = TestRenderer::default();
+    let test_dir = common::test_dir();
+
+    let mut journal_builder = JournalBuilder::load(&test_dir).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let (journal, config, root) = JournalBuilder::load(&test_dir)
+        .expect("failed to load journal")
+        .into_parts()
+        .expect("failed to process journal");
+
+    assert_eq!(renderer.journal(), journal);
+    assert_eq!(renderer.config(), config);
+    assert_eq!(test_dir, root);
+}
+
+#[test]
+fn it_lists_programmatic_and_configured_renderers() {
+    let config: Config = r#"
+[journal]
+source = "journal"
+
+[[build.renderers]]
+name = "html"
+"#
+    .parse()
+    .expect("failed to parse config");
+
+    let mut journal_builder = JournalBuilder::load_with_config(common::test_dir(), config)
+        .expect("failed to load journal");
+    journal_builder.with_renderer(TestRenderer::default());
+
+    assert_eq!(
+        vec!["test_renderer", "html"],
+        journal_builder.list_renderers()
+    );
+}
@@ -0,0 +1,66 @@
+use dungeon_mark::{build::JournalBuilder, model::journal::JournalItem};
+use std::fs;
+
+fn build(config: &str, session: &str) -> (String, Vec<usize>) {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(source.join("places")).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), config).expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Session](./session.md)\n- [Tavern](./places/tavern.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("session.md"), session).expect("failed to write entry");
+    fs::write(
+        source.join("places/tavern.md"),
+        "# Cellar\n\nBarrels\n\n```toml,metadata,cellar\nlocked = true\n```\n\n## Trapdoor\n\nHidden",
+    )
+    .expect("failed to write entry");
+
+    let journal = JournalBuilder::load(root.path())
+        .expect("failed to load journal")
+        .build_dry_run()
+        .expect("failed to build journal");
+    let JournalItem::Entry(ref session) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    let metadata = session
+        .iter_sections()
+        .map(|section| section.metadata.len())
+        .collect::<Vec<_>>();
+
+    (session.to_markdown(), metadata)
+}
+
+#[test]
+fn it_embeds_sections_when_enabled() {
+    let (markdown, metadata) = build(
+        "[build]\ntransformers = [\"embed\", \"metadata\"]\n",
+        "# Recap\n\n{{#embed places/tavern.md#Cellar}}",
+    );
+
+    assert_eq!(
+        "# Recap\n\nBarrels\n\n```toml,metadata,cellar\nlocked = true\n```\n\n## Trapdoor\n\nHidden",
+        markdown
+    );
+    assert_eq!(vec![1, 0], metadata);
+}
+
+#[test]
+fn it_leaves_embeds_by_default() {
+    assert!(build("", "# Recap\n\n{{#embed places/tavern.md#Cellar}}")
+        .0
+        .contains("{{#embed places/tavern.md#Cellar}}"));
+}
+
+#[test]
+fn it_writes_escaped_embeds_out_as_is() {
+    let (markdown, _) = build(
+        "[build]\ntransformers = [\"embed\"]\n",
+        "# Recap\n\n\\{{#embed places/tavern.md#Cellar}}",
+    );
+
+    assert_eq!("# Recap\n\n{{#embed places/tavern.md#Cellar}}", markdown);
+}
@@ -0,0 +1,69 @@
+use dungeon_mark::build::{render::EpubRenderer, JournalBuilder};
+use std::{
+    fs::{self, File},
+    io::Read,
+};
+use zip::{CompressionMethod, ZipArchive};
+
+#[test]
+fn it_packages_the_journal_as_an_epub() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(source.join("town")).expect("failed to create source dir");
+    fs::write(
+        root.path().join("journal.toml"),
+        "[journal]\ntitle = \"Campaign\"\nauthors = [\"Ada\"]\n",
+    )
+    .expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town/index.md)\n  - [Tavern](./town/tavern.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        source.join("town/index.md"),
+        "# Streets\n\nSee the [cellar](tavern.md#cellar).",
+    )
+    .expect("failed to write entry");
+    fs::write(source.join("town/tavern.md"), "# Cellar\n\nBarrels & ale")
+        .expect("failed to write entry");
+
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(EpubRenderer::new());
+    let render_output = journal_builder.build().expect("failed to build journal");
+
+    let path = root.path().join("build/epub/journal.epub");
+    assert_eq!(vec![path.clone()], render_output.files);
+
+    let mut archive = ZipArchive::new(File::open(&path).expect("failed to open EPUB"))
+        .expect("failed to read EPUB");
+    let mut read = |name: &str| {
+        let mut file = archive.by_name(name).expect("EPUB is missing a file");
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)
+            .expect("failed to read file");
+
+        contents
+    };
+
+    let package = read("OEBPS/content.opf");
+    assert!(package.contains("<dc:title>Campaign</dc:title>"));
+    assert!(package.contains("<dc:creator>Ada</dc:creator>"));
+    assert!(package.contains("<itemref idref=\"page-0\"/>\n<itemref idref=\"page-1\"/>"));
+
+    let navigation = read("OEBPS/nav.xhtml");
+    assert!(navigation.contains(
+        "<li><a href=\"town/index.xhtml\">Town</a>\n<ol>\n<li><a href=\"town/tavern.xhtml\">Tavern</a>\n</li>\n</ol>\n</li>"
+    ));
+
+    let index = read("OEBPS/town/index.xhtml");
+    assert!(index.contains("<h1 id=\"streets\">Streets</h1>"));
+    assert!(index.contains("<a href=\"tavern.xhtml#cellar\">cellar</a>"));
+    assert!(index.contains("href=\"../style.css\""));
+    assert!(read("OEBPS/town/tavern.xhtml").contains("<p>Barrels &amp; ale</p>"));
+    assert!(read("META-INF/container.xml").contains("OEBPS/content.opf"));
+
+    let mimetype = archive.by_index(0).expect("EPUB is empty");
+    assert_eq!("mimetype", mimetype.name());
+    assert_eq!(CompressionMethod::Stored, mimetype.compression());
+}
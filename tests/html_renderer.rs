@@ -0,0 +1,125 @@
+use dungeon_mark::build::{render::HtmlRenderer, JournalBuilder};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+mod common;
+
+#[test]
+fn it_renders_the_journal_to_html() {
+    let test_dir = common::copy_test_dir();
+    let mut journal_builder =
+        JournalBuilder::load(test_dir.path()).expect("failed to load journal");
+
+    journal_builder.with_renderer(HtmlRenderer::new());
+    let render_output = journal_builder.build().expect("failed to build journal");
+
+    let output = test_dir.path().join("build/html");
+
+    assert_eq!(
+        vec![output.join("entry_1.html"), output.join("index.html")],
+        render_output.files
+    );
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("golden/html");
+
+    for page in ["index.html", "entry_1.html"] {
+        let expected = fs::read_to_string(golden.join(page)).expect("failed to read golden file");
+        let actual = fs::read_to_string(output.join(page)).expect("failed to read rendered page");
+
+        assert_eq!(expected, actual, "{page} does not match its golden file");
+    }
+}
+
+#[test]
+fn it_renders_the_journal_to_the_given_directory() {
+    let test_dir = common::copy_test_dir();
+    let export_dir = tempfile::tempdir().expect("failed to create temp dir");
+    let mut journal_builder =
+        JournalBuilder::load(test_dir.path()).expect("failed to load journal");
+
+    journal_builder.with_renderer(HtmlRenderer::new());
+    let render_output = journal_builder
+        .build_to(export_dir.path())
+        .expect("failed to build journal");
+
+    let output = export_dir.path().join("html");
+
+    assert_eq!(
+        vec![output.join("entry_1.html"), output.join("index.html")],
+        render_output.files
+    );
+    assert!(!test_dir.path().join("build/html").exists());
+}
+
+#[test]
+fn it_copies_assets_into_the_destination() {
+    let test_dir = common::copy_test_dir();
+    let assets = test_dir.path().join("assets");
+    fs::create_dir_all(assets.join("images")).expect("failed to create assets");
+    fs::write(assets.join("style.css"), "body{}").expect("failed to write asset");
+    fs::write(assets.join("images/map.png"), "map").expect("failed to write asset");
+    fs::write(assets.join("index.html"), "asset").expect("failed to write asset");
+
+    let build = |overwrite: bool| {
+        let mut journal_builder =
+            JournalBuilder::load(test_dir.path()).expect("failed to load journal");
+
+        journal_builder
+            .with_config_override(|config| {
+                config.build.assets = Some(PathBuf::from("assets"));
+                config.build.overwrite_assets = overwrite;
+            })
+            .with_renderer(HtmlRenderer::new());
+        journal_builder.build().expect("failed to build journal")
+    };
+
+    let output = test_dir.path().join("build/html");
+    let render_output = build(false);
+
+    assert!(render_output.files.contains(&output.join("style.css")));
+    assert!(render_output.files.contains(&output.join("images/map.png")));
+    assert_eq!(
+        "map",
+        fs::read_to_string(output.join("images/map.png")).unwrap()
+    );
+    assert_ne!(
+        "asset",
+        fs::read_to_string(output.join("index.html")).unwrap()
+    );
+
+    build(true);
+
+    assert_eq!(
+        "asset",
+        fs::read_to_string(output.join("index.html")).unwrap()
+    );
+}
+
+#[test]
+fn it_skips_ignored_assets() {
+    let test_dir = common::copy_test_dir();
+    let assets = test_dir.path().join("assets");
+    fs::create_dir_all(assets.join("images/.cache")).expect("failed to create assets");
+    fs::write(assets.join("style.css"), "body{}").expect("failed to write asset");
+    fs::write(assets.join("images/map.png"), "map").expect("failed to write asset");
+    fs::write(assets.join("images/map.psd"), "layers").expect("failed to write asset");
+    fs::write(assets.join("images/.cache/map.png"), "thumb").expect("failed to write asset");
+
+    let mut journal_builder =
+        JournalBuilder::load(test_dir.path()).expect("failed to load journal");
+    journal_builder
+        .with_config_override(|config| {
+            config.build.assets = Some(PathBuf::from("assets"));
+            config.journal.ignore = vec![String::from("*.psd"), String::from(".cache/")];
+        })
+        .with_renderer(HtmlRenderer::new());
+    let render_output = journal_builder.build().expect("failed to build journal");
+
+    let output = test_dir.path().join("build/html");
+
+    assert!(render_output.files.contains(&output.join("style.css")));
+    assert!(render_output.files.contains(&output.join("images/map.png")));
+    assert!(!output.join("images/map.psd").exists());
+    assert!(!output.join("images/.cache").exists());
+}
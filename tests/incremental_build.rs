@@ -0,0 +1,386 @@
+use crate::common::TestRenderer;
+use dungeon_mark::{
+    build::{
+        preprocess::{Preprocessor, PreprocessorContext},
+        JournalBuilder,
+    },
+    error::Result,
+    model::journal::{Journal, JournalItem, Matter},
+};
+use std::{
+    cell::RefCell,
+    fs::{self, File},
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+mod common;
+
+fn build_journal(root: &std::path::Path) -> dungeon_mark::model::journal::Journal {
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root).expect("failed to load journal");
+
+    journal_builder
+        .with_cache(true)
+        .with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    renderer.journal()
+}
+
+#[test]
+fn it_reuses_cached_entries_between_builds() {
+    let test_dir = common::copy_test_dir();
+
+    let first = build_journal(test_dir.path());
+    assert!(test_dir.path().join("build/.cache/entries.json").exists());
+
+    let second = build_journal(test_dir.path());
+
+    assert_eq!(first, second);
+}
+
+#[test]
+fn it_invalidates_cached_entries_when_an_include_changes() {
+    let test_dir = common::copy_test_dir();
+
+    build_journal(test_dir.path());
+    fs::write(test_dir.path().join("journal/include.md"), "Changed!")
+        .expect("failed to update include");
+
+    let journal = build_journal(test_dir.path());
+    let JournalItem::Entry(ref entry) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!("Changed!", entry.sections[0].body);
+}
+
+#[test]
+fn it_rolls_the_same_dice_for_a_seed_across_cached_builds() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "[build]\nseed = 1234\n")
+        .expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Loot](./loot.md)\n- [Town](./town.md)\n- [Ambush](./ambush.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("loot.md"), "# Loot\n\n{{#roll 3d1000}} gold")
+        .expect("failed to write entry");
+    fs::write(source.join("town.md"), "# Town\n\nQuiet").expect("failed to write entry");
+    fs::write(
+        source.join("ambush.md"),
+        "# Ambush\n\n{{#roll 3d1000}} bandits",
+    )
+    .expect("failed to write entry");
+
+    build_journal(root.path());
+    fs::write(
+        source.join("ambush.md"),
+        "# Ambush\n\n{{#roll 3d1000}} wolves",
+    )
+    .expect("failed to update entry");
+
+    let cached = build_journal(root.path());
+    fs::remove_dir_all(root.path().join("build")).expect("failed to remove the cache");
+    let uncached = build_journal(root.path());
+
+    assert_eq!(uncached, cached);
+}
+
+#[test]
+fn it_only_rebuilds_entries_changed_since_a_time() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md)\n- [Tavern](./tavern.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("town.md"), "# Town\n\nQuiet").expect("failed to write entry");
+    fs::write(source.join("tavern.md"), "# Tavern\n\nEmpty").expect("failed to write entry");
+
+    let previous = build_journal(root.path());
+    let cache = fs::read_to_string(root.path().join("build/.cache/entries.json"))
+        .expect("failed to read cache");
+    let since = SystemTime::now();
+
+    // NOTE: Backdate the town's change, so that only the tavern counts as changed since the previous build.
+    fs::write(source.join("town.md"), "# Town\n\nBusy").expect("failed to update entry");
+    File::options()
+        .write(true)
+        .open(source.join("town.md"))
+        .and_then(|file| file.set_modified(since - Duration::from_secs(60)))
+        .expect("failed to backdate entry");
+    fs::write(source.join("tavern.md"), "# Tavern\n\nCrowded").expect("failed to update entry");
+    File::options()
+        .write(true)
+        .open(source.join("tavern.md"))
+        .and_then(|file| file.set_modified(since + Duration::from_secs(60)))
+        .expect("failed to update entry time");
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder
+        .build_changed_since(since)
+        .expect("failed to build journal");
+    let journal = renderer.journal();
+
+    let bodies = journal
+        .iter()
+        .map(|item| match item {
+            JournalItem::Entry(entry) => entry.sections[0].body.as_str(),
+            _ => panic!("item was not an entry"),
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(vec!["Quiet", "Crowded"], bodies);
+    assert_eq!(previous.items.len(), journal.items.len());
+    assert_eq!(
+        cache,
+        fs::read_to_string(root.path().join("build/.cache/entries.json"))
+            .expect("failed to read cache"),
+        "the disabled cache should not be written"
+    );
+}
+
+#[test]
+fn it_rebuilds_entries_whose_includes_changed_since_a_time() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("town.md"), "# Town\n\n{{#include gate.md}}")
+        .expect("failed to write entry");
+    fs::write(source.join("gate.md"), "Open").expect("failed to write include");
+
+    build_journal(root.path());
+    let since = SystemTime::now();
+
+    File::options()
+        .write(true)
+        .open(source.join("town.md"))
+        .and_then(|file| file.set_modified(since - Duration::from_secs(60)))
+        .expect("failed to backdate entry");
+    fs::write(source.join("gate.md"), "Closed").expect("failed to update include");
+    File::options()
+        .write(true)
+        .open(source.join("gate.md"))
+        .and_then(|file| file.set_modified(since + Duration::from_secs(60)))
+        .expect("failed to update include time");
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder
+        .build_changed_since(since)
+        .expect("failed to build journal");
+    let journal = renderer.journal();
+    let JournalItem::Entry(ref entry) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!("Closed", entry.sections[0].body);
+}
+
+#[test]
+fn it_reassigns_the_chapters_of_cached_entries() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n# Towns\n\n- [Town](./town.md)\n\n# Dungeons\n\n- [Crypt](./crypt.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("town.md"), "# Town\n\nQuiet").expect("failed to write entry");
+    fs::write(source.join("crypt.md"), "# Crypt\n\nDark").expect("failed to write entry");
+
+    build_journal(root.path());
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n# Towns\n\n# Dungeons\n\n- [Town](./town.md)\n- [Crypt](./crypt.md)\n",
+    )
+    .expect("failed to update table of contents");
+
+    let journal = build_journal(root.path());
+    let JournalItem::Entry(ref town) = journal.items[2] else {
+        panic!("third item was not an entry")
+    };
+
+    assert_eq!(Some("Dungeons"), town.chapter.as_deref());
+}
+
+#[test]
+fn it_reassigns_the_matter_of_cached_entries() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n# Towns\n\n- [Town](./town.md)\n- [Maps](./maps.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("town.md"), "# Town\n\nQuiet").expect("failed to write entry");
+    fs::write(source.join("maps.md"), "# Maps\n\nFaded").expect("failed to write entry");
+
+    build_journal(root.path());
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n# Towns\n\n- [Town](./town.md)\n\n---\n\n- [Maps](./maps.md)\n",
+    )
+    .expect("failed to update table of contents");
+
+    let journal = build_journal(root.path());
+    let JournalItem::Entry(ref maps) = journal.items[3] else {
+        panic!("fourth item was not an entry")
+    };
+
+    assert_eq!(Matter::Back, maps.matter);
+}
+
+#[test]
+fn it_reassigns_the_link_titles_of_cached_entries() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md \"A quiet town\")\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("town.md"), "# Town\n\nQuiet").expect("failed to write entry");
+
+    build_journal(root.path());
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md \"A busy town\")\n",
+    )
+    .expect("failed to update table of contents");
+
+    let journal = build_journal(root.path());
+    let JournalItem::Entry(ref town) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!(Some("A busy town"), town.link_title.as_deref());
+}
+
+#[test]
+fn it_never_caches_entries_reading_the_environment() {
+    // NOTE: The variable is only read by this test, so setting it does not race with the other tests.
+    const VARIABLE: &str = "DUNGEON_MARK_INCREMENTAL_BUILD_TEST_URL";
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        source.join("town.md"),
+        format!("See {{{{#env {VARIABLE}}}}}"),
+    )
+    .expect("failed to write entry");
+
+    std::env::set_var(VARIABLE, "https://one");
+    build_journal(root.path());
+    std::env::set_var(VARIABLE, "https://two");
+
+    let journal = build_journal(root.path());
+    let JournalItem::Entry(ref town) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!(Some("See https://two"), town.body.as_deref());
+}
+
+/// Records the titles of the entries that reach the preprocessors with a body, which are the ones the cache missed.
+#[derive(Clone, Default)]
+struct RebuiltEntries(Rc<RefCell<Vec<String>>>);
+
+impl RebuiltEntries {
+    fn take(&self) -> Vec<String> {
+        self.0.take()
+    }
+}
+
+impl Preprocessor for RebuiltEntries {
+    fn name(&self) -> &str {
+        "rebuilt-entries"
+    }
+
+    fn run(&self, _ctx: &PreprocessorContext, journal: Journal) -> Result<Journal> {
+        journal.for_each_entry(|entry| {
+            if entry.body.is_some() {
+                self.0.borrow_mut().push(entry.title.clone());
+            }
+        });
+
+        Ok(journal)
+    }
+}
+
+#[test]
+fn it_rebuilds_entries_when_a_nested_include_or_the_directive_config_changes() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(source.join("parts")).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md)\n- [Tavern](./tavern.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        source.join("town.md"),
+        "# Town\n\n{{#include parts/gate.md}}",
+    )
+    .expect("failed to write entry");
+    fs::write(source.join("tavern.md"), "# Tavern\n\nQuiet").expect("failed to write entry");
+    fs::write(source.join("parts/gate.md"), "{{#include guard.md}}")
+        .expect("failed to write include");
+    fs::write(source.join("parts/guard.md"), "Borin").expect("failed to write include");
+
+    let rebuilt = RebuiltEntries::default();
+    let build = || {
+        let mut journal_builder =
+            JournalBuilder::load(root.path()).expect("failed to load journal");
+        journal_builder
+            .with_cache(true)
+            .with_preprocessor(rebuilt.clone())
+            .with_renderer(TestRenderer::default());
+        journal_builder.build().expect("failed to build journal");
+
+        rebuilt.take()
+    };
+
+    assert_eq!(vec!["Town", "Tavern"], build());
+    assert!(build().is_empty());
+
+    fs::write(source.join("parts/guard.md"), "Dorn").expect("failed to update include");
+    assert_eq!(vec!["Town"], build());
+
+    fs::write(
+        root.path().join("journal.toml"),
+        "[directive]\nallow-outside-root = true\n",
+    )
+    .expect("failed to update config");
+    assert_eq!(vec!["Town", "Tavern"], build());
+}
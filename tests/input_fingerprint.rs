@@ -0,0 +1,91 @@
+use dungeon_mark::build::JournalBuilder;
+use std::{fs, path::Path};
+
+fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().expect("file should have a parent"))
+        .expect("failed to create directory");
+    fs::write(path, contents).expect("failed to write file");
+}
+
+/// A journal linking `town.md`, which includes `patrons.md`, next to an unlinked `notes.md`.
+fn journal() -> tempfile::TempDir {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+
+    write(&root.path().join("journal.toml"), "");
+    write(
+        &source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md)\n",
+    );
+    write(
+        &source.join("town.md"),
+        "# Town\n\n{{#include shared/patrons.md}}\n",
+    );
+    write(&source.join("shared/patrons.md"), "Borin");
+    write(&source.join("notes.md"), "# Notes");
+
+    root
+}
+
+fn fingerprint(root: &Path) -> String {
+    JournalBuilder::load(root)
+        .expect("failed to load journal")
+        .input_fingerprint()
+        .expect("failed to fingerprint journal")
+}
+
+#[test]
+fn it_fingerprints_journals_by_their_contents() {
+    let first = journal();
+    let second = journal();
+    let original = fingerprint(first.path());
+
+    assert_eq!(64, original.len());
+    assert_eq!(original, fingerprint(first.path()));
+    assert_eq!(original, fingerprint(second.path()));
+
+    write(&first.path().join("src/notes.md"), "# Unlinked notes");
+    assert_eq!(original, fingerprint(first.path()));
+}
+
+#[test]
+fn it_changes_the_fingerprint_with_any_input() {
+    for (file, contents) in [
+        ("journal.toml", "[journal]\ntitle = \"Town\"\n"),
+        ("journal.local.toml", "[build]\nstrict = true\n"),
+        ("src/JOURNAL.md", "# Town Journal\n\n- [Town](./town.md)\n"),
+        (
+            "src/town.md",
+            "# Town\n\nQuiet.\n\n{{#include shared/patrons.md}}\n",
+        ),
+        ("src/shared/patrons.md", "Borin and Ilsa"),
+    ] {
+        let root = journal();
+        let original = fingerprint(root.path());
+
+        write(&root.path().join(file), contents);
+
+        assert_ne!(original, fingerprint(root.path()), "{file}");
+    }
+}
+
+#[test]
+fn it_changes_the_fingerprint_with_the_environment_variables_entries_read() {
+    // NOTE: The variable is only read by this test, so setting it does not race with the other tests.
+    const VARIABLE: &str = "DUNGEON_MARK_FINGERPRINT_TEST_URL";
+    let root = journal();
+    write(
+        &root.path().join("src/town.md"),
+        &format!("# Town\n\nSee {{{{#env {VARIABLE}}}}}\n"),
+    );
+
+    std::env::remove_var(VARIABLE);
+    let unset = fingerprint(root.path());
+    std::env::set_var(VARIABLE, "https://one");
+    let one = fingerprint(root.path());
+    std::env::set_var(VARIABLE, "https://two");
+    let two = fingerprint(root.path());
+
+    assert_ne!(unset, one);
+    assert_ne!(one, two);
+}
@@ -1,9 +1,10 @@
 use crate::common::TestRenderer;
 use dungeon_mark::{
     build::JournalBuilder,
-    model::journal::{JournalEntry, JournalItem, Section, SectionLevel},
+    config::Config,
+    model::journal::{JournalEntry, JournalItem, Matter, Metadata, Section, SectionLevel},
 };
-use std::{collections::HashMap, path::PathBuf, str::FromStr};
+use std::{fs, path::PathBuf, str::FromStr};
 
 mod common;
 
@@ -25,12 +26,693 @@ fn it_loads_the_journal_as_expected() {
             title: String::from("Test Entry"),
             level: SectionLevel::H1,
             body: String::from("This is a test entry!"),
-            metadata: HashMap::new(),
+            metadata: Metadata::new(),
             sections: Vec::new(),
+            span: None,
         }],
         path: PathBuf::from_str("./entry_1.md").ok(),
+        source_dir: Some(PathBuf::from("journal")),
         level: 1,
+        children: Vec::new(),
+        tags: Vec::new(),
+        content_hash: Some(String::from(
+            "5608b6bf38b5f2c441533f7955ab72a4ea3e80fd8db4d8e266ec577f8112ea8b",
+        )),
+        excerpt: Some(String::from("Test Entry This is a test entry!")),
+        number: None,
+        chapter: None,
+        heading_offset: 0,
+        matter: Matter::Body,
+        link_title: None,
+        draft: false,
     })];
 
     assert_eq!(expected, journal.items);
 }
+
+#[test]
+fn it_nests_entries_below_their_parent_link() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md)\n  - [Tavern](./tavern.md)\n- [Dungeon](./dungeon.md)\n",
+    )
+    .expect("failed to write table of contents");
+
+    for entry in ["town", "tavern", "dungeon"] {
+        fs::write(source.join(format!("{entry}.md")), "# Heading").expect("failed to write entry");
+    }
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let journal = renderer.journal();
+    let titles = |items: &[JournalItem]| -> Vec<String> {
+        items
+            .iter()
+            .filter_map(|item| match item {
+                JournalItem::Entry(entry) => Some(entry.title.clone()),
+                _ => None,
+            })
+            .collect()
+    };
+
+    assert_eq!(vec!["Town", "Dungeon"], titles(&journal.items));
+
+    let JournalItem::Entry(ref town) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!(vec!["Tavern"], titles(&town.children));
+    assert_eq!(1, town.children.len());
+    assert_eq!(
+        vec!["Town", "Tavern", "Dungeon"],
+        titles(&journal.iter().cloned().collect::<Vec<_>>())
+    );
+}
+
+#[test]
+fn it_derives_entry_titles_from_headings_and_file_names() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Keep](./keep.md)\n- [](./town_square.md)\n- [Crypt](./crypt.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        source.join("keep.md"),
+        "{{#title}}\n\n## Overview\n\n# The Sunken Keep\n",
+    )
+    .expect("failed to write entry");
+    fs::write(source.join("town_square.md"), "## Stalls\n").expect("failed to write entry");
+    fs::write(source.join("crypt.md"), "{{#title}}\n\n## No H1\n").expect("failed to write entry");
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let journal = renderer.journal();
+    let titles: Vec<_> = journal
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            JournalItem::Entry(entry) => Some(entry.title.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(vec!["The Sunken Keep", "town_square", "Crypt"], titles);
+
+    let JournalItem::Entry(ref keep) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert!(!keep
+        .body
+        .as_deref()
+        .unwrap_or_default()
+        .contains("{{#title}}"));
+}
+
+#[test]
+fn it_gives_chapter_titles_unique_anchors() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n# Part Two\n\n- [Part Two](./a.md)\n\n# Part Two\n\n- [B](./b.md)\n",
+    )
+    .expect("failed to write table of contents");
+
+    for entry in ["a", "b"] {
+        fs::write(source.join(format!("{entry}.md")), "# Heading").expect("failed to write entry");
+    }
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let anchors: Vec<_> = renderer
+        .journal()
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            JournalItem::ChapterTitle(chapter) => Some(chapter.anchor.clone()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(vec!["part-two", "part-two-2"], anchors);
+}
+
+#[test]
+fn it_prefers_the_configured_title_over_the_table_of_contents() {
+    let load_title = |config: &str| {
+        let config = config.parse().expect("failed to parse config");
+        let renderer = TestRenderer::default();
+        let mut journal_builder = JournalBuilder::load_with_config(common::test_dir(), config)
+            .expect("failed to load journal");
+
+        journal_builder.with_renderer(renderer.clone());
+        journal_builder.build().expect("failed to build journal");
+
+        renderer.journal().title
+    };
+
+    assert_eq!(
+        Some(String::from("Configured")),
+        load_title("[journal]\nsource = \"journal\"\ntitle = \"Configured\"")
+    );
+    assert_eq!(
+        Some(String::from("Journal")),
+        load_title("[journal]\nsource = \"journal\"")
+    );
+}
+
+#[test]
+fn it_leaves_entries_exempt_from_directives_unchanged() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Syntax](./syntax.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        source.join("syntax.md"),
+        "<!-- dungeon-mark: no-directives -->\n{{#title}}\n\n# Directives\n\nUse {{#include missing.md}}.\n",
+    )
+    .expect("failed to write entry");
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let journal = renderer.journal();
+    let JournalItem::Entry(ref syntax) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!("Syntax", syntax.title);
+    assert!(syntax
+        .body
+        .as_deref()
+        .unwrap_or_default()
+        .contains("{{#title}}"));
+    assert!(syntax.sections[0].body.contains("{{#include missing.md}}"));
+}
+
+#[test]
+fn it_loads_entries_from_named_roots() {
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let root = dir.path().join("journal");
+    let bestiary = dir.path().join("bestiary");
+    fs::create_dir_all(root.join("src")).expect("failed to create source dir");
+    fs::create_dir_all(&bestiary).expect("failed to create bestiary dir");
+    fs::write(
+        root.join("journal.toml"),
+        "[roots]\nbestiary = \"../bestiary\"\n",
+    )
+    .expect("failed to write config");
+    fs::write(
+        root.join("src/JOURNAL.md"),
+        "# Journal\n\n- [Goblin](@bestiary/goblin.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        bestiary.join("goblin.md"),
+        "# Goblin\n\n{{#include stats.md}}\n",
+    )
+    .expect("failed to write entry");
+    fs::write(bestiary.join("stats.md"), "Small and sneaky.").expect("failed to write include");
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(&root).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let journal = renderer.journal();
+    let JournalItem::Entry(ref goblin) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!(Some(PathBuf::from("@bestiary/goblin.md")), goblin.path);
+    assert_eq!("Small and sneaky.", goblin.sections[0].body.trim());
+
+    fs::write(
+        root.join("src/JOURNAL.md"),
+        "# Journal\n\n- [Fireball](@spells/fireball.md)\n",
+    )
+    .expect("failed to write table of contents");
+
+    let err = JournalBuilder::load(&root)
+        .and_then(|builder| builder.build())
+        .expect_err("unknown roots should fail");
+
+    assert!(format!("{err:#}").contains("@spells, which is not in the [roots] table"));
+}
+
+#[test]
+fn it_splits_included_headings_into_sections() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Keep](./keep.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("keep.md"), "{{#include shared.md}}\n").expect("failed to write entry");
+    fs::write(
+        source.join("shared.md"),
+        "Crumbling walls.\n\n# Keep\n\nA ruined tower.\n\n## Dungeon\n\nCells\n\n# Bailey\n\nMud",
+    )
+    .expect("failed to write include");
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let journal = renderer.journal();
+    let JournalItem::Entry(ref keep) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    let outline = keep
+        .iter_sections()
+        .map(|section| (section.level, section.title.as_str(), section.body.trim()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        Some("Crumbling walls."),
+        keep.body.as_deref().map(str::trim)
+    );
+    assert_eq!(
+        vec![
+            (SectionLevel::H1, "Keep", "A ruined tower."),
+            (SectionLevel::H2, "Dungeon", "Cells"),
+            (SectionLevel::H1, "Bailey", "Mud"),
+        ],
+        outline
+    );
+    assert_eq!(1, keep.sections[0].sections.len());
+}
+
+#[test]
+fn it_rejects_structural_problems_in_strict_builds() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./a.md)\n- [Town](./b.md)\n",
+    )
+    .expect("failed to write table of contents");
+
+    for entry in ["a", "b"] {
+        fs::write(source.join(format!("{entry}.md")), "# Heading").expect("failed to write entry");
+    }
+
+    let build = |strict: bool| {
+        fs::write(
+            root.path().join("journal.toml"),
+            format!("[build]\nstrict = {strict}\n"),
+        )
+        .expect("failed to write config");
+
+        let mut journal_builder =
+            JournalBuilder::load(root.path()).expect("failed to load journal");
+        journal_builder.with_renderer(TestRenderer::default());
+        journal_builder.build()
+    };
+
+    let err = build(true).expect_err("strict build should fail");
+
+    assert!(format!("{err:#}").contains("has the same slug `town`"));
+    assert!(build(false).is_ok());
+}
+
+#[test]
+fn it_resolves_links_without_extensions() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(
+        root.path().join("journal.toml"),
+        "[journal]\nextensions = [\"markdown\", \"mdx\"]\n",
+    )
+    .expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Keep](./keep)\n- [Town](./town)\n- [Dock](./dock.md)\n",
+    )
+    .expect("failed to write table of contents");
+
+    for file in ["keep.markdown", "keep.mdx", "town.mdx", "dock.md"] {
+        fs::write(source.join(file), "# Heading").expect("failed to write entry");
+    }
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let paths: Vec<_> = renderer
+        .journal()
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            JournalItem::Entry(entry) => entry.path.clone(),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        vec![
+            PathBuf::from("./keep.markdown"),
+            PathBuf::from("./town.mdx"),
+            PathBuf::from("./dock.md")
+        ],
+        paths
+    );
+
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Crypt](./crypt)\n",
+    )
+    .expect("failed to write table of contents");
+
+    let err = JournalBuilder::load(root.path())
+        .and_then(|builder| builder.build())
+        .expect_err("missing entries should fail");
+
+    assert!(format!("{err:#}").contains("with any of the extensions: markdown, mdx"));
+}
+
+#[test]
+fn it_loads_directory_links_from_their_index_file() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(source.join("town/tavern")).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town/)\n  - [Tavern](tavern/)\n    - [Cellar](cellar.md)\n  - [Dock](./dock.md)\n",
+    )
+    .expect("failed to write table of contents");
+
+    for file in [
+        "town/index.md",
+        "town/tavern/index.md",
+        "town/tavern/cellar.md",
+        "town/dock.md",
+    ] {
+        fs::write(source.join(file), format!("Written in {file}")).expect("failed to write entry");
+    }
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let entries: Vec<_> = renderer
+        .journal()
+        .iter()
+        .filter_map(|item| match item {
+            JournalItem::Entry(entry) => Some((entry.path.clone(), entry.body.clone())),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        vec![
+            (
+                Some(PathBuf::from("./town/index.md")),
+                Some(String::from("Written in town/index.md"))
+            ),
+            (
+                Some(PathBuf::from("./town/tavern/index.md")),
+                Some(String::from("Written in town/tavern/index.md"))
+            ),
+            (
+                Some(PathBuf::from("./town/tavern/cellar.md")),
+                Some(String::from("Written in town/tavern/cellar.md"))
+            ),
+            (
+                Some(PathBuf::from("./town/dock.md")),
+                Some(String::from("Written in town/dock.md"))
+            ),
+        ],
+        entries
+    );
+}
+
+#[test]
+fn it_rejects_directory_links_without_an_index_file() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(source.join("town")).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+
+    let build = |toc: &str| {
+        fs::write(source.join("JOURNAL.md"), toc).expect("failed to write table of contents");
+
+        JournalBuilder::load(root.path())
+            .and_then(|builder| builder.build())
+            .expect_err("missing index files should fail")
+    };
+
+    let err = build("# Journal\n\n- [Town](./town/)\n");
+    assert!(
+        format!("{err:#}").contains("Failed to find the index file index.md"),
+        "{err:#}"
+    );
+
+    let err = build("# Journal\n\n- [Crypt](./crypt/)\n");
+    assert!(
+        format!("{err:#}").contains("Failed to find the directory of journal entry"),
+        "{err:#}"
+    );
+}
+
+#[test]
+fn it_serializes_metadata_in_a_stable_order() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Keep](./keep.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        source.join("keep.md"),
+        "# Keep\n\n```toml,metadata,zeta\nz = 1\n```\n\n```json,metadata,alpha\n{}\n```\n\n\
+         ```metadata,mid\nm = 2\n```\n\n```toml,metadata,beta\nb = 3\n```\n",
+    )
+    .expect("failed to write entry");
+
+    let serialize = || {
+        let renderer = TestRenderer::default();
+        let mut journal_builder =
+            JournalBuilder::load(root.path()).expect("failed to load journal");
+        journal_builder.with_renderer(renderer.clone());
+        journal_builder.build().expect("failed to build journal");
+
+        serde_json::to_string(&renderer.journal()).expect("failed to serialize journal")
+    };
+
+    let first = serialize();
+    let keys: Vec<_> = ["zeta", "alpha", "mid", "beta"]
+        .iter()
+        .map(|key| {
+            first
+                .find(&format!("\"{key}\":"))
+                .expect("key should be serialized")
+        })
+        .collect();
+
+    assert_eq!(first, serialize());
+    assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn it_loads_a_journal_without_items() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(source.join("JOURNAL.md"), "# Journal\n").expect("failed to write table of contents");
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let journal = renderer.journal();
+
+    assert_eq!(Some("Journal"), journal.title.as_deref());
+    assert!(journal.items.is_empty());
+}
+
+#[test]
+fn it_loads_empty_entries() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Empty](./empty.md)\n- [Blank](./blank.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("empty.md"), "").expect("failed to write entry");
+    fs::write(source.join("blank.md"), "\n  \n\n").expect("failed to write entry");
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let journal = renderer.journal();
+
+    assert_eq!(2, journal.items.len());
+
+    for item in &journal.items {
+        let JournalItem::Entry(entry) = item else {
+            panic!("item was not an entry")
+        };
+
+        assert_eq!(None, entry.body, "{}", entry.title);
+        assert!(entry.sections.is_empty(), "{}", entry.title);
+        assert_eq!(None, entry.excerpt, "{}", entry.title);
+    }
+}
+
+#[test]
+fn it_defers_loading_the_table_of_contents_until_the_build() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+
+    let journal_builder = JournalBuilder::load_with_config(root.path(), Config::default())
+        .expect("constructing a builder should not read the source");
+    let err = journal_builder
+        .build_dry_run()
+        .expect_err("the table of contents should be missing");
+
+    assert!(format!("{err:#}").contains("JOURNAL.md"), "{err:#}");
+
+    fs::write(
+        root.path().join("JOURNAL.md"),
+        "# Journal\n\n- [Notes](./notes.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(root.path().join("notes.md"), "# Notes").expect("failed to write entry");
+
+    let mut journal_builder = JournalBuilder::load_with_config(root.path(), Config::default())
+        .expect("failed to create builder");
+    journal_builder.with_source(".");
+    let journal = journal_builder
+        .build_dry_run()
+        .expect("failed to build journal");
+
+    assert_eq!(1, journal.items.len());
+}
+
+#[test]
+fn it_assigns_entries_to_the_preceding_chapter() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Intro](./intro.md)\n\n# Towns\n\n- [Town](./town.md)\n  - [Tavern](./tavern.md)\n\n---\n\n- [Market](./market.md)\n\n# Dungeons\n\n- [Crypt](./crypt.md)\n",
+    )
+    .expect("failed to write table of contents");
+
+    for entry in ["intro", "town", "tavern", "market", "crypt"] {
+        fs::write(source.join(format!("{entry}.md")), "# Heading").expect("failed to write entry");
+    }
+
+    let journal = JournalBuilder::load(root.path())
+        .expect("failed to load journal")
+        .build_dry_run()
+        .expect("failed to process journal");
+    let chapters: Vec<_> = journal
+        .iter()
+        .filter_map(|item| match item {
+            JournalItem::Entry(entry) => Some((entry.title.as_str(), entry.chapter.as_deref())),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        vec![
+            ("Intro", None),
+            ("Town", Some("Towns")),
+            ("Tavern", Some("Towns")),
+            ("Market", Some("Towns")),
+            ("Crypt", Some("Dungeons")),
+        ],
+        chapters
+    );
+}
+
+#[test]
+fn it_classifies_front_and_back_matter() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Foreword](./foreword.md)\n\n# Rules\n\n- [Combat](./combat.md)\n\n---\n\n- [Magic](./magic.md)\n\n# Setting\n\n- [Town](./town.md)\n\n---\n\n- [Appendix](./appendix.md)\n  - [Tables](./tables.md)\n",
+    )
+    .expect("failed to write table of contents");
+
+    for entry in ["foreword", "combat", "magic", "town", "appendix", "tables"] {
+        fs::write(source.join(format!("{entry}.md")), "# Heading").expect("failed to write entry");
+    }
+
+    let journal = JournalBuilder::load(root.path())
+        .expect("failed to load journal")
+        .build_dry_run()
+        .expect("failed to process journal");
+    let matter: Vec<_> = journal
+        .iter()
+        .filter_map(|item| match item {
+            JournalItem::Entry(entry) => Some((entry.title.as_str(), entry.matter)),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        vec![
+            ("Foreword", Matter::Front),
+            ("Combat", Matter::Body),
+            ("Magic", Matter::Body),
+            ("Town", Matter::Body),
+            ("Appendix", Matter::Back),
+            ("Tables", Matter::Back),
+        ],
+        matter
+    );
+}
@@ -0,0 +1,21 @@
+use crate::common::TestRenderer;
+use dungeon_mark::build::JournalBuilder;
+use std::{fs, path::Path};
+
+mod common;
+
+#[test]
+fn it_snapshots_the_journal_as_the_golden_file() {
+    let renderer = TestRenderer::default();
+    let mut journal_builder =
+        JournalBuilder::load(common::test_dir()).expect("failed to load journal");
+
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let snapshot = renderer.journal().to_snapshot_string();
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("golden/journal.json");
+    let expected = fs::read_to_string(golden).expect("failed to read golden file");
+
+    assert_eq!(expected.replace("\r\n", "\n"), snapshot);
+}
@@ -0,0 +1,99 @@
+use dungeon_mark::{build::JournalBuilder, model::journal::JournalItem};
+use std::{fs, path::Path, process::Command};
+
+fn write_journal(root: &Path) {
+    let source = root.join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(
+        root.join("journal.toml"),
+        "[build]\ntransformers = [\"metadata\", \"last-modified\"]\n",
+    )
+    .expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md)\n- [Draft](./draft.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("town.md"), "# Town\n\n## Tavern\n").expect("failed to write entry");
+}
+
+fn git(root: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args([
+            "-c",
+            "user.name=Dungeon Master",
+            "-c",
+            "user.email=dm@example.com",
+        ])
+        .args(args)
+        .env("GIT_AUTHOR_DATE", "2024-03-01T19:30:00+01:00")
+        .env("GIT_COMMITTER_DATE", "2024-03-02T08:00:00+01:00")
+        .status()
+        .expect("failed to run git");
+
+    assert!(status.success(), "git {args:?} failed");
+}
+
+fn has_git() -> bool {
+    Command::new("git")
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+fn last_modified(root: &Path) -> Vec<Option<String>> {
+    let journal = JournalBuilder::load(root)
+        .expect("failed to load journal")
+        .build_dry_run()
+        .expect("failed to process journal");
+
+    journal
+        .iter()
+        .filter_map(|item| match item {
+            JournalItem::Entry(entry) => Some(entry),
+            _ => None,
+        })
+        .flat_map(|entry| entry.iter_sections())
+        .map(|section| {
+            section
+                .metadata
+                .get("__last_modified")
+                .map(|metadata| metadata.data.clone())
+        })
+        .collect()
+}
+
+#[test]
+fn it_annotates_sections_with_the_last_commit_of_their_file() {
+    if !has_git() {
+        eprintln!("Skipping, as git is not installed");
+        return;
+    }
+
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    write_journal(root.path());
+    git(root.path(), &["init", "--quiet"]);
+    git(root.path(), &["add", "."]);
+    git(root.path(), &["commit", "--quiet", "-m", "Add the town"]);
+    fs::write(root.path().join("src/draft.md"), "# Draft\n").expect("failed to write entry");
+
+    let expected = Some(String::from(
+        "date = \"2024-03-01T19:30:00+01:00\"\nauthor = \"Dungeon Master\"\n",
+    ));
+
+    assert_eq!(
+        vec![expected.clone(), expected, None],
+        last_modified(root.path())
+    );
+}
+
+#[test]
+fn it_does_nothing_outside_of_a_git_repository() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    write_journal(root.path());
+    fs::write(root.path().join("src/draft.md"), "# Draft\n").expect("failed to write entry");
+
+    assert_eq!(vec![None, None, None], last_modified(root.path()));
+}
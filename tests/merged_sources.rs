@@ -0,0 +1,122 @@
+use dungeon_mark::{build::JournalBuilder, model::journal::JournalItem};
+use std::{fs, path::Path};
+
+fn write_source(source: &Path, entries: &[(&str, &str)]) {
+    fs::create_dir_all(source).expect("failed to create source dir");
+
+    let mut toc = String::from("# Journal\n\n");
+
+    for (title, path) in entries {
+        toc.push_str(&format!("- [{title}](./{path})\n"));
+        fs::write(source.join(path), format!("# {title}\n")).expect("failed to write entry");
+    }
+
+    fs::write(source.join("JOURNAL.md"), toc).expect("failed to write table of contents");
+}
+
+fn outline(items: &[JournalItem]) -> Vec<String> {
+    items
+        .iter()
+        .map(|item| match item {
+            JournalItem::Entry(entry) => format!(
+                "{} {}",
+                entry.title,
+                entry.path.as_deref().unwrap_or(Path::new("")).display()
+            ),
+            JournalItem::ChapterTitle(chapter) => format!("# {}", chapter.title),
+            JournalItem::Separator(_) => String::from("---"),
+            JournalItem::Error(error) => format!("! {}", error.title),
+        })
+        .collect()
+}
+
+#[test]
+fn it_merges_the_items_of_every_source() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    fs::write(
+        root.path().join("journal.toml"),
+        "[journal]\nsource = [\"players\", \"game-master\"]\nsource-chapters = true\n",
+    )
+    .expect("failed to write config");
+    write_source(&root.path().join("players"), &[("Town", "town.md")]);
+    write_source(
+        &root.path().join("game-master"),
+        &[("Secrets", "secrets.md"), ("Villains", "villains.md")],
+    );
+
+    let journal = JournalBuilder::load(root.path())
+        .expect("failed to load journal")
+        .build_dry_run()
+        .expect("failed to process journal");
+
+    assert_eq!(
+        vec![
+            "# Players",
+            "Town ./town.md",
+            "# Game master",
+            "Secrets ./secrets.md",
+            "Villains ./villains.md"
+        ],
+        outline(&journal.items)
+    );
+}
+
+#[test]
+fn it_reports_collisions_between_sources() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    fs::write(
+        root.path().join("journal.toml"),
+        "[journal]\nsource = [\"players\", \"gm\"]\n",
+    )
+    .expect("failed to write config");
+    write_source(&root.path().join("players"), &[("Town", "town.md")]);
+    write_source(
+        &root.path().join("gm"),
+        &[("Town secrets", "town.md"), ("Town", "other.md")],
+    );
+
+    let err = JournalBuilder::load(root.path())
+        .expect("failed to load journal")
+        .build_dry_run()
+        .expect_err("should fail on collisions");
+    let message = format!("{err:#}");
+
+    assert!(
+        message.contains("path ./town.md in players and gm"),
+        "{message}"
+    );
+    assert!(
+        message.contains("title \"Town\" in players and gm"),
+        "{message}"
+    );
+}
+
+#[test]
+fn it_resolves_directives_against_the_source_of_each_entry() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    fs::write(
+        root.path().join("journal.toml"),
+        "[journal]\nsource = [\"players\", \"gm\"]\n",
+    )
+    .expect("failed to write config");
+    write_source(&root.path().join("players"), &[("Town", "town.md")]);
+    write_source(&root.path().join("gm"), &[("Secrets", "secrets.md")]);
+    fs::write(
+        root.path().join("gm/secrets.md"),
+        "# Secrets\n\n{{#include notes.txt}}\n",
+    )
+    .expect("failed to write entry");
+    fs::write(root.path().join("gm/notes.txt"), "The mayor is a lich.")
+        .expect("failed to write notes");
+
+    let journal = JournalBuilder::load(root.path())
+        .expect("failed to load journal")
+        .build_dry_run()
+        .expect("failed to process journal");
+
+    let JournalItem::Entry(ref entry) = journal.items[1] else {
+        panic!("second item was not an entry")
+    };
+
+    assert_eq!("The mayor is a lich.", entry.sections[0].body.trim());
+}
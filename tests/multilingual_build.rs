@@ -0,0 +1,79 @@
+use dungeon_mark::{
+    build::{
+        render::{RenderContext, RenderOutput, Renderer},
+        JournalBuilder,
+    },
+    error::Result,
+    model::journal::JournalItem,
+};
+use std::{cell::RefCell, fs, path::PathBuf, rc::Rc};
+
+/// The language, destination and first entry title of a rendered edition.
+type RenderedEdition = (Option<String>, PathBuf, String);
+
+/// A renderer that records every edition it renders.
+#[derive(Clone, Default)]
+struct EditionRenderer(Rc<RefCell<Vec<RenderedEdition>>>);
+
+impl Renderer for EditionRenderer {
+    fn name(&self) -> &str {
+        "editions"
+    }
+
+    fn render(&self, ctx: RenderContext) -> Result<RenderOutput> {
+        let JournalItem::Entry(ref entry) = ctx.journal.items[0] else {
+            panic!("first item was not an entry")
+        };
+
+        self.0
+            .borrow_mut()
+            .push((ctx.language, ctx.destination, entry.title.clone()));
+
+        Ok(RenderOutput::default())
+    }
+}
+
+#[test]
+fn it_builds_each_language_edition_from_its_own_source() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let write = |path: &str, contents: &str| {
+        let path = root.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).expect("failed to create directory");
+        fs::write(path, contents).expect("failed to write file");
+    };
+
+    write(
+        "journal.toml",
+        "[journal]\nsource = \"src\"\n\n[language.en]\n\n[language.fr]\n",
+    );
+    write(
+        "src/en/JOURNAL.md",
+        "# Journal\n\n- [Tavern](./tavern.md)\n",
+    );
+    write("src/en/tavern.md", "Welcome!");
+    write(
+        "src/fr/JOURNAL.md",
+        "# Journal\n\n- [Taverne](./taverne.md)\n",
+    );
+    write("src/fr/taverne.md", "Bienvenue !");
+
+    let renderer = EditionRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let expected = vec![
+        (
+            Some(String::from("en")),
+            PathBuf::from("build/editions/en"),
+            String::from("Tavern"),
+        ),
+        (
+            Some(String::from("fr")),
+            PathBuf::from("build/editions/fr"),
+            String::from("Taverne"),
+        ),
+    ];
+
+    assert_eq!(expected, *renderer.0.borrow());
+}
@@ -0,0 +1,113 @@
+use crate::common::TestRenderer;
+use dungeon_mark::build::JournalBuilder;
+use std::{fs, path::Path};
+
+mod common;
+
+fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().expect("file should have a parent"))
+        .expect("failed to create directory");
+    fs::write(path, contents).expect("failed to write file");
+}
+
+/// A journal linking `town.md`, which includes `patrons.md`, and `tavern.md` below it, next to an unlinked
+/// `notes.md` and `drafts/idea.md`.
+fn journal(config: &str) -> tempfile::TempDir {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+
+    write(&root.path().join("journal.toml"), config);
+    write(
+        &source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md)\n  - [Tavern](./places/tavern)\n",
+    );
+    write(
+        &source.join("town.md"),
+        "# Town\n\n{{#include shared/patrons.md}}\n",
+    );
+    write(&source.join("shared/patrons.md"), "Borin");
+    write(&source.join("places/tavern.md"), "# Tavern");
+    write(&source.join("notes.md"), "# Notes");
+    write(&source.join("drafts/idea.md"), "# Idea");
+    write(&source.join("map.png"), "");
+
+    root
+}
+
+#[test]
+fn it_finds_entries_missing_from_the_table_of_contents() {
+    let root = journal("");
+    let journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+
+    assert_eq!(
+        vec![
+            root.path().join("src/drafts/idea.md"),
+            root.path().join("src/notes.md"),
+        ],
+        journal_builder
+            .orphaned_files()
+            .expect("failed to find orphaned files")
+    );
+}
+
+#[test]
+fn it_skips_the_build_directory() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+
+    write(
+        &root.path().join("journal.toml"),
+        "[journal]\nsource = \".\"\n",
+    );
+    write(&root.path().join("JOURNAL.md"), "- [Town](./town.md)\n");
+    write(&root.path().join("town.md"), "# Town");
+    write(&root.path().join("build/pandoc/pandoc.md"), "# Town");
+
+    let journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+
+    assert!(journal_builder
+        .orphaned_files()
+        .expect("failed to find orphaned files")
+        .is_empty());
+}
+
+#[test]
+fn it_fails_the_build_on_orphans_when_configured() {
+    let root = journal("[build]\nfail-on-orphans = true\n");
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(TestRenderer::default());
+
+    let err = journal_builder
+        .build()
+        .expect_err("build with orphans should fail");
+    let message = format!("{err:#}");
+
+    assert!(
+        message.contains("Found entries that the table of contents does not link to"),
+        "{message}"
+    );
+    assert!(message.contains("notes.md"), "{message}");
+    assert!(message.contains("idea.md"), "{message}");
+
+    let root = journal("");
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(TestRenderer::default());
+
+    journal_builder
+        .build()
+        .expect("orphans should not fail the build by default");
+}
+
+#[test]
+fn it_skips_ignored_files_and_directories() {
+    let root = journal("[journal]\nignore = [\"drafts/\", \".obsidian\"]\n");
+    write(&root.path().join("src/.obsidian/workspace.md"), "");
+
+    let journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+
+    assert_eq!(
+        vec![root.path().join("src/notes.md")],
+        journal_builder
+            .orphaned_files()
+            .expect("failed to find orphaned files")
+    );
+}
@@ -0,0 +1,54 @@
+use dungeon_mark::{build::JournalBuilder, model::journal::JournalItem};
+use std::fs;
+
+fn build(config: &str) -> dungeon_mark::error::Result<Vec<String>> {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), config).expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Tavern](./tavern.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        source.join("tavern.md"),
+        "# Tavern\n\n## Patrons\n\n## Cellar\n\n```metadata,gm-only\n```\n\n\
+         ### Barrel\n\n### Tunnel\n\n## Stables\n",
+    )
+    .expect("failed to write entry");
+
+    let journal = JournalBuilder::load(root.path())?.build_dry_run()?;
+    let JournalItem::Entry(ref entry) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    Ok(entry
+        .iter_sections()
+        .map(|section| section.title.clone())
+        .collect())
+}
+
+#[test]
+fn it_removes_secret_sections_and_their_children() {
+    let titles = build("[build]\nredact = true\n").expect("failed to build journal");
+
+    assert_eq!(vec!["Tavern", "Patrons", "Stables"], titles);
+}
+
+#[test]
+fn it_keeps_secret_sections_unless_redacting() {
+    let titles = build("").expect("failed to build journal");
+
+    assert_eq!(
+        vec!["Tavern", "Patrons", "Cellar", "Barrel", "Tunnel", "Stables"],
+        titles
+    );
+}
+
+#[test]
+fn it_refuses_to_redact_without_the_redaction_transformer() {
+    let result = build("[build]\nredact = true\ntransformers = [\"metadata\"]\n");
+
+    assert!(result.is_err());
+}
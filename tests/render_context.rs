@@ -0,0 +1,117 @@
+use dungeon_mark::{
+    build::{
+        render::{RenderContext, RenderOutput, Renderer},
+        JournalBuilder,
+    },
+    error::Result,
+    model::toc::{TOCItem, TableOfContents},
+};
+use std::{cell::RefCell, fs, rc::Rc};
+
+/// A renderer that captures the table of contents after a round trip through JSON, as a command renderer sees it.
+#[derive(Clone, Default)]
+struct TableOfContentsRenderer(Rc<RefCell<Option<TableOfContents>>>);
+
+impl Renderer for TableOfContentsRenderer {
+    fn name(&self) -> &str {
+        "toc"
+    }
+
+    fn render(&self, ctx: RenderContext) -> Result<RenderOutput> {
+        let json = serde_json::to_string(&ctx)?;
+        let ctx: RenderContext = serde_json::from_str(&json)?;
+
+        *self.0.borrow_mut() = Some(ctx.table_of_contents);
+
+        Ok(RenderOutput::default())
+    }
+}
+
+#[test]
+fn it_provides_the_nested_table_of_contents_to_renderers() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md)\n  - [Tavern](./tavern.md)\n\n---\n\n# Dungeon\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("town.md"), "Town").expect("failed to write entry");
+    fs::write(source.join("tavern.md"), "Tavern").expect("failed to write entry");
+
+    let renderer = TableOfContentsRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let toc = renderer
+        .0
+        .borrow_mut()
+        .take()
+        .expect("renderer was not run");
+    let TOCItem::Link(ref town) = toc.items[0] else {
+        panic!("first item was not a link")
+    };
+
+    assert_eq!("Town", town.name);
+    assert_eq!(
+        Some("Tavern"),
+        town.nested_items[0]
+            .maybe_link()
+            .map(|link| link.name.as_str())
+    );
+    assert!(toc.items[1].is_separator());
+    assert_eq!(
+        Some("Dungeon"),
+        toc.items[2]
+            .maybe_section_title()
+            .map(|section| section.title.as_str())
+    );
+}
+
+/// A renderer that captures the build time and tool version after a round trip through JSON.
+#[derive(Clone)]
+struct StampRenderer(&'static str, Rc<RefCell<Vec<(String, String)>>>);
+
+impl Renderer for StampRenderer {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn render(&self, ctx: RenderContext) -> Result<RenderOutput> {
+        let json = serde_json::to_string(&ctx)?;
+        let ctx: RenderContext = serde_json::from_str(&json)?;
+
+        self.1
+            .borrow_mut()
+            .push((ctx.generated_at, ctx.tool_version));
+
+        Ok(RenderOutput::default())
+    }
+}
+
+#[test]
+fn it_stamps_renderers_with_the_build_time_and_version() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(source.join("JOURNAL.md"), "# Journal\n").expect("failed to write table of contents");
+
+    let stamps = Rc::new(RefCell::new(Vec::new()));
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder
+        .with_build_time(1709296205)
+        .with_renderer(StampRenderer("first", stamps.clone()))
+        .with_renderer(StampRenderer("second", stamps.clone()));
+    journal_builder.build().expect("failed to build journal");
+
+    let stamps = stamps.borrow();
+
+    assert_eq!(2, stamps.len());
+    assert_eq!(stamps[0], stamps[1]);
+    assert_eq!("2024-03-01T12:30:05Z", stamps[0].0);
+    assert!(!stamps[0].1.is_empty());
+}
@@ -0,0 +1,32 @@
+use dungeon_mark::build::{render::CommandRenderer, JournalBuilder};
+use std::time::{Duration, Instant};
+
+mod common;
+
+#[test]
+fn it_kills_a_renderer_that_exceeds_its_timeout() {
+    let mut journal_builder = JournalBuilder::load(common::test_dir()).expect("failed to load");
+    journal_builder.with_renderer(
+        CommandRenderer::new(String::from("sleep"), Some(String::from("sleep 10")))
+            .with_timeout(Some(Duration::from_millis(200))),
+    );
+
+    let started = Instant::now();
+    let err = journal_builder
+        .build()
+        .expect_err("renderer should time out");
+
+    assert!(err.to_string().contains("timed out"));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn it_finishes_a_renderer_within_its_timeout() {
+    let mut journal_builder = JournalBuilder::load(common::test_dir()).expect("failed to load");
+    journal_builder.with_renderer(
+        CommandRenderer::new(String::from("true"), None)
+            .with_timeout(Some(Duration::from_secs(10))),
+    );
+
+    journal_builder.build().expect("failed to build journal");
+}
@@ -0,0 +1,29 @@
+use dungeon_mark::build::{render::SingleFileRenderer, JournalBuilder};
+use std::fs;
+
+mod common;
+
+#[test]
+fn it_renders_the_journal_to_a_single_page() {
+    let test_dir = common::copy_test_dir();
+    let mut journal_builder =
+        JournalBuilder::load(test_dir.path()).expect("failed to load journal");
+
+    journal_builder.with_renderer(SingleFileRenderer::new());
+    let render_output = journal_builder.build().expect("failed to build journal");
+
+    let page = test_dir.path().join("build/single-file/index.html");
+    assert_eq!(vec![page.clone()], render_output.files);
+
+    let html = fs::read_to_string(page).expect("failed to read rendered page");
+
+    assert!(html.starts_with("<!DOCTYPE html>\n<html>\n<head>\n"));
+    assert!(html.ends_with("</main>\n</body>\n</html>\n"));
+    assert!(html.contains("<title>Journal</title>"));
+    assert!(html.contains("<style>"));
+    assert!(!html.contains("<link"));
+    assert!(!html.contains("<script"));
+    assert!(html.contains("<li><a href=\"#entry-1\">Entry 1</a>"));
+    assert!(html.contains("<article id=\"entry-1\">"));
+    assert!(html.contains("<h1 id=\"test-entry\">Test Entry</h1>"));
+}
@@ -0,0 +1,100 @@
+use dungeon_mark::{build::JournalBuilder, model::journal::JournalItem};
+use std::fs;
+
+#[test]
+fn it_strips_byte_order_marks_and_carriage_returns() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "\u{feff}# Journal\r\n\r\n- [Tavern](./tavern.md)\r\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        source.join("tavern.md"),
+        "\u{feff}{{#title The Tavern}}\r\n# Patrons\r\n\r\n{{#include patrons.md}}\r\n",
+    )
+    .expect("failed to write entry");
+    fs::write(source.join("patrons.md"), "\u{feff}Borin\r\nIlsa\r\n")
+        .expect("failed to write include");
+
+    let journal = JournalBuilder::load(root.path())
+        .expect("failed to load journal")
+        .build_dry_run()
+        .expect("failed to process journal");
+
+    let JournalItem::Entry(ref entry) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!(Some("Journal"), journal.title.as_deref());
+    assert_eq!("The Tavern", entry.title);
+    assert_eq!("Patrons", entry.sections[0].title);
+    assert_eq!("Borin\nIlsa", entry.sections[0].body.trim());
+}
+
+#[test]
+fn it_reads_utf16_sources_with_a_byte_order_mark() {
+    let utf16le = |text: &str| {
+        [0xFF, 0xFE]
+            .into_iter()
+            .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect::<Vec<u8>>()
+    };
+
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        utf16le("# Journal\r\n\r\n- [Tavern](./tavern.md)\r\n"),
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        source.join("tavern.md"),
+        utf16le("# Patrons\r\n\r\n{{#include patrons.md}}\r\n"),
+    )
+    .expect("failed to write entry");
+    fs::write(source.join("patrons.md"), utf16le("Borin\r\nÍlsa\r\n"))
+        .expect("failed to write include");
+
+    let journal = JournalBuilder::load(root.path())
+        .expect("failed to load journal")
+        .build_dry_run()
+        .expect("failed to process journal");
+
+    let JournalItem::Entry(ref entry) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!(Some("Journal"), journal.title.as_deref());
+    assert_eq!("Patrons", entry.sections[0].title);
+    assert_eq!("Borin\nÍlsa", entry.sections[0].body.trim());
+}
+
+#[test]
+fn it_names_the_file_and_encoding_of_invalid_sources() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Tavern](./tavern.md)\n",
+    )
+    .expect("failed to write table of contents");
+    // UTF-16LE without a byte order mark.
+    fs::write(source.join("tavern.md"), [b'#', 0, b' ', 0, 0xFF, 0xDB])
+        .expect("failed to write entry");
+
+    let err = JournalBuilder::load(root.path())
+        .and_then(|builder| builder.build_dry_run())
+        .expect_err("invalid entry should fail to load");
+    let message = format!("{err:#}");
+
+    assert!(message.contains("tavern.md"), "{message}");
+    assert!(message.contains("Source is not valid UTF-8"), "{message}");
+}
@@ -0,0 +1,57 @@
+use crate::common::TestRenderer;
+use dungeon_mark::{
+    build::{
+        render::{SourceMap, SOURCE_MAP_VERSION},
+        JournalBuilder,
+    },
+    config::Config,
+};
+use std::{fs, path::PathBuf};
+
+mod common;
+
+fn config(source_map: bool) -> Config {
+    format!("[journal]\nsource = \"journal\"\n\n[build]\nsource-map = {source_map}\n")
+        .parse()
+        .expect("failed to parse config")
+}
+
+#[test]
+fn it_writes_a_source_map_for_each_renderer() {
+    let test_dir = common::copy_test_dir();
+    let mut journal_builder = JournalBuilder::load_with_config(test_dir.path(), config(true))
+        .expect("failed to load journal");
+    journal_builder.with_renderer(TestRenderer::default());
+
+    let render_output = journal_builder.build().expect("failed to build journal");
+    let path = test_dir.path().join("build/test_renderer/sourcemap.json");
+
+    assert!(render_output.files.contains(&path));
+
+    let json = fs::read_to_string(&path).expect("failed to read source map");
+    let source_map: SourceMap = serde_json::from_str(&json).expect("failed to parse source map");
+
+    assert_eq!(SOURCE_MAP_VERSION, source_map.version);
+    assert_eq!(
+        Some(PathBuf::from("entry_1.md")),
+        source_map.entries[0].source
+    );
+    assert!(source_map.entries[0]
+        .sections
+        .iter()
+        .all(|section| section.span.is_some()));
+}
+
+#[test]
+fn it_writes_no_source_map_by_default() {
+    let test_dir = common::copy_test_dir();
+    let mut journal_builder = JournalBuilder::load_with_config(test_dir.path(), config(false))
+        .expect("failed to load journal");
+    journal_builder.with_renderer(TestRenderer::default());
+    journal_builder.build().expect("failed to build journal");
+
+    assert!(!test_dir
+        .path()
+        .join("build/test_renderer/sourcemap.json")
+        .exists());
+}
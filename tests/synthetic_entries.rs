@@ -0,0 +1,114 @@
+use dungeon_mark::{
+    build::{InsertPosition, JournalBuilder},
+    model::journal::{JournalEntry, JournalItem},
+};
+use std::{fs, path::PathBuf};
+
+fn journal() -> tempfile::TempDir {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), "").expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Intro](./intro.md)\n\n# Towns\n\n- [Town](./town.md)\n  - [Tavern](./tavern.md)\n",
+    )
+    .expect("failed to write table of contents");
+
+    for entry in ["intro", "town", "tavern"] {
+        fs::write(source.join(format!("{entry}.md")), "# Heading").expect("failed to write entry");
+    }
+
+    root
+}
+
+fn synthetic(title: &str) -> JournalEntry {
+    JournalEntry {
+        title: String::from(title),
+        body: Some(String::from("Generated.\n\n# Summary\n\nTotals.")),
+        path: Some(PathBuf::from("./ignored.md")),
+        ..Default::default()
+    }
+}
+
+/// The titles of the items, with nested entries indented below their parent.
+fn outline(items: &[JournalItem], depth: usize, lines: &mut Vec<String>) {
+    for item in items {
+        match item {
+            JournalItem::Entry(entry) => {
+                lines.push(format!("{:indent$}{}", "", entry.title, indent = depth * 2));
+                outline(&entry.children, depth + 1, lines);
+            }
+            JournalItem::ChapterTitle(title) => lines.push(format!("# {}", title.title)),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn it_inserts_synthetic_entries_at_each_position() {
+    let root = journal();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder
+        .with_synthetic_entry(synthetic("Index"), InsertPosition::Back)
+        .with_synthetic_entry(synthetic("Cover"), InsertPosition::Front)
+        .with_synthetic_entry(
+            synthetic("Town Stats"),
+            InsertPosition::AfterChapter(String::from("Towns")),
+        )
+        .with_synthetic_entry(
+            synthetic("Tavern Stats"),
+            InsertPosition::AfterEntry(String::from("Tavern")),
+        );
+
+    let journal = journal_builder
+        .build_dry_run()
+        .expect("failed to build journal");
+
+    let mut lines = Vec::new();
+    outline(&journal.items, 0, &mut lines);
+
+    assert_eq!(
+        vec![
+            "Cover",
+            "Intro",
+            "# Towns",
+            "Town Stats",
+            "Town",
+            "  Tavern",
+            "  Tavern Stats",
+            "Index"
+        ],
+        lines
+    );
+
+    let index = journal
+        .iter()
+        .find_map(|item| match item {
+            JournalItem::Entry(entry) if entry.title == "Index" => Some(entry),
+            _ => None,
+        })
+        .expect("should have the index");
+
+    assert_eq!(None, index.path);
+    assert_eq!(Some("Towns"), index.chapter.as_deref());
+    assert_eq!("Summary", index.sections[0].title);
+}
+
+#[test]
+fn it_fails_to_insert_after_a_missing_entry() {
+    let root = journal();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_synthetic_entry(
+        synthetic("Stats"),
+        InsertPosition::AfterEntry(String::from("Dungeon")),
+    );
+
+    let err = journal_builder
+        .build_dry_run()
+        .expect_err("should fail to insert");
+
+    assert!(format!("{err:#}").contains(
+        "Failed to add the entry \"Stats\", as the journal has no entry titled \"Dungeon\""
+    ));
+}
@@ -0,0 +1,36 @@
+use dungeon_mark::{build::JournalBuilder, model::journal::JournalItem};
+use std::fs;
+
+fn build(config: &str) -> dungeon_mark::error::Result<String> {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), config).expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [the prancing pony](./tavern.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("tavern.md"), "Patrons\n").expect("failed to write entry");
+
+    let journal = JournalBuilder::load(root.path())?.build_dry_run()?;
+    let JournalItem::Entry(ref entry) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    Ok(entry.title.clone())
+}
+
+#[test]
+fn it_title_cases_titles_when_enabled() {
+    let title = build("[build]\ntitle-case = true\n").expect("failed to build journal");
+
+    assert_eq!("The Prancing Pony", title);
+}
+
+#[test]
+fn it_leaves_titles_as_written_by_default() {
+    let title = build("").expect("failed to build journal");
+
+    assert_eq!("the prancing pony", title);
+}
@@ -0,0 +1,76 @@
+use dungeon_mark::{
+    build::JournalBuilder,
+    error::Result,
+    model::journal::{Journal, JournalItem},
+};
+use std::fs;
+
+fn journal(tolerant: bool) -> tempfile::TempDir {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(
+        root.path().join("journal.toml"),
+        format!("[build]\ntolerant = {tolerant}\n"),
+    )
+    .expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Intro](./intro.md)\n- [Town](./town.md)\n  - [Tavern](./tavern.md)\n",
+    )
+    .expect("failed to write table of contents");
+
+    fs::write(source.join("intro.md"), "# Intro").expect("failed to write entry");
+    fs::write(source.join("town.md"), "# Town\n\n{{#title Town").expect("failed to write entry");
+    fs::write(source.join("tavern.md"), "# Tavern").expect("failed to write entry");
+
+    root
+}
+
+fn dry_run(tolerant: bool) -> Result<Journal> {
+    let root = journal(tolerant);
+
+    JournalBuilder::load(root.path())
+        .expect("failed to load journal")
+        .build_dry_run()
+}
+
+#[test]
+fn it_replaces_a_malformed_entry_with_an_error() {
+    let journal = dry_run(true).expect("failed to build journal");
+
+    let JournalItem::Error(ref error) = journal.items[1] else {
+        panic!("expected an error in place of the malformed entry");
+    };
+    assert_eq!("Town", error.title);
+    assert!(error
+        .message
+        .contains("Cannot find matching closing brace pair"));
+
+    let titles: Vec<_> = journal
+        .iter()
+        .filter_map(|item| match item {
+            JournalItem::Entry(entry) => Some(entry.title.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(vec!["Intro", "Tavern"], titles);
+    assert_eq!(1, journal.errors().len());
+}
+
+#[test]
+fn it_collects_the_errors_of_a_tolerant_build() {
+    let root = journal(true);
+    let output = JournalBuilder::load(root.path())
+        .expect("failed to load journal")
+        .build()
+        .expect("failed to build journal");
+
+    assert_eq!(1, output.errors.len());
+    assert_eq!("Town", output.errors[0].title);
+}
+
+#[test]
+fn it_fails_on_a_malformed_entry_by_default() {
+    assert!(dry_run(false).is_err());
+}
@@ -0,0 +1,92 @@
+use crate::common::TestRenderer;
+use dungeon_mark::{
+    build::{
+        transform::{Transformer, TransformerContext},
+        JournalBuilder,
+    },
+    error::Result,
+    model::journal::{Journal, JournalItem},
+};
+use std::fs;
+
+mod common;
+
+struct TitleTransformer {
+    title: &'static str,
+    renderers: &'static [&'static str],
+}
+
+impl Transformer for TitleTransformer {
+    fn name(&self) -> &str {
+        "title"
+    }
+
+    fn run(&self, _ctx: &TransformerContext, mut journal: Journal) -> Result<Journal> {
+        journal.title = Some(String::from(self.title));
+
+        Ok(journal)
+    }
+
+    fn supported_renderers(&self) -> Option<&[&str]> {
+        Some(self.renderers)
+    }
+}
+
+#[test]
+fn it_only_runs_transformers_supporting_the_renderer() {
+    let renderer = TestRenderer::default();
+    let test_dir = common::test_dir();
+    let mut journal_builder = JournalBuilder::load(test_dir).expect("failed to load journal");
+
+    journal_builder
+        .with_transformer(TitleTransformer {
+            title: "Supported",
+            renderers: &["test_renderer"],
+        })
+        .with_transformer(TitleTransformer {
+            title: "Unsupported",
+            renderers: &["other_renderer"],
+        })
+        .with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let journal = renderer.journal();
+
+    assert_eq!(Some(String::from("Supported")), journal.title);
+}
+
+#[test]
+fn it_resolves_conditions_for_the_renderer() {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(&source).expect("failed to create source dir");
+    fs::write(
+        root.path().join("journal.toml"),
+        "[build]\nprofile = \"print\"",
+    )
+    .expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(
+        source.join("town.md"),
+        "# Town\n\n{{#if renderer=test_renderer}}\nRendered\n{{#else}}\nElsewhere\n{{#endif}}\n\n\
+         {{#if profile!=print}}\n# Online\n{{#endif}}\n",
+    )
+    .expect("failed to write entry");
+
+    let renderer = TestRenderer::default();
+    let mut journal_builder = JournalBuilder::load(root.path()).expect("failed to load journal");
+    journal_builder.with_renderer(renderer.clone());
+    journal_builder.build().expect("failed to build journal");
+
+    let journal = renderer.journal();
+    let JournalItem::Entry(ref entry) = journal.items[0] else {
+        panic!("first item was not an entry")
+    };
+
+    assert_eq!(1, entry.sections.len());
+    assert_eq!("Rendered", entry.sections[0].body.trim());
+}
@@ -0,0 +1,49 @@
+use dungeon_mark::{build::JournalBuilder, model::journal::JournalItem};
+use std::fs;
+
+fn build(config: &str) -> String {
+    let root = tempfile::tempdir().expect("failed to create temp dir");
+    let source = root.path().join("src");
+    fs::create_dir_all(source.join("town")).expect("failed to create source dir");
+    fs::write(root.path().join("journal.toml"), config).expect("failed to write config");
+    fs::write(
+        source.join("JOURNAL.md"),
+        "# Journal\n\n- [Town](./town/index.md)\n  - [Tavern](./town/tavern.md)\n- [Notes](./notes.md)\n",
+    )
+    .expect("failed to write table of contents");
+    fs::write(source.join("town/index.md"), "# Streets\n\nBusy").expect("failed to write entry");
+    fs::write(
+        source.join("town/tavern.md"),
+        "{{#title The Rusty Tankard}}\n\n# Cellar\n\nBarrels",
+    )
+    .expect("failed to write entry");
+    fs::write(
+        source.join("notes.md"),
+        "Met Borin at [[The Rusty Tankard#Cellar|the cellar]], then walked the [[index#Streets]].",
+    )
+    .expect("failed to write entry");
+
+    let journal = JournalBuilder::load(root.path())
+        .expect("failed to load journal")
+        .build_dry_run()
+        .expect("failed to build journal");
+    let JournalItem::Entry(ref notes) = journal.items[1] else {
+        panic!("second item was not an entry")
+    };
+
+    notes.body.clone().expect("notes should have a body")
+}
+
+#[test]
+fn it_converts_wikilinks_when_enabled() {
+    assert_eq!(
+        "Met Borin at [the cellar](town/tavern.md#cellar), then walked the \
+         [index > Streets](town/index.md#streets).",
+        build("[build]\ntransformers = [\"metadata\", \"title\", \"wikilinks\"]\n")
+    );
+}
+
+#[test]
+fn it_leaves_wikilinks_by_default() {
+    assert!(build("").contains("Tankard#Cellar"));
+}